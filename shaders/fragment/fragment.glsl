@@ -6,6 +6,7 @@ layout(location = 0) out vec4 f_color;
 
 layout(set = 0, binding = 0) uniform sampler2D tex;
 
+// the glyph cache only holds how much of each pixel the glyphs cover
 void main() {
-    f_color = v_color * texture(tex, v_tex_position)[0];
+    f_color = vec4(v_color.rgb, v_color.a * texture(tex, v_tex_position)[0]);
 }
\ No newline at end of file
@@ -2,10 +2,12 @@
 
 layout(location = 0) in vec2 tex_coords;
 layout(location = 1) flat in uint layer;
+layout(location = 2) in float transparency;
 layout(location = 0) out vec4 f_color;
 
 layout(set = 0, binding = 0) uniform sampler2DArray tex;
 
 void main() {
     f_color = texture(tex, vec3(tex_coords, layer));
+    f_color.a *= 1.0 - transparency;
 }
\ No newline at end of file
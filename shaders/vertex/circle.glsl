@@ -5,12 +5,16 @@ layout(location = 1) in uint texture_id;
 layout(location = 2) in float radius;
 layout(location = 3) in vec2 center;
 layout(location = 4) in vec3 color;
+// where the vertex would be if the circle wasn't deformed
+layout(location = 5) in vec2 tex_position;
+layout(location = 6) in float transparency;
 
 layout(location = 0) out flat uint out_tex_i;
 layout(location = 1) out flat float out_radius;
 layout(location = 2) out flat vec2 out_center;
 layout(location = 3) out vec2 out_position;
 layout(location = 4) out vec3 out_color;
+layout(location = 5) out flat float out_transparency;
 
 void main() {
     float pct = 0.0;
@@ -18,6 +22,7 @@ void main() {
     out_tex_i = texture_id;
     out_radius = radius;
     out_center = center;
-    out_position = position;
+    out_position = tex_position;
     out_color = color;
+    out_transparency = transparency;
 }
\ No newline at end of file
@@ -0,0 +1,282 @@
+//! finding the images, fonts and levels the game ships with wherever it's started from. The
+//! assets directory is looked for in the `WHISKY_ASSETS` environment variable, then next to the
+//! executable and then in the current directory, once, the first time an asset is opened
+
+use std::{
+    env,
+    fmt::{self, Display},
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// the environment variable the assets directory can be chosen with
+pub const ASSETS_VARIABLE: &str = "WHISKY_ASSETS";
+/// the name of the assets directory next to the executable or in the current directory
+pub const ASSETS_DIRECTORY: &str = "assets";
+
+/// the arguments followed by values, with how many, so the values aren't taken for the level
+const VALUE_ARGS: &[(&str, usize)] = &[
+    (crate::ghost::GHOST_ARG, 1),
+    #[cfg(feature = "soft-render")]
+    (crate::soft_render::RENDER_EVERY_ARG, 2),
+];
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where the assets directory was found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RootSource {
+    Variable,
+    Executable,
+    CurrentDirectory,
+}
+
+impl Display for RootSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Variable => write!(f, "set by {ASSETS_VARIABLE}"),
+            Self::Executable => write!(f, "next to the executable"),
+            Self::CurrentDirectory => write!(f, "in the current directory"),
+        }
+    }
+}
+
+/// the assets directory: the one in `variable` if it's a directory, else the one in the
+/// directory of the executable, else the one in `current`. When none of them exists it's the
+/// one in `current` anyway, so the errors name the path the game was started with
+pub fn resolve_root(
+    variable: Option<PathBuf>,
+    executable: Option<&Path>,
+    current: &Path,
+) -> (PathBuf, RootSource) {
+    if let Some(variable) = variable {
+        if variable.is_dir() {
+            return (variable, RootSource::Variable);
+        }
+        eprintln!(
+            "warning: {ASSETS_VARIABLE} is set to {}, which isn't a directory",
+            variable.display()
+        );
+    }
+    let next_to_executable = executable
+        .and_then(Path::parent)
+        .map(|directory| directory.join(ASSETS_DIRECTORY))
+        .filter(|directory| directory.is_dir());
+    match next_to_executable {
+        Some(directory) => (directory, RootSource::Executable),
+        None => (current.join(ASSETS_DIRECTORY), RootSource::CurrentDirectory),
+    }
+}
+
+/// the assets directory of this run, found and logged the first time it's needed
+pub fn root() -> &'static Path {
+    ROOT.get_or_init(|| {
+        let executable = env::current_exe().ok();
+        let (root, source) = resolve_root(
+            env::var_os(ASSETS_VARIABLE).map(PathBuf::from),
+            executable.as_deref(),
+            Path::new(""),
+        );
+        println!("using the assets in {} ({source})", root.display());
+        root
+    })
+}
+
+/// the path with a leading `assets` directory left out, as paths in level files start with it
+fn within_root(relative: &Path) -> &Path {
+    match relative.components().next() {
+        Some(Component::Normal(first)) if first == ASSETS_DIRECTORY => {
+            relative.strip_prefix(ASSETS_DIRECTORY).unwrap_or(relative)
+        }
+        _ => relative,
+    }
+}
+
+/// where to open an asset from: the path as given if there's such a file, else its path inside
+/// the assets directory. Paths starting with `assets/`, as written in level files, are inside it
+/// too, and absolute ones are left alone
+pub fn path(relative: impl AsRef<Path>) -> PathBuf {
+    path_in(root(), relative.as_ref())
+}
+
+fn path_in(root: &Path, relative: &Path) -> PathBuf {
+    if relative.exists() {
+        relative.to_path_buf()
+    } else {
+        // joining an absolute path gives it back as it is
+        root.join(within_root(relative))
+    }
+}
+
+/// where to open a level from: the path as given if there's such a file, else the level with
+/// that path among the ones shipped with the game, next to the assets directory
+pub fn level(path: impl AsRef<Path>) -> PathBuf {
+    level_in(root(), path.as_ref())
+}
+
+/// the level to open, the first argument which is neither a flag nor the value of one
+pub fn level_path_from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(&(_, values)) = VALUE_ARGS.iter().find(|(flag, _)| *flag == arg) {
+            args.by_ref().take(values).for_each(drop);
+        } else if !arg.starts_with("--") {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+fn level_in(root: &Path, path: &Path) -> PathBuf {
+    if path.is_file() || path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match root.parent() {
+        Some(directory) if directory.join(path).is_file() => directory.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    /// a temporary directory with an assets directory in each of the named ones
+    fn directories(name: &str, with_assets: &[&str]) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("whisky-assets-{name}-{}", std::process::id()));
+        for inner in ["variable", "bin", "current"] {
+            fs::create_dir_all(directory.join(inner)).unwrap();
+        }
+        for inner in with_assets {
+            fs::create_dir_all(directory.join(inner).join(ASSETS_DIRECTORY)).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let directory = directories("order", &["bin", "current"]);
+        let variable = directory.join("variable");
+        let executable = directory.join("bin").join("whisky");
+        let current = directory.join("current");
+
+        assert_eq!(
+            resolve_root(Some(variable.clone()), Some(&executable), &current),
+            (variable, RootSource::Variable)
+        );
+        assert_eq!(
+            resolve_root(Some(directory.join("missing")), Some(&executable), &current),
+            (directory.join("bin/assets"), RootSource::Executable)
+        );
+        assert_eq!(
+            resolve_root(None, Some(&executable), &current),
+            (directory.join("bin/assets"), RootSource::Executable)
+        );
+        fs::remove_dir_all(directory.join("bin/assets")).unwrap();
+        assert_eq!(
+            resolve_root(None, Some(&executable), &current),
+            (
+                directory.join("current/assets"),
+                RootSource::CurrentDirectory
+            )
+        );
+        assert_eq!(
+            resolve_root(None, None, &current),
+            (
+                directory.join("current/assets"),
+                RootSource::CurrentDirectory
+            )
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_falls_back_to_current_directory() {
+        let directory = directories("fallback", &[]);
+        let executable = directory.join("bin").join("whisky");
+        assert_eq!(
+            resolve_root(None, Some(&executable), Path::new("")),
+            (PathBuf::from("assets"), RootSource::CurrentDirectory)
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_paths_inside_root() {
+        let root = Path::new("/opt/whisky/assets");
+        assert_eq!(
+            path_in(root, Path::new("images/ball.png")),
+            root.join("images/ball.png")
+        );
+        assert_eq!(
+            path_in(root, Path::new("assets/images/sky.png")),
+            root.join("images/sky.png")
+        );
+        assert_eq!(
+            path_in(root, Path::new("/home/player/sky.png")),
+            PathBuf::from("/home/player/sky.png")
+        );
+    }
+
+    #[test]
+    fn test_existing_paths_as_given() {
+        let root = Path::new("/opt/whisky/assets");
+        // tests run in the crate directory, next to the assets shipped with it
+        assert_eq!(
+            path_in(root, Path::new("assets/images/ball.png")),
+            PathBuf::from("assets/images/ball.png")
+        );
+        assert_eq!(
+            path_in(root, Path::new("assets/images/missing.png")),
+            root.join("images/missing.png")
+        );
+    }
+
+    #[test]
+    fn test_levels_next_to_root() {
+        let directory = directories("levels", &["bin"]);
+        let root = directory.join("bin/assets");
+        fs::write(directory.join("bin/shipped.ron"), "").unwrap();
+
+        assert_eq!(
+            level_in(&root, Path::new("shipped.ron")),
+            directory.join("bin/shipped.ron")
+        );
+        // a level which isn't shipped with the game keeps its path, so the error names it
+        assert_eq!(
+            level_in(&root, Path::new("mine.ron")),
+            PathBuf::from("mine.ron")
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_level_path_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let level = |given: &[&str]| level_path_from_args(args(given));
+        assert_eq!(
+            level(&["level1.ron", "--ghost", "friend.ghost"]).as_deref(),
+            Some("level1.ron")
+        );
+        assert_eq!(
+            level(&["--fullscreen", "--ghost", "friend.ghost", "level1.ron"]).as_deref(),
+            Some("level1.ron")
+        );
+        assert_eq!(level(&["--ghost", "friend.ghost"]), None);
+        #[cfg(feature = "soft-render")]
+        assert_eq!(
+            level(&[
+                "--headless",
+                "--render-every",
+                "10",
+                "frames/",
+                "level1.ron"
+            ])
+            .as_deref(),
+            Some("level1.ron")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! picking the sound a collision makes from what the colliding entities are made of
+
+use rand::Rng;
+
+/// collisions with an impulse at least this big are played at full volume
+const LOUDEST_IMPULSE: f64 = 0.2;
+/// the volume and pitch of every sound are randomly varied by up to this fraction
+const VOLUME_VARIATION: f32 = 0.1;
+const PITCH_VARIATION: f32 = 0.05;
+
+/// What an entity sounds like when hit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Material {
+    /// used for shapes drawn by the player and entities with no material set
+    #[default]
+    Wood,
+    Metal,
+    Stone,
+    Rubber,
+}
+
+impl Material {
+    /// parses the name used in level files, `None` if it's not a known material
+    pub fn from_name(name: &str) -> Option<Material> {
+        match name {
+            "wood" => Some(Material::Wood),
+            "metal" => Some(Material::Metal),
+            "stone" => Some(Material::Stone),
+            "rubber" => Some(Material::Rubber),
+            _ => None,
+        }
+    }
+}
+
+/// A group of interchangeable samples, one of which is played for a collision
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SampleSet {
+    Knock,
+    Clunk,
+    Clang,
+    Grind,
+    Bounce,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sound {
+    pub samples: SampleSet,
+    /// from 0 to 1
+    pub volume: f32,
+    /// playback speed multiplier, 1 plays the sample as recorded
+    pub pitch: f32,
+}
+
+/// the sample set played when entities made of `a` and `b` collide, regardless of their order
+pub fn sample_set(a: Material, b: Material) -> SampleSet {
+    use Material::*;
+
+    match (a, b) {
+        (Rubber, _) | (_, Rubber) => SampleSet::Bounce,
+        (Metal, Metal) => SampleSet::Clang,
+        (Stone, Stone) => SampleSet::Grind,
+        (Wood, Wood) => SampleSet::Knock,
+        _ => SampleSet::Clunk,
+    }
+}
+
+/// harder collisions are louder, every sound is varied slightly so repeated hits don't sound
+/// mechanical
+pub fn collision_sound(a: Material, b: Material, impulse: f64, rng: &mut impl Rng) -> Sound {
+    let loudness = (impulse.abs() / LOUDEST_IMPULSE).min(1.0) as f32;
+    let volume = loudness * (1.0 + rng.gen_range(-VOLUME_VARIATION..=VOLUME_VARIATION));
+    let pitch = 1.0 + rng.gen_range(-PITCH_VARIATION..=PITCH_VARIATION);
+
+    Sound {
+        samples: sample_set(a, b),
+        volume: volume.clamp(0.0, 1.0),
+        pitch,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sample_sets() {
+        use Material::*;
+
+        assert_eq!(sample_set(Wood, Wood), SampleSet::Knock);
+        assert_eq!(sample_set(Metal, Metal), SampleSet::Clang);
+        assert_eq!(sample_set(Metal, Stone), SampleSet::Clunk);
+        assert_eq!(sample_set(Stone, Metal), SampleSet::Clunk);
+        assert_eq!(sample_set(Rubber, Metal), SampleSet::Bounce);
+        assert_eq!(sample_set(Wood, Rubber), SampleSet::Bounce);
+    }
+
+    #[test]
+    fn test_volume_is_clamped() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let sound = collision_sound(Material::Metal, Material::Wood, 1e9, &mut rng);
+            assert!(sound.volume <= 1.0);
+            assert!(sound.volume >= 1.0 - VOLUME_VARIATION);
+            assert!((sound.pitch - 1.0).abs() <= PITCH_VARIATION);
+
+            let quiet = collision_sound(Material::Metal, Material::Wood, 0.0, &mut rng);
+            assert_eq!(quiet.volume, 0.0);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! `levels-tool <fmt|upgrade|validate-all|thumbnails> <directory>` runs the operation on every
+//! level in the directory, see `zpr_game_engine::level_tool`, and `levels-tool heatmap <level>`
+//! sums up where the level is lost, see `zpr_game_engine::heatmap`
+
+use std::{env, path::Path, process};
+
+use zpr_game_engine::{
+    heatmap::{self, Heatmap},
+    level_tool,
+};
+
+const USAGE: &str = "usage: levels-tool <fmt|upgrade|validate-all|thumbnails> <directory>
+       levels-tool heatmap <level>";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [operation, argument] = &args[..] else {
+        eprintln!("{USAGE}");
+        process::exit(2);
+    };
+    if operation == "heatmap" {
+        let level = heatmap::level_name(argument);
+        let heatmap = Heatmap::load(Path::new(heatmap::HEATMAP_FILE));
+        println!("{}", heatmap::summary(&level, heatmap.deaths(&level)));
+        process::exit(0);
+    }
+    let directory = Path::new(argument);
+
+    let is_ok = match operation.as_str() {
+        "fmt" => report(
+            level_tool::for_each_level(directory, level_tool::format),
+            |changed| {
+                if changed {
+                    "formatted".to_string()
+                } else {
+                    "already formatted".to_string()
+                }
+            },
+        ),
+        "upgrade" => report(
+            level_tool::for_each_level(directory, level_tool::upgrade),
+            |upgrade| {
+                let mut changes = vec![];
+                if !upgrade.filled_in.is_empty() {
+                    changes.push(format!("filled in {}", upgrade.filled_in.join(", ")));
+                }
+                if !upgrade.migrated.is_empty() {
+                    changes.push(format!("migrated {}", upgrade.migrated.join(", ")));
+                }
+                if changes.is_empty() {
+                    "up to date".to_string()
+                } else {
+                    changes.join("; ")
+                }
+            },
+        ),
+        "validate-all" => {
+            let validations = level_tool::for_each_level(directory, level_tool::validate);
+            print!("{}", level_tool::validation_table(&validations));
+            validations.iter().all(|(_, validation)| validation.is_ok())
+        }
+        #[cfg(feature = "soft-render")]
+        "thumbnails" => {
+            let thumbnails = directory.join(level_tool::THUMBNAIL_DIRECTORY);
+            report(
+                level_tool::for_each_level(directory, |path| {
+                    level_tool::save_thumbnail(path, &thumbnails)
+                }),
+                |thumbnail| format!("saved to {}", thumbnail.display()),
+            )
+        }
+        #[cfg(not(feature = "soft-render"))]
+        "thumbnails" => {
+            eprintln!("thumbnails can't be rendered, levels-tool was built without `soft-render`");
+            process::exit(2);
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    process::exit(if is_ok { 0 } else { 1 });
+}
+
+/// prints a line for each level, returning whether the operation succeeded on all of them
+fn report<T>(
+    results: Vec<(
+        zpr_game_engine::levels::LevelInfo,
+        Result<T, level_tool::ToolError>,
+    )>,
+    describe: impl Fn(T) -> String,
+) -> bool {
+    let mut is_ok = true;
+    for (info, result) in results {
+        match result {
+            Ok(outcome) => println!("{}: {}", info.name, describe(outcome)),
+            Err(err) => {
+                eprintln!("{}: {err}", info.name);
+                is_ok = false;
+            }
+        }
+    }
+    is_ok
+}
@@ -0,0 +1,464 @@
+//! the camera following the ball, which zooms out and looks ahead of it while it moves fast.
+//! It also shows the whole level when it starts and lingers where the ball was lost
+
+use std::time::Duration;
+
+use crate::geometry::{Aabb, Point, Vector};
+
+/// how long the whole level is shown for before the camera moves onto the ball
+const OVERVIEW_TIME: Duration = Duration::from_secs(1);
+/// the level overview leaves this much of the view empty around the level on every side
+const OVERVIEW_MARGIN: f64 = 0.1;
+/// how long the camera stays where the ball was lost before cutting back to the ball
+const DEATH_CAM_TIME: Duration = Duration::from_millis(300);
+
+/// How the camera reacts to the speed of the ball
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    /// the ball has to move faster than this, in units per second, for the camera to zoom out
+    pub zoom_speed_threshold: f64,
+    /// how much the zoom drops for every unit per second above the threshold
+    pub zoom_per_speed: f64,
+    /// the furthest the camera zooms out, a zoom of 0.5 shows twice as much of the level
+    pub min_zoom: f64,
+    /// how many seconds ahead along its velocity the camera looks at the ball
+    pub look_ahead_time: f64,
+    /// the furthest the camera looks ahead, as a fraction of the distance from the center of
+    /// the view to its edge
+    pub max_look_ahead: f64,
+    /// roughly how many seconds the camera takes to settle after the ball changes its speed
+    pub smoothing_time: f64,
+    /// keeps the view centered on the ball and cuts between shots instead of easing, for
+    /// players who get motion sick
+    pub reduce_motion: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            zoom_speed_threshold: 0.5,
+            zoom_per_speed: 0.4,
+            min_zoom: 0.5,
+            look_ahead_time: 0.25,
+            max_look_ahead: 0.3,
+            smoothing_time: 0.3,
+            reduce_motion: false,
+        }
+    }
+}
+
+/// A value eased towards a target with a critically damped spring, so it neither jerks when
+/// the target jumps nor overshoots it
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Spring<T> {
+    value: T,
+    velocity: T,
+}
+
+impl<T> Spring<T>
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f64, Output = T>,
+{
+    fn new(value: T, zero: T) -> Self {
+        Spring {
+            value,
+            velocity: zero,
+        }
+    }
+
+    /// moves the value `dt` seconds towards `target`, stable however long the step is
+    fn update(&mut self, target: T, smoothing_time: f64, dt: f64) {
+        let omega = 2.0 / smoothing_time.max(f64::EPSILON);
+        let x = omega * dt;
+        // an approximation of e^-x, exact enough for the steps of a frame
+        let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+        let offset = self.value - target;
+        let change = (self.velocity + offset * omega) * dt;
+        self.velocity = (self.velocity - change * omega) * decay;
+        self.value = target + (offset + change) * decay;
+    }
+}
+
+/// How the level is shown in a frame, which the shapes as displayed are moved and scaled by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    /// the point in the middle of the window
+    pub center: Point,
+    /// see [`Camera::zoom`]
+    pub zoom: f64,
+}
+
+impl Default for View {
+    /// the level as it's laid out, as if there was no camera
+    fn default() -> Self {
+        View {
+            center: Point(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+impl View {
+    /// where the point, as displayed, is shown in the window, which spans from -1 to 1 both ways
+    pub fn to_window(self, point: Point) -> Point {
+        (point - self.center) * self.zoom
+    }
+
+    /// the point, as displayed, shown at the point of the window, the opposite of `to_window`
+    pub fn to_display(self, point: Point) -> Point {
+        point * (1.0 / self.zoom) + self.center
+    }
+
+    /// the part of the level, as displayed, which is in the window
+    pub fn visible(self) -> Aabb {
+        let half_size = Point(1.0, 1.0) * (1.0 / self.zoom);
+        Aabb {
+            min: self.center - half_size,
+            max: self.center + half_size,
+        }
+    }
+}
+
+/// What the camera is showing instead of just following the ball
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Shot {
+    Follow,
+    /// the whole level, until the time runs out
+    Overview {
+        bounds: Aabb,
+        left: Duration,
+    },
+    /// the place the ball was lost at, until the time runs out
+    DeathCam {
+        at: Point,
+        left: Duration,
+    },
+}
+
+/// Follows the ball, updated once for every frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub settings: CameraSettings,
+    /// how far the camera looks ahead of the ball
+    look_ahead: Spring<Vector>,
+    /// how far from the ball the camera looks while it's showing something else
+    offset: Spring<Vector>,
+    zoom: Spring<f64>,
+    target: Point,
+    shot: Shot,
+}
+
+impl Camera {
+    pub fn new(settings: CameraSettings, target: Point) -> Self {
+        Camera {
+            settings,
+            look_ahead: Spring::new(Point(0.0, 0.0), Point(0.0, 0.0)),
+            offset: Spring::new(Point(0.0, 0.0), Point(0.0, 0.0)),
+            zoom: Spring::new(1.0, 0.0),
+            target,
+            shot: Shot::Follow,
+        }
+    }
+
+    /// shows the whole of a level which just started, see `DisplayMessage::level_bounds`,
+    /// before moving onto the ball at `target`
+    pub fn start_level(&mut self, bounds: Aabb, target: Point) {
+        let Point(width, height) = bounds.max - bounds.min;
+        let half_size = width.max(height) / 2.0 + OVERVIEW_MARGIN;
+        self.target = target;
+        self.look_ahead = Spring::new(Point(0.0, 0.0), Point(0.0, 0.0));
+        self.offset = Spring::new((bounds.min + bounds.max) * 0.5 - target, Point(0.0, 0.0));
+        // small levels are shown at the usual zoom rather than blown up
+        self.zoom = Spring::new((1.0 / half_size).min(1.0), 0.0);
+        self.shot = Shot::Overview {
+            bounds,
+            left: OVERVIEW_TIME,
+        };
+    }
+
+    /// stays where the ball was lost for a moment before cutting back to it, or cuts back
+    /// right away when reducing motion
+    pub fn ball_lost(&mut self, at: Point) {
+        if self.settings.reduce_motion {
+            self.cut_to_ball();
+            return;
+        }
+        self.offset = Spring::new(at - self.target, Point(0.0, 0.0));
+        self.look_ahead = Spring::new(Point(0.0, 0.0), Point(0.0, 0.0));
+        self.shot = Shot::DeathCam {
+            at,
+            left: DEATH_CAM_TIME,
+        };
+    }
+
+    fn cut_to_ball(&mut self) {
+        self.offset = Spring::new(Point(0.0, 0.0), Point(0.0, 0.0));
+        self.look_ahead = Spring::new(Point(0.0, 0.0), Point(0.0, 0.0));
+        self.shot = Shot::Follow;
+    }
+
+    /// follows the ball at `target` moving with `velocity`, in units per second, for `dt`
+    pub fn update(&mut self, target: Point, velocity: Vector, dt: Duration) {
+        self.target = target;
+        match self.shot {
+            Shot::Follow => {}
+            Shot::Overview { bounds, left } => {
+                // the ball may move while the level is shown, the view mustn't
+                let center = (bounds.min + bounds.max) * 0.5;
+                self.offset = Spring::new(center - target, Point(0.0, 0.0));
+                let left = left.saturating_sub(dt);
+                self.shot = Shot::Overview { bounds, left };
+                if left.is_zero() {
+                    if self.settings.reduce_motion {
+                        self.zoom = Spring::new(self.target_zoom(velocity), 0.0);
+                        self.cut_to_ball();
+                    } else {
+                        self.shot = Shot::Follow;
+                    }
+                }
+                return;
+            }
+            Shot::DeathCam { at, left } => {
+                self.offset = Spring::new(at - target, Point(0.0, 0.0));
+                let left = left.saturating_sub(dt);
+                self.shot = Shot::DeathCam { at, left };
+                if left.is_zero() {
+                    self.cut_to_ball();
+                }
+                return;
+            }
+        }
+
+        let dt = dt.as_secs_f64();
+        let settings = self.settings;
+        self.zoom
+            .update(self.target_zoom(velocity), settings.smoothing_time, dt);
+        let look_ahead = if settings.reduce_motion {
+            Point(0.0, 0.0)
+        } else {
+            self.target_look_ahead(velocity)
+        };
+        self.look_ahead
+            .update(look_ahead, settings.smoothing_time, dt);
+        self.offset
+            .update(Point(0.0, 0.0), settings.smoothing_time, dt);
+    }
+
+    /// the zoom the camera settles at for a ball moving with `velocity`
+    fn target_zoom(&self, velocity: Vector) -> f64 {
+        let settings = &self.settings;
+        let excess = (velocity.norm() - settings.zoom_speed_threshold).max(0.0);
+        (1.0 - excess * settings.zoom_per_speed).clamp(settings.min_zoom.min(1.0), 1.0)
+    }
+
+    /// where ahead of the ball the camera settles for a ball moving with `velocity`
+    fn target_look_ahead(&self, velocity: Vector) -> Vector {
+        let settings = &self.settings;
+        let look_ahead = velocity * settings.look_ahead_time;
+        // relative to the view the camera settles at, so it doesn't lag behind the zoom
+        let max = settings.max_look_ahead / self.target_zoom(velocity);
+        if look_ahead.norm() > max {
+            look_ahead.unit() * max
+        } else {
+            look_ahead
+        }
+    }
+
+    /// at a zoom of 1 the view reaches 1 away from its center, smaller zooms show more
+    pub fn zoom(&self) -> f64 {
+        self.zoom.value
+    }
+
+    /// the point the camera looks at, ahead of the ball when it's moving fast
+    pub fn center(&self) -> Point {
+        self.target + self.look_ahead.value + self.offset.value
+    }
+
+    /// how the level is shown, with the view kept inside `bounds`, the camera bounds of the
+    /// level as displayed, see [`crate::levels::Level::camera_center`]
+    pub fn view(&self, bounds: Aabb) -> View {
+        let zoom = self.zoom();
+        let half_size = 1.0 / zoom;
+        View {
+            center: bounds.clamp_view(self.center(), Point(half_size, half_size)),
+            zoom,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn test_spring_converges_without_overshooting() {
+        let mut spring = Spring::new(0.0, 0.0);
+        let mut previous = 0.0;
+        for _ in 0..200 {
+            spring.update(1.0, 0.3, FRAME.as_secs_f64());
+            assert!(spring.value >= previous && spring.value <= 1.0);
+            previous = spring.value;
+        }
+        assert!((spring.value - 1.0).abs() < 1e-3);
+
+        // a single long step doesn't blow up either
+        let mut spring = Spring::new(0.0, 0.0);
+        spring.update(1.0, 0.3, 10.0);
+        assert!((spring.value - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_fast_ball_zooms_out_and_looks_ahead() {
+        let mut camera = Camera::new(CameraSettings::default(), Point(0.0, 0.0));
+        let velocity = Point(1.0, 0.0);
+        let mut previous_zoom = camera.zoom();
+        for i in 0..200 {
+            let ball = velocity * (i as f64 * FRAME.as_secs_f64());
+            camera.update(ball, velocity, FRAME);
+            assert!(camera.zoom() <= previous_zoom);
+            previous_zoom = camera.zoom();
+        }
+        let ball = velocity * (199.0 * FRAME.as_secs_f64());
+        assert!((camera.zoom() - 0.8).abs() < 1e-3);
+        assert!(camera.center().is_close_enough_to(ball + Point(0.25, 0.0)));
+
+        // slowing down brings the camera back onto the ball
+        for _ in 0..200 {
+            camera.update(ball, Point(0.0, 0.0), FRAME);
+        }
+        assert!((camera.zoom() - 1.0).abs() < 1e-3);
+        assert!((camera.center() - ball).norm() < 1e-3);
+    }
+
+    #[test]
+    fn test_zoom_and_look_ahead_are_clamped() {
+        let settings = CameraSettings::default();
+        let mut camera = Camera::new(settings, Point(0.0, 0.0));
+        for _ in 0..300 {
+            camera.update(Point(0.0, 0.0), Point(0.0, -50.0), FRAME);
+        }
+        assert!((camera.zoom() - settings.min_zoom).abs() < 1e-3);
+        let look_ahead = settings.max_look_ahead / settings.min_zoom;
+        assert!((camera.center() - Point(0.0, -look_ahead)).norm() < 1e-3);
+
+        let mut camera = Camera::new(
+            CameraSettings {
+                reduce_motion: true,
+                ..settings
+            },
+            Point(0.0, 0.0),
+        );
+        for _ in 0..300 {
+            camera.update(Point(0.0, 0.0), Point(0.0, -50.0), FRAME);
+        }
+        assert_eq!(camera.center(), Point(0.0, 0.0));
+        assert!((camera.zoom() - settings.min_zoom).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_view_is_kept_inside_bounds() {
+        let bounds = Aabb {
+            min: Point(-3.0, -1.0),
+            max: Point(3.0, 1.0),
+        };
+        let mut camera = Camera::new(CameraSettings::default(), Point(2.5, 0.5));
+        let view = camera.view(bounds);
+        // the level is as high as the view, so it's only moved sideways
+        assert_eq!(view.center, Point(2.0, 0.0));
+        assert_eq!(
+            view.visible(),
+            Aabb {
+                min: Point(1.0, -1.0),
+                max: Point(3.0, 1.0),
+            }
+        );
+
+        let at = Point(2.5, -0.5);
+        assert_eq!(view.to_window(at), Point(0.5, -0.5));
+        assert!(view.to_display(view.to_window(at)).is_close_enough_to(at));
+
+        for _ in 0..300 {
+            camera.update(Point(0.0, 0.0), Point(0.0, -50.0), FRAME);
+        }
+        let view = camera.view(bounds);
+        assert!((view.zoom - 0.5).abs() < 1e-3);
+        assert!(view
+            .to_window(Point(0.0, -2.0))
+            .is_close_enough_to(Point(0.0, -1.0)));
+    }
+
+    fn frames(camera: &mut Camera, ball: Point, duration: Duration) {
+        let n = duration.as_millis() / FRAME.as_millis();
+        for _ in 0..n {
+            camera.update(ball, Point(0.0, 0.0), FRAME);
+        }
+    }
+
+    #[test]
+    fn test_level_overview_eases_onto_ball() {
+        let bounds = Aabb {
+            min: Point(-2.0, -1.0),
+            max: Point(2.0, 1.0),
+        };
+        let ball = Point(-1.5, 0.0);
+        let mut camera = Camera::new(CameraSettings::default(), ball);
+        camera.start_level(bounds, ball);
+        frames(&mut camera, ball, Duration::from_millis(500));
+        assert!(camera.center().is_close_enough_to(Point(0.0, 0.0)));
+        assert!((camera.zoom() - 1.0 / 2.1).abs() < 1e-9);
+
+        frames(&mut camera, ball, Duration::from_millis(500));
+        let mut distance = camera.center().to(ball).norm();
+        for _ in 0..200 {
+            camera.update(ball, Point(0.0, 0.0), FRAME);
+            assert!(camera.center().to(ball).norm() <= distance);
+            distance = camera.center().to(ball).norm();
+        }
+        assert!(distance < 1e-3);
+        assert!((camera.zoom() - 1.0).abs() < 1e-3);
+
+        // reducing motion cuts straight to the ball once the level has been shown
+        let mut camera = Camera::new(
+            CameraSettings {
+                reduce_motion: true,
+                ..Default::default()
+            },
+            ball,
+        );
+        camera.start_level(bounds, ball);
+        frames(&mut camera, ball, Duration::from_millis(992));
+        assert!(camera.center().is_close_enough_to(Point(0.0, 0.0)));
+        camera.update(ball, Point(0.0, 0.0), FRAME);
+        assert_eq!(camera.center(), ball);
+        assert_eq!(camera.zoom(), 1.0);
+    }
+
+    #[test]
+    fn test_death_cam_holds_then_cuts_to_ball() {
+        let spawn = Point(0.0, 0.5);
+        let lost_at = Point(1.0, -3.0);
+        for reduce_motion in [false, true] {
+            let settings = CameraSettings {
+                reduce_motion,
+                ..Default::default()
+            };
+            let mut camera = Camera::new(settings, lost_at);
+            camera.ball_lost(lost_at);
+            if reduce_motion {
+                camera.update(spawn, Point(0.0, 0.0), FRAME);
+                assert_eq!(camera.center(), spawn);
+                continue;
+            }
+
+            frames(&mut camera, spawn, Duration::from_millis(288));
+            assert!(camera.center().is_close_enough_to(lost_at));
+            camera.update(spawn, Point(0.0, 0.0), FRAME);
+            assert_eq!(camera.center(), spawn);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! commands typed into the in-game console while developing levels, e.g. `spawn circle 0.5 0.5 0.1`
+
+use crate::{
+    geometry::{Point, Vector},
+    physics::{
+        shape::{Circle, Polygon},
+        Engine,
+    },
+};
+
+/// the name of every command along with its arguments, as listed by `help`
+pub const COMMANDS: [(&str, &str); 9] = [
+    (
+        "spawn",
+        "spawn circle <x> <y> <radius> | spawn box <x> <y> <width> <height>",
+    ),
+    ("gravity", "gravity <x> <y>, e.g. gravity 0 -0.000002"),
+    ("tilt", "tilt <degrees>"),
+    ("jump", "jump"),
+    ("blast", "blast <x> <y> <strength> <radius>"),
+    ("reset", "reset"),
+    ("clear", "clear"),
+    ("debug", "debug"),
+    ("help", "help"),
+];
+
+/// A command typed into the console, parsed and ready to be run by the engine
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    SpawnCircle {
+        center: Point,
+        radius: f64,
+    },
+    SpawnBox {
+        center: Point,
+        width: f64,
+        height: f64,
+    },
+    /// points gravity along the vector by tilting the world, its length becomes the
+    /// gravity coefficient
+    Gravity(Vector),
+    /// tilts the world to the angle, in degrees
+    Tilt(f64),
+    Jump,
+    /// pushes everything within the radius away from the center
+    Blast {
+        center: Point,
+        strength: f64,
+        radius: f64,
+    },
+    /// returns the ball to the start
+    Reset,
+    /// erases everything the player has drawn
+    Clear,
+    /// toggles showing the load on every binding
+    Debug,
+    Help,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ConsoleError {
+    #[error("unknown command \"{0}\", type help to list them")]
+    UnknownCommand(String),
+    #[error("usage: {0}")]
+    Usage(&'static str),
+}
+
+/// parses a line typed into the console
+pub fn parse(line: &str) -> Result<Command, ConsoleError> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let Some(&(_, usage)) = COMMANDS.iter().find(|(command, _)| *command == name) else {
+        return Err(ConsoleError::UnknownCommand(name.to_string()));
+    };
+    let kind = if name == "spawn" { words.next() } else { None };
+    let numbers = words
+        .map(str::parse)
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| ConsoleError::Usage(usage))?;
+    if numbers.iter().any(|number| !number.is_finite()) {
+        return Err(ConsoleError::Usage(usage));
+    }
+
+    let command = match (name, kind, numbers.as_slice()) {
+        ("spawn", Some("circle"), &[x, y, radius]) if radius > 0.0 => Command::SpawnCircle {
+            center: Point(x, y),
+            radius,
+        },
+        ("spawn", Some("box"), &[x, y, width, height]) if width > 0.0 && height > 0.0 => {
+            Command::SpawnBox {
+                center: Point(x, y),
+                width,
+                height,
+            }
+        }
+        ("gravity", _, &[x, y]) => Command::Gravity(Point(x, y)),
+        ("tilt", _, &[degrees]) => Command::Tilt(degrees),
+        ("jump", _, []) => Command::Jump,
+        ("blast", _, &[x, y, strength, radius]) if radius > 0.0 => Command::Blast {
+            center: Point(x, y),
+            strength,
+            radius,
+        },
+        ("reset", _, []) => Command::Reset,
+        ("clear", _, []) => Command::Clear,
+        ("debug", _, []) => Command::Debug,
+        ("help", _, []) => Command::Help,
+        _ => return Err(ConsoleError::Usage(usage)),
+    };
+    Ok(command)
+}
+
+impl Command {
+    /// runs the command, `help` prints the list of commands
+    pub fn apply(self, engine: &mut Engine) {
+        match self {
+            Self::SpawnCircle { center, radius } => {
+                if let Err(err) = engine.add_circle(Circle::new(center, radius)) {
+                    eprintln!("{err}");
+                }
+            }
+            Self::SpawnBox {
+                center,
+                width,
+                height,
+            } => {
+                let Point(x, y) = Point(width, height) * 0.5;
+                let spawned = engine.add_polygon(Polygon::new(vec![
+                    center + Point(-x, -y),
+                    center + Point(x, -y),
+                    center + Point(x, y),
+                    center + Point(-x, y),
+                ]));
+                if let Err(err) = spawned {
+                    eprintln!("{err}");
+                }
+            }
+            Self::Gravity(gravity) => {
+                let mut params = engine.params();
+                params.gravity_coefficient = -gravity.norm();
+                engine.set_params(params);
+                if gravity.norm() > 0.0 {
+                    // gravity points down once the world is tilted by the angle
+                    let angle = -Point(0.0, -1.0).angle_to(gravity);
+                    engine.set_target_angle(angle as f32);
+                }
+            }
+            Self::Tilt(degrees) => engine.set_target_angle(degrees.to_radians() as f32),
+            Self::Jump => engine.jump(),
+            Self::Blast {
+                center,
+                strength,
+                radius,
+            } => engine.apply_radial_impulse(center, strength, radius),
+            Self::Reset => engine.reset_level(),
+            Self::Clear => engine.clear_drawn(),
+            Self::Debug => {
+                engine.toggle_debugging();
+                println!("playing with the {} physics", engine.params().preset);
+                println!(
+                    "enforcing the bindings {} times per step",
+                    engine.binding_substeps()
+                );
+                println!(
+                    "{} of at most {} entities",
+                    engine.entity_ids().len(),
+                    engine.params().max_entities
+                );
+            }
+            Self::Help => {
+                for (_, usage) in COMMANDS {
+                    println!("{usage}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{levels::Level, physics::EngineBuilder};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("spawn circle 0.5 0.5 0.1"),
+            Ok(Command::SpawnCircle {
+                center: Point(0.5, 0.5),
+                radius: 0.1
+            })
+        );
+        assert_eq!(
+            parse("  spawn box 0 1 0.2 0.1 "),
+            Ok(Command::SpawnBox {
+                center: Point(0.0, 1.0),
+                width: 0.2,
+                height: 0.1
+            })
+        );
+        assert_eq!(
+            parse("gravity 0 -0.00001"),
+            Ok(Command::Gravity(Point(0.0, -0.00001)))
+        );
+        assert_eq!(parse("tilt 15"), Ok(Command::Tilt(15.0)));
+        assert_eq!(
+            parse("blast 0 0.5 0.01 0.3"),
+            Ok(Command::Blast {
+                center: Point(0.0, 0.5),
+                strength: 0.01,
+                radius: 0.3
+            })
+        );
+        assert_eq!(parse("reset"), Ok(Command::Reset));
+        assert_eq!(parse("debug"), Ok(Command::Debug));
+
+        assert_eq!(
+            parse("teleport 1 2"),
+            Err(ConsoleError::UnknownCommand("teleport".to_string()))
+        );
+        assert_eq!(parse(""), Err(ConsoleError::UnknownCommand(String::new())));
+        assert!(matches!(parse("tilt"), Err(ConsoleError::Usage(_))));
+        assert!(matches!(parse("tilt up"), Err(ConsoleError::Usage(_))));
+        assert!(matches!(parse("tilt NaN"), Err(ConsoleError::Usage(_))));
+        assert!(matches!(parse("reset now"), Err(ConsoleError::Usage(_))));
+        assert!(matches!(
+            parse("blast 0 0 0.01 0"),
+            Err(ConsoleError::Usage(_))
+        ));
+        assert!(matches!(
+            parse("spawn circle 0 0 -1"),
+            Err(ConsoleError::Usage(_))
+        ));
+        assert!(matches!(
+            parse("spawn triangle 0 0 1"),
+            Err(ConsoleError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_commands_call_engine() {
+        let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5))).build();
+
+        parse("spawn circle 0.5 0.5 0.1")
+            .unwrap()
+            .apply(&mut engine);
+        parse("spawn box -0.5 0.5 0.2 0.1")
+            .unwrap()
+            .apply(&mut engine);
+        let ids = engine.entity_ids();
+        assert_eq!(ids.len(), 3);
+        assert!(engine
+            .entity_position(ids[1])
+            .unwrap()
+            .is_close_enough_to(Point(0.5, 0.5)));
+        assert!(engine
+            .entity_position(ids[2])
+            .unwrap()
+            .is_close_enough_to(Point(-0.5, 0.5)));
+
+        parse("gravity 0.00001 0").unwrap().apply(&mut engine);
+        assert_eq!(engine.params().gravity_coefficient, -0.00001);
+        for _ in 0..200 {
+            engine.step(Duration::from_millis(10));
+        }
+        assert!(engine
+            .gravity_direction()
+            .is_close_enough_to(Point(1.0, 0.0)));
+
+        parse("clear").unwrap().apply(&mut engine);
+        assert_eq!(engine.entity_ids(), vec![engine.main_ball_id()]);
+    }
+}
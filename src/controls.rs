@@ -0,0 +1,141 @@
+//! the ways the player can tilt the world, only one of which is listened to at a time
+
+use crate::InputMessage;
+
+/// An input which tilts the world if it comes from the control scheme in use
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TiltInput {
+    /// the cursor moved sideways while the mouse tilts, already scaled by the sensitivity
+    Mouse(f32),
+    /// an arrow key was pressed or is held down
+    Keys(f32),
+    /// the phone turned, already scaled by `PhoneConfig`
+    Phone(f32),
+}
+
+/// Where the tilt of the world comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlScheme {
+    #[default]
+    Mouse,
+    Keyboard,
+    Phone,
+}
+
+impl ControlScheme {
+    /// the scheme after this one, for switching between them with a single key
+    pub fn next(self) -> Self {
+        match self {
+            Self::Mouse => Self::Keyboard,
+            Self::Keyboard => Self::Phone,
+            Self::Phone => Self::Mouse,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mouse => "mouse",
+            Self::Keyboard => "keyboard",
+            Self::Phone => "phone",
+        }
+    }
+
+    /// the message the input turns into, `None` if it comes from another scheme
+    pub fn map(self, input: TiltInput) -> Option<InputMessage> {
+        match (self, input) {
+            (Self::Mouse, TiltInput::Mouse(angle))
+            | (Self::Keyboard, TiltInput::Keys(angle))
+            | (Self::Phone, TiltInput::Phone(angle)) => Some(InputMessage::Angle(angle)),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps track of the control scheme in use. Connecting a phone switches to it and
+/// disconnecting it switches back to whatever was used before
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Controls {
+    scheme: ControlScheme,
+    // the scheme to return to once the phone disconnects
+    before_phone: Option<ControlScheme>,
+}
+
+impl Controls {
+    pub fn new(scheme: ControlScheme) -> Self {
+        Controls {
+            scheme,
+            before_phone: None,
+        }
+    }
+
+    pub fn scheme(&self) -> ControlScheme {
+        self.scheme
+    }
+
+    /// switches to the scheme, even while a phone is connected
+    pub fn select(&mut self, scheme: ControlScheme) {
+        self.scheme = scheme;
+        self.before_phone = None;
+    }
+
+    pub fn set_phone_connected(&mut self, is_connected: bool) {
+        if is_connected && self.scheme != ControlScheme::Phone {
+            self.before_phone = Some(self.scheme);
+            self.scheme = ControlScheme::Phone;
+        } else if !is_connected {
+            if let Some(scheme) = self.before_phone.take() {
+                self.scheme = scheme;
+            }
+        }
+    }
+
+    /// see [`ControlScheme::map`]
+    pub fn map(&self, input: TiltInput) -> Option<InputMessage> {
+        self.scheme.map(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn angle(message: Option<InputMessage>) -> Option<f32> {
+        match message? {
+            InputMessage::Angle(angle) => Some(angle),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_only_active_scheme_tilts() {
+        let inputs = [
+            TiltInput::Mouse(0.1),
+            TiltInput::Keys(0.2),
+            TiltInput::Phone(0.3),
+        ];
+        let mut controls = Controls::default();
+        let tilts = |controls: &Controls| -> Vec<_> {
+            inputs
+                .iter()
+                .filter_map(|&input| angle(controls.map(input)))
+                .collect()
+        };
+        assert_eq!(tilts(&controls), vec![0.1]);
+
+        controls.select(controls.scheme().next());
+        assert_eq!(controls.scheme(), ControlScheme::Keyboard);
+        assert_eq!(tilts(&controls), vec![0.2]);
+
+        // the phone takes over while it's connected
+        controls.set_phone_connected(true);
+        assert_eq!(tilts(&controls), vec![0.3]);
+        controls.set_phone_connected(false);
+        assert_eq!(tilts(&controls), vec![0.2]);
+
+        // unless the player picks something else while it is
+        controls.set_phone_connected(true);
+        controls.select(ControlScheme::Mouse);
+        controls.set_phone_connected(false);
+        assert_eq!(tilts(&controls), vec![0.1]);
+    }
+}
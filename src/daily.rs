@@ -0,0 +1,348 @@
+//! the daily challenge level, downloaded once a day and cached on disk, along with the best
+//! time each day's level was finished in
+
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crossbeam::channel::Sender;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    levels::{self, Level, LevelInfo},
+    persist::{self, Persist},
+};
+
+/// base url of the server hosting `daily.ron`
+pub const URL_VARIABLE: &str = "WHISKY_DAILY_URL";
+/// the directory the daily levels are cached in, in the directory the game is run from
+pub const CACHE_DIRECTORY: &str = "daily";
+/// the file the daily completion times are kept in, in the directory the game is run from
+pub const RECORDS_FILE: &str = "daily_records.ron";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DailyError {
+    #[error("couldn't download today's level and there is no earlier one cached: {0}")]
+    Fetch(String),
+    #[error("today's level can't be played and there is no earlier one cached: {0}")]
+    Invalid(String),
+    #[error("couldn't cache today's level: {0}")]
+    Cache(#[from] io::Error),
+}
+
+/// A daily level ready to be loaded with `Level::load_from_file`
+#[derive(Debug, PartialEq)]
+pub struct Daily {
+    /// the day the level was published for, `YYYY-MM-DD`
+    pub date: String,
+    pub path: PathBuf,
+    /// true if today's level couldn't be downloaded and an older one is used instead
+    pub is_stale: bool,
+}
+
+/// The best time the daily level was finished in, kept apart from other levels' ghosts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Records {
+    /// in milliseconds, keyed by the day of the level, `YYYY-MM-DD`
+    times_ms: BTreeMap<String, u64>,
+}
+
+impl Persist for Records {
+    const VERSION: u32 = 1;
+}
+
+impl Records {
+    /// the times saved in the file, none if there's no file yet or it can't be read, see
+    /// [`persist::load_or_quarantine`]
+    pub fn load(path: &Path) -> Self {
+        persist::load_or_quarantine(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        persist::save(path, self)
+    }
+
+    /// keeps the time if the level of the day hasn't been finished faster, returning whether
+    /// it's the new best
+    pub fn record(&mut self, date: &str, time: Duration) -> bool {
+        let time_ms = time.as_millis() as u64;
+        match self.times_ms.get(date) {
+            Some(&best) if best <= time_ms => false,
+            _ => {
+                self.times_ms.insert(date.to_string(), time_ms);
+                true
+            }
+        }
+    }
+
+    pub fn best(&self, date: &str) -> Option<Duration> {
+        self.times_ms.get(date).copied().map(Duration::from_millis)
+    }
+}
+
+/// the day of the daily level at the path, `None` if it isn't one from `cache_directory`
+pub fn date_of(path: &Path, cache_directory: &Path) -> Option<String> {
+    if path.parent()? != cache_directory {
+        return None;
+    }
+    Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+/// the levels in `directory`, see [`levels::list_levels`], followed by the daily level of
+/// `date` if it has been downloaded, so it can be picked along with the others
+pub fn list_levels(directory: &Path, cache_directory: &Path, date: &str) -> Vec<LevelInfo> {
+    let mut levels = levels::list_levels(directory);
+    let path = cache_directory.join(format!("{date}.ron"));
+    if path.is_file() {
+        levels.push(LevelInfo {
+            name: date.to_string(),
+            path,
+        });
+    }
+    levels
+}
+
+/// fetches today's level on a separate thread, so a slow connection never stalls the game
+pub fn fetch_daily_level(channel: Sender<Result<Daily, DailyError>>) {
+    std::thread::spawn(move || {
+        let result = load(Path::new(CACHE_DIRECTORY), &today(), || {
+            let base = env::var(URL_VARIABLE)
+                .map_err(|_| format!("the {URL_VARIABLE} environment variable is not set"))?;
+            download(&format!("{}/daily.ron", base.trim_end_matches('/')))
+        });
+        let _ = channel.send(result);
+    });
+}
+
+#[cfg(feature = "net")]
+fn download(url: &str) -> Result<String, String> {
+    ureq::get(url)
+        .call()
+        .map_err(|err| err.to_string())?
+        .into_string()
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "net"))]
+fn download(_url: &str) -> Result<String, String> {
+    Err("the game was built without the `net` feature".to_string())
+}
+
+/// returns the cached level for `date`, or fetches, validates and caches it.
+/// When that fails the most recent cached level is used instead
+pub fn load(
+    cache_directory: &Path,
+    date: &str,
+    fetch: impl FnOnce() -> Result<String, String>,
+) -> Result<Daily, DailyError> {
+    let path = cache_directory.join(format!("{date}.ron"));
+    if path.is_file() {
+        return Ok(Daily {
+            date: date.to_string(),
+            path,
+            is_stale: false,
+        });
+    }
+
+    let failure = match fetch() {
+        Ok(text) => match parse(&text) {
+            Ok(()) => {
+                fs::create_dir_all(cache_directory)?;
+                fs::write(&path, text)?;
+                return Ok(Daily {
+                    date: date.to_string(),
+                    path,
+                    is_stale: false,
+                });
+            }
+            Err(err) => DailyError::Invalid(err),
+        },
+        Err(err) => DailyError::Fetch(err),
+    };
+
+    latest_cached(cache_directory).ok_or(failure)
+}
+
+fn parse(text: &str) -> Result<(), String> {
+    let level: Level = ron::from_str(text).map_err(|err| err.to_string())?;
+    level.validate().map_err(|err| err.to_string())
+}
+
+fn latest_cached(cache_directory: &Path) -> Option<Daily> {
+    // the dates are zero padded, so they sort the same way as the file names
+    let path = fs::read_dir(cache_directory)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .max()?;
+
+    Some(Daily {
+        date: path.file_stem()?.to_string_lossy().into_owned(),
+        path,
+        is_stale: true,
+    })
+}
+
+/// the current UTC date as `YYYY-MM-DD`
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// converts days since 1970-01-01 to a proleptic gregorian calendar date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VALID: &str = "(
+        initial_ball_position: (0.0, 0.5),
+        circles: [],
+        polygons: [(shape: [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], is_static: true, is_bindable: false)],
+        flags_positions: [],
+    )";
+    const DEGENERATE: &str = "(
+        initial_ball_position: (0.0, 0.5),
+        circles: [],
+        polygons: [(shape: [(0.0, 0.0), (1.0, 0.0)], is_static: true, is_bindable: false)],
+        flags_positions: [],
+    )";
+
+    fn cache_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("whisky-daily-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20742), (2026, 10, 16));
+    }
+
+    #[test]
+    fn test_fetched_level_is_cached() {
+        let directory = cache_directory("cached");
+
+        let daily = load(&directory, "2026-10-16", || Ok(VALID.to_string())).unwrap();
+        assert_eq!(daily.path, directory.join("2026-10-16.ron"));
+        assert!(!daily.is_stale);
+        assert!(Level::load_from_file(&daily.path).is_ok());
+
+        // the second time around the server isn't asked at all
+        let again = load(&directory, "2026-10-16", || panic!("fetched twice")).unwrap();
+        assert_eq!(again, daily);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_failures_fall_back_to_latest_cached() {
+        let directory = cache_directory("fallback");
+
+        assert!(matches!(
+            load(&directory, "2026-10-14", || Err("offline".to_string())),
+            Err(DailyError::Fetch(_))
+        ));
+        load(&directory, "2026-10-14", || Ok(VALID.to_string())).unwrap();
+        load(&directory, "2026-10-15", || Ok(VALID.to_string())).unwrap();
+
+        let offline = load(&directory, "2026-10-16", || Err("offline".to_string())).unwrap();
+        assert_eq!(offline.date, "2026-10-15");
+        assert!(offline.is_stale);
+
+        let invalid = load(&directory, "2026-10-16", || Ok(DEGENERATE.to_string())).unwrap();
+        assert_eq!(invalid.date, "2026-10-15");
+        assert!(!directory.join("2026-10-16.ron").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_level_is_not_cached() {
+        let directory = cache_directory("invalid");
+
+        assert!(matches!(
+            load(&directory, "2026-10-16", || Ok(DEGENERATE.to_string())),
+            Err(DailyError::Invalid(_))
+        ));
+        assert!(!directory.join("2026-10-16.ron").exists());
+    }
+
+    #[test]
+    fn test_records_keep_best_time_of_each_day() {
+        let directory = cache_directory("records");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(RECORDS_FILE);
+
+        let mut records = Records::load(&path);
+        assert_eq!(records.best("2026-10-16"), None);
+        assert!(records.record("2026-10-16", Duration::from_millis(9000)));
+        assert!(!records.record("2026-10-16", Duration::from_millis(9500)));
+        assert!(records.record("2026-10-16", Duration::from_millis(8000)));
+        // another day's level is timed on its own
+        assert!(records.record("2026-10-17", Duration::from_millis(12000)));
+        records.save(&path).unwrap();
+
+        let loaded = Records::load(&path);
+        assert_eq!(loaded, records);
+        assert_eq!(loaded.best("2026-10-16"), Some(Duration::from_millis(8000)));
+        assert_eq!(
+            loaded.best("2026-10-17"),
+            Some(Duration::from_millis(12000))
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_cached_daily_is_listed_with_levels() {
+        let directory = cache_directory("listed");
+        let levels = directory.join("levels");
+        let cache = directory.join(CACHE_DIRECTORY);
+        fs::create_dir_all(&levels).unwrap();
+        fs::write(levels.join("level1.ron"), VALID).unwrap();
+
+        let names = |date| {
+            list_levels(&levels, &cache, date)
+                .into_iter()
+                .map(|info| info.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("2026-10-16"), ["level1"]);
+
+        let daily = load(&cache, "2026-10-16", || Ok(VALID.to_string())).unwrap();
+        assert_eq!(names("2026-10-16"), ["level1", "2026-10-16"]);
+        // only today's level is offered
+        assert_eq!(names("2026-10-17"), ["level1"]);
+
+        assert_eq!(date_of(&daily.path, &cache).as_deref(), Some("2026-10-16"));
+        assert_eq!(date_of(&levels.join("level1.ron"), &cache), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
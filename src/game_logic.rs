@@ -1,11 +1,28 @@
 use crossbeam::channel;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, KeyboardInput},
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    },
 };
 
-use crate::{geometry::Circle, InputMessage};
-use std::time::{Duration, Instant};
+use crate::{
+    camera::View,
+    console,
+    controls::TiltInput,
+    geometry::{Circle, Point},
+    physics::{EntityEdit, SelectedEntity, TunableParam},
+    InputMessage,
+};
+use std::{
+    num::ParseFloatError,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use self::ui::UiRegistry;
+
+pub mod ui;
 
 #[derive(Debug, Clone)]
 pub struct EditorState {
@@ -14,11 +31,138 @@ pub struct EditorState {
     pub free_quad: Vec<[f32; 2]>,
 }
 
+/// What moving the mouse does, switched with the T key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseMode {
+    /// the cursor only points, e.g. at where level shapes are placed
+    Draw,
+    /// moving the mouse sideways tilts the world, the cursor is kept in the middle of the window
+    Tilt,
+}
+
+/// The properties of the selected entity which can be edited, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectorField {
+    Static,
+    Deadly,
+    Fragile,
+    Friction,
+    X,
+    Y,
+}
+
+const INSPECTOR_FIELDS: [InspectorField; 6] = [
+    InspectorField::Static,
+    InspectorField::Deadly,
+    InspectorField::Fragile,
+    InspectorField::Friction,
+    InspectorField::X,
+    InspectorField::Y,
+];
+
 pub struct GameState {
     pub mouse_position: [f32; 2],
     pub timer: Instant,
     pub player: Circle,
     pub reset_position: bool,
+    pub is_aiming: bool,
+    /// the window is switched to match this at the next redraw
+    pub is_fullscreen: bool,
+    /// where the level shape being dragged out with the right mouse button was started, as
+    /// displayed
+    pub shape_start: Option<Point>,
+    /// when clearing all drawn shapes was last asked for, it has to be confirmed by asking again
+    pub clear_requested: Option<Instant>,
+    /// the modifier keys currently held
+    pub modifiers: ModifiersState,
+    pub mouse_mode: MouseMode,
+    /// how many radians the world tilts by when the mouse moves across the whole window
+    pub tilt_sensitivity: f32,
+    /// clicking selects entities and the arrows edit them instead of playing, toggled with E
+    pub is_editing: bool,
+    /// the index in `INSPECTOR_FIELDS` of the field the left and right arrows change
+    pub inspector_field: usize,
+    /// the entity selected for editing as of the last frame
+    pub selected: Option<SelectedEntity>,
+    /// every frame is saved as a PNG while this is set, toggled with F9
+    pub is_recording: bool,
+    /// where the recorded frames are saved
+    pub recording_directory: PathBuf,
+    /// the line typed into the developer console, which takes all keys while it's open.
+    /// Toggled with the key left of 1
+    pub console: Option<String>,
+    /// the note for level designers being typed in editing mode along with where it goes, as
+    /// displayed. It takes all keys like the console. Started with Enter at the cursor
+    pub note: Option<(Point, String)>,
+    /// a grid turning with the world is drawn behind the level while this is set, toggled with G
+    pub is_grid_shown: bool,
+    /// how far apart the lines of the grid are
+    pub grid_spacing: f64,
+    /// the corners of level shapes are moved to the nearest crossing of the grid while this is
+    /// set, toggled with S
+    pub is_snapping: bool,
+    /// how far the world was turned in the last frame, which the grid turns by
+    pub world_angle: f64,
+    /// purely cosmetic animations, like the ball squashing when it lands hard, are left out.
+    /// Set with `--reduce-motion`
+    pub reduce_motion: bool,
+    /// the interactive UI drawn in the last frame, which takes the clicks on it
+    pub ui: UiRegistry,
+    /// the index in `DisplayMessage::objectives` of the flag or door the arrow at the edge of
+    /// the window points at once the player has picked one with Tab, the nearest one until then
+    pub objective: Option<usize>,
+    /// the objective the arrow pointed at in the last frame, which Tab moves on from
+    pub shown_objective: Option<usize>,
+    /// how the level was shown in the last frame, which tells what's under the cursor
+    pub view: View,
+}
+
+/// level shapes thinner than this are not created, as they would have no mass
+const MIN_LEVEL_SHAPE_SIZE: f64 = 0.01;
+/// how long a request to clear all drawn shapes waits for a confirmation
+const CLEAR_CONFIRMATION_TIME: Duration = Duration::from_secs(1);
+/// how much a physics parameter changes with each press of a tuning key
+const TUNING_FACTOR: f64 = 1.25;
+/// how far the left and right arrows move the selected entity
+const NUDGE_STEP: f64 = 0.01;
+/// how much the left and right arrows change the friction coefficient of the selected entity
+const FRICTION_STEP: f64 = 0.1;
+/// how far touchpads scroll for one step of a mouse wheel
+const PIXELS_PER_WHEEL_STEP: f64 = 40.0;
+/// how far each press of the left and right arrows tilts the world, in radians
+const KEY_TILT_STEP: f32 = 0.05;
+/// the tilt sensitivity used unless `--mouse-sensitivity=<number>` is given
+pub const DEFAULT_TILT_SENSITIVITY: f32 = 1.5;
+const TILT_SENSITIVITY_ARG: &str = "--mouse-sensitivity=";
+/// the directory frames are recorded to unless `--record=<directory>` is given
+pub const DEFAULT_RECORDING_DIRECTORY: &str = "recording";
+const RECORD_ARG: &str = "--record=";
+/// the grid spacing used unless `--grid-spacing=<number>` is given
+pub const DEFAULT_GRID_SPACING: f64 = 0.1;
+const GRID_SPACING_ARG: &str = "--grid-spacing=";
+
+/// reads `--mouse-sensitivity=<number>`, ignoring other arguments
+pub fn tilt_sensitivity_from_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<f32, ParseFloatError> {
+    args.into_iter()
+        .find_map(|arg| arg.strip_prefix(TILT_SENSITIVITY_ARG).map(str::parse))
+        .unwrap_or(Ok(DEFAULT_TILT_SENSITIVITY))
+}
+
+/// reads `--grid-spacing=<number>`, ignoring other arguments
+pub fn grid_spacing_from_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<f64, ParseFloatError> {
+    args.into_iter()
+        .find_map(|arg| arg.strip_prefix(GRID_SPACING_ARG).map(str::parse))
+        .unwrap_or(Ok(DEFAULT_GRID_SPACING))
+}
+
+/// reads `--record=<directory>`, which starts recording right away
+pub fn recording_directory_from_args(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    args.into_iter()
+        .find_map(|arg| arg.strip_prefix(RECORD_ARG).map(PathBuf::from))
 }
 
 impl GameState {
@@ -28,10 +172,33 @@ impl GameState {
         dimensions: PhysicalSize<u32>,
         input_physics_actions: &mut channel::Sender<InputMessage>,
     ) {
+        let previous_position = self.mouse_position;
         self.mouse_position = Self::normalize_mouse_position(dimensions, position);
 
+        // the level stays still while a shape is placed, only its preview follows the cursor
+        if self.shape_start.is_some() {
+            return;
+        }
+
+        // while aiming the cursor is used as a pointer, so it must not tilt the level
+        // nor be pulled back to the center of the window
+        if self.is_aiming {
+            input_physics_actions
+                .send(InputMessage::AimMove(self.cursor()))
+                .unwrap();
+            return;
+        }
+
+        if self.mouse_mode == MouseMode::Draw {
+            return;
+        }
+
+        // the normalized coordinates span 2 units across the window
+        let delta = self.mouse_position[0] - previous_position[0];
         input_physics_actions
-            .send(InputMessage::Angle(self.mouse_position[0] / 2.0))
+            .send(InputMessage::Tilt(TiltInput::Mouse(
+                delta * self.tilt_sensitivity / 2.0,
+            )))
             .unwrap();
 
         if self.timer.elapsed() >= Duration::from_millis(100) {
@@ -40,20 +207,115 @@ impl GameState {
             self.reset_position = true;
             self.timer = Instant::now();
         }
-        // if button == MouseButton::Right && element_state == ElementState::Pressed {
-        //     self.mpsaved = self.mouse_position;
-        //     eprintln!("aa");
-        // }
-        // if button == MouseButton::Middle && element_state == ElementState::Pressed {
-        //     let [x1, y1] = self.mouse_position;
-        //     let [x2, y2] = self.mpsaved;
+    }
 
-        //     input_physics_actions
-        //         .send(InputMessage::CreateLevelShape([x1, -y1], [x2, -y2]))
-        //         .unwrap();
-        //     //println!("(shape: [({x1},{y1}),({x1},{y2}),({x2},{y2}),({x2},{y1})], is_bindable: false, is_static: true),\n");
-        //     //eprintln!("(shape: [({x1},{y1}),({x1},{y2}),({x2},{y2}),({x2},{y1})], is_bindable: false, is_static: true),\n");
-        // }
+    pub fn handle_mouse_input(
+        &mut self,
+        state: ElementState,
+        button: MouseButton,
+        input_physics_actions: &mut channel::Sender<InputMessage>,
+    ) {
+        // a press on the UI while a stroke is already under way is still part of the stroke
+        let is_stroke_started = self.is_aiming || self.shape_start.is_some();
+        let is_for_ui = match state {
+            ElementState::Pressed => !is_stroke_started && self.ui.press(self.mouse_position),
+            ElementState::Released => self.ui.release(self.mouse_position),
+        };
+        if is_for_ui {
+            return;
+        }
+
+        match (button, state) {
+            (MouseButton::Left, ElementState::Pressed) if self.is_editing => {
+                let point = self.cursor();
+                // Shift erases the whole contraption under the cursor instead
+                let message = if self.modifiers.shift() {
+                    InputMessage::EraseConnected(point)
+                } else {
+                    InputMessage::Select(point)
+                };
+                input_physics_actions.send(message).unwrap();
+            }
+            (MouseButton::Left, _) if self.is_editing => {}
+            (MouseButton::Left, ElementState::Pressed) => {
+                self.is_aiming = true;
+                input_physics_actions.send(InputMessage::AimStart).unwrap();
+            }
+            (MouseButton::Left, ElementState::Released) => {
+                self.is_aiming = false;
+                input_physics_actions.send(InputMessage::Launch).unwrap();
+            }
+            (MouseButton::Right, ElementState::Pressed) if self.is_editing => {
+                self.shape_start = Some(self.cursor());
+            }
+            (MouseButton::Right, ElementState::Released) if self.is_editing => {
+                if let Some(vertices) = self.level_shape_preview() {
+                    let Point(width, height) = vertices[0].to(vertices[2]);
+                    if width >= MIN_LEVEL_SHAPE_SIZE && height >= MIN_LEVEL_SHAPE_SIZE {
+                        input_physics_actions
+                            .send(InputMessage::CreateLevelShape(vertices))
+                            .unwrap();
+                    }
+                }
+                self.shape_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// in editing mode scrolling up makes the hinge under the cursor stiffer and scrolling down
+    /// softer, otherwise the wheel does nothing
+    pub fn handle_mouse_wheel(
+        &mut self,
+        delta: MouseScrollDelta,
+        input_physics_actions: &mut channel::Sender<InputMessage>,
+    ) {
+        let steps = match delta {
+            MouseScrollDelta::LineDelta(_, lines) => lines as f64,
+            MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => y / PIXELS_PER_WHEEL_STEP,
+        };
+        if !self.is_editing || steps == 0.0 {
+            return;
+        }
+        input_physics_actions
+            .send(InputMessage::TuneBinding(self.cursor(), steps))
+            .unwrap();
+    }
+
+    /// the point of the level under the cursor, as displayed
+    pub fn cursor(&self) -> Point {
+        let [x, y] = self.mouse_position;
+        // the window's y axis points down
+        self.view.to_display(Point(x as f64, -y as f64))
+    }
+
+    /// the point moved to the nearest crossing of the grid if snapping is on, as displayed
+    fn snapped(&self, point: Point) -> Point {
+        let spacing = self.grid_spacing;
+        if !self.is_snapping || spacing.is_nan() || spacing <= 0.0 {
+            return point;
+        }
+        // the grid is laid out in the level, which is turned as it's displayed
+        let Point(x, y) = point.rotate(-self.world_angle);
+        let round = |coordinate: f64| (coordinate / spacing).round() * spacing;
+        Point(round(x), round(y)).rotate(self.world_angle)
+    }
+
+    /// the rectangle which would be added to the level if the right mouse button was released
+    /// now, with its vertices in counterclockwise order. Its corners snap to the grid while
+    /// snapping is on
+    pub fn level_shape_preview(&self) -> Option<Vec<Point>> {
+        let Point(x1, y1) = self.snapped(self.shape_start?);
+        let Point(x2, y2) = self.snapped(self.cursor());
+        let (left, right) = (x1.min(x2), x1.max(x2));
+        let (bottom, top) = (y1.min(y2), y1.max(y2));
+
+        Some(vec![
+            Point(left, bottom),
+            Point(right, bottom),
+            Point(right, top),
+            Point(left, top),
+        ])
     }
 
     pub fn handle_keyboard_input(
@@ -61,7 +323,22 @@ impl GameState {
         input: KeyboardInput,
         input_physics_actions: &mut channel::Sender<InputMessage>,
     ) {
+        if self.console.is_some() {
+            self.handle_console_key(input, input_physics_actions);
+            return;
+        }
+        if self.note.is_some() {
+            self.handle_note_key(input, input_physics_actions);
+            return;
+        }
         match input {
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::Grave),
+                ..
+            } => {
+                self.console = Some(String::new());
+            }
             KeyboardInput {
                 state: ElementState::Pressed,
                 virtual_keycode: Some(winit::event::VirtualKeyCode::Space),
@@ -69,10 +346,327 @@ impl GameState {
             } => {
                 input_physics_actions.send(InputMessage::Jump).unwrap();
             }
+            KeyboardInput {
+                state,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::Back),
+                ..
+            } => {
+                input_physics_actions
+                    .send(InputMessage::Rewind(state == ElementState::Pressed))
+                    .unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::R),
+                ..
+            } => {
+                input_physics_actions.send(InputMessage::Restart).unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::F5),
+                ..
+            } => {
+                input_physics_actions.send(InputMessage::PlayDaily).unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::F11),
+                ..
+            } => {
+                self.is_fullscreen = !self.is_fullscreen;
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::F9),
+                ..
+            } => {
+                self.is_recording = !self.is_recording;
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::E),
+                ..
+            } => {
+                self.is_editing = !self.is_editing;
+                if self.is_editing {
+                    // entities are picked with the cursor, so it has to stay where it's put
+                    self.mouse_mode = MouseMode::Draw;
+                } else {
+                    // level shapes are only placed while editing
+                    self.shape_start = None;
+                    input_physics_actions.send(InputMessage::Deselect).unwrap();
+                }
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::Return),
+                ..
+            } if self.is_editing => {
+                self.note = Some((self.cursor(), String::new()));
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode:
+                    Some(
+                        key @ (VirtualKeyCode::Up
+                        | VirtualKeyCode::Down
+                        | VirtualKeyCode::Left
+                        | VirtualKeyCode::Right),
+                    ),
+                ..
+            } if self.is_editing && !self.modifiers.shift() => {
+                self.handle_inspector_key(key, input_physics_actions);
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key @ (VirtualKeyCode::Left | VirtualKeyCode::Right)),
+                ..
+            } if !self.modifiers.shift() => {
+                let step = if key == VirtualKeyCode::Left {
+                    -KEY_TILT_STEP
+                } else {
+                    KEY_TILT_STEP
+                };
+                input_physics_actions
+                    .send(InputMessage::Tilt(TiltInput::Keys(step)))
+                    .unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::C),
+                ..
+            } => {
+                input_physics_actions
+                    .send(InputMessage::NextControlScheme)
+                    .unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::F),
+                ..
+            } => {
+                // pins a weld under the cursor, which binds the next shape drawn over it
+                input_physics_actions
+                    .send(InputMessage::Weld(self.cursor()))
+                    .unwrap();
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::G),
+                ..
+            } => {
+                self.is_grid_shown = !self.is_grid_shown;
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::S),
+                ..
+            } => {
+                self.is_snapping = !self.is_snapping;
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::Tab),
+                ..
+            } => {
+                // wrapped around to the first one once it's past the last
+                self.objective = self.shown_objective.map(|objective| objective + 1);
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::T),
+                ..
+            } => {
+                self.mouse_mode = match self.mouse_mode {
+                    MouseMode::Draw => {
+                        // leaves the cursor room to move in both directions
+                        self.reset_position = true;
+                        MouseMode::Tilt
+                    }
+                    MouseMode::Tilt => MouseMode::Draw,
+                };
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(winit::event::VirtualKeyCode::X),
+                ..
+            } => match self.clear_requested.take() {
+                Some(requested) if requested.elapsed() <= CLEAR_CONFIRMATION_TIME => {
+                    input_physics_actions
+                        .send(InputMessage::ClearDrawn)
+                        .unwrap();
+                }
+                _ => {
+                    println!("press X again to erase everything you've drawn");
+                    self.clear_requested = Some(Instant::now());
+                }
+            },
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                ..
+            } if self.modifiers.shift() => {
+                if let Some((param, factor)) = Self::tuning_for(key) {
+                    input_physics_actions
+                        .send(InputMessage::TuneParam(param, factor))
+                        .unwrap();
+                }
+            }
             _ => {}
         };
     }
 
+    /// Enter runs the typed command, Escape and the key which opened the console close it
+    fn handle_console_key(
+        &mut self,
+        input: KeyboardInput,
+        input_physics_actions: &mut channel::Sender<InputMessage>,
+    ) {
+        let (ElementState::Pressed, Some(key), Some(line)) =
+            (input.state, input.virtual_keycode, &mut self.console)
+        else {
+            return;
+        };
+        match key {
+            VirtualKeyCode::Return => {
+                if !line.trim().is_empty() {
+                    match console::parse(line) {
+                        Ok(command) => input_physics_actions
+                            .send(InputMessage::Console(command))
+                            .unwrap(),
+                        Err(err) => println!("{err}"),
+                    }
+                }
+                line.clear();
+            }
+            VirtualKeyCode::Back => {
+                line.pop();
+            }
+            VirtualKeyCode::Escape | VirtualKeyCode::Grave => self.console = None,
+            _ => {}
+        }
+    }
+
+    /// Enter leaves the typed note unless it's empty, Escape throws it away
+    fn handle_note_key(
+        &mut self,
+        input: KeyboardInput,
+        input_physics_actions: &mut channel::Sender<InputMessage>,
+    ) {
+        let KeyboardInput {
+            state: ElementState::Pressed,
+            virtual_keycode: Some(key),
+            ..
+        } = input
+        else {
+            return;
+        };
+        match key {
+            VirtualKeyCode::Return => {
+                if let Some((at, text)) = self.note.take() {
+                    if !text.trim().is_empty() {
+                        input_physics_actions
+                            .send(InputMessage::AddNote(at, text))
+                            .unwrap();
+                    }
+                }
+            }
+            VirtualKeyCode::Back => {
+                if let Some((_, text)) = &mut self.note {
+                    text.pop();
+                }
+            }
+            VirtualKeyCode::Escape => self.note = None,
+            _ => {}
+        }
+    }
+
+    /// types the character into the console or the note if one of them is open
+    pub fn handle_received_character(&mut self, character: char) {
+        if let Some(line) = &mut self.console {
+            // the key toggling the console types a character too
+            if !character.is_control() && character != '`' {
+                line.push(character);
+            }
+        } else if let Some((_, text)) = &mut self.note {
+            if !character.is_control() {
+                text.push(character);
+            }
+        }
+    }
+
+    /// in the inspector the up and down arrows pick a field, the left and right ones change it
+    fn handle_inspector_key(
+        &mut self,
+        key: VirtualKeyCode,
+        input_physics_actions: &mut channel::Sender<InputMessage>,
+    ) {
+        let n_of_fields = INSPECTOR_FIELDS.len();
+        let direction = match key {
+            VirtualKeyCode::Up => {
+                self.inspector_field = (self.inspector_field + n_of_fields - 1) % n_of_fields;
+                return;
+            }
+            VirtualKeyCode::Down => {
+                self.inspector_field = (self.inspector_field + 1) % n_of_fields;
+                return;
+            }
+            VirtualKeyCode::Left => -1.0,
+            VirtualKeyCode::Right => 1.0,
+            _ => return,
+        };
+
+        let edit = match INSPECTOR_FIELDS[self.inspector_field] {
+            InspectorField::Static => EntityEdit::ToggleStatic,
+            InspectorField::Deadly => EntityEdit::ToggleDeadly,
+            InspectorField::Fragile => EntityEdit::ToggleFragile,
+            InspectorField::Friction => EntityEdit::Friction(direction * FRICTION_STEP),
+            InspectorField::X => EntityEdit::Nudge(Point(direction * NUDGE_STEP, 0.0)),
+            InspectorField::Y => EntityEdit::Nudge(Point(0.0, direction * NUDGE_STEP)),
+        };
+        input_physics_actions
+            .send(InputMessage::EditSelected(edit))
+            .unwrap();
+    }
+
+    /// the lines of the inspector panel along with whether each is the highlighted field,
+    /// empty unless an entity is selected for editing
+    pub fn inspector_lines(&self) -> Vec<(String, bool)> {
+        let Some(SelectedEntity { id, cfg, position }) = self.selected.filter(|_| self.is_editing)
+        else {
+            return vec![];
+        };
+        let yes_no = |value| if value { "yes" } else { "no" };
+
+        let fields = INSPECTOR_FIELDS.iter().enumerate().map(|(i, field)| {
+            let text = match field {
+                InspectorField::Static => format!("static: {}", yes_no(cfg.is_static)),
+                InspectorField::Deadly => format!("deadly: {}", yes_no(cfg.is_deadly)),
+                InspectorField::Fragile => format!("fragile: {}", yes_no(cfg.is_fragile)),
+                InspectorField::Friction => format!("friction: {:.2}", cfg.friction),
+                InspectorField::X => format!("x: {:.2}", position.0),
+                InspectorField::Y => format!("y: {:.2}", position.1),
+            };
+            (text, i == self.inspector_field)
+        });
+        std::iter::once((format!("{id:?}"), false))
+            .chain(fields)
+            .collect()
+    }
+
+    /// Shift with the up and down arrows tunes gravity, with the right and left ones the speed
+    /// of the whole simulation
+    fn tuning_for(key: VirtualKeyCode) -> Option<(TunableParam, f64)> {
+        match key {
+            VirtualKeyCode::Up => Some((TunableParam::Gravity, TUNING_FACTOR)),
+            VirtualKeyCode::Down => Some((TunableParam::Gravity, 1.0 / TUNING_FACTOR)),
+            VirtualKeyCode::Right => Some((TunableParam::Movement, TUNING_FACTOR)),
+            VirtualKeyCode::Left => Some((TunableParam::Movement, 1.0 / TUNING_FACTOR)),
+            _ => None,
+        }
+    }
+
     fn normalize_mouse_position(
         dimensions: PhysicalSize<u32>,
         mouse_position: PhysicalPosition<f64>,
@@ -83,3 +677,463 @@ impl GameState {
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use winit::event::{
+        ElementState::*,
+        MouseButton::{Left, Right},
+    };
+
+    use super::{ui::UiRect, *};
+
+    fn game_state(mouse_position: [f32; 2]) -> GameState {
+        GameState {
+            mouse_position,
+            timer: Instant::now(),
+            player: Circle {
+                center: Point(0.0, 0.0),
+                radius: 0.0,
+            },
+            reset_position: false,
+            is_aiming: false,
+            is_fullscreen: false,
+            shape_start: None,
+            clear_requested: None,
+            modifiers: ModifiersState::empty(),
+            mouse_mode: MouseMode::Tilt,
+            tilt_sensitivity: DEFAULT_TILT_SENSITIVITY,
+            is_editing: false,
+            inspector_field: 0,
+            selected: None,
+            is_recording: false,
+            recording_directory: DEFAULT_RECORDING_DIRECTORY.into(),
+            console: None,
+            note: None,
+            is_grid_shown: false,
+            grid_spacing: DEFAULT_GRID_SPACING,
+            is_snapping: false,
+            world_angle: 0.0,
+            reduce_motion: false,
+            ui: UiRegistry::default(),
+            objective: None,
+            shown_objective: None,
+            view: View::default(),
+        }
+    }
+
+    #[test]
+    fn test_level_shape_matches_preview() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+        state.is_editing = true;
+
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.handle_mouse_moved(
+            PhysicalPosition::new(25.0, 62.5),
+            PhysicalSize::new(100, 100),
+            &mut tx,
+        );
+        // the level isn't tilted while the shape is placed
+        assert!(rx.is_empty());
+
+        let preview = state.level_shape_preview().unwrap();
+        assert_eq!(
+            preview,
+            vec![
+                Point(-0.5, -0.25),
+                Point(0.5, -0.25),
+                Point(0.5, 0.25),
+                Point(-0.5, 0.25),
+            ]
+        );
+
+        state.handle_mouse_input(Released, Right, &mut tx);
+        let Ok(InputMessage::CreateLevelShape(vertices)) = rx.try_recv() else {
+            panic!("no level shape was created");
+        };
+        assert_eq!(vertices, preview);
+        assert!(state.level_shape_preview().is_none());
+    }
+
+    #[test]
+    fn test_level_shape_snaps_to_grid() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.52, -0.27]);
+        state.is_editing = true;
+        state.is_snapping = true;
+        state.grid_spacing = 0.25;
+
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.mouse_position = [-0.43, 0.2];
+        let preview = state.level_shape_preview().unwrap();
+        let expected = [
+            Point(-0.5, -0.25),
+            Point(0.5, -0.25),
+            Point(0.5, 0.25),
+            Point(-0.5, 0.25),
+        ];
+        assert!(preview
+            .iter()
+            .zip(expected)
+            .all(|(&vertex, expected)| vertex.is_close_enough_to(expected)));
+
+        state.handle_mouse_input(Released, Right, &mut tx);
+        let Ok(InputMessage::CreateLevelShape(vertices)) = rx.try_recv() else {
+            panic!("no level shape was created");
+        };
+        assert_eq!(vertices, preview);
+
+        // the grid turns with the world, and so do the crossings the corners snap to
+        state.world_angle = 0.3;
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.mouse_position = [0.52, -0.27];
+        let on_grid = |vertex: Point| {
+            let Point(x, y) = vertex.rotate(-0.3) * 4.0;
+            Point(x, y).is_close_enough_to(Point(x.round(), y.round()))
+        };
+        let turned = state.level_shape_preview().unwrap();
+        // where the drag started and the cursor are the bottom left and top right corners
+        assert!(on_grid(turned[0]) && on_grid(turned[2]));
+        assert!(!on_grid(turned[1]));
+    }
+
+    fn sent_angles(rx: &channel::Receiver<InputMessage>) -> Vec<f32> {
+        rx.try_iter()
+            .filter_map(|message| match message {
+                InputMessage::Tilt(TiltInput::Mouse(angle)) => Some(angle),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tilt_follows_mouse_movement() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.0, 0.0]);
+        let size = PhysicalSize::new(100, 100);
+
+        // 10 and then 20 pixels to the right, then 10 to the left
+        for x in [60.0, 80.0, 70.0] {
+            state.handle_mouse_moved(PhysicalPosition::new(x, 50.0), size, &mut tx);
+        }
+        let angles = sent_angles(&rx);
+        assert_eq!(angles.len(), 3);
+        assert!((angles[0] - 0.2 * DEFAULT_TILT_SENSITIVITY / 2.0).abs() < 1e-6);
+        assert!((angles[1] - 2.0 * angles[0]).abs() < 1e-6);
+        assert!((angles[2] + angles[0]).abs() < 1e-6);
+
+        state.tilt_sensitivity *= 2.0;
+        state.handle_mouse_moved(PhysicalPosition::new(80.0, 50.0), size, &mut tx);
+        assert!((sent_angles(&rx)[0] - 2.0 * angles[0]).abs() < 1e-6);
+
+        state.mouse_mode = MouseMode::Draw;
+        state.handle_mouse_moved(PhysicalPosition::new(20.0, 50.0), size, &mut tx);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_arrows_tilt_and_c_switches_scheme() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.0, 0.0]);
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Left), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Right), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::C), &mut tx);
+        let messages: Vec<_> = rx.try_iter().collect();
+        assert!(matches!(
+            messages[..],
+            [
+                InputMessage::Tilt(TiltInput::Keys(left)),
+                InputMessage::Tilt(TiltInput::Keys(right)),
+                InputMessage::NextControlScheme,
+            ] if left == -KEY_TILT_STEP && right == KEY_TILT_STEP
+        ));
+    }
+
+    #[test]
+    fn test_tilt_sensitivity_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            tilt_sensitivity_from_args(args(&["level.ron"])),
+            Ok(DEFAULT_TILT_SENSITIVITY)
+        );
+        assert_eq!(
+            tilt_sensitivity_from_args(args(&["level.ron", "--mouse-sensitivity=0.5"])),
+            Ok(0.5)
+        );
+        assert!(tilt_sensitivity_from_args(args(&["--mouse-sensitivity=slow"])).is_err());
+    }
+
+    #[test]
+    fn test_tab_picks_the_objective_after_the_shown_one() {
+        let (mut tx, _rx) = channel::unbounded();
+        let mut state = game_state([0.0, 0.0]);
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Tab), &mut tx);
+        assert_eq!(state.objective, None);
+
+        // the nearest one is shown until then, which needn't be the first one
+        state.shown_objective = Some(2);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Tab), &mut tx);
+        assert_eq!(state.objective, Some(3));
+    }
+
+    #[allow(deprecated)]
+    fn key_press(key: VirtualKeyCode) -> KeyboardInput {
+        KeyboardInput {
+            scancode: 0,
+            state: Pressed,
+            virtual_keycode: Some(key),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    #[test]
+    fn test_inspector_edits_selected_entity() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::E), &mut tx);
+        assert!(state.is_editing);
+        state.handle_mouse_input(Pressed, MouseButton::Left, &mut tx);
+        state.handle_mouse_input(Released, MouseButton::Left, &mut tx);
+        assert!(matches!(
+            rx.try_iter().collect::<Vec<_>>()[..],
+            [InputMessage::Select(Point(x, y))] if x == 0.5 && y == 0.25
+        ));
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Right), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::EditSelected(EntityEdit::ToggleStatic))
+        ));
+        // up from the first field wraps around to the last one
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Up), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Left), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::EditSelected(EntityEdit::Nudge(Point(x, y)))) if x == 0.0 && y < 0.0
+        ));
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Up), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Up), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Right), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::EditSelected(EntityEdit::Friction(change))) if change > 0.0
+        ));
+
+        state.modifiers = ModifiersState::SHIFT;
+        state.handle_mouse_input(Pressed, MouseButton::Left, &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::EraseConnected(Point(x, y))) if x == 0.5 && y == 0.25
+        ));
+        state.modifiers = ModifiersState::empty();
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::E), &mut tx);
+        assert!(matches!(rx.try_recv(), Ok(InputMessage::Deselect)));
+    }
+
+    #[test]
+    fn test_wheel_tunes_hinges_only_when_editing() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+
+        state.handle_mouse_wheel(MouseScrollDelta::LineDelta(0.0, 1.0), &mut tx);
+        assert!(rx.try_recv().is_err());
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::E), &mut tx);
+        state.handle_mouse_wheel(MouseScrollDelta::LineDelta(0.0, -2.0), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::TuneBinding(Point(x, y), steps)) if x == 0.5 && y == 0.25 && steps == -2.0
+        ));
+        state.handle_mouse_wheel(
+            MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, 20.0)),
+            &mut tx,
+        );
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::TuneBinding(_, steps)) if steps == 0.5
+        ));
+    }
+
+    #[test]
+    fn test_console_sends_typed_command() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.0, 0.0]);
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Grave), &mut tx);
+        "`tilt 15x"
+            .chars()
+            .for_each(|character| state.handle_received_character(character));
+        // the keys type into the console instead of playing
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Space), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Back), &mut tx);
+        assert_eq!(state.console.as_deref(), Some("tilt 15"));
+        assert!(rx.is_empty());
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::Console(console::Command::Tilt(degrees))) if degrees == 15.0
+        ));
+        assert_eq!(state.console.as_deref(), Some(""));
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Escape), &mut tx);
+        assert!(state.console.is_none());
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Space), &mut tx);
+        assert!(matches!(rx.try_recv(), Ok(InputMessage::Jump)));
+    }
+
+    #[test]
+    fn test_cursor_points_through_view() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+        // zoomed out, looking right of the middle of the level
+        state.view = View {
+            center: Point(1.0, 0.0),
+            zoom: 0.5,
+        };
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::F), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::Weld(Point(x, y))) if x == 2.0 && y == 0.5
+        ));
+    }
+
+    #[test]
+    fn test_note_is_left_at_cursor() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+
+        // Enter only starts a note in editing mode
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        assert!(state.note.is_none());
+        state.handle_keyboard_input(key_press(VirtualKeyCode::E), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        "too wide!"
+            .chars()
+            .for_each(|character| state.handle_received_character(character));
+        // the keys type into the note instead of editing
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Up), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Back), &mut tx);
+        assert!(rx.is_empty());
+
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::AddNote(Point(x, y), text)) if x == 0.5 && y == 0.25 && text == "too wide"
+        ));
+        assert!(state.note.is_none());
+
+        // an empty one is thrown away
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        state.handle_keyboard_input(key_press(VirtualKeyCode::Return), &mut tx);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_flat_level_shape_is_not_created() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+        state.is_editing = true;
+
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.mouse_position = [-0.5, -0.25];
+        state.handle_mouse_input(Released, Right, &mut tx);
+
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_level_shapes_only_placed_while_editing() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.5, -0.25]);
+
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        assert!(state.level_shape_preview().is_none());
+        state.mouse_position = [-0.5, 0.25];
+        state.handle_mouse_input(Released, Right, &mut tx);
+        assert!(rx
+            .try_iter()
+            .all(|message| !matches!(message, InputMessage::CreateLevelShape(_))));
+    }
+
+    /// a button in the bottom right corner of the window
+    const BUTTON: UiRect = UiRect {
+        min: [0.6, 0.6],
+        max: [1.0, 1.0],
+    };
+
+    #[test]
+    fn test_clicks_on_ui_dont_reach_level() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.8, 0.8]);
+        state.mouse_mode = MouseMode::Draw;
+        state.ui.begin_frame();
+        assert!(!state.ui.element(BUTTON));
+
+        state.handle_mouse_input(Pressed, Left, &mut tx);
+        state.handle_mouse_input(Released, Left, &mut tx);
+        assert!(rx.is_empty());
+        assert!(!state.is_aiming);
+        // the button finds out about the click when it's drawn in the next frame
+        state.ui.begin_frame();
+        assert!(state.ui.element(BUTTON));
+        state.ui.begin_frame();
+        assert!(!state.ui.element(BUTTON));
+
+        state.mouse_position = [0.0, 0.0];
+        state.handle_mouse_input(Pressed, Left, &mut tx);
+        state.handle_mouse_input(Released, Left, &mut tx);
+        assert!(matches!(rx.try_recv(), Ok(InputMessage::AimStart)));
+        assert!(matches!(rx.try_recv(), Ok(InputMessage::Launch)));
+    }
+
+    #[test]
+    fn test_strokes_straddling_ui_belong_to_where_they_started() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.8, 0.8]);
+        state.mouse_mode = MouseMode::Draw;
+        state.is_editing = true;
+        state.ui.begin_frame();
+        state.ui.element(BUTTON);
+
+        // started on the button, so dragging out of it is cancelled instead of drawing
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.mouse_position = [-0.5, -0.5];
+        state.handle_mouse_input(Released, Right, &mut tx);
+        assert!(rx.is_empty());
+        state.ui.begin_frame();
+        assert!(!state.ui.element(BUTTON), "the button wasn't released on");
+
+        // started in the level, so it ends there even when released over the button
+        state.handle_mouse_input(Pressed, Right, &mut tx);
+        state.mouse_position = [0.8, 0.8];
+        state.handle_mouse_input(Released, Right, &mut tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputMessage::CreateLevelShape(_))
+        ));
+        state.ui.begin_frame();
+        assert!(!state.ui.element(BUTTON));
+    }
+
+    #[test]
+    fn test_full_screen_ui_blocks_level() {
+        let (mut tx, rx) = channel::unbounded();
+        let mut state = game_state([0.0, 0.0]);
+        state.ui.begin_frame();
+        state.ui.element(UiRect::FULL_SCREEN);
+
+        for button in [Left, Right] {
+            state.handle_mouse_input(Pressed, button, &mut tx);
+            state.handle_mouse_input(Released, button, &mut tx);
+        }
+        assert!(rx.is_empty());
+        assert!(state.level_shape_preview().is_none());
+    }
+}
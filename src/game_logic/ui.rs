@@ -0,0 +1,76 @@
+//! which parts of the window are taken by interactive UI, so clicks on it don't fall through to
+//! drawing and aiming in the level behind. The renderer records every element as it draws it,
+//! and the mouse input of the next frame is tested against them
+
+/// A rectangle of the window, in the normalized coordinates of `GameState::mouse_position`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl UiRect {
+    /// the whole window and anything beyond it, for screens like the pause or the level
+    /// completion one, which block everything behind them
+    pub const FULL_SCREEN: UiRect = UiRect {
+        min: [f32::NEG_INFINITY; 2],
+        max: [f32::INFINITY; 2],
+    };
+
+    pub fn contains(self, [x, y]: [f32; 2]) -> bool {
+        self.min[0] <= x && x <= self.max[0] && self.min[1] <= y && y <= self.max[1]
+    }
+}
+
+/// The interactive UI drawn in the last frame
+#[derive(Clone, Debug, Default)]
+pub struct UiRegistry {
+    /// in the order they were drawn, so the topmost last
+    rects: Vec<UiRect>,
+    /// the element the mouse button is held down on
+    pressed: Option<usize>,
+    /// the element clicked, i.e. pressed and released on, since the last frame
+    clicked: Option<usize>,
+    /// the click reported to the elements of the current frame
+    frame_click: Option<usize>,
+}
+
+impl UiRegistry {
+    /// forgets the elements of the last frame, the renderer calls it before drawing any UI
+    pub fn begin_frame(&mut self) {
+        self.rects.clear();
+        self.frame_click = self.clicked.take();
+    }
+
+    /// records an interactive element drawn over the rectangle, returning whether it was
+    /// clicked in the last frame. Elements must be drawn in the same order every frame
+    pub fn element(&mut self, rect: UiRect) -> bool {
+        self.rects.push(rect);
+        self.frame_click == Some(self.rects.len() - 1)
+    }
+
+    /// the topmost element under the position
+    fn element_at(&self, position: [f32; 2]) -> Option<usize> {
+        self.rects.iter().rposition(|rect| rect.contains(position))
+    }
+
+    /// whether pressing a mouse button at the position presses an element instead of starting
+    /// a stroke in the level
+    pub fn press(&mut self, position: [f32; 2]) -> bool {
+        self.pressed = self.element_at(position);
+        self.pressed.is_some()
+    }
+
+    /// whether releasing the mouse button ends a press which started on an element, a click if
+    /// it's still over the same one. Otherwise the release ends a stroke in the level, even if
+    /// it has been dragged over some UI
+    pub fn release(&mut self, position: [f32; 2]) -> bool {
+        let Some(pressed) = self.pressed.take() else {
+            return false;
+        };
+        if self.element_at(position) == Some(pressed) {
+            self.clicked = Some(pressed);
+        }
+        true
+    }
+}
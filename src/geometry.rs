@@ -1,7 +1,14 @@
+//! plain geometric values shared by the physics, the levels and the graphics
+
+#![deny(missing_docs)]
+
 use std::ops;
 
 use serde::{Deserialize, Serialize};
 
+pub use crate::physics::compute::centroid;
+
+/// coordinates closer than this are considered equal
 pub const EPSILON: f64 = 1e-7;
 
 /// A point on the 2D plane or a vector.
@@ -13,28 +20,35 @@ pub const EPSILON: f64 = 1e-7;
 pub struct Point(pub f64, pub f64);
 
 impl Point {
+    /// the origin, or a vector of length zero
     pub const ZERO: Self = Self(0.0, 0.0);
 
+    /// the dot product
     pub fn dot(self: Vector, other: Vector) -> f64 {
         self.0 * other.0 + self.1 * other.1
     }
 
+    /// the vector from `self` to `other`
     pub fn to(self: Point, other: Point) -> Vector {
         other - self
     }
 
+    /// whether both coordinates are within `EPSILON` of each other
     pub fn is_close_enough_to(self, other: Self) -> bool {
         (other.0 - self.0).abs() < EPSILON && (other.1 - self.1).abs() < EPSILON
     }
 
+    /// the z coordinate of the cross product, positive if `other` is counterclockwise from `self`
     pub fn cross(self: Vector, other: Vector) -> f64 {
         self.0 * other.1 - self.1 * other.0
     }
 
+    /// the vector rotated clockwise by a right angle
     pub fn perpendicular(self: Vector) -> Vector {
         Self(self.1, -self.0)
     }
 
+    /// the vector rotated counterclockwise by `angle` radians around the origin
     pub fn rotate(self: Vector, angle: f64) -> Vector {
         Self(
             self.0 * angle.cos() - self.1 * angle.sin(),
@@ -42,19 +56,24 @@ impl Point {
         )
     }
 
+    /// the vector scaled to a length of 1, which is NaN for the zero vector
     pub fn unit(self: Vector) -> Vector {
         self / self.norm()
     }
 
+    /// the euclidean length of the vector
     pub fn norm(self) -> f64 {
         self.dot(self).sqrt()
     }
 
+    /// the signed angle from `self` to `other`, positive if it's counterclockwise
     pub fn angle_to(self: Vector, other: Vector) -> f64 {
         (self.unit().dot(other.unit())).min(1.0).acos()
             * if self.cross(other) > 0.0 { 1.0 } else { -1.0 }
     }
 
+    /// the vector perpendicular to the segment from `other` to `self` which points towards the
+    /// origin, as used by GJK
     pub fn triple_product(self: Vector, other: Vector) -> Vector {
         let segment = other.to(self);
         -other * segment.dot(segment) - segment * segment.dot(-other)
@@ -124,40 +143,367 @@ impl From<[f32; 2]> for Point {
     }
 }
 
+/// A polygon ready to be displayed. Its centroid is computed from the vertices when it's
+/// needed, so it can't fall behind them when they're moved
 #[derive(Debug)]
 pub struct Polygon {
+    /// in counterclockwise order
     pub vertices: Vec<Point>,
-    pub centroid: Point,
 }
 
 impl Polygon {
+    /// rotates the vertices counterclockwise around the origin
     pub fn rotate(&mut self, angle: f32) {
         for vertex in &mut self.vertices {
             *vertex = vertex.rotate(angle as f64);
         }
     }
+
+    /// moves the vertices by the vector
+    pub fn translate(&mut self, by: Vector) {
+        for vertex in &mut self.vertices {
+            *vertex += by;
+        }
+    }
+
+    /// the center of mass, or the average of the vertices if the polygon has no area, like a
+    /// thin line of them
+    pub fn centroid(&self) -> Point {
+        let Point(x, y) = centroid(&self.vertices);
+        if x.is_finite() && y.is_finite() {
+            return Point(x, y);
+        }
+        let sum = self
+            .vertices
+            .iter()
+            .fold(Point::ZERO, |sum, &vertex| sum + vertex);
+        sum / self.vertices.len().max(1) as f64
+    }
 }
 
+/// A circle, without the spin physics keeps track of
 #[derive(Clone, Copy, Deserialize, Serialize)]
 pub struct Circle {
+    /// the center, which is also the center of mass
     pub center: Point,
+    /// always positive in a valid level
     pub radius: f64,
 }
 
 impl Circle {
+    /// moves the center counterclockwise around the origin
     pub fn rotate(&mut self, angle: f32) {
         self.center = self.center.rotate(angle as f64);
     }
 }
 
+/// A laser sweeping back and forth, shining from `point` until it hits an entity.
+///
+/// Only the angle is updated as the laser sweeps and the direction is computed from it, so
+/// rounding errors don't pile up over a long game
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SerializedLaser")]
 pub struct Laser {
+    /// where the laser is mounted
     pub point: Point,
-    pub direction: Vector,
-    pub change: f64,
+    /// the middle of the sweep
+    pub initial_direction: Vector,
+    /// how far, in radians, the laser is currently turned away from its initial direction
+    pub current_angle: f64,
+    /// how far, in radians, the laser turns on each update. A laser which doesn't turn at all
+    /// keeps shining in the same direction
+    pub angular_speed: f64,
+    /// how far, in radians, the laser turns away from its initial direction before turning back
     pub range: f64,
-    pub inital_direction: Vector,
-    pub is_out: bool,
+}
+
+impl Laser {
+    /// where the laser currently shines
+    pub fn direction(&self) -> Vector {
+        self.initial_direction.rotate(self.current_angle)
+    }
+
+    /// scales the initial direction, and so the direction, to a length of 1
+    pub fn normalize(&mut self) {
+        self.initial_direction = self.initial_direction.unit();
+    }
+
+    /// turns the laser by its angular speed, turning back exactly at the ends of its range
+    pub fn sweep(&mut self) {
+        if self.angular_speed == 0.0 {
+            return;
+        }
+        self.current_angle += self.angular_speed;
+        if self.current_angle.abs() >= self.range {
+            self.current_angle = self.current_angle.clamp(-self.range, self.range);
+            self.angular_speed = -self.angular_speed.copysign(self.current_angle);
+        }
+    }
+}
+
+/// The way lasers are written in level files. Older levels store the current direction and call
+/// the angular speed `change`
+#[derive(Deserialize)]
+struct SerializedLaser {
+    point: Point,
+    #[serde(alias = "inital_direction")]
+    initial_direction: Vector,
+    #[serde(default, deserialize_with = "some")]
+    direction: Option<Vector>,
+    #[serde(default)]
+    current_angle: f64,
+    #[serde(alias = "change")]
+    angular_speed: f64,
+    range: f64,
+}
+
+impl From<SerializedLaser> for Laser {
+    fn from(laser: SerializedLaser) -> Self {
+        let current_angle = match laser.direction {
+            // the zero vector has no angle, so the level is rejected when it's validated
+            Some(Vector::ZERO) => f64::NAN,
+            Some(direction) if direction != laser.initial_direction => {
+                laser.initial_direction.angle_to(direction)
+            }
+            _ => laser.current_angle,
+        };
+        Laser {
+            point: laser.point,
+            initial_direction: laser.initial_direction,
+            current_angle,
+            angular_speed: laser.angular_speed,
+            range: laser.range,
+        }
+    }
+}
+
+/// reads a value which is optional, but written without `Some` when it's there
+fn some<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Vector>, D::Error> {
+    Vector::deserialize(deserializer).map(Some)
+}
+
+/// An axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Aabb {
+    /// the bottom left corner
+    pub min: Point,
+    /// the top right corner
+    pub max: Point,
+}
+
+impl Aabb {
+    /// the smallest box containing all the points, `None` if there are none
+    pub fn around(points: impl IntoIterator<Item = Point>) -> Option<Aabb> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(
+            Aabb {
+                min: first,
+                max: first,
+            },
+            |Aabb { min, max }, Point(x, y)| Aabb {
+                min: Point(min.0.min(x), min.1.min(y)),
+                max: Point(max.0.max(x), max.1.max(y)),
+            },
+        ))
+    }
+
+    /// how far the point is from the box, 0 if it's inside
+    pub fn distance_to(&self, Point(x, y): Point) -> f64 {
+        let dx = (self.min.0 - x).max(x - self.max.0).max(0.0);
+        let dy = (self.min.1 - y).max(y - self.max.1).max(0.0);
+        dx.hypot(dy)
+    }
+
+    /// the box grown by `margin` on every side
+    pub fn expanded(self, margin: f64) -> Aabb {
+        Aabb {
+            min: self.min - Point(margin, margin),
+            max: self.max + Point(margin, margin),
+        }
+    }
+
+    /// moves the center of a view reaching `half_size` away from it in each axis as little as
+    /// possible to keep the view inside the box. Along an axis the view is too big for, it's
+    /// centered on the box instead
+    pub fn clamp_view(&self, center: Point, half_size: Vector) -> Point {
+        let clamp = |center: f64, half_size: f64, min: f64, max: f64| {
+            if 2.0 * half_size >= max - min {
+                (min + max) / 2.0
+            } else {
+                center.clamp(min + half_size, max - half_size)
+            }
+        };
+        Point(
+            clamp(center.0, half_size.0, self.min.0, self.max.0),
+            clamp(center.1, half_size.1, self.min.1, self.max.1),
+        )
+    }
+
+    /// true for boxes which overlap or touch
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+}
+
+/// An affine transform of the plane, i.e. a linear map followed by a translation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Affine2 {
+    /// the linear part, row by row
+    pub matrix: [[f64; 2]; 2],
+    /// added after applying the linear part
+    pub translation: Vector,
+}
+
+impl Affine2 {
+    /// leaves every point where it is
+    pub const IDENTITY: Self = Affine2 {
+        matrix: [[1.0, 0.0], [0.0, 1.0]],
+        translation: Vector::ZERO,
+    };
+
+    /// counterclockwise around the origin
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Affine2 {
+            matrix: [[cos, -sin], [sin, cos]],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// scales each axis separately, a negative factor mirrors the plane
+    pub fn scaling(x: f64, y: f64) -> Self {
+        Affine2 {
+            matrix: [[x, 0.0], [0.0, y]],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// moves every point along the x axis by `factor` times its y coordinate
+    pub fn shear(factor: f64) -> Self {
+        Affine2 {
+            matrix: [[1.0, factor], [0.0, 1.0]],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// moves every point by `offset`
+    pub fn translation(offset: Vector) -> Self {
+        Affine2 {
+            translation: offset,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// the transform applying `self` first and `next` after it
+    pub fn then(self, next: Affine2) -> Self {
+        let [[a, b], [c, d]] = next.matrix;
+        let [[e, f], [g, h]] = self.matrix;
+        Affine2 {
+            matrix: [
+                [a * e + b * g, a * f + b * h],
+                [c * e + d * g, c * f + d * h],
+            ],
+            translation: next.apply(self.translation),
+        }
+    }
+
+    /// where the transform moves the point
+    pub fn apply(&self, point: Point) -> Point {
+        self.apply_linear(point) + self.translation
+    }
+
+    /// applies only the linear part, as directions don't depend on where they start
+    pub fn apply_linear(&self, vector: Vector) -> Vector {
+        let [[a, b], [c, d]] = self.matrix;
+        Point(a * vector.0 + b * vector.1, c * vector.0 + d * vector.1)
+    }
+
+    /// how many times areas grow, negative if the transform mirrors the plane
+    pub fn determinant(&self) -> f64 {
+        let [[a, b], [c, d]] = self.matrix;
+        a * d - b * c
+    }
+}
+
+/// the vertices of a polygon without the ones closer than `EPSILON` to the previous vertex,
+/// the last vertex also being compared with the first one
+pub fn merge_close_vertices(vertices: Vec<Point>) -> Vec<Point> {
+    let mut merged: Vec<Point> = Vec::with_capacity(vertices.len());
+    for vertex in vertices {
+        if !merged
+            .last()
+            .is_some_and(|last| last.is_close_enough_to(vertex))
+        {
+            merged.push(vertex);
+        }
+    }
+    if merged.len() > 1 && merged[0].is_close_enough_to(merged[merged.len() - 1]) {
+        merged.pop();
+    }
+    merged
+}
+
+/// how far along the ray from `from` in the unit `direction` it crosses the segment from `start`
+/// to `end`, if it does at all
+pub fn ray_crossing(from: Point, direction: Vector, start: Point, end: Point) -> Option<f64> {
+    let edge = start.to(end);
+    let denominator = direction.cross(edge);
+    if denominator.abs() < EPSILON {
+        return None;
+    }
+
+    let to_start = from.to(start);
+    let distance = to_start.cross(edge) / denominator;
+    let along_edge = to_start.cross(direction) / denominator;
+    (distance >= 0.0 && (0.0..=1.0).contains(&along_edge)).then_some(distance)
+}
+
+/// how far along the ray from `from` in the unit `direction` it enters the circle, zero if it
+/// starts inside
+pub fn ray_circle_crossing(from: Point, direction: Vector, circle: Circle) -> Option<f64> {
+    let to_center = from.to(circle.center);
+    let along = to_center.dot(direction);
+    let off_ray_squared = to_center.dot(to_center) - along * along;
+    let radius_squared = circle.radius * circle.radius;
+    if off_ray_squared > radius_squared {
+        return None;
+    }
+
+    let half_chord = (radius_squared - off_ray_squared).sqrt();
+    if along + half_chord < 0.0 {
+        None
+    } else {
+        Some((along - half_chord).max(0.0))
+    }
+}
+
+/// the distance from `point` to the closest point of the segment from `start` to `end`
+pub fn distance_to_segment(point: Point, start: Point, end: Point) -> f64 {
+    let edge = start.to(end);
+    let length_squared = edge.dot(edge);
+    let along = if length_squared < EPSILON * EPSILON {
+        0.0
+    } else {
+        (start.to(point).dot(edge) / length_squared).clamp(0.0, 1.0)
+    };
+    (start + edge * along).to(point).norm()
+}
+
+/// true if `point` lies inside the polygon, which doesn't have to be convex
+pub fn is_inside(point: Point, vertices: &[Point]) -> bool {
+    let Point(x, y) = point;
+    windows::Looped::from(vertices.iter().copied())
+        .filter(|[Point(_, first_y), Point(_, second_y)]| (*first_y > y) != (*second_y > y))
+        .filter(|[Point(first_x, first_y), Point(second_x, second_y)]| {
+            x < first_x + (y - first_y) * (second_x - first_x) / (second_y - first_y)
+        })
+        .count()
+        % 2
+        == 1
 }
 
 #[cfg(test)]
@@ -172,6 +518,88 @@ mod test {
             .rotate(PI / 2.0)
             .is_close_enough_to(Point(0.0, 1.0)))
     }
+
+    #[test]
+    fn test_affine_composition() {
+        let transform = Affine2::rotation(PI / 2.0).then(Affine2::translation(Point(1.0, 0.0)));
+
+        assert!(transform
+            .apply(Point(1.0, 0.0))
+            .is_close_enough_to(Point(1.0, 1.0)));
+        assert!(transform
+            .apply_linear(Point(1.0, 0.0))
+            .is_close_enough_to(Point(0.0, 1.0)));
+        assert!((transform.determinant() - 1.0).abs() < EPSILON);
+
+        let sheared = Affine2::shear(0.5).then(Affine2::scaling(-2.0, 1.0));
+        assert!(sheared
+            .apply(Point(1.0, 2.0))
+            .is_close_enough_to(Point(-4.0, 2.0)));
+        assert!((sheared.determinant() + 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_polygon_centroid_follows_vertices() {
+        // a right triangle, whose centroid is a third of the way along its legs
+        let mut polygon = Polygon {
+            vertices: vec![Point(1.0, 0.0), Point(4.0, 0.0), Point(1.0, 3.0)],
+        };
+        assert!(polygon.centroid().is_close_enough_to(Point(2.0, 1.0)));
+
+        // the angle is only as exact as an f32
+        polygon.rotate((PI / 2.0) as f32);
+        assert!(polygon.centroid().to(Point(-1.0, 2.0)).norm() < 1e-6);
+        polygon.translate(Point(1.0, -2.0));
+        assert!(polygon.centroid().norm() < 1e-6);
+
+        let line = Polygon {
+            vertices: vec![Point(0.0, 0.0), Point(1.0, 1.0), Point(2.0, 2.0)],
+        };
+        assert!(line.centroid().is_close_enough_to(Point(1.0, 1.0)));
+    }
+
+    #[test]
+    fn test_laser_sweep_is_exact() {
+        let mut laser = Laser {
+            point: Point(0.0, 0.0),
+            initial_direction: Point(0.6, 0.8),
+            current_angle: 0.0,
+            angular_speed: 0.013,
+            range: 0.5,
+        };
+
+        let mut extremes = vec![];
+        for _ in 0..100_000 {
+            let speed = laser.angular_speed;
+            laser.sweep();
+            assert!(laser.current_angle.abs() <= laser.range);
+            assert!((laser.direction().norm() - 1.0).abs() < EPSILON);
+            if laser.angular_speed != speed {
+                extremes.push(laser.current_angle);
+            }
+        }
+
+        assert!(extremes.len() > 100);
+        for (i, &angle) in extremes.iter().enumerate() {
+            assert_eq!(angle, if i % 2 == 0 { 0.5 } else { -0.5 });
+        }
+    }
+
+    #[test]
+    fn test_laser_old_format() {
+        let old: Laser = ron::from_str(
+            "(point: (1.0, 0.0), direction: (0.0, 1.0), change: 0.01, range: 2.0,
+            inital_direction: (1.0, 0.0), is_out: false)",
+        )
+        .unwrap();
+        assert!((old.current_angle - PI / 2.0).abs() < EPSILON);
+        assert_eq!(old.angular_speed, 0.01);
+        assert!(old.direction().is_close_enough_to(Point(0.0, 1.0)));
+
+        let saved: Laser = ron::from_str(&ron::to_string(&old).unwrap()).unwrap();
+        assert_eq!(saved.current_angle, old.current_angle);
+        assert_eq!(saved.initial_direction, old.initial_direction);
+    }
 }
 
 /// An iterator very much like the standard library [std::slice::Windows], [`std::slice::Windows`],
@@ -179,6 +607,8 @@ mod test {
 pub mod windows {
     use std::mem::{self, MaybeUninit};
 
+    /// Yields every `N` consecutive items, including the ones wrapping around from the last item
+    /// to the first, so `N - 1` more than the underlying iterator
     pub struct Looped<I: Iterator, const N: usize> {
         items: I,
         state: Option<State<I::Item, N>>,
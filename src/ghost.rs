@@ -0,0 +1,319 @@
+//! time-trial ghosts: the path of the ball through a level sampled at a fixed interval, saved
+//! after a personal best so it can be shared and raced against with `--ghost <file>`
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::Point,
+    persist::{self, Persist, PersistError},
+    physics::PRESETS,
+};
+
+/// how often the position of the ball is sampled
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+/// the directory personal bests are saved to, in the directory the game is run from
+pub const GHOST_DIRECTORY: &str = "ghosts";
+pub const GHOST_EXTENSION: &str = "ghost";
+pub(crate) const GHOST_ARG: &str = "--ghost";
+
+/// Reasons for which a ghost can't be raced against
+#[derive(Debug, thiserror::Error)]
+pub enum GhostError {
+    #[error("couldn't read or write the ghost: {0}")]
+    Io(#[from] io::Error),
+    #[error("the file isn't a ghost: {0}")]
+    Parse(PersistError),
+    #[error("the ghost was recorded in {found}, not in {expected}")]
+    WrongLevel { expected: String, found: String },
+    #[error("the ghost was recorded with the {found} physics, not with the {expected} one")]
+    WrongPreset { expected: String, found: String },
+    #[error("the ghost's samples aren't evenly spaced over its time, it may have been cut short")]
+    Spacing,
+}
+
+impl From<PersistError> for GhostError {
+    fn from(err: PersistError) -> Self {
+        match err {
+            PersistError::Io(err) => GhostError::Io(err),
+            err => GhostError::Parse(err),
+        }
+    }
+}
+
+/// A finished run through a level
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ghost {
+    /// the name of the level file without its extension, see [`crate::heatmap::level_name`]
+    pub level: String,
+    /// the physics preset the run was played with, see [`crate::physics::PhysicsParams::preset`].
+    /// Ghosts recorded before there were presets were played with the classic one
+    #[serde(default = "classic_preset")]
+    pub preset: String,
+    /// how long the run took, in milliseconds
+    pub time_ms: u64,
+    /// the time between two samples, in milliseconds
+    pub interval_ms: u64,
+    /// where the ball was, from the start of the run to its end
+    pub samples: Vec<(f32, f32)>,
+}
+
+fn classic_preset() -> String {
+    PRESETS[0].to_string()
+}
+
+impl Ghost {
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+
+    /// where the ball was `elapsed` into the run, between the samples around it, or `None` once
+    /// the run is over
+    pub fn position_at(&self, elapsed: Duration) -> Option<Point> {
+        if elapsed > self.time() || self.interval_ms == 0 {
+            return None;
+        }
+        let sample = elapsed.as_secs_f64() * 1000.0 / self.interval_ms as f64;
+        let i = sample as usize;
+        let point = |i: usize| {
+            let (x, y) = *self.samples.get(i).or(self.samples.last())?;
+            Some(Point(x as f64, y as f64))
+        };
+        let (from, to) = (point(i)?, point(i + 1)?);
+        Some(from + (to - from) * sample.fract())
+    }
+
+    /// checks that the samples cover the whole time of the run at the interval, give or take
+    /// the sample the run ended between
+    fn validate(&self) -> Result<(), GhostError> {
+        if self.interval_ms == 0 || self.samples.is_empty() {
+            return Err(GhostError::Spacing);
+        }
+        let expected = self.time_ms / self.interval_ms + 1;
+        if (self.samples.len() as u64).abs_diff(expected) > 1 {
+            return Err(GhostError::Spacing);
+        }
+        Ok(())
+    }
+
+    /// reads a ghost to race against in the level
+    pub fn load(path: &Path, level: &str) -> Result<Self, GhostError> {
+        let ghost: Ghost = persist::load(path)?;
+        if ghost.level != level {
+            return Err(GhostError::WrongLevel {
+                expected: level.to_string(),
+                found: ghost.level,
+            });
+        }
+        ghost.validate()?;
+        Ok(ghost)
+    }
+
+    /// checks that the ghost was played with the preset, as times of different ones can't be
+    /// compared
+    pub fn check_preset(&self, preset: &str) -> Result<(), GhostError> {
+        if self.preset != preset {
+            return Err(GhostError::WrongPreset {
+                expected: preset.to_string(),
+                found: self.preset.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// where the best run of the level with the preset is kept in the directory:
+    /// `<level>.ghost` for the classic preset and `<level>.<preset>.ghost` for the others
+    pub fn best_path(directory: &Path, level: &str, preset: &str) -> PathBuf {
+        if preset == PRESETS[0] {
+            directory.join(format!("{level}.{GHOST_EXTENSION}"))
+        } else {
+            directory.join(format!("{level}.{preset}.{GHOST_EXTENSION}"))
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GhostError> {
+        Ok(persist::save(path, self)?)
+    }
+
+    /// saves the run to [`Ghost::best_path`] if there's no faster one there yet, returning
+    /// where it was saved
+    pub fn save_if_best(&self, directory: &Path) -> Result<Option<PathBuf>, GhostError> {
+        let path = Ghost::best_path(directory, &self.level, &self.preset);
+        match Ghost::load(&path, &self.level) {
+            Ok(best) if best.time_ms <= self.time_ms => return Ok(None),
+            Err(GhostError::Io(err)) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err.into())
+            }
+            // a best which can't be read is replaced
+            _ => {}
+        }
+        fs::create_dir_all(directory)?;
+        self.save(&path)?;
+        Ok(Some(path))
+    }
+}
+
+impl Persist for Ghost {
+    const VERSION: u32 = 1;
+}
+
+/// reads `--ghost <file>`, ignoring other arguments
+pub fn ghost_path_from_args(args: impl IntoIterator<Item = String>) -> Option<PathBuf> {
+    args.into_iter()
+        .skip_while(|arg| arg != GHOST_ARG)
+        .nth(1)
+        .map(PathBuf::from)
+}
+
+/// The path of the ball in the current run, sampled as the run goes
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    elapsed: Duration,
+    samples: Vec<(f32, f32)>,
+}
+
+impl Recorder {
+    /// how long the current run has taken
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// moves the run on by the time step, at the end of which the ball is at `ball`
+    pub fn advance(&mut self, time_step: Duration, Point(x, y): Point) {
+        self.elapsed += time_step;
+        while self.samples.len() as u32 * SAMPLE_INTERVAL <= self.elapsed {
+            self.samples.push((x as f32, y as f32));
+        }
+    }
+
+    /// goes back to the moment `elapsed` into the run, forgetting where the ball went after
+    pub fn rewind_to(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed.min(self.elapsed);
+        let kept = (self.elapsed.as_millis() / SAMPLE_INTERVAL.as_millis()) as usize + 1;
+        self.samples.truncate(kept);
+    }
+
+    /// starts a new run, as when the ball is returned to its starting position
+    pub fn restart(&mut self) {
+        *self = Recorder::default();
+    }
+
+    /// the run so far as a ghost of the level played with the preset
+    pub fn ghost(&self, level: &str, preset: &str) -> Ghost {
+        Ghost {
+            level: level.to_string(),
+            preset: preset.to_string(),
+            time_ms: self.elapsed.as_millis() as u64,
+            interval_ms: SAMPLE_INTERVAL.as_millis() as u64,
+            samples: self.samples.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(steps: u32) -> Recorder {
+        let mut recorder = Recorder::default();
+        for i in 1..=steps {
+            recorder.advance(Duration::from_millis(10), Point(i as f64 * 0.01, 0.5));
+        }
+        recorder
+    }
+
+    #[test]
+    fn test_ghost_file_round_trip() {
+        let directory = std::env::temp_dir().join(format!("whisky-ghost-{}", std::process::id()));
+        let ghost = run(123).ghost("level1", "classic");
+        assert_eq!(ghost.time_ms, 1230);
+        assert_eq!(ghost.samples.len(), 25);
+
+        let path = ghost.save_if_best(&directory).unwrap().unwrap();
+        assert_eq!(path, directory.join("level1.ghost"));
+        assert_eq!(Ghost::load(&path, "level1").unwrap(), ghost);
+        assert!(matches!(
+            Ghost::load(&path, "level2"),
+            Err(GhostError::WrongLevel { .. })
+        ));
+        // only a faster run replaces the best one
+        assert_eq!(
+            run(150)
+                .ghost("level1", "classic")
+                .save_if_best(&directory)
+                .unwrap(),
+            None
+        );
+        assert!(run(100)
+            .ghost("level1", "classic")
+            .save_if_best(&directory)
+            .unwrap()
+            .is_some());
+        // a slower run with another preset doesn't compete with the classic ones
+        let floaty = run(150).ghost("level1", "floaty");
+        assert_eq!(
+            floaty.save_if_best(&directory).unwrap(),
+            Some(directory.join("level1.floaty.ghost"))
+        );
+        assert!(matches!(
+            floaty.check_preset("classic"),
+            Err(GhostError::WrongPreset { .. })
+        ));
+        // ghosts saved before there were presets were played with the classic one
+        let old = ron::to_string(&ghost)
+            .unwrap()
+            .replace("preset:\"classic\",", "");
+        assert!(!old.contains("preset"));
+        fs::write(&path, old).unwrap();
+        assert_eq!(Ghost::load(&path, "level1").unwrap(), ghost);
+
+        let mut cut_short = run(100).ghost("level1", "classic");
+        cut_short.samples.truncate(10);
+        cut_short.save(&path).unwrap();
+        assert!(matches!(
+            Ghost::load(&path, "level1"),
+            Err(GhostError::Spacing)
+        ));
+        fs::write(&path, "(level: \"level1\", time_ms: ").unwrap();
+        assert!(matches!(
+            Ghost::load(&path, "level1"),
+            Err(GhostError::Parse(_))
+        ));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_ghost_path_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            ghost_path_from_args(args(&["level1.ron", "--ghost", "friend.ghost"])),
+            Some(PathBuf::from("friend.ghost"))
+        );
+        assert_eq!(ghost_path_from_args(args(&["level1.ron", "--ghost"])), None);
+    }
+
+    #[test]
+    fn test_position_between_samples() {
+        let ghost = run(100).ghost("level1", "classic");
+        let Point(x, _) = ghost.position_at(Duration::from_millis(75)).unwrap();
+        // the ball moves 0.01 every 10 ms, so it's halfway between the samples at 50 and 100 ms
+        assert!((x - 0.075).abs() < 1e-6);
+        assert_eq!(ghost.position_at(Duration::from_millis(1001)), None);
+    }
+
+    #[test]
+    fn test_rewinding_forgets_later_samples() {
+        let mut recorder = run(100);
+        recorder.rewind_to(Duration::from_millis(420));
+        assert_eq!(recorder.elapsed(), Duration::from_millis(420));
+        assert_eq!(recorder.samples.len(), 9);
+        recorder.restart();
+        assert_eq!(recorder.elapsed(), Duration::ZERO);
+    }
+}
@@ -1,6 +1,8 @@
 use crossbeam::channel;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::thread::JoinHandle;
+use std::time::Instant;
 use std::vec;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::format::Format;
@@ -10,14 +12,18 @@ use vulkano::pipeline::{GraphicsPipeline, Pipeline};
 use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer},
-    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBufferAbstract},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        CopyImageToBufferInfo, PrimaryCommandBufferAbstract,
+    },
     descriptor_set::allocator::StandardDescriptorSetAllocator,
-    image::{view::ImageView, ImageAccess, MipmapsCount, SwapchainImage},
+    device::{Device, Queue},
+    image::{view::ImageView, ImageAccess, SwapchainImage},
     memory::allocator::StandardMemoryAllocator,
     pipeline::graphics::viewport::Viewport,
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
     swapchain::{
-        acquire_next_image, AcquireError, SwapchainCreateInfo, SwapchainCreationError,
+        acquire_next_image, AcquireError, Swapchain, SwapchainCreateInfo, SwapchainCreationError,
         SwapchainPresentInfo,
     },
     sync::{self, FlushError, GpuFuture},
@@ -27,41 +33,96 @@ use winit::event::{ElementState, KeyboardInput};
 use winit::{
     event::{Event, WindowEvent},
     event_loop::ControlFlow,
-    window::Window,
+    window::{Fullscreen, Window},
 };
 
 use vertex::Vertex;
 
-use crate::game_logic::GameState;
-use crate::geometry::{windows, Circle, Point};
+use crate::assets;
+use crate::camera::{Camera, CameraSettings, View};
+use crate::game_logic::{GameState, MouseMode};
+use crate::geometry::{windows, Aabb, Affine2, Circle, Point, Vector, EPSILON};
 use crate::graphics_engine::monospace::Monospace;
+use crate::graphics_engine::recording::Recorder;
 use crate::graphics_engine::render_pass::SimpleShapes;
-use crate::physics::{DisplayMessage, WithColor};
+use crate::levels::BackgroundSpec;
+use crate::physics::{DisplayMessage, LevelPreview, Player, WithColor};
 use crate::InputMessage;
 
 use self::draw_text::DrawText;
 
 use super::geometry::Polygon;
 
+mod background;
 mod draw_text;
+mod grid;
 mod monospace;
+mod objective;
+mod paint;
+mod recording;
 mod render_pass;
 mod setup;
+mod squash;
 mod texture;
+mod upload;
 mod vertex;
 
 pub struct VertexBuffers {
-    background: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    /// what's drawn behind everything else, a color and the textures drawn over it
+    clear_color: [f32; 3],
+    backgrounds: Vec<BackgroundLayer>,
     polygons: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    /// the entities drawn with textures, `None` if there are none
+    textured: Option<Arc<CpuAccessibleBuffer<[Vertex]>>>,
     circles: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    /// translucent dots drawn over the level: the paint marks the ball left, the ghost being
+    /// raced against, and where the ball was lost in earlier games in editor mode
+    dots: Option<Arc<CpuAccessibleBuffer<[Vertex]>>>,
     level_status: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    /// drawn last, over everything else
+    door_preview: Option<DoorPreviewLayer>,
+}
+
+/// The level behind a door, drawn into its own part of the window
+pub struct DoorPreviewLayer {
+    viewport: Viewport,
+    polygons: Arc<CpuAccessibleBuffer<[Vertex]>>,
+}
+
+/// A texture covering the whole window
+pub struct BackgroundLayer {
+    texture: Arc<PersistentDescriptorSet>,
+    quad: Arc<CpuAccessibleBuffer<[Vertex]>>,
 }
 
 pub struct Textures {
-    background: texture::Texture,
+    /// the images and the background of the level last received, which may still be uploading
+    requested: Option<(Vec<String>, Option<BackgroundSpec>)>,
+    background: background::Background,
     test_set: texture::Texture,
     ball: texture::Texture,
     level: texture::Texture,
+    /// the images of the current level's entities, reloaded whenever the level changes
+    entities: texture::EntityTextures,
+}
+
+/// What a texture being uploaded replaces once it's on the GPU
+enum Upload {
+    /// the images of the level's entities, the texture left out
+    Entities(texture::EntityTextures),
+    /// the frames of the level's background
+    Background { frames: u32, fps: f32 },
+}
+
+impl Upload {
+    /// the background is smoothed, as it shimmers when the camera zooms out otherwise, while
+    /// the entities keep their pixels sharp
+    fn sampling(&self, support: texture::SamplingSupport) -> texture::Sampling {
+        match self {
+            Upload::Entities(_) => texture::Sampling::Nearest,
+            Upload::Background { .. } => texture::Sampling::Smooth(support),
+        }
+    }
 }
 
 pub struct Pipelines {
@@ -71,21 +132,106 @@ pub struct Pipelines {
     circle_pipeline: Arc<GraphicsPipeline>,
 }
 
-/// Runs simple graphics engine, as argument takes channel providing Polygon data to be drawn
+/// The Vulkan objects living as long as the window, destroyed in order by `shutdown` rather
+/// than in whatever order the event loop happens to drop what it captured
+struct Gpu {
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    uploads: upload::UploadQueue<Upload>,
+    textures: Textures,
+    pipelines: Pipelines,
+    render_pass: Arc<RenderPass>,
+    /// the text drawn over the frame
+    text: DrawText,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    memory_allocator: StandardMemoryAllocator,
+    images: Vec<Arc<SwapchainImage>>,
+    swapchain: Arc<Swapchain>,
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    validation: Option<setup::Validation>,
+}
+
+impl Gpu {
+    /// waits for the GPU to finish the last frame, then destroys everything, each object
+    /// before the ones it was created from
+    fn shutdown(self) {
+        let Gpu {
+            previous_frame_end,
+            framebuffers,
+            uploads,
+            textures,
+            pipelines,
+            render_pass,
+            text,
+            descriptor_set_allocator,
+            command_buffer_allocator,
+            memory_allocator,
+            images,
+            swapchain,
+            queue,
+            device,
+            validation,
+        } = self;
+
+        if let Some(frame) = previous_frame_end {
+            if let Err(err) = frame
+                .then_signal_fence_and_flush()
+                .and_then(|fence| fence.wait(None))
+            {
+                eprintln!("couldn't wait for the last frame: {err:?}");
+            }
+        }
+        // nothing is submitted anymore, so nothing else uses the device while it's waited for
+        if let Err(err) = unsafe { device.wait_idle() } {
+            eprintln!("couldn't wait for the GPU to finish: {err}");
+        }
+
+        drop(framebuffers);
+        drop(uploads);
+        drop(textures);
+        drop(pipelines);
+        drop(render_pass);
+        drop(text);
+        drop(descriptor_set_allocator);
+        drop(command_buffer_allocator);
+        drop(memory_allocator);
+        drop(images);
+        drop(swapchain);
+        drop(queue);
+        drop(device);
+        // kept until the end, to report problems with destroying everything else
+        if let Some(validation) = validation {
+            eprintln!("validation layer errors: {}", validation.errors());
+        }
+    }
+}
+
+/// Runs simple graphics engine, as argument takes channel providing Polygon data to be drawn.
+/// With `validation` set the Vulkan validation layer checks the renderer, if it's installed,
+/// and with `stress_uploads` the textures of the level are uploaded again every few seconds,
+/// reporting the frames which take too long meanwhile. Once the window closes the `physics`
+/// thread is told to quit and waited for, so it can save what's kept between runs
 pub fn run(
     channel: channel::Receiver<DisplayMessage>,
     mut messages: channel::Sender<InputMessage>,
     mut game_state: GameState,
+    validation: bool,
+    stress_uploads: bool,
+    physics: JoinHandle<()>,
 ) {
     let setup::Init {
         device,
         queue,
         surface,
         event_loop,
-        mut swapchain,
+        swapchain,
         images,
         max_sample_count,
-    } = setup::init();
+        sampling_support,
+        validation,
+    } = setup::init(validation);
 
     let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
 
@@ -104,9 +250,6 @@ pub fn run(
         texture_array_pipeline,
         texture_pipeline,
     };
-    let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
-
-    let dimensions = window.inner_size();
 
     let mut first_frame = AutoCommandBufferBuilder::primary(
         &command_buffer_allocator,
@@ -121,82 +264,43 @@ pub fn run(
 
     let test_set = texture::Texture::new(
         device.clone(),
-        &["assets/images/pineapple.png"],
+        &[assets::path("images/pineapple.png")],
         &memory_allocator,
         &mut first_frame,
-        MipmapsCount::One,
+        texture::Sampling::Nearest,
         pipelines.texture_pipeline.clone(),
         &descriptor_set_allocator,
     );
 
     let ball = texture::Texture::new(
         device.clone(),
-        &["assets/images/ball.png"],
+        &[assets::path("images/ball.png")],
         &memory_allocator,
         &mut first_frame,
-        MipmapsCount::One,
+        texture::Sampling::Nearest,
         pipelines.texture_pipeline.clone(),
         &descriptor_set_allocator,
     );
 
-    let background_set = texture::Texture::new(
-        device.clone(),
-        &[
-            "assets/images/background/0001.png",
-            "assets/images/background/0002.png",
-            "assets/images/background/0003.png",
-            "assets/images/background/0004.png",
-            "assets/images/background/0005.png",
-            "assets/images/background/0006.png",
-            "assets/images/background/0007.png",
-            "assets/images/background/0008.png",
-            "assets/images/background/0009.png",
-            "assets/images/background/0010.png",
-            "assets/images/background/0011.png",
-            "assets/images/background/0012.png",
-            "assets/images/background/0013.png",
-            "assets/images/background/0014.png",
-            "assets/images/background/0015.png",
-            "assets/images/background/0016.png",
-            "assets/images/background/0017.png",
-            "assets/images/background/0018.png",
-            "assets/images/background/0019.png",
-            "assets/images/background/0020.png",
-            "assets/images/background/0021.png",
-            "assets/images/background/0022.png",
-            "assets/images/background/0023.png",
-            "assets/images/background/0024.png",
-        ],
-        &memory_allocator,
-        &mut first_frame,
-        MipmapsCount::One,
-        pipelines.texture_array_pipeline.clone(),
-        &descriptor_set_allocator,
-    );
-
     let level_status_set = texture::Texture::new(
         device.clone(),
-        &[
-            "assets/images/file-tree-0-green.png",
-            "assets/images/file-tree-1-green.png",
-            "assets/images/file-tree-2-green.png",
-            "assets/images/file-tree-3-green.png",
-            "assets/images/file-tree-4-green.png",
-            "assets/images/file-tree-5-green.png",
-            "assets/images/file-tree-6-green.png",
-        ],
+        &(0..7)
+            .map(|i| assets::path(format!("images/file-tree-{i}-green.png")))
+            .collect::<Vec<_>>(),
         &memory_allocator,
         &mut first_frame,
-        MipmapsCount::One,
+        texture::Sampling::Smooth(sampling_support),
         pipelines.texture_array_pipeline.clone(),
         &descriptor_set_allocator,
     );
 
     let game_textures = Textures {
-        background: background_set,
+        requested: None,
+        background: Default::default(),
         test_set,
         ball,
         level: level_status_set,
+        entities: Default::default(),
     };
 
     let mut viewport = Viewport {
@@ -204,7 +308,7 @@ pub fn run(
         dimensions: [0.0, 0.0],
         depth_range: 0.0..1.0,
     };
-    let mut framebuffers = window_size_dependent_setup(
+    let framebuffers = window_size_dependent_setup(
         &images,
         render_pass.clone(),
         &mut viewport,
@@ -213,7 +317,7 @@ pub fn run(
     );
 
     let mut recreate_swapchain = false;
-    let mut previous_frame_end = Some(
+    let previous_frame_end = Some(
         first_frame
             .build()
             .unwrap()
@@ -223,15 +327,70 @@ pub fn run(
     );
 
     let mut is_first_run = true;
+    // the shapes of the level, as displayed, which are moved into the view at every frame
     let mut circles_vertices = vec![];
     let mut polygons_vertices = vec![];
+    let mut entity_vertices = vec![];
+    // what's drawn over the level where it is in the window, wherever the camera looks
+    let mut hud_vertices = vec![];
     let mut lvl_idx = 0;
+    // where the grid is drawn, and how far it's turned
+    let mut level_bounds = Aabb {
+        min: Point(-1.0, -1.0),
+        max: Point(1.0, 1.0),
+    };
+    let mut world_angle = 0.0;
+    let mut camera = Camera::new(
+        CameraSettings {
+            reduce_motion: game_state.reduce_motion,
+            ..Default::default()
+        },
+        Point(0.0, 0.0),
+    );
+    // the part of the window the level behind a door is previewed in, and what's drawn there
+    let mut door_preview = None;
+    let mut deaths = Arc::default();
+    let mut notes = Arc::default();
+    // the ghost's ball, the same size as the player's
+    let mut ghost = None;
+    // whether the player was last told the ball is stuck, so they're told once each time
+    let mut is_stuck = false;
+    let mut squash = squash::Squash::default();
+    let mut paint_marks = paint::PaintMarks::default();
+    let mut objective_arrow = objective::ObjectiveArrow::default();
+    // the ball's speed, shown in debug mode
+    let mut speed_readout = None;
+    let mut last_received = Instant::now();
 
     let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
     window.set_cursor_visible(false);
-    let mut timer = Instant::now();
+    // the mouse mode the cursor's visibility was last set for
+    let mut cursor_mode = MouseMode::Tilt;
+    // the size to go back to when leaving fullscreen
+    let mut windowed_size = None;
+    let mut recorder = Recorder::new(&game_state.recording_directory);
+    let mut was_recording = false;
+    let mut stress_test = stress_uploads.then(upload::StressTest::default);
 
-    let mut animation_or_sth = 0;
+    let text = DrawText::new(device.clone(), queue.clone(), swapchain.clone(), &images);
+    let mut physics = Some(physics);
+    let mut gpu = Some(Gpu {
+        previous_frame_end,
+        framebuffers,
+        uploads: Default::default(),
+        textures: game_textures,
+        pipelines,
+        render_pass,
+        text,
+        descriptor_set_allocator,
+        command_buffer_allocator,
+        memory_allocator,
+        images,
+        swapchain,
+        queue,
+        device,
+        validation,
+    });
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
@@ -244,7 +403,26 @@ pub fn run(
             event: WindowEvent::CursorMoved { position, .. },
             ..
         } => {
-            game_state.handle_mouse_moved(position, dimensions, &mut messages);
+            let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
+            game_state.handle_mouse_moved(position, window.inner_size(), &mut messages);
+        }
+        Event::WindowEvent {
+            event: WindowEvent::MouseInput { state, button, .. },
+            ..
+        } => {
+            game_state.handle_mouse_input(state, button, &mut messages);
+        }
+        Event::WindowEvent {
+            event: WindowEvent::MouseWheel { delta, .. },
+            ..
+        } => {
+            game_state.handle_mouse_wheel(delta, &mut messages);
+        }
+        Event::WindowEvent {
+            event: WindowEvent::ModifiersChanged(modifiers),
+            ..
+        } => {
+            game_state.modifiers = modifiers;
         }
         Event::WindowEvent {
             event: WindowEvent::KeyboardInput { input, .. },
@@ -255,13 +433,19 @@ pub fn run(
                     state: ElementState::Pressed,
                     virtual_keycode: Some(winit::event::VirtualKeyCode::Escape),
                     ..
-                } => {
+                } if game_state.console.is_none() => {
                     *control_flow = ControlFlow::Exit;
                 }
                 _ => {}
             };
             game_state.handle_keyboard_input(input, &mut messages);
         }
+        Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(character),
+            ..
+        } => {
+            game_state.handle_received_character(character);
+        }
         Event::WindowEvent {
             event: WindowEvent::Resized(_),
             ..
@@ -269,6 +453,9 @@ pub fn run(
             recreate_swapchain = true;
         }
         Event::RedrawEventsCleared => {
+            let Some(gpu) = gpu.as_mut() else {
+                return;
+            };
             if is_first_run {
                 println!("texture loaded");
                 is_first_run = false;
@@ -276,6 +463,26 @@ pub fn run(
 
             // window section
             let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
+            if game_state.is_fullscreen != window.fullscreen().is_some() {
+                if game_state.is_fullscreen {
+                    windowed_size = Some(window.inner_size());
+                    window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                } else {
+                    window.set_fullscreen(None);
+                    if let Some(size) = windowed_size.take() {
+                        window.set_inner_size(size);
+                    }
+                }
+                // some platforms show the cursor again after the switch, and its position
+                // relative to the window is meaningless in the new size
+                window.set_cursor_visible(game_state.mouse_mode == MouseMode::Draw);
+                game_state.reset_position = true;
+                recreate_swapchain = true;
+            }
+            if cursor_mode != game_state.mouse_mode {
+                cursor_mode = game_state.mouse_mode;
+                window.set_cursor_visible(cursor_mode == MouseMode::Draw);
+            }
             let dimensions = window.inner_size();
             if game_state.reset_position {
                 window
@@ -284,59 +491,54 @@ pub fn run(
                         dimensions.height / 2,
                     ))
                     .unwrap();
+                // the cursor jumping back mustn't count as the player moving it
+                game_state.mouse_position = [0.0, 0.0];
                 game_state.reset_position = false;
             }
             if dimensions.width == 0 || dimensions.height == 0 {
                 return;
             }
 
-            previous_frame_end.as_mut().unwrap().cleanup_finished();
+            gpu.previous_frame_end.as_mut().unwrap().cleanup_finished();
 
             if recreate_swapchain {
-                let (new_swapchain, new_images) = match swapchain.recreate(SwapchainCreateInfo {
-                    image_extent: dimensions.into(),
-                    image_usage: ImageUsage {
-                        transfer_src: false,
-                        transfer_dst: true,
-                        sampled: true,
-                        storage: false,
-                        color_attachment: true,
-                        depth_stencil_attachment: false,
-                        transient_attachment: false,
-                        input_attachment: false,
-                        ..Default::default()
-                    },
-                    ..swapchain.create_info()
-                }) {
-                    Ok(r) => r,
-                    Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
-                    Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
-                };
+                let (new_swapchain, new_images) =
+                    match gpu.swapchain.recreate(SwapchainCreateInfo {
+                        image_extent: dimensions.into(),
+                        image_usage: ImageUsage {
+                            transfer_src: gpu.swapchain.image_usage().transfer_src,
+                            transfer_dst: true,
+                            sampled: true,
+                            storage: false,
+                            color_attachment: true,
+                            depth_stencil_attachment: false,
+                            transient_attachment: false,
+                            input_attachment: false,
+                            ..Default::default()
+                        },
+                        ..gpu.swapchain.create_info()
+                    }) {
+                        Ok(r) => r,
+                        Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
+                        Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
+                    };
 
-                swapchain = new_swapchain;
-                framebuffers = window_size_dependent_setup(
+                gpu.swapchain = new_swapchain;
+                gpu.framebuffers = window_size_dependent_setup(
                     &new_images,
-                    render_pass.clone(),
+                    gpu.render_pass.clone(),
                     &mut viewport,
-                    &memory_allocator,
+                    &gpu.memory_allocator,
                     max_sample_count,
                 );
-
-                // draw_text = DrawText::new(
-                //     device.clone(),
-                //     queue.clone(),
-                //     swapchain.clone(),
-                //     &new_images,
-                //     &memory_allocator,
-                //     [dimensions.width as u32, dimensions.height as u32],
-                //     max_sample_count,
-                // );
+                gpu.text.recreate(&new_images);
+                gpu.images = new_images;
 
                 recreate_swapchain = false;
             }
 
             let (image_index, suboptimal, acquire_future) =
-                match acquire_next_image(swapchain.clone(), None) {
+                match acquire_next_image(gpu.swapchain.clone(), None) {
                     Ok(r) => r,
                     Err(AcquireError::OutOfDate) => {
                         recreate_swapchain = true;
@@ -349,77 +551,321 @@ pub fn run(
                 recreate_swapchain = true;
             }
 
+            if let Some(stress_test) = &mut stress_test {
+                if stress_test.frame(!gpu.uploads.is_empty()) {
+                    gpu.textures.requested = None;
+                }
+            }
+
+            // the textures which are completely uploaded replace the ones shown so far
+            for (upload, image) in gpu.uploads.ready() {
+                let texture = texture::Texture::from_image(
+                    gpu.device.clone(),
+                    image,
+                    upload.sampling(sampling_support),
+                    gpu.pipelines.texture_array_pipeline.clone(),
+                    &gpu.descriptor_set_allocator,
+                );
+                match upload {
+                    Upload::Entities(entities) => {
+                        gpu.textures.entities = texture::EntityTextures {
+                            texture: Some(texture),
+                            ..entities
+                        }
+                    }
+                    Upload::Background { frames, fps } => gpu
+                        .textures
+                        .background
+                        .show(background::Look::image(texture, frames, fps)),
+                }
+            }
+
             match channel.try_recv() {
                 Ok(received) => {
-                    (polygons_vertices, circles_vertices) = format_data((
-                        received.polygons,
-                        received.circles,
-                        received.lasers,
-                        received.laser_boxes,
-                        received.doors,
-                    ));
+                    // the level changed, or this is the first frame
+                    let previous = gpu
+                        .textures
+                        .requested
+                        .replace((received.textures.clone(), received.background.clone()));
+                    let are_textures_changed = previous
+                        .as_ref()
+                        .is_none_or(|(textures, _)| *textures != received.textures);
+                    let is_background_changed = previous
+                        .as_ref()
+                        .is_none_or(|(_, background)| *background != received.background);
+                    if are_textures_changed {
+                        gpu.uploads
+                            .cancel(|upload| matches!(upload, Upload::Entities(_)));
+                        let (entities, array) = texture::EntityTextures::decode(&received.textures);
+                        // the images of the previous level don't fit the entities of this one,
+                        // so they are drawn with their colors until their own are uploaded
+                        gpu.textures.entities = Default::default();
+                        match array {
+                            Some((size, pixels)) => gpu.uploads.push(
+                                Upload::Entities(entities),
+                                size,
+                                pixels,
+                                // sampled as they are, see `Upload::sampling`
+                                false,
+                                &gpu.memory_allocator,
+                                gpu.queue.queue_family_index(),
+                            ),
+                            None => gpu.textures.entities = entities,
+                        }
+                    }
+                    if is_background_changed {
+                        gpu.uploads
+                            .cancel(|upload| matches!(upload, Upload::Background { .. }));
+                        // the previous background is shown until this one is uploaded
+                        match background::select_or_default(received.background.as_ref()) {
+                            background::Source::Color(color) => {
+                                gpu.textures.background.show(background::Look::Color(color))
+                            }
+                            background::Source::Frames(background::Frames {
+                                size: (width, height),
+                                count,
+                                pixels,
+                                fps,
+                            }) => {
+                                let background = Upload::Background { frames: count, fps };
+                                let has_mipmaps =
+                                    background.sampling(sampling_support).has_mipmaps();
+                                gpu.uploads.push(
+                                    background,
+                                    (width, height, count),
+                                    pixels,
+                                    has_mipmaps,
+                                    &gpu.memory_allocator,
+                                    gpu.queue.queue_family_index(),
+                                )
+                            }
+                        }
+                    }
+                    level_bounds = received.level_bounds;
+                    // gravity is given relative to the level, which is turned the other way
+                    world_angle = received.gravity.angle_to(Point(0.0, -1.0));
+                    game_state.world_angle = world_angle;
+                    let ball = received.player.circle.center;
+                    if received.level_started {
+                        camera.start_level(turned(level_bounds, world_angle), ball);
+                    }
+                    if let Some(at) = received.ball_lost {
+                        camera.ball_lost(at);
+                    }
+                    camera.update(ball, received.player.velocity, last_received.elapsed());
+                    // the camera bounds turned with the level
+                    let camera_bounds = turned(received.camera_bounds, world_angle);
+                    game_state.view = camera.view(camera_bounds);
+                    squash.update(last_received.elapsed());
+                    paint_marks.update(last_received.elapsed());
+                    let objectives = &received.objectives;
+                    game_state.objective = game_state
+                        .objective
+                        .filter(|_| !objectives.is_empty())
+                        .map(|objective| objective % objectives.len());
+                    game_state.shown_objective = game_state
+                        .objective
+                        .or_else(|| objective::nearest(objectives, received.player.circle.center));
+                    objective_arrow.update(
+                        game_state
+                            .shown_objective
+                            .map(|objective| objectives[objective]),
+                        game_state.view.visible(),
+                        last_received.elapsed(),
+                    );
+                    last_received = Instant::now();
+                    if received.clear_paint {
+                        paint_marks.clear();
+                    }
+                    paint_marks.add(received.paint_marks);
+                    if let Some(impact) = received.player.impact {
+                        if !game_state.reduce_motion {
+                            squash.hit(impact);
+                        }
+                    }
+                    if received.stuck && !is_stuck {
+                        println!("stuck? press R to restart or X twice to erase what you've drawn");
+                    }
+                    is_stuck = received.stuck;
+                    speed_readout = received
+                        .is_debugging
+                        .then(|| format!("speed: {:.2}", received.player.speed()));
+                    deaths = received.deaths;
+                    notes = received.notes;
+                    ghost = received.ghost.map(|center| Circle {
+                        center,
+                        radius: received.player.circle.radius,
+                    });
+                    let preview_frame = match received.door_preview {
+                        Some(preview) => {
+                            let view = game_state.view;
+                            let door = Aabb {
+                                min: view.to_window(preview.door.min),
+                                max: view.to_window(preview.door.max),
+                            };
+                            let area = preview_area(door);
+                            door_preview = Some((
+                                area,
+                                polygon_vertices(preview_shapes(preview.level.as_deref())),
+                            ));
+                            preview_frame(area)
+                        }
+                        None => {
+                            door_preview = None;
+                            vec![]
+                        }
+                    };
+                    (polygons_vertices, circles_vertices, entity_vertices) = format_data(
+                        (
+                            received.polygons,
+                            received.circles,
+                            received.lasers,
+                            received.laser_boxes,
+                            received.doors,
+                            received.launch_arrow,
+                            trajectory_dots(received.trajectory),
+                            spin_indicator(received.player),
+                            weld_markers(&received.welds, &received.unbound_welds)
+                                .into_iter()
+                                .chain(load_markers(&received.binding_loads))
+                                .chain(objective_arrow.shapes())
+                                .collect(),
+                        ),
+                        &gpu.textures.entities.layers,
+                        (ball, squash.transform(ball)),
+                    );
+                    hud_vertices = polygon_vertices(
+                        gravity_indicator(received.gravity)
+                            .into_iter()
+                            .chain(preview_frame),
+                    );
                     lvl_idx = received.level_idx;
+                    game_state.selected = received.selected;
                 }
                 Err(channel::TryRecvError::Disconnected) => *control_flow = ControlFlow::Exit,
                 _ => {}
             }
 
-            let vertex_buffer_polygons =
-                create_vertex_buffer(&memory_allocator, polygons_vertices.clone());
+            if game_state.is_recording && !gpu.swapchain.image_usage().transfer_src {
+                eprintln!("frames can't be recorded, they can't be copied out of the window");
+                game_state.is_recording = false;
+            }
+            if game_state.is_recording != was_recording {
+                if game_state.is_recording {
+                    println!(
+                        "recording to {}, the game runs slower while recording",
+                        recorder.directory().display()
+                    );
+                } else {
+                    println!("recording stopped");
+                }
+                was_recording = game_state.is_recording;
+            }
+
+            // every interactive element drawn below registers itself with `game_state.ui`
+            game_state.ui.begin_frame();
+
+            let preview = game_state
+                .level_shape_preview()
+                .map_or(vec![], |vertices| ghost_outline(&vertices));
+            // drawn first, so it stays behind everything else
+            let grid = if game_state.is_grid_shown {
+                grid::grid(level_bounds, game_state.grid_spacing, world_angle)
+            } else {
+                vec![]
+            };
+            let view = game_state.view;
+            let mut level_polygons: Vec<_> = polygon_vertices(grid)
+                .into_iter()
+                .chain(polygons_vertices.iter().cloned())
+                .chain(polygon_vertices(preview))
+                .collect();
+            show_through(view, &mut level_polygons);
+            let vertex_buffer_polygons = create_vertex_buffer(
+                &gpu.memory_allocator,
+                level_polygons
+                    .into_iter()
+                    .chain(hud_vertices.iter().cloned())
+                    .collect::<Vec<_>>(),
+            );
+
+            let vertex_buffer_textured = (!entity_vertices.is_empty()).then(|| {
+                let mut textured = entity_vertices.clone();
+                show_through(view, &mut textured);
+                create_vertex_buffer(&gpu.memory_allocator, textured)
+            });
+
+            let mut dots: Vec<_> = paint_marks
+                .visible()
+                .flat_map(|(at, color, transparency)| {
+                    let mark = Circle {
+                        center: at.rotate(world_angle),
+                        radius: PAINT_MARK_RADIUS,
+                    };
+                    dot(mark, color, transparency)
+                })
+                .chain(
+                    ghost
+                        .into_iter()
+                        .flat_map(|ghost| dot(ghost, [0.9, 0.9, 1.0], GHOST_TRANSPARENCY)),
+                )
+                .chain(if game_state.is_editing {
+                    let mut dots = death_dots(&deaths, world_angle);
+                    dots.extend(note_dots(&notes, world_angle));
+                    dots
+                } else {
+                    vec![]
+                })
+                .collect();
+            show_through(view, &mut dots);
+            let vertex_buffer_dots =
+                (!dots.is_empty()).then(|| create_vertex_buffer(&gpu.memory_allocator, dots));
 
             let vertex_buffer_circles = if !circles_vertices.is_empty() {
-                create_vertex_buffer(&memory_allocator, circles_vertices.clone())
+                let mut circles = circles_vertices.clone();
+                show_through(view, &mut circles);
+                create_vertex_buffer(&gpu.memory_allocator, circles)
             } else {
-                create_vertex_buffer(&memory_allocator, [Vertex::default(); 3])
+                create_vertex_buffer(&gpu.memory_allocator, [Vertex::default(); 3])
             };
 
             let mut builder = AutoCommandBufferBuilder::primary(
-                &command_buffer_allocator,
-                queue.queue_family_index(),
+                &gpu.command_buffer_allocator,
+                gpu.queue.queue_family_index(),
                 CommandBufferUsage::OneTimeSubmit,
             )
             .unwrap();
+            gpu.uploads.record(&mut builder, &gpu.memory_allocator);
 
-            if timer.elapsed() > Duration::from_millis(60) {
-                animation_or_sth = animation_or_sth + 1;
-                if animation_or_sth == 25 {
-                    animation_or_sth = 0;
-                }
-                timer = Instant::now();
-            }
-
-            let texture_buffer = create_vertex_buffer(
-                &memory_allocator,
-                [
-                    Vertex {
-                        position: [-1.0, -1.0],
-                        tex_position: [0.0, 0.0],
-                        texture_id: animation_or_sth,
-                        ..Default::default()
-                    },
-                    Vertex {
-                        position: [-1.0, 1.0],
-                        tex_position: [0.0, 1.0],
-                        texture_id: animation_or_sth,
-                        ..Default::default()
-                    },
-                    Vertex {
-                        position: [1.0, -1.0],
-                        tex_position: [1.0, 0.0],
-                        texture_id: animation_or_sth,
-                        ..Default::default()
-                    },
-                    Vertex {
-                        position: [1.0, 1.0],
-                        tex_position: [1.0, 1.0],
-                        texture_id: animation_or_sth,
+            let (clear_color, background_layers) = gpu.textures.background.layers();
+            let backgrounds = background_layers
+                .into_iter()
+                .map(|(texture, frame, transparency)| {
+                    let corners = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+                    let vertices = corners.map(|[x, y]| Vertex {
+                        position: [x * 2.0 - 1.0, y * 2.0 - 1.0],
+                        tex_position: [x, y],
+                        texture_id: frame,
+                        transparency,
                         ..Default::default()
-                    },
-                ],
-            );
+                    });
+                    BackgroundLayer {
+                        texture: texture.0.clone(),
+                        quad: create_vertex_buffer(&gpu.memory_allocator, vertices),
+                    }
+                })
+                .collect();
+
+            let door_preview_layer =
+                door_preview
+                    .as_ref()
+                    .map(|(area, vertices)| DoorPreviewLayer {
+                        viewport: preview_viewport(*area, viewport.dimensions),
+                        polygons: create_vertex_buffer(&gpu.memory_allocator, vertices.clone()),
+                    });
 
             let level_status_buffer = create_vertex_buffer(
-                &memory_allocator,
+                &gpu.memory_allocator,
                 [
                     Vertex {
                         position: [-0.9, -0.9],
@@ -450,50 +896,179 @@ pub fn run(
 
             SimpleShapes::render(
                 &mut builder,
-                &mut framebuffers,
+                &mut gpu.framebuffers,
                 image_index,
                 &mut viewport,
-                &game_textures,
-                &pipelines,
+                &gpu.textures,
+                &gpu.pipelines,
                 VertexBuffers {
-                    background: texture_buffer.clone(),
+                    clear_color,
+                    backgrounds,
                     polygons: vertex_buffer_polygons,
+                    textured: vertex_buffer_textured,
                     circles: vertex_buffer_circles,
+                    dots: vertex_buffer_dots,
                     level_status: level_status_buffer,
+                    door_preview: door_preview_layer,
                 },
             );
+            let [width, height] = gpu.swapchain.image_extent();
+            for (i, (line, is_highlighted)) in game_state.inspector_lines().iter().enumerate() {
+                let color = if *is_highlighted {
+                    INSPECTOR_HIGHLIGHT_COLOR
+                } else {
+                    INSPECTOR_COLOR
+                };
+                let baseline = HUD_MARGIN + f64::from(HUD_TEXT_SIZE) * (i + 1) as f64;
+                queue_hud_line(&mut gpu.text, Point(HUD_MARGIN, baseline), line, color);
+            }
+            if game_state.is_editing {
+                let cursor = game_state.cursor();
+                let window = [width, height];
+                queue_notes(&mut gpu.text, &notes, (world_angle, view), cursor, window);
+            }
+            if let Some(readout) = &speed_readout {
+                // above the console's line
+                let baseline = height as f64 - HUD_MARGIN - f64::from(HUD_TEXT_SIZE);
+                queue_hud_line(
+                    &mut gpu.text,
+                    Point(HUD_MARGIN, baseline),
+                    readout,
+                    DEBUG_COLOR,
+                );
+            }
+            if let Some(line) = &game_state.console {
+                queue_hud_line(
+                    &mut gpu.text,
+                    Point(HUD_MARGIN, height as f64 - HUD_MARGIN),
+                    &format!("> {line}"),
+                    CONSOLE_COLOR,
+                );
+            }
+            if let Some((_, note)) = &game_state.note {
+                queue_hud_line(
+                    &mut gpu.text,
+                    Point(HUD_MARGIN, height as f64 - HUD_MARGIN),
+                    &format!("note: {note}"),
+                    NOTE_COLOR,
+                );
+            }
+            gpu.text.draw_text(
+                &mut builder,
+                image_index as usize,
+                [width as usize, height as usize],
+                &gpu.descriptor_set_allocator,
+                &gpu.memory_allocator,
+            );
+            let frame_readback = game_state.is_recording.then(|| {
+                let [width, height] = gpu.swapchain.image_extent();
+                let buffer = CpuAccessibleBuffer::from_iter(
+                    &gpu.memory_allocator,
+                    BufferUsage {
+                        transfer_dst: true,
+                        ..BufferUsage::empty()
+                    },
+                    true,
+                    (0..width * height * 4).map(|_| 0u8),
+                )
+                .unwrap();
+                builder
+                    .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                        gpu.images[image_index as usize].clone(),
+                        buffer.clone(),
+                    ))
+                    .unwrap();
+                (buffer, width, height)
+            });
             let command_buffer = builder.build().unwrap();
 
-            let future = previous_frame_end
+            let future = gpu
+                .previous_frame_end
                 .take()
                 .unwrap()
                 .join(acquire_future)
-                .then_execute(queue.clone(), command_buffer)
+                .then_execute(gpu.queue.clone(), command_buffer)
                 .unwrap()
                 .then_swapchain_present(
-                    queue.clone(),
-                    SwapchainPresentInfo::swapchain_image_index(swapchain.clone(), image_index),
+                    gpu.queue.clone(),
+                    SwapchainPresentInfo::swapchain_image_index(gpu.swapchain.clone(), image_index),
                 )
                 .then_signal_fence_and_flush();
 
             match future {
                 Ok(future) => {
-                    previous_frame_end = Some(future.boxed());
+                    // vulkano only lets a fence be shared as an `Arc`, though it stays on
+                    // this thread
+                    #[allow(clippy::arc_with_non_send_sync)]
+                    let future = Arc::new(future);
+                    let fence = future.clone();
+                    gpu.uploads
+                        .submitted(move || fence.is_signaled().unwrap_or(true));
+                    if let Some((buffer, width, height)) = frame_readback {
+                        // waiting for every frame to be drawn is what slows the game down
+                        future.wait(None).unwrap();
+                        let format = gpu.swapchain.image_format();
+                        match recorder.save(&buffer.read().unwrap(), width, height, format) {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                eprintln!("the recorded frames take up too much space");
+                                game_state.is_recording = false;
+                            }
+                            Err(err) => {
+                                eprintln!("couldn't save a frame: {err}");
+                                game_state.is_recording = false;
+                            }
+                        }
+                    }
+                    gpu.previous_frame_end = Some(future.boxed());
                 }
                 Err(FlushError::OutOfDate) => {
                     recreate_swapchain = true;
-                    previous_frame_end = Some(sync::now(device.clone()).boxed());
+                    gpu.uploads.discarded();
+                    gpu.previous_frame_end = Some(sync::now(gpu.device.clone()).boxed());
                 }
                 Err(e) => {
                     println!("Failed to flush future: {:?}", e);
-                    previous_frame_end = Some(sync::now(device.clone()).boxed());
+                    gpu.uploads.discarded();
+                    gpu.previous_frame_end = Some(sync::now(gpu.device.clone()).boxed());
                 }
             }
         }
+        Event::LoopDestroyed => {
+            if let Some(gpu) = gpu.take() {
+                gpu.shutdown();
+            }
+            if let Some(physics) = physics.take() {
+                // the physics thread may have stopped already, e.g. after panicking
+                let _ = messages.send(InputMessage::Quit);
+                let _ = physics.join();
+            }
+        }
         _ => (),
     });
 }
 
+/// queues a line of the text drawn over the level, `at` the start of its baseline in pixels
+/// from the top left corner of the window, with a shadow keeping it readable over anything
+fn queue_hud_line(text: &mut DrawText, at: Point, line: &str, color: [f32; 3]) {
+    let Point(x, y) = at;
+    let [red, green, blue] = color;
+    text.queue_text(
+        x as f32 + HUD_SHADOW,
+        y as f32 + HUD_SHADOW,
+        HUD_TEXT_SIZE,
+        [0.0, 0.0, 0.0, 0.8],
+        line,
+    );
+    text.queue_text(
+        x as f32,
+        y as f32,
+        HUD_TEXT_SIZE,
+        [red, green, blue, 1.0],
+        line,
+    );
+}
+
 fn create_vertex_buffer(
     memory_allocator: &(impl MemoryAllocator + ?Sized),
     vertexes: impl IntoIterator<Item = Vertex, IntoIter = impl ExactSizeIterator<Item = Vertex>>,
@@ -510,159 +1085,619 @@ fn create_vertex_buffer(
     .unwrap()
 }
 
-/// Changes Polygon to correct order of Vertexes, also creates quads needed to draw cricles
+/// Small squares marking the predicted path of a launched ball
+fn trajectory_dots(trajectory: Vec<Point>) -> Vec<WithColor<Polygon>> {
+    const HALF_SIZE: f64 = 0.006;
+
+    trajectory
+        .into_iter()
+        .map(|Point(x, y)| WithColor {
+            color: [1.0, 1.0, 1.0],
+            shape: Polygon {
+                vertices: vec![
+                    Point(x - HALF_SIZE, y - HALF_SIZE),
+                    Point(x + HALF_SIZE, y - HALF_SIZE),
+                    Point(x + HALF_SIZE, y + HALF_SIZE),
+                    Point(x - HALF_SIZE, y + HALF_SIZE),
+                ],
+            },
+            texture: None,
+        })
+        .collect()
+}
+
+/// Thin strips along the edges of a shape which is about to be placed, so the level
+/// behind it stays visible
+fn ghost_outline(vertices: &[Point]) -> Vec<WithColor<Polygon>> {
+    const THICKNESS: f64 = 0.005;
+
+    windows::Looped::<_, 2>::from(vertices.iter().copied())
+        .filter(|[from, to]| !from.is_close_enough_to(*to))
+        .map(|[from, to]| {
+            let offset = from.to(to).perpendicular().unit() * THICKNESS;
+            WithColor {
+                color: [0.85, 0.95, 1.0],
+                shape: Polygon {
+                    vertices: vec![from, to, to + offset, from + offset],
+                },
+                texture: None,
+            }
+        })
+        .collect()
+}
+
+/// Diamonds where the welds are, hollow for the ones still waiting for a shape to be drawn
+/// over them
+fn weld_markers(welds: &[Point], unbound_welds: &[Point]) -> Vec<WithColor<Polygon>> {
+    const SIZE: f64 = 0.012;
+    const HOLE: f64 = 0.006;
+
+    let diamond = |center: Point, size: f64| {
+        vec![
+            center + Point(0.0, -size),
+            center + Point(size, 0.0),
+            center + Point(0.0, size),
+            center + Point(-size, 0.0),
+        ]
+    };
+    let marker = |center: Point, size: f64, color: [f32; 3]| WithColor {
+        color,
+        shape: Polygon {
+            vertices: diamond(center, size),
+        },
+        texture: None,
+    };
+
+    welds
+        .iter()
+        .chain(unbound_welds)
+        .map(|&weld| marker(weld, SIZE, [1.0, 0.6, 0.1]))
+        // the background is white, drawn over the unbound ones to hollow them out
+        .chain(
+            unbound_welds
+                .iter()
+                .map(|&weld| marker(weld, HOLE, [1.0, 1.0, 1.0])),
+        )
+        .collect()
+}
+
+/// the load drawn fully red. No binding can break, so rather than relative to a breaking
+/// point loads are shown relative to about what a 0.5 by 0.5 box hanging from one puts on it
+const FULL_LOAD: f64 = 0.005;
+
+/// green for an unloaded binding, through yellow to red for a fully loaded one
+fn load_color(load: f64) -> [f32; 3] {
+    let fraction = (load / FULL_LOAD).clamp(0.0, 1.0) as f32;
+    [
+        (2.0 * fraction).min(1.0),
+        (2.0 * (1.0 - fraction)).min(1.0),
+        0.0,
+    ]
+}
+
+/// Squares over every binding colored by the load it carries, only sent in debug mode
+fn load_markers(loads: &[(Point, f64)]) -> Vec<WithColor<Polygon>> {
+    const SIZE: f64 = 0.008;
+
+    loads
+        .iter()
+        .map(|&(center, load)| WithColor {
+            color: load_color(load),
+            shape: Polygon {
+                vertices: [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .map(|(x, y)| center + Point(x, y) * SIZE)
+                    .to_vec(),
+            },
+            texture: None,
+        })
+        .collect()
+}
+
+/// how see-through the ball of the ghost the player races against is
+const GHOST_TRANSPARENCY: f32 = 0.6;
+/// the size of the marks the ball leaves when it's covered in paint
+const PAINT_MARK_RADIUS: f64 = 0.012;
+/// how tall the text drawn over the level is, in pixels
+const HUD_TEXT_SIZE: f32 = 18.0;
+/// how far the text stays from the edges of the window, in pixels
+const HUD_MARGIN: f64 = 12.0;
+/// how far down and right of the text its shadow is, in pixels
+const HUD_SHADOW: f32 = 1.5;
+const CONSOLE_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+const DEBUG_COLOR: [f32; 3] = [0.6, 1.0, 0.6];
+/// the notes of level designers are marked with dots and numbered in this color in editing mode
+const NOTE_COLOR: [f32; 3] = [0.4, 0.7, 1.0];
+const NOTE_RADIUS: f64 = 0.01;
+/// the text of a note is shown while the cursor is this close to its marker
+const NOTE_HOVER_DISTANCE: f64 = 0.03;
+/// how far right of its marker the number of a note is, in pixels
+const NOTE_TEXT_OFFSET: f64 = 8.0;
+/// the inspector panel lists the fields of the selected entity in the top left corner, the one
+/// the arrows change in the color of the selection
+const INSPECTOR_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+const INSPECTOR_HIGHLIGHT_COLOR: [f32; 3] = [0.85, 0.2, 1.0];
+
+/// The two triangles a translucent dot is drawn on with the circle pipeline
+fn dot(Circle { center, radius }: Circle, color: [f32; 3], transparency: f32) -> [Vertex; 6] {
+    let flipped = |Point(x, y): Point| [x as f32, -y as f32];
+    [
+        (-1.0, -1.0),
+        (1.0, -1.0),
+        (1.0, 1.0),
+        (-1.0, -1.0),
+        (1.0, 1.0),
+        (-1.0, 1.0),
+    ]
+    .map(|(x, y)| {
+        let corner = center + Point(x, y) * radius;
+        Vertex {
+            position: flipped(corner),
+            tex_position: flipped(corner),
+            center: flipped(center),
+            radius: radius as f32,
+            color,
+            transparency,
+            ..Default::default()
+        }
+    })
+}
+
+/// Translucent red dots where the ball was lost in earlier games, the more opaque the more
+/// deaths there are around
+fn death_dots(deaths: &[(Point, f64)], world_angle: f64) -> Vec<Vertex> {
+    const RADIUS: f64 = 0.015;
+    // how see-through the dots are where deaths are the rarest and where they're the most common
+    const TRANSPARENCY: (f32, f32) = (0.8, 0.2);
+
+    deaths
+        .iter()
+        .flat_map(|&(at, crowded)| {
+            let circle = Circle {
+                center: at.rotate(world_angle),
+                radius: RADIUS,
+            };
+            let transparency = TRANSPARENCY.0 + (TRANSPARENCY.1 - TRANSPARENCY.0) * crowded as f32;
+            dot(circle, [0.9, 0.1, 0.1], transparency)
+        })
+        .collect()
+}
+
+/// Markers where the level's designers left notes
+fn note_dots(notes: &[(Point, String)], world_angle: f64) -> Vec<Vertex> {
+    notes
+        .iter()
+        .flat_map(|(at, _)| {
+            let marker = Circle {
+                center: at.rotate(world_angle),
+                radius: NOTE_RADIUS,
+            };
+            dot(marker, NOTE_COLOR, 0.0)
+        })
+        .collect()
+}
+
+/// queues the number of every note next to its marker, and the text of the note nearest to the
+/// cursor along with it if it's close enough
+fn queue_notes(
+    text: &mut DrawText,
+    notes: &[(Point, String)],
+    (world_angle, view): (f64, View),
+    cursor: Point,
+    window: [u32; 2],
+) {
+    let hovered = notes
+        .iter()
+        .enumerate()
+        .map(|(i, (at, _))| (i, at.rotate(world_angle).to(cursor).norm()))
+        .filter(|&(_, distance)| distance <= NOTE_HOVER_DISTANCE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i);
+    for (i, (at, note)) in notes.iter().enumerate() {
+        let line = if hovered == Some(i) {
+            format!("{}: {note}", i + 1)
+        } else {
+            (i + 1).to_string()
+        };
+        let at = view.to_window(at.rotate(world_angle));
+        let at = to_pixels(at, window) + Point(NOTE_TEXT_OFFSET, 0.0);
+        queue_hud_line(text, at, &line, NOTE_COLOR);
+    }
+}
+
+/// moves the vertices of shapes in the level, as displayed, to where the view shows them
+fn show_through(view: View, vertices: &mut [Vertex]) {
+    for vertex in vertices {
+        // the vertices are already flipped to the window's y axis, which points down
+        let [x, y] = vertex.position;
+        let Point(x, y) = view.to_window(Point(f64::from(x), -f64::from(y)));
+        vertex.position = [x as f32, -y as f32];
+    }
+}
+
+/// the box around `bounds`, given in the coordinates of the level, once it's turned with the
+/// world
+fn turned(bounds: Aabb, world_angle: f64) -> Aabb {
+    let Aabb { min, max } = bounds;
+    let corners = [min, Point(max.0, min.1), max, Point(min.0, max.1)];
+    Aabb::around(corners.map(|corner| corner.rotate(world_angle))).unwrap()
+}
+
+/// the point of the window, spanning from -1 to 1 both ways, in pixels from its top left corner
+fn to_pixels(Point(x, y): Point, [width, height]: [u32; 2]) -> Point {
+    Point(
+        (x + 1.0) / 2.0 * f64::from(width),
+        (1.0 - y) / 2.0 * f64::from(height),
+    )
+}
+
+/// how big the preview of the level behind a door is, in the coordinates of the window
+const PREVIEW_SIZE: f64 = 0.5;
+/// how far the preview is from the door
+const PREVIEW_GAP: f64 = 0.05;
+const PREVIEW_BORDER: f64 = 0.01;
+
+/// where the preview of the level behind a door goes: above the door, or below it if there's
+/// no room above, and always inside the window
+fn preview_area(door: Aabb) -> Aabb {
+    let x = ((door.min.0 + door.max.0 - PREVIEW_SIZE) / 2.0).clamp(-1.0, 1.0 - PREVIEW_SIZE);
+    let above = door.max.1 + PREVIEW_GAP;
+    let y = if above + PREVIEW_SIZE <= 1.0 {
+        above
+    } else {
+        (door.min.1 - PREVIEW_GAP - PREVIEW_SIZE).max(-1.0)
+    };
+    Aabb {
+        min: Point(x, y),
+        max: Point(x + PREVIEW_SIZE, y + PREVIEW_SIZE),
+    }
+}
+
+/// a rectangle filled with the color
+fn rectangle(Aabb { min, max }: Aabb, color: [f32; 3]) -> WithColor<Polygon> {
+    WithColor {
+        color,
+        shape: Polygon {
+            vertices: vec![min, Point(max.0, min.1), max, Point(min.0, max.1)],
+        },
+        texture: None,
+    }
+}
+
+/// The white card with a dark border the preview is drawn on
+fn preview_frame(area: Aabb) -> Vec<WithColor<Polygon>> {
+    vec![
+        rectangle(area.expanded(PREVIEW_BORDER), [0.2, 0.2, 0.2]),
+        rectangle(area, [1.0, 1.0, 1.0]),
+    ]
+}
+
+/// The shapes drawn in the preview's viewport, which spans from -1 to 1 both ways: the level
+/// scaled to fit, or a question mark if it couldn't be loaded
+fn preview_shapes(level: Option<&LevelPreview>) -> Vec<WithColor<Polygon>> {
+    const COLOR: [f32; 3] = [0.3, 0.3, 0.3];
+    // leaves a margin inside the frame
+    const FILL: f64 = 0.9;
+
+    let Some(LevelPreview { polygons, bounds }) = level else {
+        let bar = |min: (f64, f64), max: (f64, f64)| {
+            rectangle(
+                Aabb {
+                    min: Point(min.0, min.1),
+                    max: Point(max.0, max.1),
+                },
+                COLOR,
+            )
+        };
+        return vec![
+            bar((-0.3, 0.5), (0.3, 0.7)),
+            bar((0.1, 0.1), (0.3, 0.7)),
+            bar((-0.1, 0.1), (0.3, 0.3)),
+            bar((-0.1, -0.3), (0.1, 0.3)),
+            bar((-0.1, -0.7), (0.1, -0.5)),
+        ];
+    };
+    let center = (bounds.min + bounds.max) * 0.5;
+    let size = bounds.min.to(bounds.max);
+    let scale = 2.0 * FILL / size.0.max(size.1).max(EPSILON);
+    polygons
+        .iter()
+        .filter(|vertices| !vertices.is_empty())
+        .map(|vertices| {
+            let vertices: Vec<_> = vertices
+                .iter()
+                .map(|&vertex| (vertex - center) * scale)
+                .collect();
+            WithColor {
+                color: COLOR,
+                shape: Polygon { vertices },
+                texture: None,
+            }
+        })
+        .collect()
+}
+
+/// the part of a window of the given size in pixels the area of it covers
+fn preview_viewport(area: Aabb, [width, height]: [f32; 2]) -> Viewport {
+    // the window's coordinates go from -1 to 1 upwards, while pixels are counted downwards
+    let to_pixels = |Point(x, y): Point| {
+        [
+            (x as f32 + 1.0) / 2.0 * width,
+            (1.0 - y as f32) / 2.0 * height,
+        ]
+    };
+    let [left, top] = to_pixels(Point(area.min.0, area.max.1));
+    let [right, bottom] = to_pixels(Point(area.max.0, area.min.1));
+    Viewport {
+        origin: [left, top],
+        dimensions: [right - left, bottom - top],
+        depth_range: 0.0..1.0,
+    }
+}
+
+/// An arrow in the top right corner pointing where gravity pulls relative to the level,
+/// so the player can tell how far the world is tilted
+fn gravity_indicator(gravity: Vector) -> Vec<WithColor<Polygon>> {
+    const CENTER: Point = Point(0.85, 0.85);
+    const LENGTH: f64 = 0.12;
+    const HEAD_LENGTH: f64 = 0.04;
+    const SHAFT_WIDTH: f64 = 0.008;
+    const HEAD_WIDTH: f64 = 0.025;
+
+    let direction = gravity.unit();
+    let tail = CENTER - direction * (LENGTH / 2.0);
+    let tip = CENTER + direction * (LENGTH / 2.0);
+    let head_base = tip - direction * HEAD_LENGTH;
+    let shaft_offset = direction.perpendicular() * SHAFT_WIDTH;
+    let head_offset = direction.perpendicular() * HEAD_WIDTH;
+
+    [
+        vec![
+            tail + shaft_offset,
+            head_base + shaft_offset,
+            head_base - shaft_offset,
+            tail - shaft_offset,
+        ],
+        vec![head_base + head_offset, tip, head_base - head_offset],
+    ]
+    .into_iter()
+    .map(|vertices| WithColor {
+        color: [1.0, 1.0, 1.0],
+        shape: Polygon { vertices },
+        texture: None,
+    })
+    .collect()
+}
+
+/// An arc around the ball, starting above it and growing in the direction the ball spins.
+/// It gets thinner as the spin slows down and disappears once the ball barely spins
+fn spin_indicator(player: Player) -> Vec<WithColor<Polygon>> {
+    // in radians per second
+    const MIN_SPIN: f64 = 0.5;
+    const FADE_RANGE: f64 = 1.5;
+    const FULL_ARC_SPIN: f64 = 4.0 * PI;
+    const MAX_ARC: f64 = 1.5 * PI;
+    const GAP: f64 = 0.01;
+    const THICKNESS: f64 = 0.008;
+    const N_OF_SEGMENTS: usize = 16;
+
+    let spin = player.angular_velocity;
+    let fade = ((spin.abs() - MIN_SPIN) / FADE_RANGE).min(1.0);
+    if fade <= 0.0 {
+        return vec![];
+    }
+
+    let arc = MAX_ARC * (spin.abs() / FULL_ARC_SPIN).min(1.0) * spin.signum();
+    let inner = player.circle.radius + GAP;
+    let outer = inner + THICKNESS * fade;
+    let center = player.circle.center;
+    let at = |segment: usize, distance: f64| {
+        let angle = FRAC_PI_2 + arc * segment as f64 / N_OF_SEGMENTS as f64;
+        center + Point(angle.cos(), angle.sin()) * distance
+    };
+
+    (0..N_OF_SEGMENTS)
+        .map(|segment| WithColor {
+            color: [0.3, 0.9, 1.0],
+            shape: Polygon {
+                vertices: vec![
+                    at(segment, inner),
+                    at(segment, outer),
+                    at(segment + 1, outer),
+                    at(segment + 1, inner),
+                ],
+            },
+            texture: None,
+        })
+        .collect()
+}
+
+const POLYGON_TEX_POSITIONS: [[f32; 2]; 4] = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+
+/// Order in which the vertices of a convex polygon are passed to the triangle strip,
+/// alternating between the end and the beginning of its outline
+fn triangle_strip_order(n_of_vertices: usize) -> impl Iterator<Item = usize> {
+    let (mut front, mut back) = (0, n_of_vertices);
+    (0..n_of_vertices).map(move |k| {
+        if k % 2 == 0 {
+            back -= 1;
+            back
+        } else {
+            front += 1;
+            front - 1
+        }
+    })
+}
+
+/// Triangle strip vertices of convex polygons
+fn polygon_vertices(polygons: impl IntoIterator<Item = WithColor<Polygon>>) -> Vec<Vertex> {
+    polygons
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, pol)| {
+            let vertex = |index: usize| {
+                let Point(x, y) = pol.shape.vertices[index];
+                Vertex {
+                    texture_id: i as u32,
+                    position: [x as f32, -y as f32],
+                    ..Default::default()
+                }
+            };
+            let strip = triangle_strip_order(pol.shape.vertices.len()).collect::<Vec<_>>();
+
+            // the repeated first and last vertices form degenerate triangles
+            // separating consecutive polygons in the strip
+            std::iter::once(vertex(strip[0]))
+                .chain(strip.iter().enumerate().map(|(k, &index)| Vertex {
+                    color: pol.color,
+                    tex_position: POLYGON_TEX_POSITIONS[k % POLYGON_TEX_POSITIONS.len()],
+                    ..vertex(index)
+                }))
+                .chain(std::iter::once(vertex(*strip.last().unwrap())))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Triangle strip vertices of convex shapes drawn with a layer of the entity textures, which is
+/// stretched over the bounding box of each shape
+fn textured_vertices(shapes: impl IntoIterator<Item = (Vec<Point>, u32)>) -> Vec<Vertex> {
+    shapes
+        .into_iter()
+        .flat_map(|(vertices, layer)| {
+            let Some(Aabb { min, max }) = Aabb::around(vertices.iter().copied()) else {
+                return vec![];
+            };
+            let size = min.to(max);
+            let strip = triangle_strip_order(vertices.len())
+                .map(|index| {
+                    let Point(x, y) = vertices[index];
+                    Vertex {
+                        texture_id: layer,
+                        position: [x as f32, -y as f32],
+                        // the top of the image is at the top of the shape
+                        tex_position: [
+                            ((x - min.0) / size.0) as f32,
+                            ((max.1 - y) / size.1) as f32,
+                        ],
+                        ..Default::default()
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // separates consecutive shapes like in `polygon_vertices`
+            std::iter::once(strip[0])
+                .chain(strip.iter().copied())
+                .chain(std::iter::once(*strip.last().unwrap()))
+                .collect()
+        })
+        .collect()
+}
+
+/// how many sides the polygon a textured circle is drawn as has, as the textured pipeline
+/// has no circle mask
+const TEXTURED_CIRCLE_SIDES: usize = 32;
+
+/// Changes Polygon to correct order of Vertexes, also creates quads needed to draw cricles.
+/// The entities with a texture in one of the `layers` go to the third list, drawn textured
 fn format_data(
-    (polygons, circles, lasers, laser_boxes, doors): (
+    (
+        polygons,
+        circles,
+        lasers,
+        laser_boxes,
+        doors,
+        launch_arrow,
+        trajectory,
+        spin_indicator,
+        weld_markers,
+    ): (
         Vec<WithColor<Polygon>>,
         Vec<WithColor<Circle>>,
         Vec<WithColor<Polygon>>,
         Vec<WithColor<Polygon>>,
         Vec<WithColor<Polygon>>,
+        Vec<WithColor<Polygon>>,
+        Vec<WithColor<Polygon>>,
+        Vec<WithColor<Polygon>>,
+        Vec<WithColor<Polygon>>,
     ),
-) -> (Vec<Vertex>, Vec<Vertex>) {
+    layers: &[Option<u32>],
+    (ball, ball_transform): (Point, Affine2),
+) -> (Vec<Vertex>, Vec<Vertex>, Vec<Vertex>) {
+    let layer = |texture: Option<usize>| layers.get(texture?).copied().flatten();
+    let (textured_polygons, polygons): (Vec<_>, Vec<_>) = polygons
+        .into_iter()
+        .partition(|polygon| layer(polygon.texture).is_some());
+    let (textured_circles, circles): (Vec<_>, Vec<_>) = circles
+        .into_iter()
+        .partition(|circle| layer(circle.texture).is_some());
+    let textured_vertexes = textured_vertices(
+        textured_polygons
+            .into_iter()
+            .map(|polygon| (polygon.shape.vertices, layer(polygon.texture).unwrap()))
+            .chain(textured_circles.into_iter().map(|circle| {
+                let Circle { center, radius } = circle.shape;
+                // the image fills the bounds of the outline, so what's outside of the circle
+                // is cut off
+                let outline = (0..TEXTURED_CIRCLE_SIDES)
+                    .map(|i| {
+                        center
+                            + Point(radius, 0.0)
+                                .rotate(TAU * i as f64 / TEXTURED_CIRCLE_SIDES as f64)
+                    })
+                    .collect();
+                (outline, layer(circle.texture).unwrap())
+            })),
+    );
+
     let array = polygons
         .into_iter()
         .chain(lasers.into_iter())
         .chain(laser_boxes.into_iter())
-        .chain(doors.into_iter());
-    let polygons_vertexes = array
-        .enumerate()
-        .flat_map(|(i, pol)| {
-            std::iter::once(Vertex {
-                texture_id: i as u32,
-                position: [
-                    pol.shape.vertices.last().unwrap().0 as f32,
-                    -pol.shape.vertices.last().unwrap().1 as f32,
-                ],
-                ..Default::default()
-            })
-            .chain(if pol.shape.vertices.len() == 4 {
-                vec![
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[3].0 as f32,
-                            -pol.shape.vertices[3].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [0.0, 0.0],
-                        ..Default::default()
-                    },
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[0].0 as f32,
-                            -pol.shape.vertices[0].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [0.0, 1.0],
-                        ..Default::default()
-                    },
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[2].0 as f32,
-                            -pol.shape.vertices[2].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [1.0, 0.0],
-                        ..Default::default()
-                    },
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[1].0 as f32,
-                            -pol.shape.vertices[1].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [0.0, 0.0],
-                        ..Default::default()
-                    },
-                ]
-                .into_iter()
-            } else {
-                vec![
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[2].0 as f32,
-                            -pol.shape.vertices[2].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [0.0, 0.0],
-                        ..Default::default()
-                    },
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[0].0 as f32,
-                            -pol.shape.vertices[0].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [1.0, 0.0],
-                        ..Default::default()
-                    },
-                    Vertex {
-                        texture_id: i as u32,
-                        position: [
-                            pol.shape.vertices[1].0 as f32,
-                            -pol.shape.vertices[1].1 as f32,
-                        ],
-                        color: pol.color,
-                        tex_position: [1.0, 0.0],
-                        ..Default::default()
-                    },
-                ]
-                .into_iter()
-            })
-            .chain(std::iter::once(Vertex {
-                texture_id: i as u32,
-                position: [
-                    pol.shape.vertices[1].0 as f32,
-                    -pol.shape.vertices[1].1 as f32,
-                ],
-                ..Default::default()
-            }))
-            .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+        .chain(doors.into_iter())
+        .chain(launch_arrow)
+        .chain(trajectory)
+        .chain(spin_indicator)
+        .chain(weld_markers);
+    let polygons_vertexes = polygon_vertices(array);
     let circles_vertexes = circles
         .into_iter()
         .flat_map(|circle| {
-            let color = circle.color;
-            let center = [circle.shape.center.0 as f32, -circle.shape.center.1 as f32];
-            let radius = circle.shape.radius;
-            let center_y = -circle.shape.center.1;
-            let center_x = circle.shape.center.0;
-            let positions = [
-                [(center_x - radius) as f32, (center_y + radius) as f32],
-                [(center_x - radius) as f32, (center_y - radius) as f32],
-                [(center_x + radius) as f32, (center_y + radius) as f32],
-                [(center_x + radius) as f32, (center_y - radius) as f32],
-            ];
-            create_circle_vertices(positions, radius as f32, center, color)
+            let Circle { center, radius } = circle.shape;
+            // only the ball is deformed, it's the circle drawn at its center
+            let transform = if center == ball {
+                ball_transform
+            } else {
+                Affine2::IDENTITY
+            };
+            let corners = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)]
+                .map(|(x, y)| center + Point(x, y) * radius);
+            create_circle_vertices(corners, transform, radius as f32, center, circle.color)
         })
         .collect::<Vec<_>>();
 
-    (polygons_vertexes, circles_vertexes)
+    (polygons_vertexes, circles_vertexes, textured_vertexes)
 }
 
+/// The quad a circle is drawn on, deformed by the transform. The shader cuts the circle out
+/// at the positions the corners had before, passed as their texture positions, so the circle
+/// is deformed along with the quad
 fn create_circle_vertices(
-    positions: [[f32; 2]; 4],
+    corners: [Point; 4],
+    transform: Affine2,
     radius: f32,
-    center: [f32; 2],
+    center: Point,
     color: [f32; 3],
 ) -> Vec<Vertex> {
-    let tex_coords = [[0.2, 0.8], [0.2, 0.2], [0.8, 0.8], [0.8, 0.2]];
-    positions
+    let flipped = |Point(x, y): Point| [x as f32, -y as f32];
+    corners
         .into_iter()
-        .enumerate()
-        .map(|(i, position)| Vertex {
-            position,
+        .map(|corner| Vertex {
+            position: flipped(transform.apply(corner)),
             radius,
-            center,
+            center: flipped(center),
             color: [1.0, 0.0, 1.0],
-            tex_position: tex_coords[i],
+            tex_position: flipped(corner),
             ..Default::default()
         })
         .collect()
@@ -720,3 +1755,195 @@ fn window_size_dependent_setup(
         })
         .collect::<Vec<_>>()
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::levels::{Entity, Level};
+    use crate::physics::EngineBuilder;
+
+    #[test]
+    fn test_load_color() {
+        assert_eq!(load_color(0.0), [0.0, 1.0, 0.0]);
+        assert_eq!(load_color(FULL_LOAD / 2.0), [1.0, 1.0, 0.0]);
+        assert_eq!(load_color(FULL_LOAD), [1.0, 0.0, 0.0]);
+        assert_eq!(load_color(10.0 * FULL_LOAD), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_death_dots() {
+        let dots = death_dots(&[(Point(0.5, 0.0), 0.0), (Point(0.0, 0.5), 1.0)], FRAC_PI_2);
+        assert_eq!(dots.len(), 12);
+        // turned along with the level, with the y axis flipped for the screen
+        assert!((dots[0].center[0] - 0.0).abs() < 1e-6 && (dots[0].center[1] + 0.5).abs() < 1e-6);
+        // crowded places are more opaque
+        assert!(dots[6].transparency < dots[0].transparency);
+    }
+
+    #[test]
+    fn test_preview_stays_in_window() {
+        let door = |min: (f64, f64), max: (f64, f64)| Aabb {
+            min: Point(min.0, min.1),
+            max: Point(max.0, max.1),
+        };
+        let is_inside = |area: Aabb| {
+            area.min.0 >= -1.0 && area.min.1 >= -1.0 && area.max.0 <= 1.0 && area.max.1 <= 1.0
+        };
+
+        let above = preview_area(door((-0.1, -0.5), (0.1, -0.3)));
+        assert!(is_inside(above));
+        assert!(above.min.1 > -0.3);
+        // there's no room above a door at the top of the window
+        let below = preview_area(door((0.8, 0.7), (1.0, 0.9)));
+        assert!(is_inside(below));
+        assert!(below.max.1 < 0.7);
+
+        let viewport = preview_viewport(above, [800.0, 600.0]);
+        assert_eq!(viewport.dimensions, [200.0, 150.0]);
+        assert_eq!(viewport.origin[0], 300.0);
+    }
+
+    #[test]
+    fn test_preview_fits_level() {
+        let level = LevelPreview {
+            polygons: vec![vec![Point(2.0, 1.0), Point(6.0, 1.0), Point(6.0, 2.0)]],
+            bounds: Aabb {
+                min: Point(2.0, 0.0),
+                max: Point(6.0, 2.0),
+            },
+        };
+        for shapes in [preview_shapes(Some(&level)), preview_shapes(None)] {
+            assert!(!shapes.is_empty());
+            for vertex in shapes.iter().flat_map(|shape| &shape.shape.vertices) {
+                assert!(vertex.0.abs() <= 1.0 && vertex.1.abs() <= 1.0);
+            }
+        }
+        // the wider side of the level spans most of the preview
+        let vertices = &preview_shapes(Some(&level))[0].shape.vertices;
+        assert!((vertices[1].0 - vertices[0].0 - 1.8).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_gravity_indicator_points_along_gravity() {
+        let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5))).build();
+        let ball = engine.main_ball_id();
+        engine.tilt_by(0.5);
+        // the world turns towards the target angle gradually
+        for _ in 0..20 {
+            engine.step(Duration::from_millis(10));
+        }
+
+        let mut positions = vec![];
+        for _ in 0..3 {
+            engine.step(Duration::from_millis(10));
+            positions.push(engine.entity_position(ball).unwrap());
+        }
+        let acceleration = (positions[2] - positions[1]) - (positions[1] - positions[0]);
+
+        let indicator = gravity_indicator(engine.gravity_direction());
+        let shaft = &indicator[0].shape.vertices;
+        let tail = (shaft[0] + shaft[3]) / 2.0;
+        let tip = indicator[1].shape.vertices[1];
+
+        let expected = Point(0.0, -1.0).rotate(-0.5);
+        assert!(tail.to(tip).unit().is_close_enough_to(expected));
+        assert!(acceleration.unit().is_close_enough_to(expected));
+    }
+
+    #[test]
+    fn test_textured_entity_is_drawn_with_its_layer() {
+        let square = |x: f64| {
+            vec![
+                Point(x, 0.0),
+                Point(x + 0.2, 0.0),
+                Point(x + 0.2, 0.2),
+                Point(x, 0.2),
+            ]
+        };
+        let mut level = Level::new(Point(0.0, 0.5));
+        let mut missing = Entity::new(square(-0.6), true);
+        missing.texture = Some("assets/images/missing.png".into());
+        let mut textured = Entity::new(square(0.4), true);
+        textured.texture = Some("assets/images/crate.png".into());
+        level.polygons = vec![missing, Entity::new(square(-0.2), true), textured];
+
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+        engine.step(Duration::from_millis(10));
+        let frame = rx.try_recv().unwrap();
+        assert_eq!(
+            frame.textures,
+            vec!["assets/images/missing.png", "assets/images/crate.png"]
+        );
+
+        // the missing image couldn't be loaded, so the first polygon keeps its color
+        let (polygons, _, textured) = format_data(
+            (
+                frame.polygons,
+                frame.circles,
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+            ),
+            &[None, Some(0)],
+            (Point::ZERO, Affine2::IDENTITY),
+        );
+        assert_eq!(polygons.len(), 2 * 6);
+        assert_eq!(textured.len(), 6);
+        for vertex in &textured {
+            assert_eq!(vertex.texture_id, 0);
+            assert!(vertex.position[0] >= 0.4 - 1e-6);
+            assert!(vertex
+                .tex_position
+                .iter()
+                .all(|coordinate| (0.0..=1.0).contains(coordinate)));
+        }
+    }
+
+    #[test]
+    fn test_to_pixels_counts_from_top_left() {
+        assert_eq!(to_pixels(Point(-1.0, 1.0), [800, 600]), Point(0.0, 0.0));
+        assert_eq!(to_pixels(Point(0.0, 0.0), [800, 600]), Point(400.0, 300.0));
+        assert_eq!(to_pixels(Point(1.0, -1.0), [800, 600]), Point(800.0, 600.0));
+    }
+
+    #[test]
+    fn test_textured_circle_stays_round() {
+        let circle = WithColor {
+            color: [1.0; 3],
+            shape: Circle {
+                center: Point(0.5, 0.5),
+                radius: 0.1,
+            },
+            texture: Some(0),
+        };
+        let (_, circles, textured) = format_data(
+            (
+                vec![],
+                vec![circle],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+            ),
+            &[Some(0)],
+            (Point::ZERO, Affine2::IDENTITY),
+        );
+        assert!(circles.is_empty());
+        assert!(textured.len() > 6);
+        for vertex in &textured {
+            let [x, y] = vertex.position;
+            let from_center = Point(x as f64, -y as f64) - Point(0.5, 0.5);
+            assert!(from_center.norm() <= 0.1 + 1e-6);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! what's drawn behind the level: an animation, a still image or a flat color, as the level
+//! asks for. When the level changes its background fades into the next one
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use super::texture::Texture;
+use crate::{assets, levels::BackgroundSpec};
+
+/// the animation shown behind the levels which don't choose their background, in the assets
+const DEFAULT_DIRECTORY: &str = "images/background";
+const DEFAULT_FPS: f32 = 1000.0 / 60.0;
+/// what the frame is cleared with when not even the default background can be loaded
+const WHITE: [f32; 3] = [1.0, 1.0, 1.0];
+/// how long the background of the previous level takes to fade into the next one
+const CROSSFADE_TIME: Duration = Duration::from_millis(500);
+
+/// The pixels of a background drawn with images, one frame per layer
+#[derive(Debug, PartialEq)]
+pub struct Frames {
+    pub size: (u32, u32),
+    pub count: u32,
+    pub pixels: Vec<u8>,
+    pub fps: f32,
+}
+
+/// A background loaded from its spec, before it's sent to the GPU
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    Color([f32; 3]),
+    Frames(Frames),
+}
+
+/// the PNG images in the directory in the order of their names
+fn frame_paths(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|err| format!("{}: {err}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .collect();
+    if paths.is_empty() {
+        return Err(format!("{} has no PNG images", dir.display()));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// reads the images the background is drawn with, which all have to be the same size
+fn load(spec: &BackgroundSpec) -> Result<Source, String> {
+    let (paths, fps) = match spec {
+        BackgroundSpec::Color(color) => return Ok(Source::Color(*color)),
+        BackgroundSpec::Static { path } => (vec![assets::path(path)], 0.0),
+        BackgroundSpec::Animated { dir, fps } => (frame_paths(&assets::path(dir))?, *fps),
+    };
+    let mut size = None;
+    let mut pixels = vec![];
+    for path in &paths {
+        let (width, height, image_data) = Texture::decode(path)?;
+        if *size.get_or_insert((width, height)) != (width, height) {
+            return Err(format!(
+                "{} isn't the same size as the other frames",
+                path.display()
+            ));
+        }
+        pixels.extend(image_data);
+    }
+    Ok(Source::Frames(Frames {
+        // there's at least one path
+        size: size.unwrap(),
+        count: paths.len() as u32,
+        pixels,
+        fps,
+    }))
+}
+
+/// the background the level asks for, the default animation if it doesn't or its images can't
+/// be loaded, and white if not even the default ones can
+pub fn select_or_default(spec: Option<&BackgroundSpec>) -> Source {
+    let default = BackgroundSpec::Animated {
+        dir: DEFAULT_DIRECTORY.to_string(),
+        fps: DEFAULT_FPS,
+    };
+    select(spec, &default)
+}
+
+/// the background the level asks for, `default` if it doesn't or its images can't be loaded,
+/// and white if not even the default ones can
+fn select(spec: Option<&BackgroundSpec>, default: &BackgroundSpec) -> Source {
+    if let Some(spec) = spec {
+        match load(spec) {
+            Ok(source) => return source,
+            Err(err) => eprintln!("couldn't load the background of the level: {err}"),
+        }
+    }
+    load(default).unwrap_or_else(|err| {
+        eprintln!("couldn't load the default background: {err}");
+        Source::Color(WHITE)
+    })
+}
+
+/// How a background covers the frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fill {
+    Color([f32; 3]),
+    Image,
+}
+
+/// How the backgrounds are drawn partway through a crossfade
+#[derive(Debug, PartialEq)]
+struct Blend {
+    clear_color: [f32; 3],
+    /// the transparency the image of the previous background is drawn with, if it has one
+    /// and is still showing
+    previous: Option<f32>,
+    /// the transparency the image of the current background is drawn over it with
+    current: Option<f32>,
+}
+
+/// mixes the backgrounds `progress` of the way from the previous one to the current one. Colors
+/// are the clear color of the frame, and images are drawn over it
+fn blend(previous: Option<Fill>, current: Fill, progress: f32) -> Blend {
+    // without a previous background there's nothing to fade from
+    let (previous, progress) = match previous {
+        Some(fill) if progress < 1.0 => (Some(fill), progress.max(0.0)),
+        _ => (None, 1.0),
+    };
+    match (previous, current) {
+        (Some(Fill::Color(from)), Fill::Color(to)) => Blend {
+            clear_color: [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * progress),
+            previous: None,
+            current: None,
+        },
+        (previous, Fill::Color(color)) => Blend {
+            clear_color: color,
+            previous: (previous == Some(Fill::Image)).then_some(progress),
+            current: None,
+        },
+        (previous, Fill::Image) => Blend {
+            clear_color: match previous {
+                Some(Fill::Color(color)) => color,
+                _ => WHITE,
+            },
+            // the current image fading in covers the previous one by the end
+            previous: (previous == Some(Fill::Image)).then_some(0.0),
+            current: Some(1.0 - progress),
+        },
+    }
+}
+
+/// A background on the GPU
+pub enum Look {
+    Color([f32; 3]),
+    Image {
+        texture: Texture,
+        frames: u32,
+        fps: f32,
+        shown_since: Instant,
+    },
+}
+
+impl Look {
+    /// an animation starting from its first frame, or a still image if it has only one
+    pub fn image(texture: Texture, frames: u32, fps: f32) -> Self {
+        Look::Image {
+            texture,
+            frames,
+            fps,
+            shown_since: Instant::now(),
+        }
+    }
+
+    fn fill(&self) -> Fill {
+        match self {
+            Look::Color(color) => Fill::Color(*color),
+            Look::Image { .. } => Fill::Image,
+        }
+    }
+
+    /// the texture and the layer of it with the frame to show now
+    fn frame(&self) -> Option<(&Texture, u32)> {
+        match self {
+            Look::Color(_) => None,
+            Look::Image {
+                texture,
+                frames,
+                fps,
+                shown_since,
+            } => {
+                let frame = (shown_since.elapsed().as_secs_f32() * fps) as u32 % frames;
+                Some((texture, frame))
+            }
+        }
+    }
+}
+
+/// The background of the level being played, along with the one of the previous level while
+/// it's fading out
+pub struct Background {
+    current: Option<Look>,
+    previous: Option<Look>,
+    changed_at: Instant,
+}
+
+impl Default for Background {
+    /// nothing until the first level is shown
+    fn default() -> Self {
+        Background {
+            current: None,
+            previous: None,
+            changed_at: Instant::now(),
+        }
+    }
+}
+
+impl Background {
+    /// starts fading into the look
+    pub fn show(&mut self, look: Look) {
+        self.previous = self.current.replace(look);
+        self.changed_at = Instant::now();
+    }
+
+    /// the color to clear the frame with, and the textures to draw over it along with the
+    /// layer and the transparency each is drawn with
+    pub fn layers(&self) -> ([f32; 3], Vec<(&Texture, u32, f32)>) {
+        let Some(current) = &self.current else {
+            return (WHITE, vec![]);
+        };
+        let progress = self.changed_at.elapsed().as_secs_f32() / CROSSFADE_TIME.as_secs_f32();
+        let blend = blend(
+            self.previous.as_ref().map(Look::fill),
+            current.fill(),
+            progress,
+        );
+        let layers = [
+            (&self.previous, blend.previous),
+            (&self.current, blend.current),
+        ]
+        .into_iter()
+        .filter_map(|(look, transparency)| {
+            let (texture, frame) = look.as_ref()?.frame()?;
+            Some((texture, frame, transparency?))
+        })
+        .collect();
+        (blend.clear_color, layers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, path::Path};
+
+    use super::*;
+
+    fn write_png(path: &Path, (width, height): (u32, u32)) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&vec![255; (width * height * 4) as usize])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_select_falls_back_to_default() {
+        let directory =
+            std::env::temp_dir().join(format!("whisky-background-{}", std::process::id()));
+        let frames = directory.join("frames");
+        fs::create_dir_all(&frames).unwrap();
+        write_png(&frames.join("0002.png"), (2, 2));
+        write_png(&frames.join("0001.png"), (2, 2));
+        fs::write(frames.join("notes.txt"), "not a frame").unwrap();
+        let odd_one = directory.join("odd");
+        fs::create_dir_all(&odd_one).unwrap();
+        write_png(&odd_one.join("a.png"), (2, 2));
+        write_png(&odd_one.join("b.png"), (3, 2));
+        let still = directory.join("still.png");
+        write_png(&still, (4, 1));
+
+        let animated = |dir: &Path| BackgroundSpec::Animated {
+            dir: dir.to_string_lossy().into_owned(),
+            fps: 10.0,
+        };
+        let default = animated(&frames);
+        let is_default = |source: Source| {
+            matches!(
+                source,
+                Source::Frames(Frames {
+                    count: 2,
+                    size: (2, 2),
+                    ..
+                })
+            )
+        };
+
+        assert!(is_default(select(None, &default)));
+        assert_eq!(
+            select(Some(&BackgroundSpec::Color([0.0, 0.5, 1.0])), &default),
+            Source::Color([0.0, 0.5, 1.0])
+        );
+        let Source::Frames(still_frames) = select(
+            Some(&BackgroundSpec::Static {
+                path: still.to_string_lossy().into_owned(),
+            }),
+            &default,
+        ) else {
+            panic!("the still image wasn't loaded");
+        };
+        assert_eq!((still_frames.size, still_frames.count), ((4, 1), 1));
+
+        let missing = BackgroundSpec::Static {
+            path: directory.join("missing.png").to_string_lossy().into_owned(),
+        };
+        assert!(is_default(select(Some(&missing), &default)));
+        assert!(is_default(select(Some(&animated(&odd_one)), &default)));
+        assert!(is_default(select(
+            Some(&animated(&directory.join("empty"))),
+            &default
+        )));
+        assert_eq!(select(Some(&missing), &missing), Source::Color(WHITE));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let red = Fill::Color([1.0, 0.0, 0.0]);
+        let blue = Fill::Color([0.0, 0.0, 1.0]);
+
+        assert_eq!(
+            blend(None, Fill::Image, 0.0),
+            Blend {
+                clear_color: WHITE,
+                previous: None,
+                current: Some(0.0),
+            }
+        );
+        assert_eq!(
+            blend(Some(red), blue, 0.25),
+            Blend {
+                clear_color: [0.75, 0.0, 0.25],
+                previous: None,
+                current: None,
+            }
+        );
+        // an image fades out over the color replacing it
+        assert_eq!(
+            blend(Some(Fill::Image), blue, 0.25),
+            Blend {
+                clear_color: [0.0, 0.0, 1.0],
+                previous: Some(0.25),
+                current: None,
+            }
+        );
+        // and fades in over the color it replaces or over the previous image
+        assert_eq!(
+            blend(Some(red), Fill::Image, 0.25),
+            Blend {
+                clear_color: [1.0, 0.0, 0.0],
+                previous: None,
+                current: Some(0.75),
+            }
+        );
+        assert_eq!(
+            blend(Some(Fill::Image), Fill::Image, 0.25),
+            Blend {
+                clear_color: WHITE,
+                previous: Some(0.0),
+                current: Some(0.75),
+            }
+        );
+        // once the crossfade is over only the current background is left
+        assert_eq!(blend(Some(Fill::Image), blue, 1.5), blend(None, blue, 0.0));
+    }
+}
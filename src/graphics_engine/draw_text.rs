@@ -1,37 +1,34 @@
-use bytemuck::{Pod, Zeroable};
 use rusttype::gpu_cache::Cache;
 use rusttype::{point, Font, PositionedGlyph, Rect, Scale};
 
 use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CopyBufferToImageInfo, CopyImageToBufferInfo,
-    PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents,
+    AutoCommandBufferBuilder, CopyBufferToImageInfo, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
+    SubpassContents,
 };
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::device::{Device, Queue};
-use vulkano::format::{ClearValue, Format};
-use vulkano::image::sys::ImageCreateInfo;
-use vulkano::image::view::{ImageView, ImageViewCreateInfo};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
 use vulkano::image::{
-    ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage, ImmutableImage, SwapchainImage, AttachmentImage, ImageAccess, SampleCount,
+    ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage, ImmutableImage, SwapchainImage,
 };
-use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
-use vulkano::pipeline::graphics::vertex_input::{VertexInputState, BuffersDefinition};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::pipeline::{DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint};
-use vulkano::render_pass::{Framebuffer, RenderPass, Subpass, FramebufferCreateInfo};
-use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, Sampler, SamplerCreateInfo};
 use vulkano::swapchain::Swapchain;
 
-use std::iter;
+use std::fs;
 use std::sync::Arc;
 use vulkano::memory::allocator::MemoryAllocator;
 
-use super::Pipelines;
 use super::vertex::Vertex;
-
+use crate::assets;
 
 mod chars_vs {
     vulkano_shaders::shader! {
@@ -52,15 +49,18 @@ struct TextData {
     color: [f32; 4],
 }
 
+/// Text queued during a frame and drawn over it at its end, in a render pass of its own which
+/// keeps what was drawn into the window before
 pub struct DrawText {
-    device: Arc<Device>,
     queue: Arc<Queue>,
     font: Font<'static>,
     cache: Cache<'static>,
     cache_pixel_buffer: Vec<u8>,
+    render_pass: Arc<RenderPass>,
     framebuffers: Vec<Arc<Framebuffer>>,
     texts: Vec<TextData>,
-    pipeline: Arc<GraphicsPipeline>
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
 }
 
 const CACHE_WIDTH: usize = 1000;
@@ -72,116 +72,117 @@ impl DrawText {
         queue: Arc<Queue>,
         swapchain: Arc<Swapchain>,
         images: &[Arc<SwapchainImage>],
-        memory_allocator: &impl MemoryAllocator,
-        dimentions: [u32; 2],
-        max_sample_count: SampleCount,
     ) -> DrawText {
-        let font_data = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
-        let font = Font::try_from_bytes(font_data as &[u8]).unwrap();
+        // the font in the assets, or the copy built into the game if it can't be read there
+        let font = fs::read(assets::path("fonts/DejaVuSans.ttf"))
+            .ok()
+            .and_then(Font::try_from_vec)
+            .unwrap_or_else(|| {
+                let font_data = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+                Font::try_from_bytes(font_data as &[u8]).unwrap()
+            });
         let cache = Cache::builder()
             .dimensions(CACHE_WIDTH as u32, CACHE_HEIGHT as u32)
             .build();
         let cache_pixel_buffer = vec![0; CACHE_WIDTH * CACHE_HEIGHT];
 
+        // the frame is already resolved into the window's image, so the text is drawn straight
+        // into it, over what's there
         let render_pass = vulkano::single_pass_renderpass!(device.clone(),
-        attachments: {
-            intermediary: {
-                load: Load,
-                store: DontCare,
-                format: swapchain.image_format(),
-                samples: max_sample_count,
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: swapchain.image_format(),
+                    samples: 1,
+                }
             },
-            color: {
-                load: DontCare,
-                store: Store,
-                format: swapchain.image_format(),
-                samples: 1,
+            pass: {
+                color: [color],
+                depth_stencil: {}
             }
-        },
-        pass: {
-            color: [intermediary],
-            depth_stencil: {}
-            resolve: [color],
-        }
         )
-        .unwrap() as Arc<RenderPass>;
+        .unwrap();
 
         let c_vs = chars_vs::load(device.clone()).unwrap();
         let c_fs = chars_fs::load(device.clone()).unwrap();
 
-
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
         let pipeline = GraphicsPipeline::start()
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
-        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
-        .vertex_shader(c_vs.entry_point("main").unwrap(), ())
-        .input_assembly_state(
-            InputAssemblyState::new(),
-        )
-        .fragment_shader(c_fs.entry_point("main").unwrap(), ())
-        .multisample_state(MultisampleState {
-            rasterization_samples: Subpass::from(render_pass.clone(), 0).unwrap().num_samples().unwrap(),
-            ..Default::default()
-        })
-        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-        .build(device.clone())
-        .unwrap();
-
-        let framebuffers = images
-        .iter()
-        .map(|image| {
-
-            let intermediary = ImageView::new_default(
-                AttachmentImage::transient_multisampled(
-                    memory_allocator,
-                    dimentions,
-                    max_sample_count,
-                    image.format(),
-                )
-                .unwrap(),
-            )
+            .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .vertex_shader(c_vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .fragment_shader(c_fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass)
+            .build(device.clone())
             .unwrap();
 
-            let view = ImageView::new_default(image.clone()).unwrap();
-
-            Framebuffer::new(
-                render_pass.clone(),
-                FramebufferCreateInfo {
-                    attachments: vec![intermediary, view],
-                    ..Default::default()
-                },
-            )
-            .unwrap()
-        })
-        .collect::<Vec<_>>();
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
+        let framebuffers = Self::framebuffers(&render_pass, images);
 
         DrawText {
-            device,
             queue,
             font,
             cache,
             cache_pixel_buffer,
+            render_pass,
             framebuffers,
             texts: vec![],
-            pipeline
+            pipeline,
+            sampler,
         }
     }
 
+    fn framebuffers(
+        render_pass: &Arc<RenderPass>,
+        images: &[Arc<SwapchainImage>],
+    ) -> Vec<Arc<Framebuffer>> {
+        images
+            .iter()
+            .map(|image| {
+                let view = ImageView::new_default(image.clone()).unwrap();
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    /// draws into the images of the swapchain recreated with the window
+    pub fn recreate(&mut self, images: &[Arc<SwapchainImage>]) {
+        self.framebuffers = Self::framebuffers(&self.render_pass, images);
+    }
+
+    /// queues the text to be drawn with its baseline starting `x` and `y` pixels from the top
+    /// left corner of the window, `size` pixels high
     pub fn queue_text(&mut self, x: f32, y: f32, size: f32, color: [f32; 4], text: &str) {
         let glyphs: Vec<PositionedGlyph> = self
             .font
             .layout(text, Scale::uniform(size), point(x, y))
-            .map(|x| x.clone())
             .collect();
         for glyph in &glyphs {
             self.cache.queue_glyph(0, glyph.clone());
         }
-        self.texts.push(TextData {
-            glyphs: glyphs.clone(),
-            color,
-        });
+        self.texts.push(TextData { glyphs, color });
     }
 
+    /// draws the text queued since the last frame over the frame drawn into the image
     pub fn draw_text<'a>(
         &mut self,
         command_buffer: &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
@@ -190,42 +191,34 @@ impl DrawText {
         descriptor_set_allocator: &StandardDescriptorSetAllocator,
         memory_allocator: &impl MemoryAllocator,
     ) -> &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+        if self.texts.is_empty() {
+            return command_buffer;
+        }
         let cache_pixel_buffer = &mut self.cache_pixel_buffer;
         let cache = &mut self.cache;
 
-        // update texture cache
-        cache
-            .cache_queued(|rect, src_data| {
-                let width = (rect.max.x - rect.min.x) as usize;
-                let height = (rect.max.y - rect.min.y) as usize;
-                let mut dst_index = rect.min.y as usize * CACHE_WIDTH + rect.min.x as usize;
-                let mut src_index = 0;
+        // update texture cache, the glyphs which don't fit are left out
+        let _ = cache.cache_queued(|rect, src_data| {
+            let width = (rect.max.x - rect.min.x) as usize;
+            let height = (rect.max.y - rect.min.y) as usize;
+            let mut dst_index = rect.min.y as usize * CACHE_WIDTH + rect.min.x as usize;
+            let mut src_index = 0;
 
-                for _ in 0..height {
-                    let dst_slice = &mut cache_pixel_buffer[dst_index..dst_index + width];
-                    let src_slice = &src_data[src_index..src_index + width];
-                    dst_slice.copy_from_slice(src_slice);
+            for _ in 0..height {
+                let dst_slice = &mut cache_pixel_buffer[dst_index..dst_index + width];
+                let src_slice = &src_data[src_index..src_index + width];
+                dst_slice.copy_from_slice(src_slice);
 
-                    dst_index += CACHE_WIDTH;
-                    src_index += width;
-                }
-            })
-            .unwrap();
+                dst_index += CACHE_WIDTH;
+                src_index += width;
+            }
+        });
 
         let buffer = CpuAccessibleBuffer::<[u8]>::from_iter(
             memory_allocator,
             BufferUsage {
                 transfer_src: true,
-                transfer_dst: true,
-                uniform_texel_buffer: true,
-                storage_texel_buffer: true,
-                uniform_buffer: true,
-                storage_buffer: true,
-                index_buffer: true,
-                vertex_buffer: true,
-                indirect_buffer: true,
-                shader_device_address: true,
-                ..Default::default()
+                ..BufferUsage::empty()
             },
             false,
             cache_pixel_buffer.iter().cloned(),
@@ -246,24 +239,13 @@ impl DrawText {
                 transfer_dst: true,
                 ..ImageUsage::empty()
             },
-          ImageCreateFlags::empty(),
-            ImageLayout::General,
+            ImageCreateFlags::empty(),
+            ImageLayout::ShaderReadOnlyOptimal,
             Some(self.queue.queue_family_index()),
         )
         .unwrap();
 
-        let sampler = Sampler::new(
-            self.device.clone(),
-            SamplerCreateInfo {
-                mag_filter: Filter::Linear,
-                min_filter: Filter::Linear,
-                ..Default::default()
-            },
-        )
-        .unwrap();
-
-        let cache_texture_view = ImageView::new_default(cache_texture.clone())
-        .unwrap();
+        let cache_texture_view = ImageView::new_default(cache_texture).unwrap();
 
         let set = PersistentDescriptorSet::new(
             descriptor_set_allocator,
@@ -271,11 +253,17 @@ impl DrawText {
             [WriteDescriptorSet::image_view_sampler(
                 0,
                 cache_texture_view,
-                sampler,
+                self.sampler.clone(),
             )],
-        );
+        )
+        .unwrap();
 
-        let mut command_buffer = command_buffer
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimentions[0] as f32, dimentions[1] as f32],
+            depth_range: 0.0..1.0,
+        };
+        let command_buffer = command_buffer
             .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
                 buffer,
                 cache_texture_write,
@@ -283,17 +271,23 @@ impl DrawText {
             .unwrap()
             .begin_render_pass(
                 RenderPassBeginInfo {
-                    clear_values: vec![Some([1.0, 1.0, 1.0, 1.0].into()), None],
-                    ..RenderPassBeginInfo::framebuffer(
-                        self.framebuffers[image_num as usize].clone(),
-                    )
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(self.framebuffers[image_num].clone())
                 },
                 SubpassContents::Inline,
             )
-            .unwrap();
+            .unwrap()
+            .set_viewport(0, [viewport])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                set,
+            );
 
         // draw
-        for text in &mut self.texts.drain(..) {
+        for text in self.texts.drain(..) {
             let vertices: Vec<Vertex> = text
                 .glyphs
                 .iter()
@@ -309,48 +303,21 @@ impl DrawText {
                                 (screen_rect.max.y as f32 / dimentions[1] as f32 - 0.5) * 2.0,
                             ),
                         };
+                        let [red, green, blue, alpha] = text.color;
+                        let corner = |x: f32, y: f32, u: f32, v: f32| Vertex {
+                            position: [x, y],
+                            tex_position: [u, v],
+                            color: [red, green, blue],
+                            transparency: 1.0 - alpha,
+                            ..Default::default()
+                        };
                         vec![
-                            Vertex {
-                                position: [gl_rect.min.x, gl_rect.max.y],
-                                tex_position: [uv_rect.min.x, uv_rect.max.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                ..Default::default()
-                            },
-                            Vertex {
-                                position: [gl_rect.min.x, gl_rect.min.y],
-                                tex_position: [uv_rect.min.x, uv_rect.min.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                // color: text.color,
-                                ..Default::default()
-                            },
-                            Vertex {
-                                position: [gl_rect.max.x, gl_rect.min.y],
-                                tex_position: [uv_rect.max.x, uv_rect.min.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                // color: text.color,
-                                ..Default::default()
-                            },
-                            Vertex {
-                                position: [gl_rect.max.x, gl_rect.min.y],
-                                tex_position: [uv_rect.max.x, uv_rect.min.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                // color: text.color,
-                                ..Default::default()
-                            },
-                            Vertex {
-                                position: [gl_rect.max.x, gl_rect.max.y],
-                                tex_position: [uv_rect.max.x, uv_rect.max.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                // color: text.color,
-                                ..Default::default()
-                            },
-                            Vertex {
-                                position: [gl_rect.min.x, gl_rect.max.y],
-                                tex_position: [uv_rect.min.x, uv_rect.max.y],
-                                color: [text.color[0], text.color[1], text.color[2]],
-                                // color: text.color,
-                                ..Default::default()
-                            },
+                            corner(gl_rect.min.x, gl_rect.max.y, uv_rect.min.x, uv_rect.max.y),
+                            corner(gl_rect.min.x, gl_rect.min.y, uv_rect.min.x, uv_rect.min.y),
+                            corner(gl_rect.max.x, gl_rect.min.y, uv_rect.max.x, uv_rect.min.y),
+                            corner(gl_rect.max.x, gl_rect.min.y, uv_rect.max.x, uv_rect.min.y),
+                            corner(gl_rect.max.x, gl_rect.max.y, uv_rect.max.x, uv_rect.max.y),
+                            corner(gl_rect.min.x, gl_rect.max.y, uv_rect.min.x, uv_rect.max.y),
                         ]
                         .into_iter()
                     } else {
@@ -358,26 +325,22 @@ impl DrawText {
                     }
                 })
                 .collect();
+            if vertices.is_empty() {
+                continue;
+            }
 
             let vertex_buffer = CpuAccessibleBuffer::from_iter(
                 memory_allocator,
                 BufferUsage {
                     vertex_buffer: true,
-                    transfer_dst: true,
-                    ..Default::default()
+                    ..BufferUsage::empty()
                 },
                 false,
-                vertices.into_iter(),
+                vertices,
             )
             .unwrap();
-            command_buffer = command_buffer
-            .bind_vertex_buffers(0, vertex_buffer.clone())
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Graphics,
-                    self.pipeline.layout().clone(),
-                    0,
-                    set.clone().unwrap(),
-                )
+            command_buffer
+                .bind_vertex_buffers(0, vertex_buffer.clone())
                 .draw(vertex_buffer.len() as u32, 1, 0, 0)
                 .unwrap();
         }
@@ -385,33 +348,3 @@ impl DrawText {
         command_buffer.end_render_pass().unwrap()
     }
 }
-
-impl DrawTextTrait for AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
-    fn draw_text(
-        &mut self,
-        data: &mut DrawText,
-        image_num: usize,
-        dimensions: [usize; 2],
-        descriptor_set_allocator: &StandardDescriptorSetAllocator,
-        memory_allocator: &impl MemoryAllocator,
-    ) -> &mut Self {
-        data.draw_text(
-            self,
-            image_num,
-            dimensions,
-            descriptor_set_allocator,
-            memory_allocator,
-        )
-    }
-}
-
-pub trait DrawTextTrait {
-    fn draw_text(
-        &mut self,
-        data: &mut DrawText,
-        image_num: usize,
-        dimensions: [usize; 2],
-        descriptor_set_allocator: &StandardDescriptorSetAllocator,
-        memory_allocator: &impl MemoryAllocator,
-    ) -> &mut Self;
-}
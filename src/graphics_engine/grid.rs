@@ -0,0 +1,139 @@
+//! reference lines drawn behind the level, which turn along with it so the tilt of the world
+//! is easy to judge and geometry is easy to line up while editing
+
+use crate::{
+    geometry::{Aabb, Point, Polygon, EPSILON},
+    physics::WithColor,
+};
+
+/// how far past the level bounds the grid reaches
+const MARGIN: f64 = 0.2;
+/// how far from the edge of the grid its lines fade to the background
+const FADE_DISTANCE: f64 = 0.3;
+const LINE_THICKNESS: f64 = 0.002;
+const AXIS_THICKNESS: f64 = 0.005;
+const LINE_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+const AXIS_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+const BACKGROUND: [f32; 3] = [1.0, 1.0, 1.0];
+
+/// The lines of a grid with the spacing covering the box, vertical ones first. The lines are
+/// placed at multiples of the spacing, so one of each passes through the origin if it's inside
+pub fn grid_lines(bounds: Aabb, spacing: f64) -> Vec<(Point, Point)> {
+    if spacing.is_nan() || spacing <= 0.0 {
+        return vec![];
+    }
+    let multiples = |min: f64, max: f64| {
+        // a bound lying on a multiple still gets its line despite rounding errors
+        ((min / spacing - EPSILON).ceil() as i64..=(max / spacing + EPSILON).floor() as i64)
+            .map(move |i| i as f64 * spacing)
+    };
+    let Aabb { min, max } = bounds;
+
+    multiples(min.0, max.0)
+        .map(|x| (Point(x, min.1), Point(x, max.1)))
+        .chain(multiples(min.1, max.1).map(|y| (Point(min.0, y), Point(max.0, y))))
+        .collect()
+}
+
+/// The grid around the level bounds along with the axes through the origin, turned by `angle`
+/// like the entities are. Every line is split into pieces a spacing long, which get lighter
+/// the closer they are to the edge of the grid
+pub fn grid(bounds: Aabb, spacing: f64, angle: f64) -> Vec<WithColor<Polygon>> {
+    let bounds = bounds.expanded(MARGIN);
+    let Aabb { min, max } = bounds;
+    let fade = |point: Point| {
+        let distance = (point.0 - min.0)
+            .min(max.0 - point.0)
+            .min(point.1 - min.1)
+            .min(max.1 - point.1);
+        (distance / FADE_DISTANCE).clamp(0.0, 1.0) as f32
+    };
+    let vertical_axis = (min.0..=max.0)
+        .contains(&0.0)
+        .then_some((Point(0.0, min.1), Point(0.0, max.1)));
+    let horizontal_axis = (min.1..=max.1)
+        .contains(&0.0)
+        .then_some((Point(min.0, 0.0), Point(max.0, 0.0)));
+    let axes = vertical_axis.into_iter().chain(horizontal_axis);
+
+    grid_lines(bounds, spacing)
+        .into_iter()
+        .map(|line| (line, LINE_THICKNESS, LINE_COLOR))
+        .chain(axes.map(|line| (line, AXIS_THICKNESS, AXIS_COLOR)))
+        .flat_map(|((from, to), thickness, color)| {
+            let pieces = (from.to(to).norm() / spacing).ceil().max(1.0) as usize;
+            let offset = from.to(to).perpendicular().unit() * (thickness / 2.0);
+            (0..pieces).map(move |i| {
+                let start = from + from.to(to) * (i as f64 / pieces as f64);
+                let end = from + from.to(to) * ((i + 1) as f64 / pieces as f64);
+                let opacity = fade((start + end) / 2.0);
+                let vertices = [start - offset, end - offset, end + offset, start + offset]
+                    .map(|vertex| vertex.rotate(angle));
+                WithColor {
+                    color: [0, 1, 2].map(|c| BACKGROUND[c] + (color[c] - BACKGROUND[c]) * opacity),
+                    shape: Polygon {
+                        vertices: vertices.to_vec(),
+                    },
+                    texture: None,
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid_lines_cover_bounds() {
+        let bounds = Aabb {
+            min: Point(-1.0, -0.5),
+            max: Point(1.0, 0.5),
+        };
+        let lines = grid_lines(bounds, 0.25);
+        // 9 vertical lines from -1 to 1 and 5 horizontal ones from -0.5 to 0.5
+        assert_eq!(lines.len(), 9 + 5);
+        assert!(lines[..9]
+            .iter()
+            .all(|(from, to)| from.0 == to.0 && from.1 == -0.5 && to.1 == 0.5));
+        assert!(lines[9..]
+            .iter()
+            .all(|(from, to)| from.1 == to.1 && from.0 == -1.0 && to.0 == 1.0));
+
+        // bounds which aren't multiples of the spacing only get the lines inside them
+        let offset = Aabb {
+            min: Point(0.05, 0.05),
+            max: Point(0.3, 0.2),
+        };
+        assert_eq!(grid_lines(offset, 0.1).len(), 3 + 2);
+    }
+
+    #[test]
+    fn test_grid_turns_with_world_and_fades() {
+        let bounds = Aabb {
+            min: Point(-0.5, -0.5),
+            max: Point(0.5, 0.5),
+        };
+        let straight = grid(bounds, 0.1, 0.0);
+        let turned = grid(bounds, 0.1, 0.3);
+        assert_eq!(straight.len(), turned.len());
+        for (straight, turned) in straight.iter().zip(&turned) {
+            assert!(straight
+                .shape
+                .centroid()
+                .rotate(0.3)
+                .is_close_enough_to(turned.shape.centroid()));
+        }
+
+        // the pieces at the edge almost blend into the background, the ones in the middle are
+        // as dark as the lines
+        let lightest = straight
+            .iter()
+            .map(|line| line.color[0])
+            .fold(0.0, f32::max);
+        assert!(lightest > 0.95);
+        assert!(straight.iter().any(|line| line.color == LINE_COLOR));
+        assert!(straight.iter().any(|line| line.color == AXIS_COLOR));
+    }
+}
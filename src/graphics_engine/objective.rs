@@ -0,0 +1,185 @@
+//! the arrow at the edge of the window pointing at the flag or door the player is heading for
+//! while it's out of sight, fading away once it comes into view
+
+use std::time::Duration;
+
+use crate::{
+    geometry::{Aabb, Point, Polygon, Vector},
+    physics::{Objective, ObjectiveKind, WithColor},
+};
+
+/// how far inside the edges of the view the tip of the arrow stays. It and the size of the arrow
+/// are given for a view reaching 1 away from its center, as the window does, and grow with it
+const MARGIN: f64 = 0.06;
+/// how long the arrow takes to appear or fade away
+const FADE_TIME: Duration = Duration::from_millis(300);
+const LENGTH: f64 = 0.06;
+const WIDTH: f64 = 0.05;
+
+/// where an arrow from the center of the view towards `target` crosses the edge of the view
+/// brought in by `margin` on every side, `None` if the target is inside that area
+pub fn edge_position(target: Point, view: Aabb, margin: f64) -> Option<Point> {
+    let inner = view.expanded(-margin);
+    if inner.distance_to(target) == 0.0 {
+        return None;
+    }
+    let center = (inner.min + inner.max) * 0.5;
+    let Point(half_width, half_height) = (inner.max - inner.min) * 0.5;
+    let Point(x, y) = center.to(target);
+    // the arrow leaves through the side it reaches first, a division by zero only rules out
+    // the sides it runs along
+    let scale = (half_width / x.abs()).min(half_height / y.abs());
+    Some(center + Point(x, y) * scale)
+}
+
+/// the index of the objective nearest to the ball, the one pointed at until the player picks
+/// another, `None` if there are none
+pub fn nearest(objectives: &[Objective], ball: Point) -> Option<usize> {
+    (0..objectives.len()).min_by(|&a, &b| {
+        let distance = |i: usize| ball.to(objectives[i].at).norm();
+        distance(a).total_cmp(&distance(b))
+    })
+}
+
+/// The arrow pointing at the objective chosen by the player
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ObjectiveArrow {
+    /// where the tip of the arrow was last, which way it pointed and at what
+    shown: Option<(Point, Vector, ObjectiveKind)>,
+    /// how far the view reached from its center then, which the arrow is scaled by to keep its
+    /// size in the window
+    scale: f64,
+    /// 1 while the objective is out of view, 0 once the arrow has faded away
+    opacity: f64,
+}
+
+impl ObjectiveArrow {
+    /// points the arrow at the objective from the edge of `view`, the part of the level shown in
+    /// the window, `dt` after the last update. It stays where it was while it fades away, once
+    /// the objective is in view
+    pub fn update(&mut self, objective: Option<Objective>, view: Aabb, dt: Duration) {
+        let step = dt.as_secs_f64() / FADE_TIME.as_secs_f64();
+        let Some(objective) = objective else {
+            self.shown = None;
+            self.opacity = 0.0;
+            return;
+        };
+        let scale = (view.max.1 - view.min.1) / 2.0;
+        match edge_position(objective.at, view, MARGIN * scale) {
+            Some(tip) => {
+                let center = (view.min + view.max) * 0.5;
+                self.shown = Some((tip, center.to(objective.at).unit(), objective.kind));
+                self.scale = scale;
+                self.opacity = (self.opacity + step).min(1.0);
+            }
+            None => self.opacity = (self.opacity - step).max(0.0),
+        }
+    }
+
+    /// a triangle in the color of the objective, shrinking as it fades
+    pub fn shapes(&self) -> Vec<WithColor<Polygon>> {
+        let Some((tip, direction, kind)) = self.shown.filter(|_| self.opacity > 0.0) else {
+            return vec![];
+        };
+        let size = self.opacity * self.scale;
+        let base = tip - direction * (LENGTH * size);
+        let side = direction.perpendicular() * (WIDTH / 2.0 * size);
+        vec![WithColor {
+            color: match kind {
+                ObjectiveKind::Flag => [1.0, 0.85, 0.2],
+                ObjectiveKind::Door => [0.0, 1.0, 0.0],
+            },
+            shape: Polygon {
+                vertices: vec![base + side, tip, base - side],
+            },
+            texture: None,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// a view as big as the window
+    const WINDOW: Aabb = Aabb {
+        min: Point(-1.0, -1.0),
+        max: Point(1.0, 1.0),
+    };
+
+    #[test]
+    fn test_arrow_is_clamped_to_edge() {
+        // inside the view, even if only just
+        assert_eq!(edge_position(Point(0.3, -0.2), WINDOW, 0.1), None);
+        assert_eq!(edge_position(Point(0.9, 0.9), WINDOW, 0.1), None);
+
+        let at = |target| edge_position(target, WINDOW, 0.1).unwrap();
+        assert!(at(Point(3.0, 0.0)).is_close_enough_to(Point(0.9, 0.0)));
+        assert!(at(Point(0.0, -5.0)).is_close_enough_to(Point(0.0, -0.9)));
+        // leaves through the top, which it reaches before the right side
+        assert!(at(Point(1.0, 2.0)).is_close_enough_to(Point(0.45, 0.9)));
+        assert!(at(Point(-4.0, -4.0)).is_close_enough_to(Point(-0.9, -0.9)));
+        // just outside the area the arrow stays out of
+        assert!(at(Point(0.95, 0.0)).is_close_enough_to(Point(0.9, 0.0)));
+
+        // a view away from the origin, wider than it's high
+        let view = Aabb {
+            min: Point(1.0, 0.0),
+            max: Point(5.0, 2.0),
+        };
+        let at = edge_position(Point(9.0, 1.5), view, 0.0).unwrap();
+        assert!(at.is_close_enough_to(Point(5.0, 1.0 + 0.5 / 3.0)));
+    }
+
+    #[test]
+    fn test_nearest_objective() {
+        let objectives =
+            [Point(-0.9, 0.5), Point(0.3, 0.5), Point(-0.45, 0.5)].map(|at| Objective {
+                at,
+                kind: ObjectiveKind::Flag,
+            });
+        assert_eq!(nearest(&objectives, Point(0.0, 0.5)), Some(1));
+        assert_eq!(nearest(&objectives, Point(-0.6, 0.0)), Some(2));
+        assert_eq!(nearest(&[], Point(0.0, 0.5)), None);
+    }
+
+    #[test]
+    fn test_arrow_fades_once_objective_is_in_view() {
+        let objective = |x| Objective {
+            at: Point(x, 0.0),
+            kind: ObjectiveKind::Door,
+        };
+        let mut arrow = ObjectiveArrow::default();
+        arrow.update(Some(objective(3.0)), WINDOW, FADE_TIME);
+        let [shown] = &arrow.shapes()[..] else {
+            panic!("the arrow isn't shown");
+        };
+        assert_eq!(shown.shape.vertices[1], Point(1.0 - MARGIN, 0.0));
+
+        arrow.update(Some(objective(0.5)), WINDOW, FADE_TIME / 2);
+        assert_eq!(arrow.shapes().len(), 1);
+        arrow.update(Some(objective(0.5)), WINDOW, FADE_TIME / 2);
+        assert!(arrow.shapes().is_empty());
+
+        arrow.update(Some(objective(-3.0)), WINDOW, FADE_TIME);
+        assert_eq!(arrow.shapes().len(), 1);
+        arrow.update(None, WINDOW, FADE_TIME / 10);
+        assert!(arrow.shapes().is_empty());
+
+        // zoomed out to twice as much of the level, the arrow is twice as big, to look the same
+        let zoomed_out = Aabb {
+            min: Point(0.0, -2.0),
+            max: Point(4.0, 2.0),
+        };
+        arrow.update(Some(objective(9.0)), zoomed_out, FADE_TIME);
+        let [shown] = &arrow.shapes()[..] else {
+            panic!("the arrow isn't shown");
+        };
+        let [_, tip, _] = shown.shape.vertices[..] else {
+            panic!("the arrow isn't a triangle");
+        };
+        assert!(tip.is_close_enough_to(Point(4.0 - 2.0 * MARGIN, 0.0)));
+        let base = (shown.shape.vertices[0] + shown.shape.vertices[2]) * 0.5;
+        assert!((base.to(tip).norm() - 2.0 * LENGTH).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,98 @@
+//! the marks of paint the ball leaves on what it touches, which fade away over a few seconds.
+//! They're only drawn, the engine doesn't keep them
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    geometry::Point,
+    physics::{PaintMark, MAX_PAINT_MARKS},
+};
+
+/// how long a mark takes to fade away
+const FADE_TIME: Duration = Duration::from_secs(8);
+/// how see-through a mark left with the most paint is right after it's left
+const FRESH_TRANSPARENCY: f32 = 0.2;
+
+/// The marks being shown, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct PaintMarks {
+    marks: VecDeque<(PaintMark, Duration)>,
+}
+
+impl PaintMarks {
+    /// shows the new marks, forgetting the oldest ones past [`MAX_PAINT_MARKS`]
+    pub fn add(&mut self, marks: impl IntoIterator<Item = PaintMark>) {
+        self.marks
+            .extend(marks.into_iter().map(|mark| (mark, Duration::ZERO)));
+        if self.marks.len() > MAX_PAINT_MARKS {
+            self.marks.drain(..self.marks.len() - MAX_PAINT_MARKS);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    /// lets the marks fade for `dt`, forgetting the ones which faded away
+    pub fn update(&mut self, dt: Duration) {
+        for (_, age) in &mut self.marks {
+            *age += dt;
+        }
+        self.marks.retain(|(_, age)| *age < FADE_TIME);
+    }
+
+    /// where each mark is in the coordinates of the level, along with its color and how
+    /// see-through it is
+    pub fn visible(&self) -> impl Iterator<Item = (Point, [f32; 3], f32)> + '_ {
+        self.marks.iter().map(|(mark, age)| {
+            let left = 1.0 - age.as_secs_f32() / FADE_TIME.as_secs_f32();
+            let opacity = (1.0 - FRESH_TRANSPARENCY) * mark.strength * left;
+            (mark.at, mark.color, 1.0 - opacity.clamp(0.0, 1.0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mark(x: f64, strength: f32) -> PaintMark {
+        PaintMark {
+            at: Point(x, 0.0),
+            color: [0.2, 0.4, 1.0],
+            strength,
+        }
+    }
+
+    #[test]
+    fn test_marks_are_capped_oldest_first() {
+        let mut marks = PaintMarks::default();
+        marks.add((0..MAX_PAINT_MARKS + 20).map(|i| mark(i as f64, 1.0)));
+        let visible: Vec<_> = marks.visible().collect();
+        assert_eq!(visible.len(), MAX_PAINT_MARKS);
+        assert_eq!(visible[0].0, Point(20.0, 0.0));
+        marks.clear();
+        assert_eq!(marks.visible().count(), 0);
+    }
+
+    #[test]
+    fn test_marks_fade() {
+        let mut marks = PaintMarks::default();
+        marks.add([mark(0.0, 1.0), mark(1.0, 0.5)]);
+        let assert_transparency = |marks: &PaintMarks, expected: &[f32]| {
+            let transparency: Vec<_> = marks.visible().map(|(_, _, t)| t).collect();
+            assert_eq!(transparency.len(), expected.len());
+            for (actual, expected) in transparency.into_iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+            }
+        };
+        assert_transparency(&marks, &[FRESH_TRANSPARENCY, 0.6]);
+
+        marks.update(FADE_TIME / 2);
+        marks.add([mark(2.0, 1.0)]);
+        assert_transparency(&marks, &[0.6, 0.8, FRESH_TRANSPARENCY]);
+
+        marks.update(FADE_TIME / 2);
+        assert_transparency(&marks, &[0.6]);
+    }
+}
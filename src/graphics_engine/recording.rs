@@ -0,0 +1,121 @@
+//! saving every rendered frame as a numbered PNG, to put gameplay videos together from
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use vulkano::format::Format;
+
+/// recording stops once the saved frames take up this many bytes
+const MAX_RECORDED_BYTES: u64 = 4 << 30;
+
+/// Saves frames to a directory as `frame_000000.png`, `frame_000001.png` and so on.
+///
+/// Each frame has to be read back from the GPU before the next one is drawn, so the game runs
+/// noticeably slower while recording
+pub struct Recorder {
+    directory: PathBuf,
+    next_frame: u64,
+    recorded_bytes: u64,
+}
+
+impl Recorder {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            next_frame: 0,
+            recorded_bytes: 0,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// saves the pixels read back from a swapchain image as the next frame.
+    /// Returns false without saving anything once the frames take up too much disk space
+    pub fn save(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: Format,
+    ) -> io::Result<bool> {
+        if self.recorded_bytes >= MAX_RECORDED_BYTES {
+            return Ok(false);
+        }
+        let png = encode_png(pixels, width, height, format).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("frames in {format:?} can't be saved"),
+            )
+        })?;
+        fs::create_dir_all(&self.directory)?;
+        fs::write(frame_path(&self.directory, self.next_frame), &png)?;
+        self.next_frame += 1;
+        self.recorded_bytes += png.len() as u64;
+        Ok(true)
+    }
+}
+
+/// the path of the frame with the given index, zero padded so the names sort in order
+pub fn frame_path(directory: &Path, index: u64) -> PathBuf {
+    directory.join(format!("frame_{index:06}.png"))
+}
+
+/// encodes tightly packed pixels with 8 bits per channel as an opaque RGBA PNG,
+/// or returns None for any other format
+pub fn encode_png(pixels: &[u8], width: u32, height: u32, format: Format) -> Option<Vec<u8>> {
+    let is_bgra = match format {
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => true,
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => false,
+        _ => return None,
+    };
+    let mut rgba = pixels.to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        if is_bgra {
+            pixel.swap(0, 2);
+        }
+        // the window isn't see-through, whatever the alpha ends up being
+        pixel[3] = u8::MAX;
+    }
+
+    let mut png = vec![];
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(&rgba).ok()?;
+    writer.finish().ok()?;
+    Some(png)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_path() {
+        let directory = Path::new("recording");
+        assert_eq!(frame_path(directory, 7), directory.join("frame_000007.png"));
+        assert!(frame_path(directory, 9) < frame_path(directory, 10));
+    }
+
+    #[test]
+    fn test_encode_png() {
+        // a blue and a half transparent red pixel, as read back from a BGRA swapchain
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 128];
+        let png = encode_png(&pixels, 2, 1, Format::B8G8R8A8_SRGB).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(decoded, [0, 0, 255, 255, 255, 0, 0, 255]);
+
+        assert!(encode_png(&pixels, 2, 1, Format::R16G16B16A16_SFLOAT).is_none());
+        assert!(encode_png(&pixels, 3, 1, Format::R8G8B8A8_UNORM).is_none());
+    }
+}
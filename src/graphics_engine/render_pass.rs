@@ -23,7 +23,7 @@ use vulkano::{
     swapchain::Swapchain, memory::allocator::{GenericMemoryAllocator, FreeListAllocator, MemoryAllocator}, descriptor_set::allocator::StandardDescriptorSetAllocator,
 };
 
-use super::{vertex::Vertex, Pipelines, Textures, VertexBuffers, draw_text::{DrawText, DrawTextTrait}};
+use super::{vertex::Vertex, BackgroundLayer, DoorPreviewLayer, Pipelines, Textures, VertexBuffers};
 
 pub struct SimpleShapes {
     pub command_buffer_allocator: StandardCommandBufferAllocator,
@@ -168,26 +168,33 @@ impl SimpleShapes {
 
 
 
+        let [red, green, blue] = buffers.clear_color;
         builder
             .begin_render_pass(
                 RenderPassBeginInfo {
-                    clear_values: vec![Some([1.0, 1.0, 1.0, 1.0].into()), None],
+                    clear_values: vec![Some([red, green, blue, 1.0].into()), None],
                     ..RenderPassBeginInfo::framebuffer(framebuffers[image_index as usize].clone())
                 },
                 SubpassContents::Inline,
             )
             .unwrap()
-            .set_viewport(0, [viewport.clone()])
-            .bind_pipeline_graphics(pipelines.texture_array_pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                pipelines.texture_array_pipeline.layout().clone(),
-                0,
-                textures.background.0.clone(),
-            )
-            .bind_vertex_buffers(0, buffers.background.clone())
-            .draw(buffers.background.len() as u32, 1, 0, 0)
-            .unwrap()
+            .set_viewport(0, [viewport.clone()]);
+
+        for BackgroundLayer { texture, quad } in buffers.backgrounds {
+            builder
+                .bind_pipeline_graphics(pipelines.texture_array_pipeline.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipelines.texture_array_pipeline.layout().clone(),
+                    0,
+                    texture,
+                )
+                .bind_vertex_buffers(0, quad.clone())
+                .draw(quad.len() as u32, 1, 0, 0)
+                .unwrap();
+        }
+
+        builder
             .bind_pipeline_graphics(pipelines.polygon_pipeline.clone())
             // .bind_descriptor_sets(
             //     PipelineBindPoint::Graphics,
@@ -197,7 +204,25 @@ impl SimpleShapes {
             // )
             .bind_vertex_buffers(0, buffers.polygons.clone())
             .draw(buffers.polygons.len() as u32, 1, 0, 0)
-            .unwrap()
+            .unwrap();
+
+        if let (Some(textured), Some(entity_texture)) =
+            (buffers.textured, &textures.entities.texture)
+        {
+            builder
+                .bind_pipeline_graphics(pipelines.texture_array_pipeline.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipelines.texture_array_pipeline.layout().clone(),
+                    0,
+                    entity_texture.0.clone(),
+                )
+                .bind_vertex_buffers(0, textured.clone())
+                .draw(textured.len() as u32, 1, 0, 0)
+                .unwrap();
+        }
+
+        builder
             .bind_pipeline_graphics(pipelines.texture_pipeline.clone())
             .bind_vertex_buffers(0, buffers.circles.clone())
             .bind_descriptor_sets(
@@ -207,7 +232,17 @@ impl SimpleShapes {
                 textures.ball.0.clone(),
             )
             .draw(buffers.circles.len() as u32, 1, 0, 0)
-            .unwrap()
+            .unwrap();
+
+        if let Some(dots) = buffers.dots {
+            builder
+                .bind_pipeline_graphics(pipelines.circle_pipeline.clone())
+                .bind_vertex_buffers(0, dots.clone())
+                .draw(dots.len() as u32, 1, 0, 0)
+                .unwrap();
+        }
+
+        builder
             .bind_pipeline_graphics(pipelines.texture_array_pipeline.clone())
             .bind_vertex_buffers(0, buffers.level_status.clone())
             .bind_descriptor_sets(
@@ -217,10 +252,19 @@ impl SimpleShapes {
                 textures.level.0.clone(),
             )
             .draw(buffers.level_status.len() as u32, 1, 0, 0)
-            .unwrap()
-            .end_render_pass()
             .unwrap();
-            // .draw_text(&mut draw_text, image_num, dimensions, descriptor_set_allocator, memory_allocator);
+
+        if let Some(DoorPreviewLayer { viewport: preview, polygons }) = buffers.door_preview {
+            builder
+                .set_viewport(0, [preview])
+                .bind_pipeline_graphics(pipelines.polygon_pipeline.clone())
+                .bind_vertex_buffers(0, polygons.clone())
+                .draw(polygons.len() as u32, 1, 0, 0)
+                .unwrap()
+                .set_viewport(0, [viewport.clone()]);
+        }
+
+        builder.end_render_pass().unwrap();
     }
 }
 
@@ -1,12 +1,18 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use vulkano::{
     device::{
         physical::PhysicalDeviceType, Device, DeviceCreateInfo, DeviceExtensions, Features, Queue,
         QueueCreateInfo,
     },
-    image::{ImageUsage, SwapchainImage, SampleCount, ImageFormatInfo, ImageType},
-    instance::{Instance, InstanceCreateInfo},
+    image::{ImageFormatInfo, ImageType, ImageUsage, SampleCount, SampleCounts, SwapchainImage},
+    instance::{
+        debug::{DebugUtilsMessageType, DebugUtilsMessenger, DebugUtilsMessengerCreateInfo},
+        Instance, InstanceCreateInfo, InstanceExtensions,
+    },
     swapchain::{Surface, Swapchain, SwapchainCreateInfo},
     VulkanLibrary,
 };
@@ -17,6 +23,8 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use super::texture::SamplingSupport;
+
 pub struct Init {
     pub device: Arc<Device>,
     pub queue: Arc<Queue>,
@@ -25,21 +33,86 @@ pub struct Init {
     pub swapchain: Arc<Swapchain>,
     pub images: Vec<Arc<SwapchainImage>>,
     pub max_sample_count: SampleCount,
+    pub sampling_support: SamplingSupport,
+    /// set if the validation layer was asked for and is installed
+    pub validation: Option<Validation>,
+}
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Prints everything the validation layer reports, counting the errors
+pub struct Validation {
+    _messenger: DebugUtilsMessenger,
+    errors: Arc<AtomicUsize>,
+}
+
+impl Validation {
+    fn new(instance: Arc<Instance>) -> Validation {
+        let errors = Arc::new(AtomicUsize::new(0));
+        let counted = errors.clone();
+        let create_info = DebugUtilsMessengerCreateInfo {
+            message_type: DebugUtilsMessageType {
+                general: true,
+                validation: true,
+                performance: true,
+                ..DebugUtilsMessageType::empty()
+            },
+            ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(move |message| {
+                if message.severity.error {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                }
+                eprintln!(
+                    "{}: {}",
+                    message.layer_prefix.unwrap_or("validation"),
+                    message.description
+                );
+            }))
+        };
+        // the callback only prints, it doesn't call Vulkan
+        let messenger = unsafe { DebugUtilsMessenger::new(instance, create_info) }.unwrap();
+        Validation {
+            _messenger: messenger,
+            errors,
+        }
+    }
+
+    /// how many errors have been reported so far
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
 }
 
-/// Creates new Vulkan library instance, sets up virtual vulkan device
-pub fn init() -> Init {
+/// Creates new Vulkan library instance, sets up virtual vulkan device. With `validation` set
+/// the validation layer checks every call, if it's installed
+pub fn init(with_validation: bool) -> Init {
     let library = VulkanLibrary::new().unwrap();
-    let required_extensions = vulkano_win::required_extensions(&library);
+    let validation = with_validation
+        && library
+            .layer_properties()
+            .unwrap()
+            .any(|layer| layer.name() == VALIDATION_LAYER);
+    if with_validation && !validation {
+        eprintln!("{VALIDATION_LAYER} isn't installed, running without validation");
+    }
     let instance = Instance::new(
-        library,
+        library.clone(),
         InstanceCreateInfo {
-            enabled_extensions: required_extensions,
+            enabled_extensions: InstanceExtensions {
+                ext_debug_utils: validation,
+                ..vulkano_win::required_extensions(&library)
+            },
+            enabled_layers: if validation {
+                vec![VALIDATION_LAYER.to_string()]
+            } else {
+                vec![]
+            },
             enumerate_portability: true,
             ..Default::default()
         },
     )
     .unwrap();
+    // created first so it's there to report on everything else
+    let validation = validation.then(|| Validation::new(instance.clone()));
 
     let event_loop = EventLoop::new();
     let surface = WindowBuilder::new()
@@ -73,10 +146,13 @@ pub fn init() -> Init {
         })
         .unwrap();
 
+    // smooth textures are filtered anisotropically where it's supported
+    let sampler_anisotropy = physical_device.supported_features().sampler_anisotropy;
     let (device, mut queues) = Device::new(
         physical_device,
         DeviceCreateInfo {
             enabled_features: Features {
+                sampler_anisotropy,
                 descriptor_indexing: true,
                 runtime_descriptor_array: true,
                 descriptor_binding_variable_descriptor_count: true,
@@ -118,6 +194,8 @@ pub fn init() -> Init {
                 image_format,
                 image_extent: window.inner_size().into(),
                 image_usage: ImageUsage {
+                    // frames are copied out of the swapchain images to record them
+                    transfer_src: surface_capabilities.supported_usage_flags.transfer_src,
                     transfer_dst: true,
                     color_attachment: true,
                     sampled: true,
@@ -135,19 +213,44 @@ pub fn init() -> Init {
         .unwrap()
     };
 
-    let test_sample_count = device
+    // the multisampled image is a transient attachment resolved into the swapchain image,
+    // so that's the usage its sample count has to be supported for
+    let format_sample_counts = device
         .physical_device()
         .image_format_properties(ImageFormatInfo {
             format: Some(swapchain.image_format()),
             image_type: ImageType::Dim2d,
             tiling: vulkano::image::ImageTiling::Optimal,
-            usage: swapchain.image_usage(),
+            usage: ImageUsage {
+                color_attachment: true,
+                transient_attachment: true,
+                ..ImageUsage::empty()
+            },
             ..Default::default()
         })
-        .unwrap()
-        .unwrap()
-        .sample_counts;
-    let max_sample_count = test_sample_count.max_count();
+        .ok()
+        .flatten()
+        .map(|properties| properties.sample_counts);
+    let framebuffer_sample_counts = device
+        .physical_device()
+        .properties()
+        .framebuffer_color_sample_counts;
+    let max_sample_count = choose_sample_count(format_sample_counts, framebuffer_sample_counts);
+    if format_sample_counts.is_none() {
+        eprintln!(
+            "multisampling disabled: {:?} can't be used for a multisampled attachment",
+            swapchain.image_format()
+        );
+    } else if max_sample_count != framebuffer_sample_counts.max_count() {
+        eprintln!(
+            "{:?} supports at most {:?}, using it instead of {:?}",
+            swapchain.image_format(),
+            max_sample_count,
+            framebuffer_sample_counts.max_count()
+        );
+    }
+
+    let sampling_support = SamplingSupport::query(&device);
 
     Init {
         device,
@@ -157,5 +260,59 @@ pub fn init() -> Init {
         swapchain,
         images,
         max_sample_count,
+        sampling_support,
+        validation,
+    }
+}
+
+/// the highest sample count supported both by the swapchain format and by the framebuffers,
+/// or a single sample if the format can't be multisampled at all
+fn choose_sample_count(
+    format_sample_counts: Option<SampleCounts>,
+    framebuffer_sample_counts: SampleCounts,
+) -> SampleCount {
+    format_sample_counts
+        .map(|counts| counts.intersection(&framebuffer_sample_counts))
+        .filter(|counts| !counts.is_empty())
+        .map_or(SampleCount::Sample1, |counts| counts.max_count())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// the set of the counts, going over every count rather than matching on the given ones, as
+    /// vulkano may add more of them
+    fn counts(supported: &[SampleCount]) -> SampleCounts {
+        let mut counts = SampleCounts::empty();
+        counts.sample1 = supported.contains(&SampleCount::Sample1);
+        counts.sample2 = supported.contains(&SampleCount::Sample2);
+        counts.sample4 = supported.contains(&SampleCount::Sample4);
+        counts.sample8 = supported.contains(&SampleCount::Sample8);
+        counts.sample16 = supported.contains(&SampleCount::Sample16);
+        counts.sample32 = supported.contains(&SampleCount::Sample32);
+        counts.sample64 = supported.contains(&SampleCount::Sample64);
+        counts
+    }
+
+    #[test]
+    fn test_choose_sample_count() {
+        use SampleCount::*;
+        let framebuffer = counts(&[Sample1, Sample2, Sample4, Sample8]);
+
+        assert_eq!(
+            choose_sample_count(Some(counts(&[Sample1, Sample2, Sample4])), framebuffer),
+            Sample4
+        );
+        assert_eq!(
+            choose_sample_count(Some(counts(&[Sample1, Sample16])), framebuffer),
+            Sample1
+        );
+        assert_eq!(
+            choose_sample_count(Some(counts(&[Sample2, Sample8, Sample16])), framebuffer),
+            Sample8
+        );
+        assert_eq!(choose_sample_count(Some(counts(&[])), framebuffer), Sample1);
+        assert_eq!(choose_sample_count(None, framebuffer), Sample1);
     }
 }
@@ -0,0 +1,134 @@
+//! the ball squashing flat against whatever it hits hard and springing back into shape. Only
+//! the way it's drawn changes, the simulated circle stays round
+
+use std::time::Duration;
+
+use crate::{
+    geometry::{Affine2, Point, Vector},
+    physics::Impact,
+};
+
+/// how long the ball takes to get back into shape
+const RECOVERY_TIME: Duration = Duration::from_millis(150);
+/// hits with this impulse or more squash the ball the most
+const FULL_SQUASH_IMPULSE: f64 = 0.1;
+/// how much shorter the ball gets along the normal of the hardest hits
+const MAX_SQUASH: f64 = 0.3;
+
+/// How the ball is deformed by the last hard hit it took
+#[derive(Clone, Copy, Debug)]
+pub struct Squash {
+    /// unit vector pointing from the center of the ball towards what it hit
+    normal: Vector,
+    /// the squash right after the hit
+    depth: f64,
+    since_hit: Duration,
+}
+
+impl Default for Squash {
+    /// a round ball
+    fn default() -> Self {
+        Squash {
+            normal: Vector::ZERO,
+            depth: 0.0,
+            since_hit: Duration::ZERO,
+        }
+    }
+}
+
+impl Squash {
+    /// squashes the ball along the normal of the impact, unless an earlier hit still squashes
+    /// it more
+    pub fn hit(&mut self, impact: Impact) {
+        let depth = (impact.impulse / FULL_SQUASH_IMPULSE).min(1.0) * MAX_SQUASH;
+        if depth > self.amount() {
+            *self = Squash {
+                normal: impact.normal,
+                depth,
+                since_hit: Duration::ZERO,
+            };
+        }
+    }
+
+    /// lets the ball recover for `dt`
+    pub fn update(&mut self, dt: Duration) {
+        self.since_hit += dt;
+    }
+
+    /// how much shorter than its diameter the ball currently is along the normal, as a
+    /// fraction of the diameter
+    pub fn amount(&self) -> f64 {
+        self.depth * recovery(self.since_hit.as_secs_f64() / RECOVERY_TIME.as_secs_f64())
+    }
+
+    /// squashes a ball centered at `center` along the normal and stretches it across, so it
+    /// keeps its area
+    pub fn transform(&self, center: Point) -> Affine2 {
+        let amount = self.amount();
+        if amount == 0.0 {
+            return Affine2::IDENTITY;
+        }
+        let angle = self.normal.1.atan2(self.normal.0);
+        let along = 1.0 - amount;
+
+        Affine2::translation(-center)
+            .then(Affine2::rotation(-angle))
+            .then(Affine2::scaling(along, along.recip()))
+            .then(Affine2::rotation(angle))
+            .then(Affine2::translation(center))
+    }
+}
+
+/// how much of the squash is left `progress` of the way through the recovery, all of it at
+/// first and easing out to none
+fn recovery(progress: f64) -> f64 {
+    (1.0 - progress.clamp(0.0, 1.0)).powi(2)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recovery_eases_out() {
+        assert_eq!(recovery(0.0), 1.0);
+        assert_eq!(recovery(1.0), 0.0);
+        assert_eq!(recovery(2.0), 0.0);
+        let samples = (0..=10)
+            .map(|i| recovery(i as f64 / 10.0))
+            .collect::<Vec<_>>();
+        assert!(samples.windows(2).all(|pair| pair[1] < pair[0]));
+        // most of the shape comes back in the first half
+        assert!(recovery(0.5) < 0.3);
+    }
+
+    #[test]
+    fn test_squash_along_normal() {
+        let mut squash = Squash::default();
+        let center = Point(0.3, -0.2);
+        assert_eq!(squash.transform(center), Affine2::IDENTITY);
+
+        squash.hit(Impact {
+            normal: Point(0.0, -1.0),
+            impulse: 2.0 * FULL_SQUASH_IMPULSE,
+        });
+        let transform = squash.transform(center);
+        let bottom = transform.apply(center + Point(0.0, -0.1));
+        let side = transform.apply(center + Point(0.1, 0.0));
+        assert!(bottom.is_close_enough_to(center + Point(0.0, -0.1 * (1.0 - MAX_SQUASH))));
+        assert!(side.is_close_enough_to(center + Point(0.1 / (1.0 - MAX_SQUASH), 0.0)));
+        assert!((transform.determinant() - 1.0).abs() < 1e-9);
+
+        // a weaker hit doesn't interrupt the recovery from a stronger one
+        squash.update(RECOVERY_TIME / 4);
+        let amount = squash.amount();
+        squash.hit(Impact {
+            normal: Point(1.0, 0.0),
+            impulse: 0.1 * FULL_SQUASH_IMPULSE,
+        });
+        assert_eq!(squash.amount(), amount);
+
+        squash.update(RECOVERY_TIME);
+        assert_eq!(squash.transform(center), Affine2::IDENTITY);
+    }
+}
@@ -2,44 +2,185 @@ use std::fs::File;
 use std::path::Path;
 use std::sync::Arc;
 
-use png::Info;
+use png::{BitDepth, ColorType, Info};
 use vulkano::command_buffer::allocator::CommandBufferAllocator;
 use vulkano::descriptor_set::layout::DescriptorSetLayout;
 use vulkano::device::Device;
 use vulkano::memory::allocator::MemoryAllocator;
 use vulkano::pipeline::GraphicsPipeline;
 use vulkano::{
-    command_buffer::AutoCommandBufferBuilder,
+    command_buffer::{AutoCommandBufferBuilder, BlitImageInfo, ImageBlit},
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
     },
-    format::Format,
-    image::{view::ImageView, ImageDimensions, ImmutableImage, MipmapsCount},
+    format::{Format, FormatProperties},
+    image::{
+        view::ImageView, ImageAccess, ImageDimensions, ImageSubresourceLayers, ImageViewAbstract,
+        ImmutableImage, MipmapsCount,
+    },
     pipeline::Pipeline,
-    sampler::{Filter, Sampler, SamplerCreateInfo},
+    sampler::{Filter, Sampler, SamplerCreateInfo, SamplerMipmapMode, LOD_CLAMP_NONE},
 };
 
+use crate::assets;
+
+/// the format every texture is stored in
+pub const TEXTURE_FORMAT: Format = Format::R8G8B8A8_SRGB;
+/// smooth textures aren't filtered with a higher anisotropy even if the device allows it
+const MAX_ANISOTROPY: f32 = 16.0;
+
+/// What the device can do to keep textures from shimmering when they're drawn smaller than
+/// they are, found out once when it's set up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplingSupport {
+    /// textures can be sampled with linear filtering
+    pub linear: bool,
+    /// textures can be blitted with linear filtering, which generating mipmaps takes
+    pub mipmaps: bool,
+    /// the highest anisotropy samplers can use, `None` if the device doesn't have the
+    /// `sampler_anisotropy` feature enabled
+    pub max_anisotropy: Option<f32>,
+}
+
+impl SamplingSupport {
+    pub fn query(device: &Device) -> Self {
+        let physical_device = device.physical_device();
+        let (linear, blit) = match physical_device.format_properties(TEXTURE_FORMAT) {
+            Ok(FormatProperties {
+                optimal_tiling_features: features,
+                ..
+            }) => (
+                features.sampled_image_filter_linear,
+                features.blit_src && features.blit_dst,
+            ),
+            Err(_) => (false, false),
+        };
+        SamplingSupport {
+            linear,
+            mipmaps: linear && blit,
+            max_anisotropy: device.enabled_features().sampler_anisotropy.then(|| {
+                physical_device
+                    .properties()
+                    .max_sampler_anisotropy
+                    .min(MAX_ANISOTROPY)
+            }),
+        }
+    }
+}
+
+/// How a texture is sampled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    /// the nearest pixel, for pixel art which should stay sharp
+    Nearest,
+    /// blends the pixels around, with mipmaps and anisotropic filtering where the device
+    /// supports them, for textures which are often drawn smaller than they are, like the
+    /// background when the camera zooms out
+    Smooth(SamplingSupport),
+}
+
+impl Sampling {
+    /// whether the texture should have a full chain of mipmaps
+    pub fn has_mipmaps(self) -> bool {
+        matches!(self, Sampling::Smooth(support) if support.mipmaps)
+    }
+
+    fn mip_levels(self) -> MipmapsCount {
+        if self.has_mipmaps() {
+            MipmapsCount::Log2
+        } else {
+            MipmapsCount::One
+        }
+    }
+
+    fn sampler_create_info(self) -> SamplerCreateInfo {
+        match self {
+            Sampling::Smooth(support) if support.linear => SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                mipmap_mode: SamplerMipmapMode::Linear,
+                lod: 0.0..=LOD_CLAMP_NONE,
+                anisotropy: support.max_anisotropy,
+                ..Default::default()
+            },
+            // without linear filtering smoothing can't be done at all
+            Sampling::Nearest | Sampling::Smooth(_) => SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// fills every mip level of the image but the first one, each from the one above it, by
+/// recording the blits into the command buffer after whatever fills the first level
+pub fn generate_mipmaps<L, A: CommandBufferAllocator>(
+    command_buffer: &mut AutoCommandBufferBuilder<L, A>,
+    image: Arc<dyn ImageAccess>,
+) {
+    let dimensions = image.dimensions();
+    for level in 1..image.mip_levels() {
+        let size = |level| {
+            dimensions
+                .mip_level_dimensions(level)
+                .unwrap()
+                .width_height_depth()
+        };
+        command_buffer
+            .blit_image(BlitImageInfo {
+                regions: [ImageBlit {
+                    src_subresource: ImageSubresourceLayers {
+                        mip_level: level - 1,
+                        ..image.subresource_layers()
+                    },
+                    src_offsets: [[0; 3], size(level - 1)],
+                    dst_subresource: ImageSubresourceLayers {
+                        mip_level: level,
+                        ..image.subresource_layers()
+                    },
+                    dst_offsets: [[0; 3], size(level)],
+                    ..Default::default()
+                }]
+                .into(),
+                filter: Filter::Linear,
+                ..BlitImageInfo::images(image.clone(), image.clone())
+            })
+            .unwrap();
+    }
+}
+
 pub struct Texture(pub Arc<PersistentDescriptorSet>);
 impl Texture {
+    /// loads the images into the layers of a texture, generating its mipmaps in the command
+    /// buffer if it's sampled smoothly
     pub fn new<L, A: CommandBufferAllocator>(
         device: Arc<Device>,
         paths: &[impl AsRef<Path>],
         memory_allocator: &(impl MemoryAllocator + ?Sized),
         command_buffer: &mut AutoCommandBufferBuilder<L, A>,
-        mip_levels: MipmapsCount,
+        sampling: Sampling,
         pipeline: Arc<GraphicsPipeline>,
         descriptor_set_allocator: &StandardDescriptorSetAllocator,
     ) -> Self {
-        let image = Self::load(paths, memory_allocator, command_buffer, mip_levels);
-        let sampler = Sampler::new(
-            device,
-            SamplerCreateInfo {
-                mag_filter: Filter::Nearest,
-                min_filter: Filter::Nearest,
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let image = Self::load(
+            paths,
+            memory_allocator,
+            command_buffer,
+            sampling.mip_levels(),
+        );
+        Self::from_image(device, image, sampling, pipeline, descriptor_set_allocator)
+    }
+
+    /// a texture sampling the image, which has to be in the format of the pipeline's sampler
+    pub fn from_image(
+        device: Arc<Device>,
+        image: Arc<dyn ImageViewAbstract>,
+        sampling: Sampling,
+        pipeline: Arc<GraphicsPipeline>,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+    ) -> Self {
+        let sampler = Sampler::new(device, sampling.sampler_create_info()).unwrap();
         let layout = pipeline.layout().set_layouts().get(0).unwrap();
         Texture(Self::create_descriptor_set(
             descriptor_set_allocator,
@@ -86,17 +227,42 @@ impl Texture {
             files_data,
             dimensions,
             mip_levels,
-            Format::R8G8B8A8_SRGB,
+            TEXTURE_FORMAT,
             command_buffer,
         )
         .unwrap();
         ImageView::new_default(image).unwrap()
     }
 
+    /// reads a PNG file with 8 bit RGBA pixels, returning its width, height and pixels
+    pub fn decode(path: impl AsRef<Path>) -> Result<(u32, u32, Vec<u8>), String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut reader = png::Decoder::new(file)
+            .read_info()
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        let &Info {
+            width,
+            height,
+            color_type,
+            bit_depth,
+            ..
+        } = reader.info();
+        if (color_type, bit_depth) != (ColorType::Rgba, BitDepth::Eight) {
+            return Err(format!("{} isn't an 8 bit RGBA image", path.display()));
+        }
+        let mut image_data = vec![0; reader.output_buffer_size()];
+        reader
+            .next_frame(&mut image_data)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        image_data.truncate((width * height * 4) as usize);
+        Ok((width, height, image_data))
+    }
+
     fn create_descriptor_set(
         descriptor_set_allocator: &StandardDescriptorSetAllocator,
         layout: &Arc<DescriptorSetLayout>,
-        drawing: Arc<ImageView<ImmutableImage>>,
+        drawing: Arc<dyn ImageViewAbstract>,
         sampler: Arc<Sampler>,
     ) -> Arc<PersistentDescriptorSet> {
         PersistentDescriptorSet::new(
@@ -107,3 +273,50 @@ impl Texture {
         .unwrap()
     }
 }
+
+/// The images level entities are drawn with, loaded into the layers of a single texture array
+#[derive(Default)]
+pub struct EntityTextures {
+    /// the layer each of the images was loaded into, `None` if it couldn't be loaded
+    pub layers: Vec<Option<u32>>,
+    /// `None` if none of the images could be loaded, or until they are uploaded
+    pub texture: Option<Texture>,
+}
+
+/// The width, height and number of layers of a texture array, along with its pixels, the
+/// layers one after another
+pub type TextureArray = ((u32, u32, u32), Vec<u8>);
+
+impl EntityTextures {
+    /// reads the images which can be, the ones missing, in another format or of another size
+    /// than the first one are reported and left out, so the entities using them keep their
+    /// colors. The texture array to upload is returned separately, `None` if there's nothing
+    /// to upload
+    pub fn decode(paths: &[String]) -> (Self, Option<TextureArray>) {
+        let mut size = None;
+        let mut pixels = vec![];
+        let mut n_of_layers = 0;
+        let layers = paths
+            .iter()
+            .map(|path| {
+                let (width, height, image_data) = Texture::decode(assets::path(path))
+                    .map_err(|err| eprintln!("couldn't load a texture: {err}"))
+                    .ok()?;
+                if *size.get_or_insert((width, height)) != (width, height) {
+                    eprintln!("{path} isn't the same size as the other textures of the level");
+                    return None;
+                }
+                pixels.extend(image_data);
+                n_of_layers += 1;
+                Some(n_of_layers - 1)
+            })
+            .collect();
+
+        let textures = Self {
+            layers,
+            texture: None,
+        };
+        let array = size.map(|(width, height)| ((width, height, n_of_layers), pixels));
+        (textures, array)
+    }
+}
@@ -0,0 +1,482 @@
+//! textures copied to the GPU a few rows at a time over several frames, so switching to a level
+//! with big textures doesn't stall the frame it arrives in. A texture is only handed over once
+//! all of it has been copied and the frame copying its last rows has finished, until then the
+//! one it replaces keeps being shown. A texture with mipmaps gets them generated in the frame
+//! copying its last rows
+
+use std::{
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{
+        allocator::CommandBufferAllocator, AutoCommandBufferBuilder, BlitImageInfo,
+        BufferImageCopy, CopyBufferToImageInfo,
+    },
+    device::{Device, DeviceOwned},
+    image::{
+        view::ImageView, ImageAccess, ImageCreateFlags, ImageDescriptorLayouts, ImageDimensions,
+        ImageInner, ImageLayout, ImageSubresourceLayers, ImageUsage, ImageViewAbstract,
+        ImmutableImage, MipmapsCount, StorageImage,
+    },
+    memory::allocator::MemoryAllocator,
+};
+
+use super::texture::{self, TEXTURE_FORMAT};
+
+/// at most how many bytes of pixels a frame copies into textures
+const FRAME_BUDGET: u64 = 4 * 1024 * 1024;
+const BYTES_PER_PIXEL: u64 = 4;
+/// how long a frame may take while textures are uploading before `--stress-uploads` reports it
+const SLOW_FRAME: Duration = Duration::from_millis(20);
+/// how often `--stress-uploads` uploads the textures of the level again
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A horizontal band of rows of one layer of an image
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slice {
+    layer: u32,
+    first_row: u32,
+    rows: u32,
+}
+
+/// How far copying an image has got
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Progress {
+    /// the width, height and number of layers of the image
+    size: (u32, u32, u32),
+    /// the layer and the row in it the copying continues from
+    next: (u32, u32),
+}
+
+impl Progress {
+    fn new(size: (u32, u32, u32)) -> Self {
+        Progress { size, next: (0, 0) }
+    }
+
+    fn is_done(&self) -> bool {
+        self.next.0 >= self.size.2
+    }
+
+    fn row_size(&self) -> u64 {
+        self.size.0 as u64 * BYTES_PER_PIXEL
+    }
+
+    /// the slices to copy next which fit in the budget, taking their size out of it
+    fn take(&mut self, budget: &mut u64) -> Vec<Slice> {
+        let height = self.size.1;
+        let mut slices = vec![];
+        while !self.is_done() {
+            let (layer, first_row) = self.next;
+            let rows = ((*budget / self.row_size()) as u32).min(height - first_row);
+            if rows == 0 {
+                break;
+            }
+            slices.push(Slice {
+                layer,
+                first_row,
+                rows,
+            });
+            *budget -= rows as u64 * self.row_size();
+            self.next = if first_row + rows == height {
+                (layer + 1, 0)
+            } else {
+                (layer, first_row + rows)
+            };
+        }
+        slices
+    }
+}
+
+/// splits the budget of a frame between the images in the order they were queued
+fn plan(images: &mut [Progress], budget: u64) -> Vec<Vec<Slice>> {
+    // an image with rows wider than the whole budget still gets copied, a row a frame
+    let mut budget = images
+        .iter()
+        .find(|image| !image.is_done())
+        .map_or(budget, |image| budget.max(image.row_size()));
+    images
+        .iter_mut()
+        .map(|image| image.take(&mut budget))
+        .collect()
+}
+
+/// An image filled over several frames. Vulkano assumes a `StorageImage` is undefined at the
+/// start of every command buffer, which allows the driver to throw away the rows copied by the
+/// earlier ones, so this one remembers once its layout has been set up
+#[derive(Debug)]
+struct StreamedImage {
+    image: Arc<StorageImage>,
+    is_layout_initialized: AtomicBool,
+}
+
+unsafe impl DeviceOwned for StreamedImage {
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
+    }
+}
+
+unsafe impl ImageAccess for StreamedImage {
+    fn inner(&self) -> ImageInner<'_> {
+        self.image.inner()
+    }
+
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        self.image.descriptor_layouts()
+    }
+
+    unsafe fn layout_initialized(&self) {
+        self.is_layout_initialized.store(true, Ordering::SeqCst);
+    }
+
+    fn is_layout_initialized(&self) -> bool {
+        self.is_layout_initialized.load(Ordering::SeqCst)
+    }
+}
+
+/// A texture waiting to be copied, along with what it's for
+struct Upload<T> {
+    payload: T,
+    pixels: Vec<u8>,
+    image: Arc<StreamedImage>,
+    progress: Progress,
+    has_mipmaps: bool,
+    /// the copy of the image with mipmaps, blitted from it in the frame copying its last rows
+    mipmapped: Option<Arc<ImmutableImage>>,
+}
+
+impl<T> Upload<T> {
+    /// the image to sample once the frame copying its last rows has finished
+    fn view(&self) -> Arc<dyn ImageViewAbstract> {
+        match &self.mipmapped {
+            Some(image) => ImageView::new_default(image.clone()).unwrap(),
+            None => ImageView::new_default(self.image.clone()).unwrap(),
+        }
+    }
+}
+
+/// A texture copied completely, waiting for the frame which copied the last of it to finish
+struct Copied<T> {
+    payload: T,
+    view: Arc<dyn ImageViewAbstract>,
+    is_finished: Rc<dyn Fn() -> bool>,
+}
+
+/// The textures being copied to the GPU. `T` tells what each of them replaces once it's there
+pub struct UploadQueue<T> {
+    uploads: Vec<Upload<T>>,
+    /// the progress of the uploads before the last frame's copies were recorded, returned to
+    /// if that frame is never submitted
+    before_frame: Vec<Progress>,
+    copied: Vec<Copied<T>>,
+}
+
+impl<T> Default for UploadQueue<T> {
+    fn default() -> Self {
+        UploadQueue {
+            uploads: vec![],
+            before_frame: vec![],
+            copied: vec![],
+        }
+    }
+}
+
+impl<T> UploadQueue<T> {
+    /// queues a texture array of the given width, height and number of layers, with `pixels`
+    /// holding the layers one after another, and a full chain of mipmaps if `has_mipmaps`, see
+    /// [`texture::Sampling::has_mipmaps`]
+    pub fn push(
+        &mut self,
+        payload: T,
+        (width, height, layers): (u32, u32, u32),
+        pixels: Vec<u8>,
+        has_mipmaps: bool,
+        memory_allocator: &(impl MemoryAllocator + ?Sized),
+        queue_family_index: u32,
+    ) {
+        let image = StorageImage::with_usage(
+            memory_allocator,
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: layers,
+            },
+            TEXTURE_FORMAT,
+            ImageUsage {
+                transfer_src: has_mipmaps,
+                transfer_dst: true,
+                sampled: true,
+                ..ImageUsage::empty()
+            },
+            ImageCreateFlags::empty(),
+            [queue_family_index],
+        )
+        .unwrap();
+        self.uploads.push(Upload {
+            payload,
+            pixels,
+            image: Arc::new(StreamedImage {
+                image,
+                is_layout_initialized: AtomicBool::new(false),
+            }),
+            progress: Progress::new((width, height, layers)),
+            has_mipmaps,
+            mipmapped: None,
+        });
+    }
+
+    /// forgets the textures which are no longer needed, because the one they were going to
+    /// replace is being replaced by another
+    pub fn cancel(&mut self, is_replaced: impl Fn(&T) -> bool) {
+        self.uploads.retain(|upload| !is_replaced(&upload.payload));
+        self.copied.retain(|copied| !is_replaced(&copied.payload));
+    }
+
+    /// whether any texture is still on its way
+    pub fn is_empty(&self) -> bool {
+        self.uploads.is_empty() && self.copied.is_empty()
+    }
+
+    /// records the copies of the next rows of the queued textures, as many as a frame has the
+    /// budget for. Must be recorded before the frame's render pass begins
+    pub fn record<L, A: CommandBufferAllocator>(
+        &mut self,
+        command_buffer: &mut AutoCommandBufferBuilder<L, A>,
+        memory_allocator: &(impl MemoryAllocator + ?Sized),
+    ) {
+        let mut progress: Vec<_> = self.uploads.iter().map(|upload| upload.progress).collect();
+        let plans = plan(&mut progress, FRAME_BUDGET);
+        self.before_frame = self.uploads.iter().map(|upload| upload.progress).collect();
+
+        for ((upload, progress), slices) in self.uploads.iter_mut().zip(progress).zip(plans) {
+            upload.progress = progress;
+            let (width, height, _) = progress.size;
+            let row_size = progress.row_size() as usize;
+            for Slice {
+                layer,
+                first_row,
+                rows,
+            } in slices
+            {
+                let start = (layer * height + first_row) as usize * row_size;
+                let end = start + rows as usize * row_size;
+                let staging = CpuAccessibleBuffer::from_iter(
+                    memory_allocator,
+                    BufferUsage {
+                        transfer_src: true,
+                        ..BufferUsage::empty()
+                    },
+                    false,
+                    upload.pixels[start..end].iter().copied(),
+                )
+                .unwrap();
+                let image = upload.image.clone();
+                command_buffer
+                    .copy_buffer_to_image(CopyBufferToImageInfo {
+                        regions: [BufferImageCopy {
+                            image_subresource: ImageSubresourceLayers {
+                                array_layers: layer..layer + 1,
+                                ..image.subresource_layers()
+                            },
+                            image_offset: [0, first_row, 0],
+                            image_extent: [width, rows, 1],
+                            ..Default::default()
+                        }]
+                        .into_iter()
+                        .collect(),
+                        ..CopyBufferToImageInfo::buffer_image(staging, image)
+                    })
+                    .unwrap();
+            }
+            if upload.has_mipmaps && progress.is_done() {
+                upload.mipmapped = Some(Self::record_mipmaps(
+                    &upload.image,
+                    command_buffer,
+                    memory_allocator,
+                ));
+            }
+        }
+    }
+
+    /// records copying the whole image into the first level of a new one with mipmaps, and
+    /// generating the rest of them
+    fn record_mipmaps<L, A: CommandBufferAllocator>(
+        image: &Arc<StreamedImage>,
+        command_buffer: &mut AutoCommandBufferBuilder<L, A>,
+        memory_allocator: &(impl MemoryAllocator + ?Sized),
+    ) -> Arc<ImmutableImage> {
+        let (mipmapped, initialization) = ImmutableImage::uninitialized(
+            memory_allocator,
+            image.dimensions(),
+            TEXTURE_FORMAT,
+            MipmapsCount::Log2,
+            ImageUsage {
+                transfer_src: true,
+                transfer_dst: true,
+                sampled: true,
+                ..ImageUsage::empty()
+            },
+            ImageCreateFlags::empty(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            image.device().active_queue_family_indices().iter().copied(),
+        )
+        .unwrap();
+        command_buffer
+            .blit_image(BlitImageInfo::images(image.clone(), initialization))
+            .unwrap();
+        texture::generate_mipmaps(command_buffer, mipmapped.clone());
+        mipmapped
+    }
+
+    /// the frame with the copies recorded last was submitted, `is_finished` tells whether the
+    /// GPU is done with it
+    pub fn submitted(&mut self, is_finished: impl Fn() -> bool + 'static) {
+        let is_finished: Rc<dyn Fn() -> bool> = Rc::new(is_finished);
+        let (done, uploads) = self
+            .uploads
+            .drain(..)
+            .partition(|upload| upload.progress.is_done());
+        self.uploads = uploads;
+        self.copied
+            .extend(done.into_iter().map(|upload: Upload<T>| Copied {
+                view: upload.view(),
+                payload: upload.payload,
+                is_finished: is_finished.clone(),
+            }));
+        self.before_frame.clear();
+    }
+
+    /// the frame with the copies recorded last was never submitted, so they'll be recorded
+    /// again into the next one
+    pub fn discarded(&mut self) {
+        for (upload, progress) in self.uploads.iter_mut().zip(self.before_frame.drain(..)) {
+            upload.progress = progress;
+            upload.mipmapped = None;
+        }
+    }
+
+    /// takes the textures which are completely on the GPU
+    pub fn ready(&mut self) -> Vec<(T, Arc<dyn ImageViewAbstract>)> {
+        let (ready, copied) = self
+            .copied
+            .drain(..)
+            .partition(|copied| (copied.is_finished)());
+        self.copied = copied;
+        ready
+            .into_iter()
+            .map(|Copied { payload, view, .. }| (payload, view))
+            .collect()
+    }
+}
+
+/// Uploads the textures of the level over and over for `--stress-uploads`, reporting the frames
+/// which take longer than [`SLOW_FRAME`] while they upload
+pub struct StressTest {
+    last_frame: Instant,
+    last_reload: Instant,
+    /// the longest frame and the number of slow ones since the textures started uploading
+    longest: Duration,
+    slow_frames: u32,
+}
+
+impl Default for StressTest {
+    fn default() -> Self {
+        StressTest {
+            last_frame: Instant::now(),
+            last_reload: Instant::now(),
+            longest: Duration::ZERO,
+            slow_frames: 0,
+        }
+    }
+}
+
+impl StressTest {
+    /// records a frame starting now, returning whether to upload the textures again
+    pub fn frame(&mut self, is_uploading: bool) -> bool {
+        let now = Instant::now();
+        let frame_time = now - self.last_frame;
+        self.last_frame = now;
+        if is_uploading {
+            self.longest = self.longest.max(frame_time);
+            if frame_time > SLOW_FRAME {
+                self.slow_frames += 1;
+                eprintln!("a frame took {frame_time:?} while textures were uploading");
+            }
+            return false;
+        }
+        if !self.longest.is_zero() {
+            println!(
+                "textures uploaded, the longest frame took {:?}, {} took over {SLOW_FRAME:?}",
+                self.longest, self.slow_frames
+            );
+            self.longest = Duration::ZERO;
+            self.slow_frames = 0;
+        }
+        if now - self.last_reload < RELOAD_INTERVAL {
+            return false;
+        }
+        self.last_reload = now;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uploads_stay_within_budget() {
+        const BUDGET: u64 = 1000;
+        // 40 bytes a row, 3 layers of 10 rows
+        let mut first = Progress::new((10, 10, 3));
+        // a single row which doesn't fit into the budget
+        let mut wide = Progress::new((300, 1, 1));
+        let mut copied = vec![vec![0; 30], vec![0; 1]];
+        let mut frames = 0;
+        while !(first.is_done() && wide.is_done()) {
+            let mut images = [first, wide];
+            let plans = plan(&mut images, BUDGET);
+            [first, wide] = images;
+            frames += 1;
+
+            let sizes = [40, 1200];
+            let used: u64 = plans
+                .iter()
+                .zip(sizes)
+                .flat_map(|(slices, size)| slices.iter().map(move |slice| slice.rows as u64 * size))
+                .sum();
+            assert!(
+                used <= BUDGET || plans[0].is_empty(),
+                "a frame copied {used} bytes"
+            );
+            for (slices, (copied, height)) in plans.iter().zip(copied.iter_mut().zip([10, 1])) {
+                for slice in slices {
+                    let start = (slice.layer * height + slice.first_row) as usize;
+                    for row in &mut copied[start..start + slice.rows as usize] {
+                        *row += 1;
+                    }
+                }
+            }
+            // an image is only done once all of it has been copied
+            assert_eq!(first.is_done(), copied[0].iter().all(|&row| row > 0));
+        }
+
+        assert!(copied.iter().flatten().all(|&row| row == 1));
+        // 1200 bytes only fit in 2 frames of 1000 once the first image is out of the way
+        assert_eq!(frames, 3);
+    }
+}
@@ -11,6 +11,18 @@ pub struct Vertex {
     pub center: [f32; 2],
     pub color: [f32; 3],
     pub tex_position: [f32; 2],
+    /// how see-through textures are drawn, 0 for opaque
+    pub transparency: f32,
 }
 
-impl_vertex!(Vertex, position, texture_id, radius, dist, center, color, tex_position);
+impl_vertex!(
+    Vertex,
+    position,
+    texture_id,
+    radius,
+    dist,
+    center,
+    color,
+    tex_position,
+    transparency
+);
@@ -0,0 +1,255 @@
+//! where players lose each level, for level designers. Every lost ball is recorded and saved to
+//! a file kept next to the game whenever the level changes and when the game ends, which the
+//! editor mode shows as red dots and `levels-tool heatmap` sums up
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    io,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    geometry::Point,
+    persist::{self, Persist},
+    physics::DeathCause,
+};
+
+/// the file the deaths are kept in, in the directory the game is run from
+pub const HEATMAP_FILE: &str = "heatmap.ron";
+/// only this many of the latest deaths are kept for each level
+pub const MAX_DEATHS: usize = 10_000;
+/// the size of the squares deaths are grouped into to tell how crowded a place is
+pub const CELL_SIZE: f64 = 0.1;
+/// how many clusters `summary` lists
+const TOP_CLUSTERS: usize = 5;
+
+/// Where the ball was lost and why
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Death {
+    pub at: Point,
+    pub cause: DeathCause,
+}
+
+/// The deaths in every level, oldest first
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    /// keyed by the name of the level file without its extension
+    levels: BTreeMap<String, Vec<Death>>,
+}
+
+impl Persist for Heatmap {
+    const VERSION: u32 = 1;
+}
+
+/// the name deaths in the level at the path are kept under, so a level is the same one no
+/// matter which directory it's loaded from
+pub fn level_name(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(
+        || path.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+impl Heatmap {
+    /// the deaths saved in the file, none if there's no file yet or it can't be read, see
+    /// [`persist::load_or_quarantine`]
+    pub fn load(path: &Path) -> Self {
+        persist::load_or_quarantine(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        persist::save(path, self)
+    }
+
+    /// appends the death, forgetting the oldest one of the level past [`MAX_DEATHS`]
+    pub fn record(&mut self, level: &str, death: Death) {
+        let deaths = self.levels.entry(level.to_string()).or_default();
+        deaths.push(death);
+        if deaths.len() > MAX_DEATHS {
+            deaths.drain(..deaths.len() - MAX_DEATHS);
+        }
+    }
+
+    pub fn deaths(&self, level: &str) -> &[Death] {
+        self.levels.get(level).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// the square of the grid the point is in
+fn cell(Point(x, y): Point) -> (i64, i64) {
+    (
+        (x / CELL_SIZE).floor() as i64,
+        (y / CELL_SIZE).floor() as i64,
+    )
+}
+
+/// how many deaths there are in each square of the grid
+fn bin(deaths: &[Death]) -> HashMap<(i64, i64), usize> {
+    let mut counts = HashMap::new();
+    for death in deaths {
+        *counts.entry(cell(death.at)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// every death along with how crowded its place is, from 0 for a single death to 1 for the
+/// square of the grid with the most of them
+pub fn density(deaths: &[Death]) -> Vec<(Point, f64)> {
+    let counts = bin(deaths);
+    let most = counts.values().copied().max().unwrap_or(1);
+    deaths
+        .iter()
+        .map(|death| {
+            let count = counts[&cell(death.at)];
+            let crowded = if most > 1 {
+                (count - 1) as f64 / (most - 1) as f64
+            } else {
+                0.0
+            };
+            (death.at, crowded)
+        })
+        .collect()
+}
+
+/// A square of the grid with deaths in it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cluster {
+    /// the average position of the deaths in it
+    pub center: Point,
+    pub count: usize,
+}
+
+/// the squares with the most deaths, the most first
+pub fn clusters(deaths: &[Death]) -> Vec<Cluster> {
+    let mut sums: HashMap<(i64, i64), (Point, usize)> = HashMap::new();
+    for death in deaths {
+        let (sum, count) = sums.entry(cell(death.at)).or_insert((Point::ZERO, 0));
+        *sum += death.at;
+        *count += 1;
+    }
+    let mut clusters: Vec<_> = sums
+        .into_iter()
+        .map(|(cell, (sum, count))| {
+            (
+                cell,
+                Cluster {
+                    center: sum * (1.0 / count as f64),
+                    count,
+                },
+            )
+        })
+        .collect();
+    // ties are broken by the position, so the order doesn't depend on the hashing
+    clusters.sort_by(|(a_cell, a), (b_cell, b)| b.count.cmp(&a.count).then(a_cell.cmp(b_cell)));
+    clusters.into_iter().map(|(_, cluster)| cluster).collect()
+}
+
+/// the number of deaths in the level by cause and where most of them happen
+pub fn summary(level: &str, deaths: &[Death]) -> String {
+    let mut text = format!("{level}: {} deaths", deaths.len());
+    if deaths.is_empty() {
+        return text;
+    }
+    let mut causes = BTreeMap::new();
+    for death in deaths {
+        *causes.entry(format!("{:?}", death.cause)).or_insert(0) += 1;
+    }
+    let causes: Vec<_> = causes
+        .into_iter()
+        .map(|(cause, count)| format!("{cause} {count}"))
+        .collect();
+    let _ = write!(text, " ({})", causes.join(", "));
+    for (i, cluster) in clusters(deaths).iter().take(TOP_CLUSTERS).enumerate() {
+        let Point(x, y) = cluster.center;
+        let _ = write!(
+            text,
+            "\n{}. around ({x:.2}, {y:.2}): {} deaths, {:.0}%",
+            i + 1,
+            cluster.count,
+            100.0 * cluster.count as f64 / deaths.len() as f64
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    fn death(x: f64, y: f64) -> Death {
+        Death {
+            at: Point(x, y),
+            cause: DeathCause::Laser,
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_latest_deaths() {
+        let mut heatmap = Heatmap::default();
+        for i in 0..MAX_DEATHS + 5 {
+            heatmap.record("level1", death(i as f64, 0.0));
+        }
+        let deaths = heatmap.deaths("level1");
+        assert_eq!(deaths.len(), MAX_DEATHS);
+        assert_eq!(deaths[0], death(5.0, 0.0));
+        assert!(heatmap.deaths("level2").is_empty());
+        assert_eq!(level_name("levels/level1.ron"), "level1");
+    }
+
+    #[test]
+    fn test_load_ignores_corrupt_file() {
+        let directory = std::env::temp_dir().join(format!("whisky-heatmap-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(HEATMAP_FILE);
+        assert_eq!(Heatmap::load(&path), Heatmap::default());
+
+        let mut heatmap = Heatmap::default();
+        heatmap.record("level1", death(0.5, 0.5));
+        heatmap.save(&path).unwrap();
+        assert_eq!(Heatmap::load(&path), heatmap);
+
+        // a heatmap saved before there were versions is still read
+        fs::write(&path, ron::to_string(&heatmap).unwrap()).unwrap();
+        assert_eq!(Heatmap::load(&path), heatmap);
+
+        fs::write(&path, "(levels: {\"level1\": [(at: (0.5,").unwrap();
+        assert_eq!(Heatmap::load(&path), Heatmap::default());
+        // the corrupt file is kept aside rather than overwritten by the next save
+        assert!(directory.join("heatmap.ron.bad").exists());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_clusters_and_density() {
+        let deaths = [
+            death(0.51, 0.51),
+            death(0.53, 0.55),
+            death(0.55, 0.52),
+            death(-0.45, 0.05),
+            death(-0.42, 0.02),
+            death(0.95, -0.95),
+        ];
+        let clusters = clusters(&deaths);
+        assert_eq!(
+            clusters.iter().map(|c| c.count).collect::<Vec<_>>(),
+            [3, 2, 1]
+        );
+        assert!(clusters[0].center.to(Point(0.53, 0.53)).norm() < 0.01);
+
+        let density = density(&deaths);
+        assert_eq!(density[0], (Point(0.51, 0.51), 1.0));
+        assert_eq!(density[3].1, 0.5);
+        assert_eq!(density[5].1, 0.0);
+
+        let summary = summary("level1", &deaths);
+        assert!(summary.starts_with("level1: 6 deaths (Laser 6)"));
+        assert!(summary.contains("\n1. around (0.53, 0.53): 3 deaths, 50%"));
+        assert_eq!(summary.lines().count(), 4);
+    }
+}
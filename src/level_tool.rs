@@ -0,0 +1,411 @@
+//! operations over every level in a directory, run by the `levels-tool` binary to keep a level
+//! pack tidy: formatting, upgrading old files, validating and rendering thumbnails
+
+use std::{collections::BTreeSet, fmt::Write as _, fs, io, path::Path};
+
+use ron::Value;
+
+use crate::levels::{self, Level, LevelInfo, LoadError};
+#[cfg(feature = "soft-render")]
+use {
+    crate::soft_render::{render_level, Image, RenderError, View},
+    std::path::PathBuf,
+};
+
+/// the width and height of a thumbnail, in pixels
+#[cfg(feature = "soft-render")]
+pub const THUMBNAIL_SIZE: u32 = 128;
+/// the directory the thumbnails are saved to, inside the level directory
+#[cfg(feature = "soft-render")]
+pub const THUMBNAIL_DIRECTORY: &str = "thumbnails";
+
+/// Reasons for which an operation couldn't be done on a level file
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    #[error("couldn't read or write the file: {0}")]
+    Io(#[from] io::Error),
+    #[error("the file isn't a level: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("the level couldn't be written: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error(transparent)]
+    Load(#[from] LoadError),
+    #[error("the file uses deprecated fields ({}), run `upgrade` first", .0.join(", "))]
+    Deprecated(Vec<String>),
+    #[cfg(feature = "soft-render")]
+    #[error("couldn't save the thumbnail: {0}")]
+    Render(#[from] RenderError),
+}
+
+/// The fields `upgrade` changed in a level file, as paths like `lasers[0].change`
+#[derive(Debug, Default, PartialEq)]
+pub struct Upgrade {
+    /// fields which were left out and are now written with their default values
+    pub filled_in: Vec<String>,
+    /// deprecated fields which were replaced by the current ones or dropped
+    pub migrated: Vec<String>,
+}
+
+/// runs the operation on every level in the directory, in the order of `levels::list_levels`
+pub fn for_each_level<T>(
+    directory: &Path,
+    mut operation: impl FnMut(&Path) -> T,
+) -> Vec<(LevelInfo, T)> {
+    levels::list_levels(directory)
+        .into_iter()
+        .map(|info| {
+            let result = operation(&info.path);
+            (info, result)
+        })
+        .collect()
+}
+
+/// rewrites the level the way `Level::save_to_file` writes it, returning whether the file
+/// changed. Levels with deprecated fields are left alone, as `upgrade` reports what it migrates
+pub fn format(path: &Path) -> Result<bool, ToolError> {
+    let text = fs::read_to_string(path)?;
+    let (formatted, upgrade) = reserialize(&text)?;
+    if !upgrade.migrated.is_empty() {
+        return Err(ToolError::Deprecated(upgrade.migrated));
+    }
+    if formatted == text {
+        return Ok(false);
+    }
+    fs::write(path, formatted)?;
+    Ok(true)
+}
+
+/// rewrites the level with every field written out and the deprecated ones migrated
+pub fn upgrade(path: &Path) -> Result<Upgrade, ToolError> {
+    let (upgraded, upgrade) = reserialize(&fs::read_to_string(path)?)?;
+    fs::write(path, upgraded)?;
+    Ok(upgrade)
+}
+
+/// the level written the way `Level::save_to_file` writes it, along with how its fields changed
+fn reserialize(text: &str) -> Result<(String, Upgrade), ToolError> {
+    let level: Level = ron::from_str(text)?;
+    let written = ron::to_string(&level)?;
+
+    let mut before = BTreeSet::new();
+    field_paths(&ron::from_str(text)?, "", &mut before);
+    let mut after = BTreeSet::new();
+    field_paths(&ron::from_str(&written)?, "", &mut after);
+    // only the outermost of the fields which were added or removed along with their parents
+    let outermost = |paths: &BTreeSet<String>, other: &BTreeSet<String>| {
+        paths
+            .iter()
+            .filter(|path| !other.contains(*path))
+            .filter(|path| parent(path).is_none_or(|parent| other.contains(parent)))
+            .cloned()
+            .collect()
+    };
+
+    let upgrade = Upgrade {
+        filled_in: outermost(&after, &before),
+        migrated: outermost(&before, &after),
+    };
+    Ok((written, upgrade))
+}
+
+/// collects the paths of all the named fields in the value, e.g. `lasers[0].range`
+fn field_paths(value: &Value, prefix: &str, paths: &mut BTreeSet<String>) {
+    match value {
+        Value::Map(map) => {
+            for (key, value) in map.iter() {
+                let Value::String(key) = key else {
+                    continue;
+                };
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                field_paths(value, &path, paths);
+                paths.insert(path);
+            }
+        }
+        Value::Seq(values) => {
+            for (i, value) in values.iter().enumerate() {
+                field_paths(value, &format!("{prefix}[{i}]"), paths);
+            }
+        }
+        Value::Option(Some(value)) => field_paths(value, prefix, paths),
+        _ => {}
+    }
+}
+
+/// the path of the field containing the one at `path`, `None` for a top level field
+fn parent(path: &str) -> Option<&str> {
+    let (mut parent, _) = path.rsplit_once('.')?;
+    // the elements of a list are part of the field holding it
+    while parent.ends_with(']') {
+        parent = &parent[..parent.rfind('[')?];
+    }
+    Some(parent)
+}
+
+/// The outcome of validating a single level
+pub struct Validation {
+    /// why the level couldn't be loaded, `None` if it can be played
+    pub error: Option<LoadError>,
+    /// how many likely mistakes `Level::warnings` found
+    pub warnings: usize,
+    /// how many reasons `Level::analyze` found for which the level may not be finishable
+    pub problems: usize,
+}
+
+impl Validation {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// loads and checks the level like the game does
+pub fn validate(path: &Path) -> Validation {
+    match Level::load_from_file(path) {
+        Ok(level) => Validation {
+            error: None,
+            warnings: level.warnings().len(),
+            problems: level.analyze().problems.len(),
+        },
+        Err(err) => Validation {
+            error: Some(err),
+            warnings: 0,
+            problems: 0,
+        },
+    }
+}
+
+/// a table with a row for each validated level
+pub fn validation_table(validations: &[(LevelInfo, Validation)]) -> String {
+    let width = validations
+        .iter()
+        .map(|(info, _)| info.name.len())
+        .chain(["level".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!("{:width$}  result  warnings  problems\n", "level");
+    for (info, validation) in validations {
+        let result = if validation.is_ok() { "ok" } else { "FAILED" };
+        let _ = write!(
+            table,
+            "{:width$}  {result:6}  {:8}  {:8}",
+            info.name, validation.warnings, validation.problems
+        );
+        if let Some(err) = &validation.error {
+            let _ = write!(table, "  {err}");
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// draws the static entities, the doors, the flags and where the ball starts, fitted into a
+/// square image `size` pixels wide
+#[cfg(feature = "soft-render")]
+pub fn render_thumbnail(level: &Level, size: u32) -> Image {
+    render_level(level, &View::fitting(level.overview_bounds(), size, size))
+}
+
+/// renders the level's thumbnail to `<name>.png` in `directory`, returning where it was saved
+#[cfg(feature = "soft-render")]
+pub fn save_thumbnail(path: &Path, directory: &Path) -> Result<PathBuf, ToolError> {
+    let level = Level::load_from_file(path)?;
+    let image = render_thumbnail(&level, THUMBNAIL_SIZE);
+
+    fs::create_dir_all(directory)?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let thumbnail = directory.join(format!("{name}.png"));
+    image.save_png(&thumbnail)?;
+    Ok(thumbnail)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::Point;
+    use std::path::PathBuf;
+
+    const MINIMAL: &str = "(
+        initial_ball_position: (0.0, 0.5),
+        circles: [],
+        polygons: [(shape: [(-1.0, -0.1), (1.0, -0.1), (1.0, 0.0), (-1.0, 0.0)], is_static: true, is_bindable: false)],
+        flags_positions: [(0.8, 0.0)],
+    )";
+    /// a laser written the way the oldest levels do
+    const LEGACY: &str = "(
+        initial_ball_position: (0.0, 0.5),
+        lasers: [(point: (0.5, 0.5), direction: (-0.1, 0.1), inital_direction: (-0.1, 0.1), change: 0.01, range: 0.8, is_out: false)],
+        circles: [],
+        polygons: [],
+        flags_positions: [],
+    )";
+    const DEGENERATE: &str = "(
+        initial_ball_position: (0.0, 0.5),
+        circles: [],
+        polygons: [(shape: [(0.0, 0.0), (1.0, 0.0)], is_static: true, is_bindable: false)],
+        flags_positions: [],
+    )";
+
+    /// an empty directory with the levels written to it
+    fn level_directory(name: &str, levels: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("whisky-level-tool-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (name, text) in levels {
+            fs::write(directory.join(format!("{name}.ron")), text).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn test_format_is_idempotent_and_leaves_deprecated_levels() {
+        let directory = level_directory("fmt", &[("legacy", LEGACY), ("minimal", MINIMAL)]);
+
+        let results = for_each_level(&directory, format);
+        assert_eq!(results[0].0.name, "legacy");
+        assert!(matches!(&results[0].1, Err(ToolError::Deprecated(fields)) if fields.len() == 4));
+        assert_eq!(
+            fs::read_to_string(directory.join("legacy.ron")).unwrap(),
+            LEGACY
+        );
+        assert!(matches!(results[1].1, Ok(true)));
+
+        let formatted = fs::read_to_string(directory.join("minimal.ron")).unwrap();
+        let level = Level::parse(&formatted).unwrap();
+        assert_eq!(level.polygons.len(), 1);
+        assert_eq!(level.flags_positions, vec![Point(0.8, 0.0)]);
+        assert!(matches!(format(&directory.join("minimal.ron")), Ok(false)));
+        assert_eq!(
+            fs::read_to_string(directory.join("minimal.ron")).unwrap(),
+            formatted
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_migrates_legacy_laser() {
+        let directory = level_directory("upgrade", &[("legacy", LEGACY)]);
+        let path = directory.join("legacy.ron");
+        let before = Level::load_from_file(&path).unwrap();
+
+        let upgrade = upgrade(&path).unwrap();
+        assert_eq!(
+            upgrade.migrated,
+            [
+                "lasers[0].change",
+                "lasers[0].direction",
+                "lasers[0].inital_direction",
+                "lasers[0].is_out",
+            ]
+        );
+        assert!(upgrade.filled_in.contains(&"ball_radius".to_string()));
+        assert!(upgrade.filled_in.contains(&"physics".to_string()));
+        assert!(upgrade
+            .filled_in
+            .contains(&"lasers[0].angular_speed".to_string()));
+        // only the outermost of the added fields is listed
+        assert!(!upgrade
+            .filled_in
+            .contains(&"physics.gravity_coefficient".to_string()));
+
+        let after = Level::load_from_file(&path).unwrap();
+        assert_eq!(
+            after.lasers[0].angular_speed,
+            before.lasers[0].angular_speed
+        );
+        assert_eq!(
+            after.lasers[0].current_angle,
+            before.lasers[0].current_angle
+        );
+        // nothing is left to upgrade, so it can be formatted
+        assert_eq!(
+            reserialize(&fs::read_to_string(&path).unwrap()).unwrap().1,
+            Upgrade::default()
+        );
+        assert!(matches!(format(&path), Ok(false)));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_validate_all_reports_failures() {
+        let directory = level_directory(
+            "validate",
+            &[("degenerate", DEGENERATE), ("minimal", MINIMAL)],
+        );
+
+        let validations = for_each_level(&directory, validate);
+        assert!(matches!(
+            validations[0].1.error,
+            Some(LoadError::Invalid(levels::InvalidLevel::DegeneratePolygon(
+                0
+            )))
+        ));
+        assert!(validations[1].1.is_ok());
+
+        let table = validation_table(&validations);
+        let rows: Vec<_> = table.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].starts_with("degenerate  FAILED"));
+        assert!(rows[1].ends_with("polygon 0 has fewer than 3 vertices"));
+        assert!(rows[2].starts_with("minimal     ok"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "soft-render")]
+    fn test_thumbnail_shows_static_geometry() {
+        let mut level = Level::parse(MINIMAL).unwrap();
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-0.5, 0.2),
+                Point(-0.3, 0.2),
+                Point(-0.3, 0.4),
+                Point(-0.5, 0.4),
+            ],
+            false,
+        ));
+        let size = 100;
+        let image = render_thumbnail(&level, size);
+        assert_eq!((image.width, image.height), (size, size));
+
+        let view = View::fitting(level.overview_bounds(), size, size);
+        let pixel_at = |point: Point| {
+            let Point(column, row) = view.position_of(point);
+            let i = 4 * (row as usize * size as usize + column as usize);
+            &image.pixels[i..i + 4]
+        };
+        let background = &image.pixels[..4];
+        let floor = pixel_at(Point(0.0, -0.05));
+        let ball = pixel_at(Point(0.0, 0.5));
+        let flag = pixel_at(Point(0.85, 0.05));
+        assert_ne!(floor, background);
+        assert_ne!(ball, background);
+        assert_ne!(ball, floor);
+        assert_ne!(flag, background);
+        assert_ne!(flag, floor);
+        // dynamic entities move around, so they aren't drawn
+        assert_eq!(pixel_at(Point(-0.4, 0.3)), background);
+
+        let directory = level_directory("thumbnails", &[("minimal", MINIMAL)]);
+        let thumbnail = save_thumbnail(
+            &directory.join("minimal.ron"),
+            &directory.join(THUMBNAIL_DIRECTORY),
+        )
+        .unwrap();
+        let decoder = png::Decoder::new(fs::File::open(&thumbnail).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(
+            (reader.info().width, reader.info().height),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,12 +1,45 @@
-use std::{fs, io, path::Path};
+//! levels as they are stored in files and handed over to the physics engine
+
+#![deny(missing_docs)]
+
+use std::{
+    f64::consts,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
-use crate::{geometry::{Circle, Laser, Point}};
+use crate::{audio::Material, geometry::{self, windows::Looped, Aabb, Affine2, Circle, Laser, Point}};
+
+/// how far from its center the view of a camera which isn't zoomed reaches
+const VIEW_HALF_SIZE: f64 = 1.0;
+/// `Level::analyze` looks for a way to the flags in at most this many cells along each axis
+const MAX_ANALYSIS_CELLS: usize = 256;
+/// the width and height of a flag, which starts at its position
+pub const FLAG_SIZE: f64 = 0.1;
 
 fn initialize_false() -> bool {
     false
 }
 
+fn initialize_none<T>() -> Option<T> {
+    None
+}
+
+/// false for NaNs as well
+fn is_positive(value: f64) -> bool {
+    value > 0.0
+}
+
+fn initialize_ball_radius() -> f64 {
+    0.07
+}
+
+fn initialize_despawn_margin() -> f64 {
+    1.0
+}
+
 fn initialize_empty_laser() -> Vec<Laser> {
     vec![]
 }
@@ -15,15 +48,248 @@ fn initialize_empty_door() -> Vec<(Vec<Point>, String)> {
     vec![]
 }
 
+fn initialize_empty_notes() -> Vec<(Point, String)> {
+    vec![]
+}
+
+fn initialize_physics_overrides() -> PhysicsOverrides {
+    PhysicsOverrides::default()
+}
+
+fn initialize_ground_only() -> JumpRefillMode {
+    JumpRefillMode::GroundOnly
+}
+
+fn initialize_empty_spawners() -> Vec<Spawner> {
+    vec![]
+}
+
+fn initialize_empty_bindings() -> Vec<LevelBinding> {
+    vec![]
+}
+
+fn initialize_empty_pins() -> Vec<LevelPin> {
+    vec![]
+}
+
+/// Physics parameters a level sets for itself, the ones left out keep the engine's values
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct PhysicsOverrides {
+    /// replaces `PhysicsParams::gravity_coefficient`, negative to pull downwards
+    #[serde(default = "initialize_none")]
+    pub gravity_coefficient: Option<f64>,
+    /// replaces `PhysicsParams::movement_coefficient`
+    #[serde(default = "initialize_none")]
+    pub movement_coefficient: Option<f64>,
+    /// replaces `PhysicsParams::strong_collision_speed`, e.g. so fragile entities still break
+    /// in a level with weak gravity
+    #[serde(default = "initialize_none")]
+    pub strong_collision_speed: Option<f64>,
+    /// replaces the preset the game was started with, see `PhysicsParams::preset`. The
+    /// parameters above still replace the ones of the preset
+    #[serde(default = "initialize_none")]
+    pub preset: Option<String>,
+}
+
+/// When the main ball gets back the jumps it used
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JumpRefillMode {
+    /// both jumps come back once the ball lands on anything, so it can jump twice in the air
+    GroundOnly,
+    /// both jumps come back in every step the ball touches anything, not only when it lands,
+    /// e.g. while it rolls along the floor or a wall. It can still jump only twice in the air
+    Always,
+}
+
+/// The shape of the entities dropped by a [`Spawner`]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum SpawnShape {
+    /// a circle with the given radius
+    Circle {
+        /// the radius of the circle
+        radius: f64,
+    },
+    /// an upright rectangle
+    Box {
+        /// the width of the rectangle
+        w: f64,
+        /// the height of the rectangle
+        h: f64,
+    },
+}
+
+/// Keeps dropping erasable dynamic entities at random points of a region, e.g. debris crumbling
+/// from a ceiling
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Spawner {
+    /// the convex polygon the entities appear in, in counterclockwise order
+    pub region: Vec<Point>,
+    /// what the entities look like
+    pub shape: SpawnShape,
+    /// how many seconds of simulated time pass between two entities
+    pub interval_seconds: f64,
+    /// once the spawner has dropped more entities than this the oldest one is removed
+    pub max_alive: usize,
+    /// the velocity the entities start with, in level units per second
+    pub initial_velocity: Point,
+    /// the entities despawn once they have existed for this long
+    #[serde(default = "initialize_none")]
+    pub lifetime: Option<Duration>,
+}
+
+/// What's drawn behind a level
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum BackgroundSpec {
+    /// the PNG images in a directory shown one after another in the order of their names, all
+    /// of them have to be the same size
+    Animated {
+        /// the directory the images are in
+        dir: String,
+        /// how many of the images are shown per second
+        fps: f32,
+    },
+    /// a single PNG image
+    Static {
+        /// where the image is
+        path: String,
+    },
+    /// a flat color, with the red, green and blue components between 0 and 1
+    Color([f32; 3]),
+}
+
+/// Refers to an entity of the level by where it is in its list, e.g. `Polygon(0)` is the first
+/// of `Level::polygons`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EntityRef {
+    /// an index into `Level::circles`
+    Circle(usize),
+    /// an index into `Level::polygons`
+    Polygon(usize),
+}
+
+/// How the entities joined by a binding move relative to each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BindingKind {
+    /// the entities can turn around the point they are joined at
+    Hinge,
+    /// the entities move as one
+    Rigid,
+    /// the entities move as one, holding their relative angle exactly
+    Weld,
+}
+
+/// Two entities of the level joined from the start, as if the player had bound them
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LevelBinding {
+    /// how the entities are joined
+    pub kind: BindingKind,
+    /// the entity the binding is pinned to
+    pub entity_a: EntityRef,
+    /// the entity bound to the first one
+    pub entity_b: EntityRef,
+    /// where the entities are joined, which has to be inside both of them
+    pub at: Point,
+}
+
+/// A binding pinned to an entity of the level, which binds it to the first shape the player
+/// draws over it
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LevelPin {
+    /// how the entities are joined once the pin is bound
+    pub kind: BindingKind,
+    /// where the pin is, it's pinned to the first entity containing it, with the polygons
+    /// coming before the circles
+    pub at: Point,
+}
+
+/// A shape placed in the level along with how it behaves
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Entity<S> {
+    /// the outline in level coordinates
     pub shape: S,
+    /// the entity stays in place no matter what hits it
     pub is_static: bool,
+    /// the player can pin hinges and rigid bindings to the entity
     pub is_bindable: bool,
+    /// the player can erase the entity like the shapes they draw
+    #[serde(default = "initialize_false")]
+    pub is_erasable: bool,
+    /// touching the entity restarts the level
     #[serde(default = "initialize_false")]
     pub is_deadly: bool,
+    /// the entity breaks when hit hard enough
     #[serde(default = "initialize_false")]
     pub is_fragile: bool,
+    /// how hard a fragile entity has to be hit to break, as a change of velocity in units per
+    /// second like `PhysicsParams::strong_collision_speed`, which is used if it isn't set
+    #[serde(default = "initialize_none")]
+    pub break_speed: Option<f64>,
+    /// the entity despawns once it has existed for this long
+    #[serde(default = "initialize_none")]
+    pub lifetime: Option<Duration>,
+    /// lasers slowly burn holes through the entity
+    #[serde(default = "initialize_false")]
+    pub is_carvable: bool,
+    /// one of "wood", "metal", "stone" or "rubber", decides the sound of collisions
+    #[serde(default = "initialize_none")]
+    pub material: Option<String>,
+    /// the friction coefficient of the entity's surface, shapes on a slope less steep than its
+    /// arctangent stay put. The engine picks one if it isn't set
+    #[serde(default = "initialize_none")]
+    pub friction: Option<f64>,
+    /// collisions never make the entity spin, e.g. for signs which should stay upright
+    #[serde(default = "initialize_false")]
+    pub lock_rotation: bool,
+    /// a name for the entity, used in diagnostics instead of where it is in the level file
+    #[serde(default = "initialize_none")]
+    pub tag: Option<String>,
+    /// the path of a PNG image the entity is drawn with instead of a flat color, stretched over
+    /// its bounding box. All the images of a level have to be the same size
+    #[serde(default = "initialize_none")]
+    pub texture: Option<String>,
+    /// the color the main ball picks up when it touches the entity, after which it leaves marks
+    /// of it on whatever it touches for a few seconds
+    #[serde(default = "initialize_none")]
+    pub paint: Option<[f32; 3]>,
+}
+
+impl<S> Entity<S> {
+    /// a bindable entity which can't be erased and has none of the optional behaviours
+    pub fn new(shape: S, is_static: bool) -> Self {
+        Entity {
+            shape,
+            is_static,
+            is_bindable: true,
+            is_erasable: false,
+            is_deadly: false,
+            is_fragile: false,
+            break_speed: None,
+            lifetime: None,
+            is_carvable: false,
+            material: None,
+            friction: None,
+            lock_rotation: false,
+            tag: None,
+            texture: None,
+            paint: None,
+        }
+    }
+
+    /// unknown and missing materials fall back to the default one
+    pub fn material(&self) -> Material {
+        self.material
+            .as_deref()
+            .and_then(Material::from_name)
+            .unwrap_or_default()
+    }
+}
+
+impl Entity<Vec<Point>> {
+    /// true if the polygon has fewer than 3 vertices once the repeated ones are merged, so the
+    /// engine leaves it out
+    pub fn is_collapsed(&self) -> bool {
+        geometry::merge_close_vertices(self.shape.clone()).len() < 3
+    }
 }
 
 /// Represents a single level
@@ -32,29 +298,1203 @@ pub struct Entity<S> {
 /// and passed directly to the physics engine
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Level {
+    /// where the ball starts and is returned to when the level restarts
     pub initial_ball_position: Point,
+    /// the radius of the ball controlled by the player
+    #[serde(default = "initialize_ball_radius")]
+    pub ball_radius: f64,
+    /// the entities shaped like circles, the ball is not one of them
     pub circles: Vec<Entity<Circle>>,
+    /// the vertices of every polygon, in counterclockwise order
     pub polygons: Vec<Entity<Vec<Point>>>,
+    /// lasers restart the level when they hit the ball
     #[serde(default = "initialize_empty_laser")]
     pub lasers: Vec<Laser>,
+    /// touching the polygon loads the level from the file next to it
     #[serde(default = "initialize_empty_door")]
     pub doors: Vec<(Vec<Point>, String)>,
+    /// the bottom left corners of the flags
     pub flags_positions: Vec<Point>,
+    /// the area the level takes up, by default the smallest box containing all of it
+    #[serde(default = "initialize_none")]
+    pub bounds: Option<Aabb>,
+    /// the area a camera following the ball keeps its view inside, by default the bounds
+    #[serde(default = "initialize_none")]
+    pub camera_bounds: Option<Aabb>,
+    /// dynamic entities are despawned once they are this far outside of the bounds
+    #[serde(default = "initialize_despawn_margin")]
+    pub despawn_margin: f64,
+    /// annotations left by level designers, which are only shown in editing mode, the physics
+    /// never looks at them
+    #[serde(default = "initialize_empty_notes")]
+    pub notes: Vec<(Point, String)>,
+    /// the ball is held in place until the player drags and releases it slingshot-style
+    #[serde(default = "initialize_false")]
+    pub launch_mode: bool,
+    /// laser beams push the first dynamic entity they hit, light ones noticeably
+    #[serde(default = "initialize_false")]
+    pub lasers_push: bool,
+    /// laser beams stop at the ball instead of restarting the level
+    #[serde(default = "initialize_false")]
+    pub lasers_harmless: bool,
+    /// the ball wedged where it can't get out is nudged free, rather than the player being told
+    /// how to restart, see [`crate::physics::Engine::is_stuck`]
+    #[serde(default = "initialize_false")]
+    pub auto_unstick: bool,
+    /// whether the ball has to touch something to jump again
+    #[serde(default = "initialize_ground_only")]
+    pub jump_refill: JumpRefillMode,
+    /// tunes the physics for this level only
+    #[serde(default = "initialize_physics_overrides")]
+    pub physics: PhysicsOverrides,
+    /// places which keep dropping new entities while the level is played
+    #[serde(default = "initialize_empty_spawners")]
+    pub spawners: Vec<Spawner>,
+    /// entities joined when the level starts
+    #[serde(default = "initialize_empty_bindings")]
+    pub bindings: Vec<LevelBinding>,
+    /// bindings waiting for the player to draw the shape they bind to
+    #[serde(default = "initialize_empty_pins")]
+    pub pins: Vec<LevelPin>,
+    /// what's drawn behind the level, the game's animated background if it isn't set or can't
+    /// be loaded
+    #[serde(default = "initialize_none")]
+    pub background: Option<BackgroundSpec>,
 }
 
+/// A level file found by `list_levels`
+#[derive(Debug, PartialEq)]
+pub struct LevelInfo {
+    /// the file name without the `.ron` extension
+    pub name: String,
+    /// where the level can be loaded from
+    pub path: PathBuf,
+}
+
+/// lists the `.ron` files in `directory` sorted by name, without loading them.
+/// A directory which can't be read has no levels
+pub fn list_levels(directory: &Path) -> Vec<LevelInfo> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return vec![];
+    };
+
+    let mut levels: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            Some(LevelInfo {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                path,
+            })
+        })
+        .collect();
+    levels.sort_by(|a, b| a.name.cmp(&b.name));
+    levels
+}
+
+/// Reasons for which a level file couldn't be loaded
 #[derive(Debug, thiserror::Error)]
 pub enum LoadError {
+    /// the file couldn't be read
     #[error("the specified file is invalid: {0}")]
     Io(#[from] io::Error),
+    /// the file isn't a level written in RON
     #[error("there was an error parsing the level: {0}")]
     Parse(#[from] ron::error::SpannedError),
+    /// the level parsed fine, but can't be played
+    #[error("the level can't be played: {0}")]
+    Invalid(#[from] InvalidLevel),
+}
+
+/// Reasons for which a level which parsed fine still can't be played
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum InvalidLevel {
+    /// the polygon at the given index can't enclose any area
+    #[error("polygon {0} has fewer than 3 vertices")]
+    DegeneratePolygon(usize),
+    /// the circle at the given index has a zero, negative or NaN radius
+    #[error("circle {0} doesn't have a positive radius")]
+    DegenerateCircle(usize),
+    /// the ball has a zero, negative or NaN radius
+    #[error("the ball doesn't have a positive radius")]
+    DegenerateBall,
+    /// a position is infinite or NaN
+    #[error("the level contains a coordinate which is not a finite number")]
+    NotFinite,
+    /// the spawner at the given index has a region with fewer than 3 vertices, or a size or an
+    /// interval which isn't positive
+    #[error("spawner {0} has no area to spawn in, a shape without any size or no interval")]
+    DegenerateSpawner(usize),
+    /// the laser at the given index has a zero direction or initial direction, or a negative
+    /// range
+    #[error("laser {0} doesn't point anywhere or has a negative range")]
+    DegenerateLaser(usize),
+    /// an entity has a negative or NaN friction coefficient
+    #[error("the level contains a friction coefficient which is negative or not a number")]
+    NegativeFriction,
+    /// the binding at the given index refers to a missing entity, joins an entity to itself or
+    /// its point isn't inside both entities
+    #[error("binding {0} doesn't join two entities of the level at a point inside both")]
+    LooseBinding(usize),
+    /// the pin at the given index isn't inside any entity
+    #[error("pin {0} isn't inside any entity")]
+    LoosePin(usize),
+    /// the ball, polygons and circles together, given, are more than the engine keeps, see
+    /// [`crate::physics::MAX_ENTITIES`]
+    #[error(
+        "the level has {0} entities, more than the {} the engine keeps",
+        crate::physics::MAX_ENTITIES
+    )]
+    TooManyEntities(usize),
+}
+
+/// Problems which don't stop a level from being played, but are likely mistakes
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum LevelWarning {
+    /// the note at the given index is empty or only whitespace
+    #[error("note {0} has no text")]
+    EmptyNote(usize),
+    /// an entity is made of a material the game doesn't know
+    #[error("unknown material \"{0}\", the default one is used instead")]
+    UnknownMaterial(String),
+    /// the polygon at the given index is left out, as it has fewer than 3 vertices once the
+    /// repeated ones are merged
+    #[error("polygon {0} has fewer than 3 distinct vertices and is left out")]
+    CollapsedPolygon(usize),
+    /// the level picks a physics preset the game doesn't know
+    #[error("unknown physics preset \"{0}\", the one the game was started with is used instead")]
+    UnknownPreset(String),
+}
+
+/// A likely reason for which a level can't be finished, which is only a guess
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum LevelProblem {
+    /// the ball starts overlapping the polygon at the given index
+    #[error("the ball starts inside polygon {0}")]
+    BallInPolygon(usize),
+    /// the ball starts overlapping the circle at the given index
+    #[error("the ball starts inside circle {0}")]
+    BallInCircle(usize),
+    /// there are neither flags nor doors, so the level can't be won or left
+    #[error("the level has no flags and no doors")]
+    NoGoal,
+    /// static entities wall the flag at the given index off from the ball
+    #[error("flag {0} can't be reached from where the ball starts")]
+    UnreachableFlag(usize),
+    /// the laser at the given index shines at the ball as soon as the level starts
+    #[error("laser {0} hits the ball where it starts")]
+    LaserHitsBall(usize),
+}
+
+/// What [`Level::analyze`] found wrong with a level
+#[derive(Debug, Default, PartialEq)]
+pub struct LevelReport {
+    /// the problems in the order they were looked for
+    pub problems: Vec<LevelProblem>,
+}
+
+impl LevelReport {
+    /// true if nothing looks wrong with the level
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
 }
 
 impl Level {
+    /// an empty level with just the ball, everything optional set as if it was missing from a
+    /// level file
+    ///
+    /// ```
+    /// use zpr_game_engine::{
+    ///     geometry::{Circle, Point},
+    ///     levels::{Entity, Level},
+    /// };
+    ///
+    /// let mut level = Level::new(Point(0.0, 1.0));
+    /// level.circles.push(Entity::new(
+    ///     Circle {
+    ///         center: Point(0.5, 0.2),
+    ///         radius: 0.1,
+    ///     },
+    ///     false,
+    /// ));
+    /// level.flags_positions.push(Point(1.5, 0.0));
+    ///
+    /// assert!(level.validate().is_ok());
+    /// ```
+    pub fn new(initial_ball_position: Point) -> Self {
+        Level {
+            initial_ball_position,
+            ball_radius: initialize_ball_radius(),
+            circles: vec![],
+            polygons: vec![],
+            lasers: initialize_empty_laser(),
+            doors: initialize_empty_door(),
+            flags_positions: vec![],
+            bounds: initialize_none(),
+            camera_bounds: initialize_none(),
+            despawn_margin: initialize_despawn_margin(),
+            notes: initialize_empty_notes(),
+            launch_mode: initialize_false(),
+            lasers_push: initialize_false(),
+            lasers_harmless: initialize_false(),
+            auto_unstick: initialize_false(),
+            jump_refill: initialize_ground_only(),
+            physics: initialize_physics_overrides(),
+            spawners: initialize_empty_spawners(),
+            bindings: initialize_empty_bindings(),
+            pins: initialize_empty_pins(),
+            background: initialize_none(),
+        }
+    }
+
+    /// reads a level file, see [`Level::parse`]
     pub fn load_from_file(path: impl AsRef<Path>) -> Result<Level, LoadError> {
-        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// reads a level written in RON and checks it can be played. The directions of lasers are
+    /// scaled to a length of 1
+    pub fn parse(text: &str) -> Result<Level, LoadError> {
+        let mut level: Level = ron::from_str(text)?;
+        level.validate()?;
+        level.lasers.iter_mut().for_each(Laser::normalize);
+        Ok(level)
     }
+
+    /// writes the level in RON, panicking if that fails
     pub fn save_to_file(&self, path: impl AsRef<Path>) {
         fs::write(path, ron::to_string(self).unwrap()).unwrap();
     }
+
+    /// the explicitly set bounds, or the box containing the ball, all the entities, lasers, doors
+    /// and flags
+    pub fn bounds(&self) -> Aabb {
+        if let Some(bounds) = self.bounds {
+            return bounds;
+        }
+
+        let circle_extremes = |Circle { center, radius }: Circle| {
+            [
+                center - Point(radius, radius),
+                center + Point(radius, radius),
+            ]
+        };
+        let ball = circle_extremes(Circle {
+            center: self.initial_ball_position,
+            radius: self.ball_radius,
+        });
+
+        Aabb::around(
+            ball.into_iter()
+                .chain(
+                    self.circles
+                        .iter()
+                        .flat_map(|circle| circle_extremes(circle.shape)),
+                )
+                .chain(
+                    self.polygons
+                        .iter()
+                        .flat_map(|polygon| polygon.shape.clone()),
+                )
+                .chain(self.doors.iter().flat_map(|(door, _)| door.clone()))
+                .chain(self.flags_positions.iter().copied())
+                .chain(self.lasers.iter().map(|laser| laser.point))
+                .chain(
+                    self.spawners
+                        .iter()
+                        .flat_map(|spawner| spawner.region.clone()),
+                ),
+        )
+        .unwrap()
+    }
+
+    /// the box a camera should show for the player to see the whole level at once, containing
+    /// the static entities, the doors, the flags and where the ball starts
+    pub fn overview_bounds(&self) -> Aabb {
+        let static_circles = self
+            .circles
+            .iter()
+            .filter(|circle| circle.is_static)
+            .flat_map(|Entity { shape, .. }| {
+                let corner = Point(shape.radius, shape.radius);
+                [shape.center - corner, shape.center + corner]
+            });
+        let static_polygons = self
+            .polygons
+            .iter()
+            .filter(|polygon| polygon.is_static)
+            .flat_map(|polygon| polygon.shape.iter().copied());
+
+        Aabb::around(
+            [self.initial_ball_position]
+                .into_iter()
+                .chain(static_circles)
+                .chain(static_polygons)
+                .chain(self.doors.iter().flat_map(|(door, _)| door.iter().copied()))
+                .chain(
+                    self.flags_positions
+                        .iter()
+                        .flat_map(|&flag| [flag, flag + Point(FLAG_SIZE, FLAG_SIZE)]),
+                ),
+        )
+        .unwrap()
+    }
+
+    /// where a camera following `target` should be centered, so that its view stays inside the
+    /// camera bounds. At a zoom of 1 the view reaches 1 away from its center, like the window
+    /// does, and zooming out makes it proportionally larger
+    pub fn camera_center(&self, target: Point, zoom: f64) -> Point {
+        let half_size = VIEW_HALF_SIZE / zoom;
+        self.camera_bounds
+            .unwrap_or_else(|| self.bounds())
+            .clamp_view(target, Point(half_size, half_size))
+    }
+
+    /// checks the parts of the level which the engine assumes to be sane
+    pub fn validate(&self) -> Result<(), InvalidLevel> {
+        if !is_positive(self.ball_radius) {
+            return Err(InvalidLevel::DegenerateBall);
+        }
+        if let Some(i) = self
+            .polygons
+            .iter()
+            .position(|polygon| polygon.shape.len() < 3)
+        {
+            return Err(InvalidLevel::DegeneratePolygon(i));
+        }
+        if let Some(i) = self
+            .circles
+            .iter()
+            .position(|circle| !is_positive(circle.shape.radius))
+        {
+            return Err(InvalidLevel::DegenerateCircle(i));
+        }
+        if let Some(i) = self.spawners.iter().position(|spawner| {
+            let has_size = match spawner.shape {
+                SpawnShape::Circle { radius } => is_positive(radius),
+                SpawnShape::Box { w, h } => is_positive(w) && is_positive(h),
+            };
+            spawner.region.len() < 3 || !has_size || !is_positive(spawner.interval_seconds)
+        }) {
+            return Err(InvalidLevel::DegenerateSpawner(i));
+        }
+        if let Some(i) = self.lasers.iter().position(|laser| {
+            !is_positive(laser.initial_direction.norm())
+                || !laser.current_angle.is_finite()
+                || laser.range.is_nan()
+                || laser.range < 0.0
+        }) {
+            return Err(InvalidLevel::DegenerateLaser(i));
+        }
+        let frictions = self
+            .polygons
+            .iter()
+            .map(|polygon| polygon.friction)
+            .chain(self.circles.iter().map(|circle| circle.friction));
+        if frictions
+            .flatten()
+            .any(|friction| friction.is_nan() || friction < 0.0)
+        {
+            return Err(InvalidLevel::NegativeFriction);
+        }
+        if let Some(i) = self.bindings.iter().position(|binding| {
+            binding.entity_a == binding.entity_b
+                || self.includes(binding.entity_a, binding.at) != Some(true)
+                || self.includes(binding.entity_b, binding.at) != Some(true)
+        }) {
+            return Err(InvalidLevel::LooseBinding(i));
+        }
+        if let Some(i) = self
+            .pins
+            .iter()
+            .position(|pin| self.entity_at(pin.at).is_none())
+        {
+            return Err(InvalidLevel::LoosePin(i));
+        }
+        let entities = 1 + self.polygons.len() + self.circles.len();
+        if entities > crate::physics::MAX_ENTITIES {
+            return Err(InvalidLevel::TooManyEntities(entities));
+        }
+
+        let is_finite = |Point(x, y): &Point| x.is_finite() && y.is_finite();
+        let all_finite = is_finite(&self.initial_ball_position)
+            && self
+                .circles
+                .iter()
+                .all(|circle| is_finite(&circle.shape.center))
+            && self
+                .polygons
+                .iter()
+                .flat_map(|polygon| &polygon.shape)
+                .chain(self.doors.iter().flat_map(|(door, _)| door))
+                .chain(&self.flags_positions)
+                .chain(self.lasers.iter().map(|laser| &laser.point))
+                .chain(self.lasers.iter().map(|laser| &laser.initial_direction))
+                .chain(self.spawners.iter().flat_map(|spawner| &spawner.region))
+                .chain(
+                    self.spawners
+                        .iter()
+                        .map(|spawner| &spawner.initial_velocity),
+                )
+                .chain(self.bindings.iter().map(|binding| &binding.at))
+                .chain(self.pins.iter().map(|pin| &pin.at))
+                .all(is_finite);
+        if !all_finite {
+            return Err(InvalidLevel::NotFinite);
+        }
+
+        Ok(())
+    }
+
+    /// whether the point is inside the entity, `None` if the level has no such entity
+    pub fn includes(&self, entity: EntityRef, point: Point) -> Option<bool> {
+        match entity {
+            EntityRef::Circle(i) => {
+                let Circle { center, radius } = self.circles.get(i)?.shape;
+                Some(center.to(point).norm() < radius)
+            }
+            EntityRef::Polygon(i) => Some(geometry::is_inside(point, &self.polygons.get(i)?.shape)),
+        }
+    }
+
+    /// the first entity containing the point, with the polygons coming before the circles like
+    /// in the engine
+    pub fn entity_at(&self, point: Point) -> Option<EntityRef> {
+        (0..self.polygons.len())
+            .map(EntityRef::Polygon)
+            .chain((0..self.circles.len()).map(EntityRef::Circle))
+            .find(|&entity| self.includes(entity, point) == Some(true))
+    }
+
+    /// the likely mistakes in the level, which are still fine to play
+    pub fn warnings(&self) -> Vec<LevelWarning> {
+        let unknown_materials = self
+            .polygons
+            .iter()
+            .filter_map(|polygon| polygon.material.as_ref())
+            .chain(
+                self.circles
+                    .iter()
+                    .filter_map(|circle| circle.material.as_ref()),
+            )
+            .filter(|name| Material::from_name(name).is_none())
+            .map(|name| LevelWarning::UnknownMaterial(name.clone()));
+
+        // the ones with fewer vertices to begin with don't pass validation
+        let collapsed_polygons = self
+            .polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| polygon.shape.len() >= 3 && polygon.is_collapsed())
+            .map(|(i, _)| LevelWarning::CollapsedPolygon(i));
+
+        let unknown_preset = self
+            .physics
+            .preset
+            .clone()
+            .filter(|name| crate::physics::PhysicsParams::preset(name).is_none())
+            .map(LevelWarning::UnknownPreset);
+
+        self.notes
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, text))| text.trim().is_empty())
+            .map(|(i, _)| LevelWarning::EmptyNote(i))
+            .chain(unknown_materials)
+            .chain(collapsed_polygons)
+            .chain(unknown_preset)
+            .collect()
+    }
+
+    /// looks for what likely keeps a valid level from being finished. Unlike [`Level::validate`]
+    /// and [`Level::warnings`] it only guesses, e.g. a gap the ball would squeeze through in the
+    /// game may be reported as closed
+    pub fn analyze(&self) -> LevelReport {
+        let ball = Circle {
+            center: self.initial_ball_position,
+            radius: self.ball_radius,
+        };
+        let mut problems: Vec<_> = self
+            .polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| overlaps(&polygon.shape, ball))
+            .map(|(i, _)| LevelProblem::BallInPolygon(i))
+            .chain(
+                self.circles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, circle)| {
+                        let Circle { center, radius } = circle.shape;
+                        center.to(ball.center).norm() < radius + ball.radius
+                    })
+                    .map(|(i, _)| LevelProblem::BallInCircle(i)),
+            )
+            .collect();
+
+        if self.flags_positions.is_empty() && self.doors.is_empty() {
+            problems.push(LevelProblem::NoGoal);
+        }
+        problems.extend(
+            self.unreachable_flags()
+                .into_iter()
+                .map(LevelProblem::UnreachableFlag),
+        );
+        if !self.lasers_harmless {
+            problems.extend(
+                self.lasers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, laser)| self.is_ball_first_hit(laser, ball))
+                    .map(|(i, _)| LevelProblem::LaserHitsBall(i)),
+            );
+        }
+
+        LevelReport { problems }
+    }
+
+    /// the indices of the flags which the ball can't get to without going through static
+    /// entities, found by flood filling a grid of cells from where the ball starts
+    fn unreachable_flags(&self) -> Vec<usize> {
+        let bounds = self.bounds();
+        let Point(width, height) = bounds.min.to(bounds.max);
+        // with cells half as wide as the ball's radius every gap it fits through stays open
+        let cell = (self.ball_radius / 2.0).max(width.max(height) / MAX_ANALYSIS_CELLS as f64);
+        let columns = (width / cell).ceil() as usize + 1;
+        let rows = (height / cell).ceil() as usize + 1;
+        let cell_of = |Point(x, y): Point| {
+            let column = ((x - bounds.min.0) / cell).floor().max(0.0) as usize;
+            let row = ((y - bounds.min.1) / cell).floor().max(0.0) as usize;
+            (column.min(columns - 1), row.min(rows - 1))
+        };
+
+        // a cell is blocked if any part of it might overlap a static entity
+        let clearance = cell * consts::FRAC_1_SQRT_2;
+        let is_blocked = |column: usize, row: usize| {
+            let center = bounds.min + Point(column as f64 + 0.5, row as f64 + 0.5) * cell;
+            self.polygons
+                .iter()
+                .filter(|polygon| polygon.is_static)
+                .any(|polygon| {
+                    overlaps(
+                        &polygon.shape,
+                        Circle {
+                            center,
+                            radius: clearance,
+                        },
+                    )
+                })
+                || self
+                    .circles
+                    .iter()
+                    .filter(|circle| circle.is_static)
+                    .any(|circle| {
+                        circle.shape.center.to(center).norm() < circle.shape.radius + clearance
+                    })
+        };
+
+        let mut is_reached = vec![false; columns * rows];
+        let start = cell_of(self.initial_ball_position);
+        is_reached[start.1 * columns + start.0] = true;
+        let mut queue = vec![start];
+        while let Some((column, row)) = queue.pop() {
+            let neighbours = [
+                (column.wrapping_sub(1), row),
+                (column + 1, row),
+                (column, row.wrapping_sub(1)),
+                (column, row + 1),
+            ];
+            for (column, row) in neighbours {
+                if column >= columns || row >= rows || is_reached[row * columns + column] {
+                    continue;
+                }
+                if !is_blocked(column, row) {
+                    is_reached[row * columns + column] = true;
+                    queue.push((column, row));
+                }
+            }
+        }
+
+        self.flags_positions
+            .iter()
+            .enumerate()
+            .filter(|(_, &flag)| {
+                let (first_column, first_row) = cell_of(flag);
+                let (last_column, last_row) = cell_of(flag + Point(FLAG_SIZE, FLAG_SIZE));
+                !(first_row..=last_row).any(|row| {
+                    (first_column..=last_column).any(|column| is_reached[row * columns + column])
+                })
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// true if the laser's beam hits the ball before any of the entities
+    fn is_ball_first_hit(&self, laser: &Laser, ball: Circle) -> bool {
+        let direction = laser.direction().unit();
+        let Some(ball_distance) = geometry::ray_circle_crossing(laser.point, direction, ball)
+        else {
+            return false;
+        };
+
+        let polygon_distances = self.polygons.iter().flat_map(|polygon| {
+            Looped::<_, 2>::from(polygon.shape.iter().copied()).filter_map(|[start, end]| {
+                geometry::ray_crossing(laser.point, direction, start, end)
+            })
+        });
+        let circle_distances = self.circles.iter().filter_map(|circle| {
+            geometry::ray_circle_crossing(laser.point, direction, circle.shape)
+        });
+        polygon_distances
+            .chain(circle_distances)
+            .all(|distance| distance > ball_distance)
+    }
+
+    /// returns a copy of the level with every position and size multiplied by `factor`.
+    /// Directions, like the ones of lasers, are left as they are
+    pub fn scale(&self, factor: f64) -> Level {
+        self.transform(Affine2::scaling(factor, factor))
+    }
+
+    /// returns a copy of the level with every position transformed by `transform`.
+    ///
+    /// Circles stay circles, so like the other sizes their radii are scaled by the square root of
+    /// how many times areas grow. Laser directions only go through the linear part and keep their
+    /// lengths, and a mirroring transform reverses the order of polygon vertices, which have to
+    /// stay counterclockwise, and the direction lasers sweep in
+    pub fn transform(&self, transform: Affine2) -> Level {
+        let mut level = self.clone();
+        let determinant = transform.determinant();
+        let size_factor = determinant.abs().sqrt();
+
+        level.initial_ball_position = transform.apply(level.initial_ball_position);
+        level.ball_radius *= size_factor;
+        level.despawn_margin *= size_factor;
+        for bounds in level.bounds.iter_mut().chain(&mut level.camera_bounds) {
+            let corners = [
+                bounds.min,
+                Point(bounds.min.0, bounds.max.1),
+                bounds.max,
+                Point(bounds.max.0, bounds.min.1),
+            ];
+            *bounds = Aabb::around(corners.map(|corner| transform.apply(corner))).unwrap();
+        }
+        for circle in &mut level.circles {
+            circle.shape.center = transform.apply(circle.shape.center);
+            circle.shape.radius *= size_factor;
+        }
+        for vertex in level
+            .polygons
+            .iter_mut()
+            .flat_map(|polygon| &mut polygon.shape)
+            .chain(level.doors.iter_mut().flat_map(|(door, _)| door))
+            .chain(&mut level.flags_positions)
+            .chain(level.notes.iter_mut().map(|(position, _)| position))
+            .chain(
+                level
+                    .spawners
+                    .iter_mut()
+                    .flat_map(|spawner| &mut spawner.region),
+            )
+            .chain(level.bindings.iter_mut().map(|binding| &mut binding.at))
+            .chain(level.pins.iter_mut().map(|pin| &mut pin.at))
+        {
+            *vertex = transform.apply(*vertex);
+        }
+        let transform_direction =
+            |direction: Point| transform.apply_linear(direction).unit() * direction.norm();
+        for laser in &mut level.lasers {
+            laser.point = transform.apply(laser.point);
+            let direction = transform_direction(laser.direction());
+            laser.initial_direction = transform_direction(laser.initial_direction);
+            // angles change under shearing or uneven scaling and flip sign when mirrored
+            if laser.current_angle != 0.0 {
+                laser.current_angle = laser.initial_direction.angle_to(direction);
+            }
+        }
+        for spawner in &mut level.spawners {
+            spawner.initial_velocity = transform.apply_linear(spawner.initial_velocity);
+            spawner.shape = match spawner.shape {
+                SpawnShape::Circle { radius } => SpawnShape::Circle {
+                    radius: radius * size_factor,
+                },
+                SpawnShape::Box { w, h } => SpawnShape::Box {
+                    w: w * size_factor,
+                    h: h * size_factor,
+                },
+            };
+        }
+
+        if determinant < 0.0 {
+            for polygon in &mut level.polygons {
+                polygon.shape.reverse();
+            }
+            for (door, _) in &mut level.doors {
+                door.reverse();
+            }
+            for laser in &mut level.lasers {
+                laser.angular_speed = -laser.angular_speed;
+            }
+            for spawner in &mut level.spawners {
+                spawner.region.reverse();
+            }
+        }
+
+        level
+    }
+}
+
+/// true if the circle overlaps the polygon or lies inside it
+fn overlaps(polygon: &[Point], circle: Circle) -> bool {
+    geometry::is_inside(circle.center, polygon)
+        || Looped::<_, 2>::from(polygon.iter().copied()).any(|[start, end]| {
+            geometry::distance_to_segment(circle.center, start, end) < circle.radius
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::{windows, EPSILON};
+
+    #[test]
+    fn test_scale() {
+        let level = Level {
+            initial_ball_position: Point(0.1, 0.2),
+            ball_radius: 0.07,
+            circles: vec![Entity {
+                shape: Circle {
+                    center: Point(-0.3, 0.4),
+                    radius: 0.05,
+                },
+                is_static: true,
+                is_bindable: false,
+                is_deadly: false,
+                is_fragile: false,
+                break_speed: None,
+                lifetime: None,
+                material: None,
+                friction: None,
+                lock_rotation: false,
+                tag: None,
+                texture: None,
+                paint: None,
+                is_erasable: false,
+                is_carvable: false,
+            }],
+            polygons: vec![Entity {
+                shape: vec![Point(0.0, 0.0), Point(0.5, 0.0), Point(0.5, 0.5)],
+                is_static: true,
+                is_bindable: false,
+                is_deadly: false,
+                is_fragile: false,
+                break_speed: None,
+                lifetime: None,
+                material: None,
+                friction: None,
+                lock_rotation: false,
+                tag: None,
+                texture: None,
+                paint: None,
+                is_erasable: false,
+                is_carvable: false,
+            }],
+            lasers: vec![Laser {
+                point: Point(0.6, -0.2),
+                initial_direction: Point(0.0, 1.0),
+                current_angle: 0.0,
+                angular_speed: 0.01,
+                range: 0.5,
+            }],
+            doors: vec![(vec![Point(0.9, 0.9), Point(1.0, 0.9)], "next.ron".into())],
+            flags_positions: vec![Point(-0.9, 0.0)],
+            bounds: None,
+            camera_bounds: None,
+            despawn_margin: 1.0,
+            notes: vec![(Point(0.3, 0.3), "jump here".into())],
+            launch_mode: false,
+            lasers_push: false,
+            lasers_harmless: false,
+            auto_unstick: false,
+            jump_refill: JumpRefillMode::GroundOnly,
+            physics: PhysicsOverrides::default(),
+            spawners: vec![],
+            bindings: vec![LevelBinding {
+                kind: BindingKind::Hinge,
+                entity_a: EntityRef::Polygon(0),
+                entity_b: EntityRef::Circle(0),
+                at: Point(0.2, 0.1),
+            }],
+            pins: vec![LevelPin {
+                kind: BindingKind::Rigid,
+                at: Point(0.4, 0.1),
+            }],
+            background: None,
+        };
+
+        let scaled = level.scale(2.0);
+
+        assert_eq!(scaled.initial_ball_position, Point(0.2, 0.4));
+        assert_eq!(scaled.ball_radius, 0.14);
+        assert_eq!(scaled.circles[0].shape.center, Point(-0.6, 0.8));
+        assert_eq!(scaled.circles[0].shape.radius, 0.1);
+        assert_eq!(
+            scaled.polygons[0].shape,
+            vec![Point(0.0, 0.0), Point(1.0, 0.0), Point(1.0, 1.0)]
+        );
+        assert_eq!(scaled.lasers[0].point, Point(1.2, -0.4));
+        assert_eq!(scaled.lasers[0].direction(), Point(0.0, 1.0));
+        assert_eq!(scaled.lasers[0].initial_direction, Point(0.0, 1.0));
+        assert_eq!(scaled.lasers[0].range, 0.5);
+        assert_eq!(scaled.doors[0].0, vec![Point(1.8, 1.8), Point(2.0, 1.8)]);
+        assert_eq!(scaled.doors[0].1, "next.ron");
+        assert_eq!(scaled.flags_positions, vec![Point(-1.8, 0.0)]);
+        assert_eq!(scaled.despawn_margin, 2.0);
+        assert_eq!(
+            scaled.bounds(),
+            Aabb {
+                min: Point(-1.8, -0.4),
+                max: Point(2.0, 1.8),
+            }
+        );
+        assert_eq!(scaled.notes, vec![(Point(0.6, 0.6), "jump here".into())]);
+        assert_eq!(scaled.bindings[0].at, Point(0.4, 0.2));
+        assert_eq!(scaled.pins[0].at, Point(0.8, 0.2));
+    }
+
+    #[test]
+    fn test_camera_view_stays_inside_bounds() {
+        let mut level = Level::new(Point(0.0, 0.0));
+        let bounds = Aabb {
+            min: Point(-2.0, -1.0),
+            max: Point(3.0, 1.5),
+        };
+        level.camera_bounds = Some(bounds);
+
+        for zoom in [0.25, 0.5, 1.0, 2.0, 4.0] {
+            let half_size = 1.0 / zoom;
+            for target in [-5.0, -1.0, 0.0, 0.7, 2.9, 8.0] {
+                let Point(x, y) = level.camera_center(Point(target, target / 2.0), zoom);
+                for (center, min, max) in [(x, -2.0, 3.0), (y, -1.0, 1.5)] {
+                    if 2.0 * half_size < max - min {
+                        assert!(center - half_size >= min - EPSILON);
+                        assert!(center + half_size <= max + EPSILON);
+                    } else {
+                        assert_eq!(center, (min + max) / 2.0);
+                    }
+                }
+            }
+        }
+        // away from the edges the camera just follows
+        assert_eq!(level.camera_center(Point(0.5, 0.2), 4.0), Point(0.5, 0.2));
+
+        level.camera_bounds = None;
+        level.bounds = Some(bounds);
+        assert_eq!(level.camera_center(Point(8.0, -3.0), 2.0), Point(2.5, -0.5));
+    }
+
+    #[test]
+    fn test_overview_leaves_out_dynamic_entities() {
+        let mut level = Level::new(Point(0.0, 0.0));
+        level
+            .polygons
+            .push(rectangle(Point(-1.0, -0.5), Point(1.0, -0.4)));
+        let mut dynamic = rectangle(Point(3.0, 3.0), Point(3.5, 3.5));
+        dynamic.is_static = false;
+        level.polygons.push(dynamic);
+        level.flags_positions.push(Point(1.5, 0.0));
+
+        assert_eq!(
+            level.overview_bounds(),
+            Aabb {
+                min: Point(-1.0, -0.5),
+                max: Point(1.6, 0.1),
+            }
+        );
+    }
+
+    fn rectangle(min: Point, max: Point) -> Entity<Vec<Point>> {
+        let vertices = vec![min, Point(max.0, min.1), max, Point(min.0, max.1)];
+        Entity::new(vertices, true)
+    }
+
+    #[test]
+    fn test_sealed_flag_is_unreachable() {
+        let mut level = Level::new(Point(-0.5, 0.5));
+        level.flags_positions.push(Point(0.6, 0.1));
+        // the floor and a box around the flag, with its lid last
+        level.polygons = [
+            (Point(-1.0, -0.1), Point(1.0, 0.0)),
+            (Point(0.4, 0.0), Point(0.45, 0.4)),
+            (Point(0.85, 0.0), Point(0.9, 0.4)),
+            (Point(0.4, 0.4), Point(0.9, 0.45)),
+        ]
+        .into_iter()
+        .map(|(min, max)| rectangle(min, max))
+        .collect();
+        assert!(level.validate().is_ok());
+
+        assert_eq!(
+            level.analyze().problems,
+            vec![LevelProblem::UnreachableFlag(0)]
+        );
+
+        level.polygons.pop();
+        assert!(level.analyze().is_clean());
+    }
+
+    fn signed_area(vertices: &[Point]) -> f64 {
+        windows::Looped::from(vertices.iter().copied())
+            .map(|[first, second]| first.cross(second) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut level = Level::new(Point(0.5, 0.0));
+        level.polygons.push(Entity::new(
+            vec![Point(0.0, 0.0), Point(0.5, 0.0), Point(0.5, 0.5)],
+            true,
+        ));
+        level.lasers.push(Laser {
+            point: Point(0.0, 1.0),
+            initial_direction: Point(0.5, 0.0),
+            current_angle: 0.0,
+            angular_speed: 0.01,
+            range: 0.5,
+        });
+
+        let rotated = level.transform(
+            Affine2::rotation(std::f64::consts::FRAC_PI_2)
+                .then(Affine2::translation(Point(1.0, 0.0))),
+        );
+        assert!(rotated
+            .initial_ball_position
+            .is_close_enough_to(Point(1.0, 0.5)));
+        assert!(rotated.polygons[0].shape[1].is_close_enough_to(Point(1.0, 0.5)));
+        assert!(rotated.lasers[0].point.is_close_enough_to(Point(0.0, 0.0)));
+        assert!(rotated.lasers[0]
+            .direction()
+            .is_close_enough_to(Point(0.0, 0.5)));
+        assert!((rotated.ball_radius - level.ball_radius).abs() < EPSILON);
+        assert!(rotated.validate().is_ok());
+        assert!(signed_area(&rotated.polygons[0].shape) > 0.0);
+
+        let mirrored = level.transform(Affine2::scaling(-1.0, 1.0));
+        assert!(mirrored.validate().is_ok());
+        assert!(signed_area(&mirrored.polygons[0].shape) > 0.0);
+        assert_eq!(mirrored.lasers[0].direction(), Point(-0.5, 0.0));
+        assert_eq!(mirrored.lasers[0].angular_speed, -0.01);
+    }
+
+    #[test]
+    fn test_collapsed_polygon_warning() {
+        let mut level = Level::new(Point(0.5, 0.5));
+        level.polygons.push(Entity::new(
+            vec![
+                Point(0.0, 0.0),
+                Point(1.0, 0.0),
+                Point(1.0, 0.0),
+                Point(1.0, 1.0),
+            ],
+            true,
+        ));
+        level.polygons.push(Entity::new(
+            vec![
+                Point(0.0, 0.0),
+                Point(1.0, 0.0),
+                Point(1.0, 0.0),
+                Point(0.0, 0.0),
+            ],
+            true,
+        ));
+
+        assert!(!level.polygons[0].is_collapsed());
+        assert!(level.polygons[1].is_collapsed());
+        assert!(level.validate().is_ok());
+        assert_eq!(level.warnings(), vec![LevelWarning::CollapsedPolygon(1)]);
+    }
+
+    #[test]
+    fn test_notes_round_trip() {
+        let level: Level = ron::from_str(
+            r#"(
+                initial_ball_position: (0.0, 0.5),
+                circles: [],
+                polygons: [],
+                flags_positions: [],
+                notes: [((0.2, 0.1), "this gap is intentionally too wide"), ((0.4, 0.1), " ")],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(level.warnings(), vec![LevelWarning::EmptyNote(1)]);
+
+        let saved: Level = ron::from_str(&ron::to_string(&level).unwrap()).unwrap();
+        assert_eq!(saved.notes, level.notes);
+
+        let without_notes: Level = ron::from_str(
+            "(initial_ball_position: (0.0, 0.5), circles: [], polygons: [], flags_positions: [])",
+        )
+        .unwrap();
+        assert!(without_notes.notes.is_empty());
+        assert!(without_notes.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_bindings_and_pins() {
+        let parse = |bindings: &str, pins: &str| {
+            Level::parse(&format!(
+                "(
+                    initial_ball_position: (0.0, 0.5),
+                    circles: [(shape: (center: (1.0, 0.0), radius: 0.2), is_static: false, is_bindable: true)],
+                    polygons: [(shape: [(0.0, 0.0), (1.0, 0.0), (1.0, 0.1), (0.0, 0.1)], is_static: true, is_bindable: true)],
+                    flags_positions: [],
+                    bindings: [{bindings}],
+                    pins: [{pins}],
+                )"
+            ))
+        };
+
+        let level = parse(
+            "(kind: Hinge, entity_a: Polygon(0), entity_b: Circle(0), at: (0.9, 0.05))",
+            "(kind: Rigid, at: (0.5, 0.05))",
+        )
+        .unwrap();
+        assert_eq!(
+            level.entity_at(level.pins[0].at),
+            Some(EntityRef::Polygon(0))
+        );
+        let saved: Level = ron::from_str(&ron::to_string(&level).unwrap()).unwrap();
+        assert_eq!(saved.bindings, level.bindings);
+        assert_eq!(saved.pins, level.pins);
+
+        for binding in [
+            "(kind: Hinge, entity_a: Polygon(0), entity_b: Circle(0), at: (0.5, 0.05))",
+            "(kind: Rigid, entity_a: Polygon(0), entity_b: Circle(1), at: (0.9, 0.05))",
+            "(kind: Hinge, entity_a: Circle(0), entity_b: Circle(0), at: (1.0, 0.0))",
+        ] {
+            assert!(matches!(
+                parse(binding, ""),
+                Err(LoadError::Invalid(InvalidLevel::LooseBinding(0)))
+            ));
+        }
+        assert!(matches!(
+            parse("", "(kind: Hinge, at: (0.5, 0.5))"),
+            Err(LoadError::Invalid(InvalidLevel::LoosePin(0)))
+        ));
+    }
+
+    #[test]
+    fn test_level_fits_in_max_entities() {
+        let mut level = Level::new(Point(0.0, 1.0));
+        let circle = Entity::new(
+            Circle {
+                center: Point(0.5, 0.2),
+                radius: 0.01,
+            },
+            false,
+        );
+        // along with the ball
+        level.circles = vec![circle; crate::physics::MAX_ENTITIES - 1];
+        assert_eq!(level.validate(), Ok(()));
+        level.circles.push(level.circles[0].clone());
+        assert_eq!(
+            level.validate(),
+            Err(InvalidLevel::TooManyEntities(
+                crate::physics::MAX_ENTITIES + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_background_spec() {
+        let parse = |background: &str| {
+            ron::from_str::<Level>(&format!(
+                "(initial_ball_position: (0.0, 0.5), circles: [], polygons: [], flags_positions: [], {background})"
+            ))
+            .unwrap()
+            .background
+        };
+
+        assert_eq!(parse(""), None);
+        assert_eq!(
+            parse(r#"background: Some(Animated(dir: "assets/images/stars", fps: 12.0))"#),
+            Some(BackgroundSpec::Animated {
+                dir: "assets/images/stars".to_string(),
+                fps: 12.0
+            })
+        );
+        assert_eq!(
+            parse(r#"background: Some(Static(path: "assets/images/sky.png"))"#),
+            Some(BackgroundSpec::Static {
+                path: "assets/images/sky.png".to_string()
+            })
+        );
+        let color = parse("background: Some(Color((0.1, 0.2, 0.3)))");
+        assert_eq!(color, Some(BackgroundSpec::Color([0.1, 0.2, 0.3])));
+
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.background = color;
+        let saved: Level = ron::from_str(&ron::to_string(&level).unwrap()).unwrap();
+        assert_eq!(saved.background, level.background);
+    }
+
+    #[test]
+    fn test_list_levels() {
+        let directory = std::env::temp_dir().join(format!("whisky-levels-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for file in ["level2.ron", "level1.ron", "notes.txt"] {
+            fs::write(directory.join(file), "").unwrap();
+        }
+
+        assert_eq!(
+            list_levels(&directory),
+            vec![
+                LevelInfo {
+                    name: "level1".into(),
+                    path: directory.join("level1.ron"),
+                },
+                LevelInfo {
+                    name: "level2".into(),
+                    path: directory.join("level2.ron"),
+                },
+            ]
+        );
+        assert!(list_levels(&directory.join("missing")).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_lasers_are_validated_and_normalized() {
+        let laser = |direction: &str, initial: &str| {
+            format!(
+                "(initial_ball_position: (0.0, 0.5), circles: [], polygons: [], flags_positions: [],
+                lasers: [(point: (0.0, 0.0), direction: {direction}, change: 0.0, range: 0.0,
+                inital_direction: {initial}, is_out: false)])"
+            )
+        };
+        let path = std::env::temp_dir().join(format!("whisky-laser-{}.ron", std::process::id()));
+
+        for (direction, initial) in [("(0.0, 0.0)", "(1.0, 0.0)"), ("(1.0, 0.0)", "(0.0, 0.0)")] {
+            fs::write(&path, laser(direction, initial)).unwrap();
+            assert!(matches!(
+                Level::load_from_file(&path),
+                Err(LoadError::Invalid(InvalidLevel::DegenerateLaser(0)))
+            ));
+        }
+
+        fs::write(&path, laser("(-0.1, 0.1)", "(0.0, 3.0)")).unwrap();
+        let level = Level::load_from_file(&path).unwrap();
+        assert!(level.lasers[0]
+            .direction()
+            .is_close_enough_to(Point(-0.5f64.sqrt(), 0.5f64.sqrt())));
+        assert_eq!(level.lasers[0].initial_direction, Point(0.0, 1.0));
+
+        fs::remove_file(&path).unwrap();
+    }
 }
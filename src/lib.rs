@@ -0,0 +1,101 @@
+//! Physics and level handling of the game, which can also be used on their own, e.g. to test or
+//! solve levels. The game itself is only built with the default `graphics` feature.
+//!
+//! A level may be put together in code instead of being loaded from a file and simulated
+//! without any window, as nothing waits for the frames to be displayed:
+//!
+//! ```
+//! use std::time::Duration;
+//! use zpr_game_engine::{
+//!     geometry::Point,
+//!     levels::{Entity, Level},
+//!     physics::EngineBuilder,
+//! };
+//!
+//! let mut level = Level::new(Point(0.0, 0.5));
+//! level.polygons.push(Entity::new(
+//!     vec![Point(-1.0, -0.1), Point(1.0, -0.1), Point(1.0, 0.0), Point(-1.0, 0.0)],
+//!     true,
+//! ));
+//!
+//! let mut engine = EngineBuilder::new(level).build();
+//! let ball = engine.main_ball_id();
+//! for _ in 0..200 {
+//!     engine.step(Duration::from_millis(10));
+//! }
+//!
+//! // the ball has fallen onto the floor and stays on top of it
+//! let Point(_, height) = engine.entity_position(ball).unwrap();
+//! assert!(height < 0.5 && height > 0.0);
+//! ```
+
+use geometry::Point;
+
+pub mod assets;
+pub mod audio;
+pub mod camera;
+pub mod console;
+pub mod controls;
+pub mod daily;
+#[cfg(feature = "graphics")]
+pub mod game_logic;
+pub mod geometry;
+pub mod ghost;
+#[cfg(feature = "graphics")]
+pub mod graphics_engine;
+pub mod heatmap;
+pub mod level_tool;
+pub mod levels;
+pub mod persist;
+pub mod phone_connector;
+pub mod physics;
+pub mod self_test;
+pub mod settings;
+#[cfg(feature = "soft-render")]
+pub mod soft_render;
+
+/// What the player did, sent from the graphics thread to the physics one
+pub enum InputMessage {
+    Erase(Point),
+    /// erases the entity at the point along with everything bound to it, see
+    /// [`physics::Engine::erase_connected`]
+    EraseConnected(Point),
+    Rigid(Point),
+    Hinge(Point),
+    Weld(Point),
+    DrawPolygon(Vec<[f32; 2]>),
+    DrawCircle(geometry::Circle),
+    /// tilts the world further by the angle
+    Angle(f32),
+    /// tilts the world if the input comes from the control scheme in use, see
+    /// [`controls::Controls`]
+    Tilt(controls::TiltInput),
+    /// switches to the next control scheme
+    NextControlScheme,
+    Jump,
+    /// returns the ball to its starting position, as when it's lost
+    Restart,
+    Rewind(bool),
+    Slice(Point, Point),
+    AimStart,
+    AimMove(Point),
+    Launch,
+    PlayDaily,
+    CreateLevelShape(Vec<Point>),
+    ClearDrawn,
+    /// makes the hinge at the point stiffer by the number of steps, or softer if it's negative,
+    /// see [`physics::Engine::tune_hinge_at`]
+    TuneBinding(Point, f64),
+    /// multiplies a physics parameter by the factor, for designing levels
+    TuneParam(physics::TunableParam, f64),
+    /// selects the entity at the point for editing, or clears the selection if there's none
+    Select(Point),
+    Deselect,
+    EditSelected(physics::EntityEdit),
+    /// leaves a note with the text at the point, given as displayed
+    AddNote(Point, String),
+    /// a command typed into the developer console
+    Console(console::Command),
+    /// the window is closing, so the physics thread saves what's kept between runs and stops
+    Quit,
+}
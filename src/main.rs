@@ -1,30 +1,27 @@
 use crossbeam::channel::{self, TryRecvError};
-use game_logic::GameState;
-use geometry::{Laser, Point};
-use levels::{Level, LoadError};
 use std::{
-    env, thread,
+    env, mem,
+    num::{ParseFloatError, ParseIntError},
+    path::Path,
+    process, thread,
     time::{Duration, Instant},
 };
-
-use physics::{compute, shape::Circle};
-
-pub mod game_logic;
-pub mod geometry;
-pub mod graphics_engine;
-pub mod levels;
-pub mod phone_connector;
-pub mod physics;
-
-pub enum InputMessage {
-    Erase(Point),
-    Rigid(Point),
-    Hinge(Point),
-    DrawPolygon(Vec<[f32; 2]>),
-    DrawCircle(geometry::Circle),
-    Angle(f32),
-    Jump,
-}
+use zpr_game_engine::{
+    assets,
+    controls::{Controls, TiltInput},
+    daily,
+    game_logic::{self, GameState, MouseMode},
+    geometry::{self, Point},
+    ghost::{self, Ghost, GhostError},
+    graphics_engine,
+    heatmap::{self, Death, Heatmap},
+    levels::{Level, LoadError},
+    phone_connector,
+    physics::{self, compute, shape::Circle, GameEvent},
+    self_test,
+    settings::Settings,
+    soft_render, InputMessage,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ArgError {
@@ -32,18 +29,72 @@ pub enum ArgError {
     MissingFileName,
     #[error(transparent)]
     Load(#[from] LoadError),
+    #[error("the phone sensitivity is not a number: {0}")]
+    PhoneSensitivity(#[from] ParseFloatError),
+    #[error("the mouse sensitivity is not a number: {0}")]
+    MouseSensitivity(ParseFloatError),
+    #[error("the grid spacing is not a number: {0}")]
+    GridSpacing(ParseFloatError),
+    #[error("--headless needs --render-every <steps> <directory>")]
+    MissingRenderEvery,
+    #[error("the number of steps between rendered frames is not a number: {0}")]
+    RenderEvery(#[from] ParseIntError),
+    #[error(transparent)]
+    Render(#[from] soft_render::RenderError),
+    #[error("can't race against the ghost: {0}")]
+    Ghost(#[from] GhostError),
+    #[error("unknown physics preset \"{0}\", the presets are {presets}", presets = physics::PRESETS.join(", "))]
+    UnknownPreset(String),
 }
 
 #[doc(hidden)]
 fn main() -> Result<(), ArgError> {
+    if env::args().any(|arg| arg == "--self-test") {
+        let report = self_test::run();
+        println!("{report}");
+        process::exit(if report.is_ok() { 0 } else { 1 });
+    }
+
     let (shapes_tx, shapes_rx) = channel::bounded(1);
     let (messages_tx, messages_rx) = channel::unbounded();
     let (phone_tx, phone_rx) = channel::unbounded();
+    let (daily_tx, daily_rx) = channel::unbounded();
+
+    let level_path =
+        assets::level_path_from_args(env::args().skip(1)).ok_or(ArgError::MissingFileName)?;
+    let mut level = Level::load_from_file(assets::level(&level_path))?;
+    for warning in level.warnings() {
+        eprintln!("warning: {warning}");
+    }
+    for problem in level.analyze().problems {
+        eprintln!("possible problem: {problem}");
+    }
 
-    let mut level = Level::load_from_file(&env::args().nth(1).ok_or(ArgError::MissingFileName)?)?;
+    if env::args().any(|arg| arg == "--headless") {
+        let (every, directory) =
+            soft_render::render_every_from_args(env::args()).ok_or(ArgError::MissingRenderEvery)?;
+        let saved = soft_render::record(level, soft_render::HEADLESS_STEPS, every?, &directory)?;
+        println!("saved {} frames to {}", saved.len(), directory.display());
+        return Ok(());
+    }
 
-    phone_connector::listen_for_phone(phone_tx);
+    let params = physics::params_from_args(env::args()).map_err(ArgError::UnknownPreset)?;
+    let settings = Settings::load();
+    let ghost = ghost::ghost_path_from_args(env::args())
+        .map(|path| Ghost::load(&path, &heatmap::level_name(&level_path)))
+        .transpose()?;
 
+    let phone_config = phone_connector::PhoneConfig::from_args(env::args())?;
+    let mut phone_connector = phone_connector::PhoneConnector::new(phone_tx, phone_config);
+    if let Err(err) = phone_connector.start(phone_connector::DEFAULT_ADDRESS) {
+        eprintln!("can't listen for a phone: {err}");
+    }
+
+    let tilt_sensitivity =
+        game_logic::tilt_sensitivity_from_args(env::args()).map_err(ArgError::MouseSensitivity)?;
+    let grid_spacing =
+        game_logic::grid_spacing_from_args(env::args()).map_err(ArgError::GridSpacing)?;
+    let recording_directory = game_logic::recording_directory_from_args(env::args());
     let game_state = GameState {
         mouse_position: [1.5, 1.5],
         player: geometry::Circle {
@@ -51,55 +102,241 @@ fn main() -> Result<(), ArgError> {
             radius: 0.,
         },
         timer: Instant::now(),
+        is_aiming: false,
+        is_fullscreen: env::args().any(|arg| arg == "--fullscreen"),
+        shape_start: None,
+        clear_requested: None,
         reset_position: false,
+        modifiers: Default::default(),
+        mouse_mode: MouseMode::Tilt,
+        tilt_sensitivity,
+        is_editing: false,
+        inspector_field: 0,
+        selected: None,
+        is_recording: recording_directory.is_some(),
+        recording_directory: recording_directory
+            .unwrap_or_else(|| game_logic::DEFAULT_RECORDING_DIRECTORY.into()),
+        console: None,
+        note: None,
+        is_grid_shown: false,
+        grid_spacing,
+        is_snapping: false,
+        world_angle: 0.0,
+        reduce_motion: env::args().any(|arg| arg == "--reduce-motion"),
+        ui: Default::default(),
+        objective: None,
+        shown_objective: None,
+        view: Default::default(),
     };
 
     let physics = thread::spawn(move || {
-        let mut physics = physics::Engine::new(shapes_tx, level.clone());
-        let mut connected = false;
+        let mut physics = physics::EngineBuilder::new(level.clone())
+            .display(shapes_tx)
+            .params(params)
+            .shape_colors(settings.shape_colors)
+            .build();
+        let mut controls = Controls::default();
+        let heatmap_path = Path::new(heatmap::HEATMAP_FILE);
+        let mut heatmap = Heatmap::load(heatmap_path);
+        // saved when the level changes and when the game ends rather than on every death
+        let mut is_heatmap_saved = true;
+        let mut level_name = heatmap::level_name(&level_path);
+        let daily_records_path = Path::new(daily::RECORDS_FILE);
+        let mut daily_records = daily::Records::load(daily_records_path);
+        let daily_cache = Path::new(daily::CACHE_DIRECTORY);
+        let mut daily_date = daily::date_of(Path::new(&level_path), daily_cache);
+        physics.show_deaths(heatmap::density(heatmap.deaths(&level_name)));
+        race_if_in_level(&mut physics, &ghost, &level_name);
         loop {
             if let Some(ref next_level) = physics.next_level {
-                let level = Level::load_from_file(next_level).unwrap();
+                let level = Level::load_from_file(assets::level(next_level)).unwrap();
                 let name_owned = next_level.clone();
+                save_heatmap(&heatmap, heatmap_path, &mut is_heatmap_saved);
+                level_name = heatmap::level_name(next_level);
+                daily_date = daily::date_of(Path::new(next_level), daily_cache);
                 physics = physics.reload_level(level, name_owned);
+                physics.show_deaths(heatmap::density(heatmap.deaths(&level_name)));
+                race_if_in_level(&mut physics, &ghost, &level_name);
             }
-            match phone_rx.try_recv() {
-                Ok(phone_connector::Message::Connected) => connected = true,
-                Ok(phone_connector::Message::Disconnected) => connected = false,
-                Ok(phone_connector::Message::AngleDiff(angle)) => physics.angle += angle,
-                Err(TryRecvError::Disconnected) => return,
-                Err(TryRecvError::Empty) => {}
-            }
-            match messages_rx.try_recv() {
-                Ok(InputMessage::Rigid(point)) => physics.add_rigid(point),
-                Ok(InputMessage::Erase(point)) => physics.erase_at(point),
-                Ok(InputMessage::Hinge(point)) => physics.add_hinge(point),
-                Ok(InputMessage::DrawPolygon(vertices)) => {
-                    physics.add_polygon(compute::hull::<24>(
-                        vertices
-                            .into_iter()
-                            .map(|[x, y]| Point(x as f64, -y as f64)),
-                    ))
+            let phone_message = match phone_rx.try_recv() {
+                Ok(phone_connector::Message::Connected) => {
+                    controls.set_phone_connected(true);
+                    None
                 }
-                Ok(InputMessage::DrawCircle(geometry::Circle { center, radius })) => {
-                    physics.add_circle(Circle::new(center, radius))
+                Ok(phone_connector::Message::Disconnected) => {
+                    controls.set_phone_connected(false);
+                    None
                 }
-                Ok(InputMessage::Angle(angle)) => {
-                    if !connected {
-                        physics.angle = (physics.angle + angle) % (std::f32::consts::PI * 2.0);
+                Ok(phone_connector::Message::AngleDiff(angle)) => {
+                    controls.map(TiltInput::Phone(angle))
+                }
+                Err(TryRecvError::Disconnected) => break,
+                Err(TryRecvError::Empty) => None,
+            };
+            match daily_rx.try_recv() {
+                Ok(Ok(daily::Daily {
+                    date,
+                    path,
+                    is_stale,
+                })) => {
+                    if is_stale {
+                        eprintln!("today's level is unavailable, playing the one from {date}");
                     }
+                    physics.next_level = Some(path.to_string_lossy().into_owned());
+                }
+                Ok(Err(err)) => eprintln!("{err}"),
+                Err(_) => {}
+            }
+            let message = match messages_rx.try_recv() {
+                Ok(InputMessage::Tilt(input)) => controls.map(input),
+                Ok(InputMessage::NextControlScheme) => {
+                    controls.select(controls.scheme().next());
+                    println!("tilting with the {}", controls.scheme().name());
+                    None
                 }
-                Ok(InputMessage::Jump) => physics.jump(),
-                Err(TryRecvError::Disconnected) => return,
-                Err(TryRecvError::Empty) => {}
+                Ok(InputMessage::Quit) | Err(TryRecvError::Disconnected) => break,
+                Ok(message) => Some(message),
+                Err(TryRecvError::Empty) => None,
+            };
+            for message in phone_message.into_iter().chain(message) {
+                handle_message(&mut physics, message, &daily_tx);
             }
 
             physics.run_iteration();
+            for event in physics.take_events() {
+                match event {
+                    GameEvent::BallLost { at, cause } => {
+                        heatmap.record(&level_name, Death { at, cause });
+                        is_heatmap_saved = false;
+                    }
+                    GameEvent::LevelFinished { time } => {
+                        let run = physics.run_ghost(&level_name);
+                        match run.save_if_best(Path::new(ghost::GHOST_DIRECTORY)) {
+                            Ok(Some(path)) => println!(
+                                "personal best in {level_name}: {:.2} s, saved to {}",
+                                time.as_secs_f64(),
+                                path.display()
+                            ),
+                            Ok(None) => {
+                                println!("finished {level_name} in {:.2} s", time.as_secs_f64())
+                            }
+                            Err(err) => eprintln!("couldn't save the ghost: {err}"),
+                        }
+                        if let Some(date) = &daily_date {
+                            if daily_records.record(date, time) {
+                                println!("best time for the daily level of {date}");
+                                if let Err(err) = daily_records.save(daily_records_path) {
+                                    eprintln!("couldn't save the daily times: {err}");
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
         }
+        save_heatmap(&heatmap, heatmap_path, &mut is_heatmap_saved);
     });
 
     thread::sleep(Duration::from_millis(100));
-    graphics_engine::run(shapes_rx, messages_tx, game_state);
-    physics.join().unwrap();
+    graphics_engine::run(
+        shapes_rx,
+        messages_tx,
+        game_state,
+        env::args().any(|arg| arg == "--validation"),
+        env::args().any(|arg| arg == "--stress-uploads"),
+        physics,
+    );
     Ok(())
 }
+
+/// writes the deaths recorded since the heatmap was last saved, if there are any
+fn save_heatmap(heatmap: &Heatmap, path: &Path, is_saved: &mut bool) {
+    if mem::replace(is_saved, true) {
+        return;
+    }
+    if let Err(err) = heatmap.save(path) {
+        eprintln!("couldn't save the heatmap: {err}");
+    }
+}
+
+/// shows the ghost given with `--ghost` if it was recorded in the level with the preset it's
+/// played with
+fn race_if_in_level(physics: &mut physics::Engine, ghost: &Option<Ghost>, level: &str) {
+    let Some(ghost) = ghost.as_ref().filter(|ghost| ghost.level == level) else {
+        return;
+    };
+    match ghost.check_preset(physics.params().preset) {
+        Ok(()) => physics.race(ghost.clone()),
+        Err(err) => eprintln!("not racing the ghost: {err}"),
+    }
+}
+
+/// passes what the player did on to the physics engine, once the control scheme has decided
+/// which tilts take effect
+fn handle_message(
+    physics: &mut physics::Engine,
+    message: InputMessage,
+    daily_tx: &channel::Sender<Result<daily::Daily, daily::DailyError>>,
+) {
+    match message {
+        InputMessage::Rigid(point) => physics.add_rigid(point),
+        InputMessage::Erase(point) => physics.erase_at(point),
+        InputMessage::EraseConnected(point) => physics.erase_connected(point),
+        InputMessage::TuneBinding(point, steps) => {
+            if let Some(compliance) = physics.tune_hinge_at(point, steps) {
+                println!("hinge compliance: {compliance}");
+            }
+        }
+        InputMessage::Hinge(point) => physics.add_hinge(point),
+        InputMessage::Weld(point) => physics.add_weld(point),
+        InputMessage::DrawPolygon(vertices) => {
+            let polygon = compute::hull::<24>(
+                vertices
+                    .into_iter()
+                    .map(|[x, y]| Point(x as f64, -y as f64)),
+            );
+            report_refused(physics.add_polygon(polygon))
+        }
+        InputMessage::DrawCircle(geometry::Circle { center, radius }) => {
+            report_refused(physics.add_circle(Circle::new(center, radius)))
+        }
+        InputMessage::Angle(angle) => physics.tilt_by(angle),
+        // already mapped by the control scheme
+        InputMessage::Tilt(_) | InputMessage::NextControlScheme => {}
+        InputMessage::Jump => physics.jump(),
+        InputMessage::Restart => physics.restart(),
+        InputMessage::Rewind(is_rewinding) => physics.set_rewinding(is_rewinding),
+        InputMessage::Slice(from, to) => physics.slice(from, to),
+        InputMessage::AimStart => physics.start_aiming(),
+        InputMessage::AimMove(point) => physics.aim_at(point),
+        InputMessage::Launch => physics.launch(),
+        InputMessage::PlayDaily => daily::fetch_daily_level(daily_tx.clone()),
+        InputMessage::CreateLevelShape(vertices) => {
+            report_refused(physics.add_level_shape(vertices))
+        }
+        InputMessage::ClearDrawn => physics.clear_drawn(),
+        InputMessage::TuneParam(param, factor) => {
+            physics.tune(param, factor);
+            let params = physics.params();
+            eprintln!(
+                "gravity coefficient: {}, movement coefficient: {}",
+                params.gravity_coefficient, params.movement_coefficient
+            );
+        }
+        InputMessage::Select(point) => physics.select_at(point),
+        InputMessage::Deselect => physics.deselect(),
+        InputMessage::EditSelected(edit) => physics.edit_selected(edit),
+        InputMessage::AddNote(at, text) => physics.add_note(at, text),
+        InputMessage::Console(command) => command.apply(physics),
+        // ends the loop in `main` before it gets here
+        InputMessage::Quit => {}
+    }
+}
+
+/// tells the player why the shape they drew didn't appear
+fn report_refused(drawn: Result<(), physics::TooManyEntities>) {
+    if let Err(err) = drawn {
+        eprintln!("not drawn: {err}");
+    }
+}
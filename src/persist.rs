@@ -0,0 +1,269 @@
+//! saving what the game keeps between runs, like the heatmap and ghosts. Files are written as
+//! `(version: N, data: ...)` so that a type which changes can still read what older versions of
+//! the game saved, by migrating it one version at a time. Files saved before there were versions
+//! are read as version 1
+
+use std::{
+    cmp::Ordering,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// the extension added to a file which can't be read, which is put aside instead of deleted
+pub const QUARANTINE_EXTENSION: &str = "bad";
+/// the extension of the file written before it replaces the saved one
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+/// Reasons for which a saved file can't be read
+#[derive(Debug, thiserror::Error)]
+pub enum PersistError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("version {0} of the file can't be read by this version of the game")]
+    UnknownVersion(u32),
+}
+
+/// A type which is saved to a file
+pub trait Persist: Serialize + DeserializeOwned {
+    /// the version files are saved with, to be increased whenever the type changes in a way
+    /// older files can't be read as
+    const VERSION: u32;
+
+    /// reads a file saved with an older version, usually by reading it with [`data`] as the type
+    /// it was at that version and converting that through each later version
+    fn migrate(version: u32, _text: &str) -> Result<Self, PersistError> {
+        Err(PersistError::UnknownVersion(version))
+    }
+}
+
+#[derive(Serialize)]
+struct Saved<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Loaded<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// the version the text was saved with, `None` if it was saved before there were versions
+fn version(text: &str) -> Option<u32> {
+    ron::from_str::<Header>(text)
+        .ok()
+        .map(|header| header.version)
+}
+
+/// reads the data saved in the text as `T` whatever its version, for migrations
+pub fn data<T: DeserializeOwned>(text: &str) -> Result<T, PersistError> {
+    if version(text).is_some() {
+        Ok(ron::from_str::<Loaded<T>>(text)?.data)
+    } else {
+        Ok(ron::from_str(text)?)
+    }
+}
+
+/// reads the text, migrating it if it was saved with an older version
+pub fn read<T: Persist>(text: &str) -> Result<T, PersistError> {
+    let version = version(text).unwrap_or(1);
+    match version.cmp(&T::VERSION) {
+        Ordering::Equal => data(text),
+        Ordering::Less => T::migrate(version, text),
+        Ordering::Greater => Err(PersistError::UnknownVersion(version)),
+    }
+}
+
+pub fn write<T: Persist>(value: &T) -> Result<String, ron::Error> {
+    ron::to_string(&Saved {
+        version: T::VERSION,
+        data: value,
+    })
+}
+
+pub fn load<T: Persist>(path: &Path) -> Result<T, PersistError> {
+    read(&fs::read_to_string(path)?)
+}
+
+/// the path with the extension added after the one it has
+fn with_extension_added(path: &Path, extension: &str) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// saves the value through a temporary file, so the saved file is never left half written
+pub fn save<T: Persist>(path: &Path, value: &T) -> io::Result<()> {
+    let text = write(value).map_err(io::Error::other)?;
+    let temporary = with_extension_added(path, TEMPORARY_EXTENSION);
+    fs::write(&temporary, text)?;
+    fs::rename(&temporary, path)
+}
+
+/// the value saved in the file, the default one if there's no file yet. A file which can't be
+/// read is renamed to `<file>.bad`, so it's neither lost nor read again, and a warning is logged
+pub fn load_or_quarantine<T: Persist + Default>(path: &Path) -> T {
+    let err = match load(path) {
+        Ok(value) => return value,
+        Err(PersistError::Io(err)) if err.kind() == io::ErrorKind::NotFound => return T::default(),
+        Err(PersistError::Io(err)) => {
+            eprintln!("warning: ignoring {}: {err}", path.display());
+            return T::default();
+        }
+        Err(err) => err,
+    };
+    let quarantined = with_extension_added(path, QUARANTINE_EXTENSION);
+    match fs::rename(path, &quarantined) {
+        Ok(()) => eprintln!(
+            "warning: {} can't be read ({err}), it's been moved to {}",
+            path.display(),
+            quarantined.display()
+        ),
+        Err(rename_err) => eprintln!(
+            "warning: ignoring {}, which can't be read ({err}) nor moved away: {rename_err}",
+            path.display()
+        ),
+    }
+    T::default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// the first version, which only had a name
+    #[derive(Deserialize)]
+    struct ProfileV1 {
+        name: String,
+    }
+
+    /// the second version, which added the number of finished levels
+    #[derive(Deserialize)]
+    struct ProfileV2 {
+        name: String,
+        finished: u32,
+    }
+
+    impl From<ProfileV1> for ProfileV2 {
+        fn from(profile: ProfileV1) -> Self {
+            ProfileV2 {
+                name: profile.name,
+                finished: 0,
+            }
+        }
+    }
+
+    /// the current version, which split the name
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        first_name: String,
+        last_name: String,
+        finished: u32,
+    }
+
+    impl From<ProfileV2> for Profile {
+        fn from(profile: ProfileV2) -> Self {
+            let (first_name, last_name) =
+                profile.name.split_once(' ').unwrap_or((&profile.name, ""));
+            Profile {
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+                finished: profile.finished,
+            }
+        }
+    }
+
+    impl Persist for Profile {
+        const VERSION: u32 = 3;
+
+        fn migrate(version: u32, text: &str) -> Result<Self, PersistError> {
+            match version {
+                1 => Ok(ProfileV2::from(data::<ProfileV1>(text)?).into()),
+                2 => Ok(data::<ProfileV2>(text)?.into()),
+                _ => Err(PersistError::UnknownVersion(version)),
+            }
+        }
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("whisky-persist-{name}-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_migrates_old_versions() {
+        let profile = Profile {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            finished: 0,
+        };
+        // saved before there were versions
+        assert_eq!(
+            read::<Profile>("(name: \"Ada Lovelace\")").unwrap(),
+            profile
+        );
+        assert_eq!(
+            read::<Profile>("(version: 1, data: (name: \"Ada Lovelace\"))").unwrap(),
+            profile
+        );
+        assert_eq!(
+            read::<Profile>("(version: 2, data: (name: \"Ada\", finished: 4))").unwrap(),
+            Profile {
+                first_name: "Ada".to_string(),
+                last_name: String::new(),
+                finished: 4
+            }
+        );
+        assert!(matches!(
+            read::<Profile>("(version: 4, data: ())"),
+            Err(PersistError::UnknownVersion(4))
+        ));
+
+        let directory = directory("migrate");
+        let path = directory.join("profile.ron");
+        save(&path, &profile).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("(version:3,"));
+        assert_eq!(load::<Profile>(&path).unwrap(), profile);
+        assert!(!with_extension_added(&path, TEMPORARY_EXTENSION).exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_quarantines_corrupt_file() {
+        let directory = directory("quarantine");
+        let path = directory.join("profile.ron");
+        assert_eq!(load_or_quarantine::<Profile>(&path), Profile::default());
+
+        fs::write(&path, "(version: 3, data: (first_na").unwrap();
+        assert_eq!(load_or_quarantine::<Profile>(&path), Profile::default());
+        assert!(!path.exists());
+        let quarantined = directory.join("profile.ron.bad");
+        assert_eq!(
+            fs::read_to_string(quarantined).unwrap(),
+            "(version: 3, data: (first_na"
+        );
+
+        // the game starts over with a file it can save again
+        let profile = Profile {
+            finished: 1,
+            ..Profile::default()
+        };
+        save(&path, &profile).unwrap();
+        assert_eq!(load_or_quarantine::<Profile>(&path), profile);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,38 +1,149 @@
 use crossbeam::channel::Sender;
 use futures_util::StreamExt;
-use std::error::Error;
-use tokio::net;
+use std::{error::Error, io, net::SocketAddr, num::ParseFloatError};
+use tokio::{net, runtime::Runtime, sync::oneshot, task::JoinHandle};
 use tokio_tungstenite::{accept_async, tungstenite};
 
+const SENSITIVITY_ARG: &str = "--phone-sensitivity=";
+const INVERT_ARG: &str = "--invert-phone";
+/// where the phone connects to unless it's changed
+pub const DEFAULT_ADDRESS: &str = "0.0.0.0:8093";
+
 pub enum Message {
     Connected,
     Disconnected,
     AngleDiff(f32),
 }
 
-pub fn listen_for_phone(channel: Sender<Message>) {
-    std::thread::spawn(move || {
-        if let Err(err) = run_listening_task(channel) {
-            eprintln!("{err}");
-        };
-    });
+/// How the tilt reported by the phone turns into the tilt of the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhoneConfig {
+    /// the world is tilted this many times further than the phone
+    pub sensitivity: f32,
+    /// tilts the world the other way, for phones held upside down
+    pub invert: bool,
+}
+
+impl Default for PhoneConfig {
+    fn default() -> Self {
+        PhoneConfig {
+            sensitivity: 2.0,
+            invert: false,
+        }
+    }
 }
 
-fn run_listening_task(channel: Sender<Message>) -> Result<(), Box<dyn Error>> {
-    tokio::runtime::Runtime::new()?.block_on(async move {
-        if let Err(err) = handle_messages(channel).await {
-            eprintln!("{err}");
+impl PhoneConfig {
+    /// reads `--phone-sensitivity=<number>` and `--invert-phone`, ignoring other arguments
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ParseFloatError> {
+        let mut config = PhoneConfig::default();
+        for arg in args {
+            if let Some(sensitivity) = arg.strip_prefix(SENSITIVITY_ARG) {
+                config.sensitivity = sensitivity.parse()?;
+            } else if arg == INVERT_ARG {
+                config.invert = true;
+            }
         }
-    });
-    Ok(())
+        Ok(config)
+    }
+
+    /// the tilt of the world corresponding to the tilt of the phone
+    pub fn apply(&self, angle_diff: f32) -> f32 {
+        let direction = if self.invert { -1.0 } else { 1.0 };
+        angle_diff * self.sensitivity * direction
+    }
+}
+
+/// The listening task along with what stops it
+struct Listening {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// Listens for a phone in the background, one at a time. It can be stopped and started again,
+/// e.g. on another port, and stops when it's dropped
+pub struct PhoneConnector {
+    channel: Sender<Message>,
+    config: PhoneConfig,
+    /// created when the connector is first started and kept for the later starts
+    runtime: Option<Runtime>,
+    listening: Option<Listening>,
 }
 
-async fn handle_messages(channel: Sender<Message>) -> Result<(), Box<dyn Error>> {
+impl PhoneConnector {
+    /// a connector sending what the phones it accepts report through the channel, which
+    /// doesn't listen until it's started
+    pub fn new(channel: Sender<Message>, config: PhoneConfig) -> Self {
+        PhoneConnector {
+            channel,
+            config,
+            runtime: None,
+            listening: None,
+        }
+    }
+
+    /// starts listening for a phone at the address, stopping the previous listener first if
+    /// it's still running. Returns the address it listens at, which tells the port if the
+    /// address asked for any
+    pub fn start(&mut self, address: &str) -> io::Result<SocketAddr> {
+        self.stop();
+        let runtime = match &mut self.runtime {
+            Some(runtime) => runtime,
+            runtime => runtime.insert(Runtime::new()?),
+        };
+        let listener = runtime.block_on(net::TcpListener::bind(address))?;
+        let address = listener.local_addr()?;
+
+        let (stop, stopped) = oneshot::channel();
+        let (channel, config) = (self.channel.clone(), self.config);
+        let task = runtime.spawn(async move {
+            tokio::select! {
+                result = handle_messages(listener, channel, config) => {
+                    if let Err(err) = result {
+                        eprintln!("{err}");
+                    }
+                }
+                // dropping the other branch drops the listener and any connection
+                _ = stopped => {}
+            }
+        });
+        self.listening = Some(Listening { stop, task });
+        Ok(address)
+    }
+
+    /// stops listening and disconnects the phone, if one is connected. Once it returns the
+    /// port is free again. Must not be called from within an asynchronous task
+    pub fn stop(&mut self) {
+        let (Some(Listening { stop, task }), Some(runtime)) =
+            (self.listening.take(), &self.runtime)
+        else {
+            return;
+        };
+        // the task may have already ended on an error
+        let _ = stop.send(());
+        let _ = runtime.block_on(task);
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.listening
+            .as_ref()
+            .is_some_and(|listening| !listening.task.is_finished())
+    }
+}
+
+impl Drop for PhoneConnector {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn handle_messages(
+    listener: net::TcpListener,
+    channel: Sender<Message>,
+    config: PhoneConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
     loop {
-        let (stream, _) = net::TcpListener::bind("0.0.0.0:8093")
-            .await?
-            .accept()
-            .await?;
+        let (stream, _) = listener.accept().await?;
 
         let sink = accept_async(stream).await?;
         println!("it has connected");
@@ -40,7 +151,7 @@ async fn handle_messages(channel: Sender<Message>) -> Result<(), Box<dyn Error>>
         sink.for_each(|message| async {
             match handle_message(message).await {
                 Ok(angle) => {
-                    channel.try_send(Message::AngleDiff(angle * 2.0));
+                    channel.try_send(Message::AngleDiff(config.apply(angle)));
                 }
                 Err(err) => eprintln!("{err}"),
             };
@@ -53,6 +164,79 @@ async fn handle_messages(channel: Sender<Message>) -> Result<(), Box<dyn Error>>
 
 async fn handle_message(
     message: Result<tungstenite::Message, tungstenite::Error>,
-) -> Result<f32, Box<dyn Error>> {
+) -> Result<f32, Box<dyn Error + Send + Sync>> {
     Ok(message?.into_text()?.parse()?)
 }
+
+#[cfg(test)]
+mod test {
+    use std::{net::TcpListener, time::Duration};
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_invert_and_sensitivity() {
+        let config = PhoneConfig {
+            sensitivity: 3.0,
+            invert: true,
+        };
+        assert_eq!(config.apply(0.25), -0.75);
+        assert_eq!(config.apply(-0.25), 0.75);
+
+        let config = PhoneConfig {
+            sensitivity: 0.5,
+            invert: false,
+        };
+        assert_eq!(config.apply(0.25), 0.125);
+    }
+
+    #[test]
+    fn test_from_args() {
+        assert_eq!(
+            PhoneConfig::from_args(args(&["level.ron", "--fullscreen"])),
+            Ok(PhoneConfig::default())
+        );
+        assert_eq!(
+            PhoneConfig::from_args(args(&["--invert-phone", "--phone-sensitivity=1.5"])),
+            Ok(PhoneConfig {
+                sensitivity: 1.5,
+                invert: true,
+            })
+        );
+        assert!(PhoneConfig::from_args(args(&["--phone-sensitivity=fast"])).is_err());
+    }
+
+    #[test]
+    fn test_restart_releases_port() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut connector = PhoneConnector::new(tx, PhoneConfig::default());
+        let address = connector.start("127.0.0.1:0").unwrap();
+        assert!(connector.is_listening());
+        assert!(TcpListener::bind(address).is_err());
+
+        let (mut phone, _) = tungstenite::connect(format!("ws://{address}")).unwrap();
+        phone
+            .write_message(tungstenite::Message::Text("0.25".to_string()))
+            .unwrap();
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(rx.recv_timeout(timeout), Ok(Message::Connected)));
+        assert!(matches!(
+            rx.recv_timeout(timeout),
+            Ok(Message::AngleDiff(angle)) if angle == 0.5
+        ));
+
+        connector.stop();
+        assert!(!connector.is_listening());
+        drop(TcpListener::bind(address).unwrap());
+
+        // and it can listen at the same port again
+        assert_eq!(connector.start(&address.to_string()).unwrap(), address);
+        assert!(TcpListener::bind(address).is_err());
+        drop(connector);
+        drop(TcpListener::bind(address).unwrap());
+    }
+}
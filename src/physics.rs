@@ -1,74 +1,561 @@
+//! The simulation of a level, running on its own thread in the game and sending what's to be
+//! displayed to the graphics one.
+//!
+//! Every entity is a convex [`shape::Polygon`] or a [`shape::Circle`], moved and collided once per
+//! [`Engine::step`]. The engine keeps everything in level coordinates and only rotates what it
+//! sends to be displayed, so gravity is what actually turns when the world is tilted
+
+#![deny(missing_docs)]
+
 use std::{
     cell::RefCell,
+    collections::{HashMap, VecDeque},
     f64::consts,
+    fs, mem,
     rc::{Rc, Weak},
-    time::Instant,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
     vec, f32::consts::E,
 };
 
 use crossbeam::channel::{self, TrySendError};
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use self::{
     binding::{Binding, Unbound},
-    shape::{Bounded, Circle, Collidable, CollisionType, Polygon},
+    shape::{velocity_change, Bounded, Circle, Collidable, CollisionData, CollisionType, Polygon},
+    snapshot::Snapshot,
 };
 use crate::{
-    geometry::{self, Laser, Point, Vector},
-    levels::Level,
+    assets,
+    audio::Material,
+    geometry::{self, windows::Looped, Aabb, Laser, Point, Vector},
+    ghost::{self, Ghost},
+    levels::{
+        BackgroundSpec, BindingKind, EntityRef, JumpRefillMode, Level, LevelBinding, LevelPin,
+        PhysicsOverrides, SpawnShape, Spawner,
+    },
 };
 
 mod binding;
 pub mod compute;
+mod contact_pairs;
 pub mod shape;
+mod snapshot;
 
+/// the default of `PhysicsParams::gravity_coefficient`
 const GRAVITY_COEFFICIENT: f64 = -0.000002;
+/// the default of `PhysicsParams::movement_coefficient`
 const MOVEMENT_COEFFICIENT: f64 = 0.0000004;
+/// the default `PhysicsParams::strong_collision_speed`
+const STRONG_COLLISION_SPEED: f64 = 0.5;
+/// the default `PhysicsParams::max_overlap_correction`
+const MAX_OVERLAP_CORRECTION: f64 = 0.25;
+/// the default `PhysicsParams::jump_impulse`
+const JUMP_IMPULSE: f64 = 1.0;
+/// how many times the main ball can jump before it has to touch something again
+const MAX_JUMPS: usize = 2;
+/// the default `PhysicsParams::max_entities`, which every level has to fit in
+pub const MAX_ENTITIES: usize = 1000;
+/// how far from the marker of a hinge, as displayed, it can be picked to be tuned
+const HINGE_PICK_RADIUS: f64 = 0.03;
+/// every step of `Engine::tune_hinge_at` doubles or halves the compliance of the hinge
+const HINGE_COMPLIANCE_STEP: f64 = 2.0;
+/// a stiff hinge is softened as if its compliance was this, and one stiffened below it
+/// becomes stiff again
+const MIN_HINGE_COMPLIANCE: f64 = 1e-4;
+/// hinges softer than this barely hold anything up
+const MAX_HINGE_COMPLIANCE: f64 = 0.1;
+/// the names of the presets [`PhysicsParams::preset`] knows, the first one is the default
+pub const PRESETS: [&str; 3] = ["classic", "floaty", "snappy"];
+/// picks the preset the game is played with, e.g. `--physics=floaty`
+const PRESET_ARG: &str = "--physics=";
+
+/// how much simulated time passes between two consecutive rewind snapshots
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(100);
+/// bounds the rewind history to roughly 5 seconds
+const MAX_SNAPSHOTS: usize = 50;
+/// speed at which the two halves of a sliced shape are pushed apart
+const SLICE_SEPARATION_SPEED: f64 = 0.05;
+/// the world can be tilted at most this far in either direction
+const MAX_TILT: f32 = consts::PI as f32;
+/// how fast, in radians per second, the world turns towards the target tilt
+const TILT_SPEED: f32 = 2.0 * consts::PI as f32;
+/// the longest launch vector the player can drag out in launch mode
+const MAX_LAUNCH_DRAG: f64 = 0.5;
+/// converts the length of the dragged launch vector to the ball's velocity
+const LAUNCH_VELOCITY_COEFFICIENT: f64 = 4.0;
+/// the selected entity is displayed in this color, whatever it normally looks like
+const SELECTED_COLOR: [f32; 3] = [0.85, 0.2, 1.0];
+/// the launch trajectory preview covers one second split into 20 dots
+const TRAJECTORY_STEP: Duration = Duration::from_millis(50);
+const N_OF_TRAJECTORY_DOTS: usize = 20;
+/// lasers carve a hole of this radius after dwelling on a carvable entity long enough
+const CARVE_RADIUS: f64 = 0.05;
+const CARVE_DWELL: Duration = Duration::from_millis(300);
+const N_OF_CARVE_HOLE_SIDES: usize = 8;
+/// carving is refused if it would leave a piece with more vertices than this
+const MAX_CARVED_VERTICES: usize = 16;
+/// carved pieces smaller than this crumble away
+const MIN_CARVED_AREA: f64 = 0.0005;
+/// the force of a pushing laser beam, which accelerates a shape with an area of 0.01
+/// as much as gravity does
+const LASER_FORCE: f64 = 0.00000002;
+/// even the lightest shapes aren't pushed faster than this
+const MAX_LASER_ACCELERATION: f64 = 0.00001;
+/// how far apart the points along a laser beam checked for hits are
+const LASER_STEP: f64 = 0.01;
+/// entities closer than this are reported to be in contact
+const CONTACT_MARGIN: f64 = 0.001;
+/// events which were not taken by the game are dropped, oldest first, past this count
+const MAX_PENDING_EVENTS: usize = 256;
+/// the main ball moving slower than this, in units per second, may be wedged
+const WEDGED_SPEED: f64 = 0.05;
+/// contacts with normals closer than this to pointing the opposite ways are on opposing sides
+/// of the main ball, about 45 degrees
+const OPPOSING_CONTACTS_COS: f64 = -0.7;
+/// how long the main ball has to be wedged to be stuck
+pub const STUCK_TIME: Duration = Duration::from_secs(2);
+/// the velocity a stuck ball is nudged with in a level with `auto_unstick`, half of a default jump
+const UNSTICK_IMPULSE: f64 = 0.5;
+/// how many directions are tried to find the one a stuck ball is least blocked in
+const N_OF_UNSTICK_DIRECTIONS: usize = 16;
+/// a binding pulling the lighter of its shapes back faster than this, in units per second,
+/// makes the engine enforce the bindings more times per step. A weight hanging still only
+/// takes about a tenth of it, what gravity adds in a step
+pub const UNSTABLE_BINDING_SPEED: f64 = 0.1;
+/// the bindings are enforced at most this many times per step, however unstable they are
+const MAX_BINDING_SUBSTEPS: usize = 8;
+/// the simulation is at rest while the total kinetic energy stays below this
+const SETTLED_ENERGY: f64 = 1e-5;
+/// how many consecutive steps the simulation has to be at rest for by default
+const SETTLE_STEPS: usize = 30;
+/// the bounce guard compares the kinetic energy of an entity over windows of this many steps
+const BOUNCE_WINDOW: usize = 50;
+/// an entity whose energy peaks this many times within a window is bouncing or jittering,
+/// rather than rolling, sliding or swinging
+const BOUNCE_MIN_PEAKS: usize = 8;
+/// the bounce guard steps in if the highest energy of a window is at least this fraction of
+/// the highest energy of the previous one
+const BOUNCE_DECAY: f64 = 0.9;
+/// an entity caught bouncing keeps this much of its velocity in the first step, and this much of
+/// what it was left with in each step after, so it eases to a stop
+const BOUNCE_DAMPING: f64 = 0.8;
+/// how close the ball has to get to a door for the level behind it to be previewed
+const DOOR_PREVIEW_DISTANCE: f64 = 0.2;
+/// how often the file of a previewed level is checked for changes
+const DOOR_PREVIEW_RECHECK: Duration = Duration::from_secs(1);
+/// how long the main ball leaves marks after touching a painted entity
+const PAINT_TIME: Duration = Duration::from_secs(3);
+/// how far apart the marks the ball leaves are, so a rolling ball leaves a dotted trail and a
+/// resting one doesn't pile them up
+const PAINT_MARK_SPACING: f64 = 0.02;
+/// only this many marks are kept, the oldest are forgotten first
+pub const MAX_PAINT_MARKS: usize = 500;
+/// the colors the shapes drawn by the player get by default, light enough to stand out against
+/// the dark background and far enough apart to tell neighbouring shapes apart
+pub const DEFAULT_SHAPE_PALETTE: [[f32; 3]; 12] = [
+    [0.92, 0.33, 0.33],
+    [0.96, 0.58, 0.24],
+    [0.96, 0.82, 0.29],
+    [0.60, 0.82, 0.31],
+    [0.27, 0.72, 0.47],
+    [0.26, 0.76, 0.76],
+    [0.38, 0.66, 0.96],
+    [0.47, 0.52, 0.96],
+    [0.67, 0.47, 0.92],
+    [0.92, 0.47, 0.77],
+    [0.86, 0.62, 0.47],
+    [0.77, 0.86, 0.96],
+];
+/// random colors of shapes darker than this relative luminance are hard to tell apart from the
+/// background, so they are rolled again
+const MIN_SHAPE_LUMINANCE: f32 = 0.25;
+
+/// How the shapes drawn by the player are colored
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ShapeColors {
+    /// one of the colors, the default ones if the list is empty
+    Palette(Vec<[f32; 3]>),
+    /// any color which isn't too dark to be seen against the background
+    Random,
+}
+
+impl Default for ShapeColors {
+    fn default() -> Self {
+        Self::Palette(DEFAULT_SHAPE_PALETTE.to_vec())
+    }
+}
+
+impl ShapeColors {
+    /// a color chosen with the random number generator
+    fn pick(&self, rng: &mut impl Rng) -> [f32; 3] {
+        match self {
+            Self::Palette(colors) => {
+                let colors = if colors.is_empty() {
+                    &DEFAULT_SHAPE_PALETTE[..]
+                } else {
+                    colors
+                };
+                colors[rng.gen_range(0..colors.len())]
+            }
+            Self::Random => loop {
+                let color = [
+                    rng.gen_range(0.0..1.0),
+                    rng.gen_range(0.0..1.0),
+                    rng.gen_range(0.0..1.0),
+                ];
+                if luminance(color) >= MIN_SHAPE_LUMINANCE {
+                    break color;
+                }
+            },
+        }
+    }
+}
+
+/// how bright the color looks, from 0 to 1, weighing the channels as the eye does
+fn luminance([red, green, blue]: [f32; 3]) -> f32 {
+    0.2126 * red + 0.7152 * green + 0.0722 * blue
+}
 
+/// A shape along with the color it's drawn in
 #[derive(Debug)]
 pub struct WithColor<S> {
+    /// red, green and blue, from 0 to 1
     pub color: [f32; 3],
+    /// the shape to draw, or a pointer to it while it's still owned by the engine
     pub shape: S,
+    /// the index in `DisplayMessage::textures` of the image the shape is drawn with instead of
+    /// the color, if it has one
+    pub texture: Option<usize>,
 }
 
-impl<S> From<S> for WithColor<S> {
-    fn from(shape: S) -> Self {
-        let mut rng = rand::thread_rng();
+impl<S> WithColor<S> {
+    /// a random color of the ones allowed, depending only on the seed and the entity, so
+    /// engines with the same seed give the entities added in the same order the same colors
+    pub fn seeded(shape: S, seed: u64, id: EntityId, colors: &ShapeColors) -> Self {
+        // the multiplication spreads the ids over all bits, so that the combined values of
+        // different seeds don't overlap in an obvious way
+        let mut rng = StdRng::seed_from_u64(seed ^ id.0.wrapping_mul(0x9e37_79b9_7f4a_7c15));
 
         Self {
-            color: [
-                rng.gen_range(0.0..1.0),
-                rng.gen_range(0.0..1.0),
-                rng.gen_range(0.0..1.0),
-            ],
+            color: colors.pick(&mut rng),
             shape,
+            texture: None,
+        }
+    }
+}
+
+/// The colors of entities which behave in a special way, so the player can tell them apart
+#[derive(Clone, Copy, Debug)]
+struct Palette {
+    dynamic: [f32; 3],
+    static_: [f32; 3],
+    deadly: [f32; 3],
+    fragile: [f32; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            dynamic: [1.0, 0.85, 0.22],
+            static_: [1.0, 0.85, 0.42],
+            deadly: [1.0, 0.0, 0.0],
+            fragile: [0.7, 0.7, 0.7],
         }
     }
 }
 
+/// the color showing the most important way the entity behaves, being deadly above everything
+fn entity_color(cfg: &EntityCfg, palette: &Palette) -> [f32; 3] {
+    if cfg.is_deadly {
+        palette.deadly
+    } else if let Some(paint) = cfg.paint {
+        paint
+    } else if cfg.is_fragile {
+        palette.fragile
+    } else if !cfg.is_static {
+        palette.dynamic
+    } else {
+        palette.static_
+    }
+}
+
+/// the number in the name of a level file like `level3.ron`, 0 for the levels named otherwise,
+/// like the daily ones or one given on the command line
+fn level_index(name: &str) -> usize {
+    let stem = std::path::Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let number = stem.strip_prefix("level").unwrap_or_default();
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    number[..digits].parse().unwrap_or(0)
+}
+
+/// a uniformly distributed random point of a convex polygon
+fn random_point_in(region: &[Point], rng: &mut impl Rng) -> Point {
+    let origin = region[0];
+    let triangles: Vec<_> = region[1..]
+        .windows(2)
+        .map(|pair| {
+            let area = (pair[0] - origin).cross(pair[1] - origin).abs() / 2.0;
+            (pair[0], pair[1], area)
+        })
+        .collect();
+
+    let mut remaining = rng.gen::<f64>() * triangles.iter().map(|(_, _, area)| area).sum::<f64>();
+    let (second, third, _) = triangles
+        .iter()
+        .copied()
+        .find(|(_, _, area)| {
+            remaining -= area;
+            remaining < 0.0
+        })
+        .unwrap_or(triangles[triangles.len() - 1]);
+
+    // folding the unit square in half keeps the points uniformly distributed over the triangle
+    let (mut u, mut v) = (rng.gen::<f64>(), rng.gen::<f64>());
+    if u + v > 1.0 {
+        (u, v) = (1.0 - u, 1.0 - v);
+    }
+    origin + (second - origin) * u + (third - origin) * v
+}
+
+/// Everything the graphics thread needs to draw a frame, already rotated by the world's tilt
 pub struct DisplayMessage {
+    /// the polygonal entities
     pub polygons: Vec<WithColor<geometry::Polygon>>,
+    /// the round entities, the main ball is the first one
     pub circles: Vec<WithColor<geometry::Circle>>,
+    /// the flags placed in the level
     pub flags: Vec<geometry::Polygon>,
+    /// where the rigid bindings are attached
     pub rigid_bindings: Vec<geometry::Point>,
+    /// where the hinges are attached
     pub hinges: Vec<Point>,
+    /// rigid bindings still waiting for a second entity to be drawn over them
     pub unbound_rigid_bindings: Vec<Point>,
+    /// hinges still waiting for a second entity to be drawn over them
     pub unbound_hinges: Vec<Point>,
+    /// where the welds are attached
+    pub welds: Vec<Point>,
+    /// welds still waiting for a second entity to be drawn over them
+    pub unbound_welds: Vec<Point>,
+    /// where each binding is attached along with its load in the last step, see
+    /// [`Binding::enforce`]. Empty outside debug mode
+    pub binding_loads: Vec<(Point, f64)>,
+    /// debug mode is on, so the numbers it shows are drawn over the level
+    pub is_debugging: bool,
+    /// the beams, cut short where they hit an entity
     pub lasers: Vec<WithColor<geometry::Polygon>>,
+    /// the boxes the lasers are mounted in
     pub laser_boxes: Vec<WithColor<geometry::Polygon>>,
+    /// the doors leading to other levels
     pub doors: Vec<WithColor<geometry::Polygon>>,
+    /// the arrow showing the launch the player is aiming, empty if they aren't
+    pub launch_arrow: Vec<WithColor<geometry::Polygon>>,
+    /// where the ball would fly if it was launched now, empty if the player isn't aiming
+    pub trajectory: Vec<Point>,
+    /// the main ball along with how it's moving
+    pub player: Player,
+    /// the unit vector gravity pulls along, relative to the level rather than to the screen
+    pub gravity: Vector,
+    /// the number of the level being played
     pub level_idx: usize,
+    /// the entity selected for editing
+    pub selected: Option<SelectedEntity>,
+    /// the paths of the images entities are drawn with, which are the same for the whole level
+    pub textures: Vec<String>,
+    /// what's drawn behind the level, the same for the whole level
+    pub background: Option<BackgroundSpec>,
+    /// the box around the static entities, doors and flags, shown when the level starts
+    pub level_bounds: Aabb,
+    /// the box the camera keeps its view inside, in the coordinates of the level rather than
+    /// the screen like `level_bounds`
+    pub camera_bounds: Aabb,
+    /// the level was just loaded or started over, so the whole of it is shown first
+    pub level_started: bool,
+    /// where the main ball was lost since the previous frame, if it was, as displayed
+    pub ball_lost: Option<Point>,
+    /// the level behind the door the ball is near, if it's near one
+    pub door_preview: Option<DoorPreview>,
+    /// where the ball of the ghost being raced against is, if there is one still running
+    pub ghost: Option<Point>,
+    /// where the ball was lost in this level in earlier games, in the coordinates of the level
+    /// rather than the screen, with how crowded each place is, see [`crate::heatmap::density`]
+    pub deaths: Arc<Vec<(Point, f64)>>,
+    /// the notes level designers left, in the coordinates of the level rather than the screen,
+    /// the same until one is added
+    pub notes: Arc<Vec<(Point, String)>>,
+    /// the marks the ball left since the previous frame, oldest first
+    pub paint_marks: Vec<PaintMark>,
+    /// the marks of earlier frames should be forgotten, as after the ball was returned to its
+    /// starting position
+    pub clear_paint: bool,
+    /// the main ball is wedged where it can't get out, so the player should be told how to start
+    /// over, see [`Engine::is_stuck`]
+    pub stuck: bool,
+    /// the flags and doors the player may be heading for, the flags first, each in the order of
+    /// the level so an objective keeps its index from frame to frame
+    pub objectives: Vec<Objective>,
+}
+
+/// What kind of place an objective is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectiveKind {
+    /// one of the flags placed in the level
+    Flag,
+    /// a door leading to another level
+    Door,
+}
+
+/// A place in the level the player may be heading for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Objective {
+    /// the center of the flag or door, as displayed
+    pub at: Point,
+    /// whether it's a flag or a door
+    pub kind: ObjectiveKind,
+}
+
+/// A mark of paint the main ball left where it touched something. It's only drawn, nothing
+/// collides with it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PaintMark {
+    /// in the coordinates of the level rather than the screen
+    pub at: Point,
+    /// the color of the paint the ball picked up
+    pub color: [f32; 3],
+    /// from 1 right after the ball picked up the paint to 0 when it ran out
+    pub strength: f32,
+}
+
+/// The paint the main ball picked up
+#[derive(Clone, Copy, Debug)]
+struct BallPaint {
+    color: [f32; 3],
+    remaining: Duration,
+    /// where the ball left its latest mark
+    last_mark: Option<Point>,
+}
+
+/// The static polygons of a level, to show what's behind a door leading to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelPreview {
+    /// in the coordinates of that level
+    pub polygons: Vec<Vec<Point>>,
+    /// the part of that level to show, see [`Level::overview_bounds`]
+    pub bounds: Aabb,
+}
+
+impl LevelPreview {
+    fn new(level: &Level) -> Self {
+        LevelPreview {
+            polygons: level
+                .polygons
+                .iter()
+                .filter(|polygon| polygon.is_static)
+                .map(|polygon| polygon.shape.clone())
+                .collect(),
+            bounds: level.overview_bounds(),
+        }
+    }
+}
+
+/// The level behind the door the ball is near
+#[derive(Clone, Debug)]
+pub struct DoorPreview {
+    /// the box around the door as displayed, the preview is drawn next to it
+    pub door: Aabb,
+    /// `None` if the level can't be loaded
+    pub level: Option<Arc<LevelPreview>>,
+}
+
+/// A level loaded to preview it, kept until its file changes
+struct CachedPreview {
+    modified: Option<SystemTime>,
+    // when `modified` was last read from the file
+    checked: Instant,
+    level: Option<Arc<LevelPreview>>,
+}
+
+/// The editable properties of the entity selected for editing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelectedEntity {
+    /// which entity is selected
+    pub id: EntityId,
+    /// how the entity behaves
+    pub cfg: EntityCfg,
+    /// the center of mass, in level coordinates
+    pub position: Point,
+}
+
+/// A change to the entity selected for editing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntityEdit {
+    /// makes a dynamic entity static, stopping it, or a static one dynamic
+    ToggleStatic,
+    /// switches whether touching the entity restarts the level
+    ToggleDeadly,
+    /// switches whether the entity breaks when hit hard enough
+    ToggleFragile,
+    /// changes the friction coefficient of the entity's surface by the amount, keeping it from
+    /// going negative
+    Friction(f64),
+    /// moves the entity by the vector, in level coordinates
+    Nudge(Vector),
 }
 
+/// The main ball along with its motion, which `geometry::Circle` doesn't carry
+#[derive(Clone, Copy)]
+pub struct Player {
+    /// the ball as it's displayed
+    pub circle: geometry::Circle,
+    /// in units per second
+    pub velocity: Vector,
+    /// in radians per second, positive when spinning counterclockwise
+    pub angular_velocity: f64,
+    /// the hardest hit the ball took since the previous frame, if any
+    pub impact: Option<Impact>,
+}
+
+/// A hard hit the main ball took, turned like the rest of the displayed level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Impact {
+    /// unit vector pointing from the center of the ball towards what it hit
+    pub normal: Vector,
+    /// the impulse which pushed the ball away
+    pub impulse: f64,
+}
+
+impl Player {
+    /// in units per second
+    pub fn speed(&self) -> f64 {
+        self.velocity.norm()
+    }
+}
+
+/// the shapes which still exist, the one at `selected` colored as selected
 fn to_geometry<G>(
     shapes: &mut Vec<WithColor<Weak<RefCell<impl Into<G> + Clone>>>>,
+    selected: Option<*const ()>,
 ) -> Vec<WithColor<G>> {
     let mut geometry_shapes = Vec::with_capacity(shapes.len());
     shapes.retain(|colored_shape| {
         if let Some(shape) = colored_shape.shape.upgrade() {
+            let is_selected = selected == Some(address(colored_shape.shape.as_ptr()));
             geometry_shapes.push(WithColor {
-                color: colored_shape.color,
+                color: if is_selected {
+                    SELECTED_COLOR
+                } else {
+                    colored_shape.color
+                },
                 shape: shape.borrow().clone().into(),
+                // the selection color has to be seen
+                texture: colored_shape.texture.filter(|_| !is_selected),
             });
             true
         } else {
@@ -87,15 +574,57 @@ fn polygon_to_geometry(
         let colored_laser = WithColor {
             shape: laser,
             color,
+            texture: None,
         };
         geometry_shapes.push(WithColor {
             color: colored_laser.color,
             shape: laser.clone().into(),
+            texture: None,
         });
     }
     geometry_shapes
 }
 
+/// true if the shapes touching at `point` barely move relative to each other there and aren't
+/// moving apart along `normal`, which points from the first shape to the second
+fn is_resting(
+    first: &CollisionData,
+    second: &CollisionData,
+    point: Point,
+    normal: Vector,
+    params: &PhysicsParams,
+) -> bool {
+    let relative_velocity =
+        second.velocity_at(second.centroid.to(point)) - first.velocity_at(first.centroid.to(point));
+    // a contact which is about to separate still needs the later iterations to let go of it
+    relative_velocity.dot(normal) <= 0.0
+        && relative_velocity.norm() * params.per_second() < params.contact_freeze_velocity
+}
+
+/// thin pointer used to compare shapes for identity regardless of their type
+fn address<T: ?Sized>(pointer: *const T) -> *const () {
+    pointer as *const ()
+}
+
+/// a shaft and a head pointing from `from` along `vector`
+fn arrow(from: Point, vector: Vector) -> Vec<Polygon> {
+    let to = from + vector;
+    let direction = vector.unit();
+    let shaft_offset = direction.perpendicular() * 0.01;
+    let head_offset = direction.perpendicular() * 0.03;
+    let head_base = to - direction * 0.05;
+
+    vec![
+        Polygon::new(vec![
+            from + shaft_offset,
+            head_base + shaft_offset,
+            head_base - shaft_offset,
+            from - shaft_offset,
+        ]),
+        Polygon::new(vec![head_base + head_offset, to, head_base - head_offset]),
+    ]
+}
+
 #[cfg(test)]
 macro_rules! make_shape {
     ($(($x:expr, $y:expr)),*$(,)?) => {
@@ -108,12 +637,350 @@ macro_rules! make_shape {
 #[cfg(test)]
 pub(crate) use make_shape;
 
-struct EntityCfg {
-    is_erasable: bool,
-    is_bindable: bool,
-    is_static: bool,
-    is_deadly: bool,
-    is_fragile: bool,
+/// Identifies an entity for as long as it exists; ids are never reused
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntityId(u64);
+
+/// Things happening in the simulation which the game may want to react to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// an entity ceased to exist, its id won't be used again
+    EntityRemoved {
+        /// the id of the removed entity
+        id: EntityId,
+        /// why the entity was removed
+        reason: RemovalReason,
+    },
+    /// two entities hit each other hard enough to be heard, stopped touching after such a hit,
+    /// or kept touching for a while after it, see [`PhysicsParams::contact_persist_interval`]
+    Collision(CollisionEvent),
+    /// everything has stopped moving for a while. Emitted once each time the simulation comes to
+    /// rest, static entities and lasers are never considered to be moving
+    Settled,
+    /// the main ball was destroyed or fell out of the level and returned to its starting position
+    BallLost {
+        /// where the main ball was when it was lost
+        at: Point,
+        /// what destroyed it or that it fell out
+        cause: DeathCause,
+    },
+    /// the main ball entered a door, see [`Engine::run_ghost`] for the path it took
+    LevelFinished {
+        /// since the level started or the ball was last lost
+        time: Duration,
+    },
+}
+
+/// What made the main ball lose the level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DeathCause {
+    /// a laser beam hit it
+    Laser,
+    /// it touched a deadly entity
+    Deadly,
+    /// it fell out of the level
+    OutOfBounds,
+}
+
+/// A change in the contact between two entities
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionEvent {
+    /// the colliding entities, the one added earlier first
+    pub entities: [EntityId; 2],
+    /// what each of the entities is made of, in the same order
+    pub materials: [Material; 2],
+    /// the impulse which pushed the entities apart when they began touching, and the strongest
+    /// one since then for the later phases
+    pub impulse: f64,
+    /// which part of the contact the event reports
+    pub phase: ContactPhase,
+}
+
+/// The part of a contact between two entities a [`CollisionEvent`] reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactPhase {
+    /// the entities hit each other hard for the first time since they started touching
+    Began,
+    /// the entities are still touching, e.g. scraping along each other
+    Persisted,
+    /// the entities stopped touching, or one of them ceased to exist
+    Ended,
+}
+
+/// The first entity hit by a ray, as found by [`Engine::raycast`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// the entity hit
+    pub id: EntityId,
+    /// where the ray crosses the outline of the entity
+    pub point: Point,
+    /// from the start of the ray to `point`
+    pub distance: f64,
+}
+
+/// A contact an entity had with another one during the last iteration
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    /// the entity touched
+    pub other: EntityId,
+    /// the deepest point of the entity inside the other one
+    pub point: Point,
+    /// unit vector pointing away from the entity, towards the other one
+    pub normal: Vector,
+    /// negative if the entities weren't overlapping, just closer than the contact margin
+    pub depth: f64,
+    /// zero unless the contact had to be resolved
+    pub impulse: f64,
+}
+
+/// Why an entity was removed from the simulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemovalReason {
+    /// its lifetime has elapsed
+    Expired,
+    /// the player erased it
+    Erased,
+    /// it fell out of the level
+    OutOfBounds,
+    /// it was fragile and got hit too hard
+    Broken,
+    /// the spawner which dropped it made room for a newer entity
+    Replaced,
+    /// it was cut in two, the halves are new entities
+    Sliced,
+    /// a laser burnt a hole in it, the remaining pieces are new entities
+    Carved,
+}
+
+/// The engine already keeps [`PhysicsParams::max_entities`] entities, so it refused to add
+/// another one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("there are already {max_entities} entities, the most the engine keeps")]
+pub struct TooManyEntities {
+    /// the limit which was reached
+    pub max_entities: usize,
+}
+
+/// Tunes how thoroughly the engine resolves contacts, trading accuracy for speed, and how the
+/// game feels, which the presets decide for players
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsParams {
+    /// how many times the shapes touching each other are collided per step, more make stacks
+    /// stiffer. The first iteration collides every pair of shapes, the later ones only those
+    /// which touched in it
+    pub solver_iterations: usize,
+    /// how many times the bindings are enforced per step, each time over an even share of it,
+    /// after the shapes are collided. More keep stiff contraptions, like heavy shapes on short
+    /// rigid bindings, from sagging and shaking. The engine raises it on its own while a
+    /// binding is pulled back harder than [`UNSTABLE_BINDING_SPEED`], see
+    /// [`Engine::binding_substeps`]
+    pub binding_substeps: usize,
+    /// touching shapes moving relative to each other slower than this, in units per second,
+    /// are left out of the later iterations, unless they are moving apart. Zero never freezes
+    /// a contact
+    pub contact_freeze_velocity: f64,
+    /// how much the velocities grow downwards every microsecond, negative as the level's y
+    /// axis points up
+    pub gravity_coefficient: f64,
+    /// scales the velocities into distances moved every microsecond, so it speeds the whole
+    /// simulation up
+    pub movement_coefficient: f64,
+    /// damps entities which keep bouncing or jittering against others without losing any
+    /// energy, until something pushes them harder or they leave the contact
+    pub bounce_guard: bool,
+    /// how often a contact which lasts is reported, `None` to only report when it begins and
+    /// ends
+    pub contact_persist_interval: Option<Duration>,
+    /// a collision is a hard hit, which is heard, squashes the ball and breaks fragile
+    /// entities, if it changes the velocity of the lighter of the entities by more than this,
+    /// in units per second. Gentler ones are just touches, like resting on the floor
+    pub strong_collision_speed: f64,
+    /// how far overlapping shapes are pushed apart in a step at most, as a fraction of the
+    /// bounding radius of the smaller one. Deeper overlaps, like of a shape drawn over another
+    /// one, are undone over several steps and don't bounce, rather than popping the shapes apart
+    pub max_overlap_correction: f64,
+    /// the name of the preset the parameters deciding how the game feels come from, see
+    /// [`PhysicsParams::preset`]
+    pub preset: &'static str,
+    /// how much of their approach shapes hitting each other hard bounce back with, from 0 to 1
+    pub restitution: f64,
+    /// scales the friction of every surface
+    pub friction_scale: f64,
+    /// how much a jump adds to the velocity of the main ball, in the units of
+    /// [`CollisionData::velocity`](shape::CollisionData)
+    pub jump_impulse: f64,
+    /// the fraction of their velocity moving entities lose every second, as if to the air
+    pub linear_damping: f64,
+    /// the most entities the engine keeps, as every step collides each pair of them. Drawing
+    /// more is refused, and spawners, slicing and carving skip what would go over it. It has to
+    /// be at least 1, so the main ball fits
+    pub max_entities: usize,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> Self {
+        PhysicsParams {
+            solver_iterations: 1,
+            binding_substeps: 1,
+            contact_freeze_velocity: 0.005,
+            gravity_coefficient: GRAVITY_COEFFICIENT,
+            movement_coefficient: MOVEMENT_COEFFICIENT,
+            bounce_guard: true,
+            contact_persist_interval: None,
+            strong_collision_speed: STRONG_COLLISION_SPEED,
+            max_overlap_correction: MAX_OVERLAP_CORRECTION,
+            preset: PRESETS[0],
+            restitution: shape::DEFAULT_RESTITUTION,
+            friction_scale: 1.0,
+            jump_impulse: JUMP_IMPULSE,
+            linear_damping: 0.0,
+            max_entities: MAX_ENTITIES,
+        }
+    }
+}
+
+impl PhysicsParams {
+    /// the parameters of a named feel of the game, `None` if there's no preset with the name.
+    /// The classic one is the default, the floaty one has weak gravity and air resistance and
+    /// the snappy one is faster, with strong gravity and grippy surfaces
+    pub fn preset(name: &str) -> Option<Self> {
+        let classic = PhysicsParams::default();
+        match name {
+            "classic" => Some(classic),
+            "floaty" => Some(PhysicsParams {
+                preset: PRESETS[1],
+                gravity_coefficient: classic.gravity_coefficient * 0.5,
+                restitution: 0.4,
+                friction_scale: 0.8,
+                jump_impulse: 0.8,
+                linear_damping: 0.3,
+                ..classic
+            }),
+            "snappy" => Some(PhysicsParams {
+                preset: PRESETS[2],
+                gravity_coefficient: classic.gravity_coefficient * 1.6,
+                movement_coefficient: classic.movement_coefficient * 1.25,
+                restitution: 0.1,
+                friction_scale: 1.2,
+                jump_impulse: 1.3,
+                ..classic
+            }),
+            _ => None,
+        }
+    }
+
+    /// the parameters with the ones deciding how the game feels taken from the preset, keeping
+    /// how contacts are solved and reported
+    fn with_feel_of(self, preset: Self) -> Self {
+        PhysicsParams {
+            preset: preset.preset,
+            gravity_coefficient: preset.gravity_coefficient,
+            movement_coefficient: preset.movement_coefficient,
+            restitution: preset.restitution,
+            friction_scale: preset.friction_scale,
+            jump_impulse: preset.jump_impulse,
+            linear_damping: preset.linear_damping,
+            ..self
+        }
+    }
+
+    /// the parameters with the ones the level sets for itself replaced. A preset the level
+    /// picks replaces the one the game was started with, and the single parameters it sets
+    /// replace the ones of the preset
+    pub fn overridden_by(self, overrides: &PhysicsOverrides) -> Self {
+        let preset = overrides.preset.as_deref().and_then(PhysicsParams::preset);
+        let params = preset.map_or(self, |preset| self.with_feel_of(preset));
+        PhysicsParams {
+            gravity_coefficient: overrides
+                .gravity_coefficient
+                .unwrap_or(params.gravity_coefficient),
+            movement_coefficient: overrides
+                .movement_coefficient
+                .unwrap_or(params.movement_coefficient),
+            strong_collision_speed: overrides
+                .strong_collision_speed
+                .unwrap_or(params.strong_collision_speed),
+            ..params
+        }
+    }
+
+    /// panics if the engine can't run with the parameters, as `max_entities` leaves no room
+    /// for the main ball
+    fn assert_usable(&self) {
+        assert!(
+            self.max_entities > 0,
+            "max_entities has to be at least 1, to fit the main ball"
+        );
+    }
+
+    /// multiplies the parameter by `factor`
+    pub fn tune(&mut self, param: TunableParam, factor: f64) {
+        match param {
+            TunableParam::Gravity => self.gravity_coefficient *= factor,
+            TunableParam::Movement => self.movement_coefficient *= factor,
+        }
+    }
+
+    /// converts the stored velocities, which are per microsecond and scaled when moving shapes,
+    /// to units per second
+    fn per_second(&self) -> f64 {
+        self.movement_coefficient * 1_000_000.0
+    }
+
+    /// [`PhysicsParams::strong_collision_speed`] in the units velocities are stored in
+    fn strong_velocity_change(&self) -> f64 {
+        self.strong_collision_speed / self.per_second()
+    }
+}
+
+/// the parameters of the preset picked with `--physics=<name>`, ignoring other arguments. The
+/// error is the name, if there's no preset with it
+pub fn params_from_args(args: impl IntoIterator<Item = String>) -> Result<PhysicsParams, String> {
+    args.into_iter()
+        .find_map(|arg| {
+            let name = arg.strip_prefix(PRESET_ARG)?;
+            Some(PhysicsParams::preset(name).ok_or_else(|| name.to_string()))
+        })
+        .unwrap_or(Ok(PhysicsParams::default()))
+}
+
+/// A parameter which can be tuned while the game runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TunableParam {
+    /// `PhysicsParams::gravity_coefficient`
+    Gravity,
+    /// `PhysicsParams::movement_coefficient`
+    Movement,
+}
+
+/// How an entity behaves, the default is how the shapes drawn by the player behave
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityCfg {
+    /// the player can erase and slice the entity
+    pub is_erasable: bool,
+    /// the player can pin hinges and rigid bindings to the entity
+    pub is_bindable: bool,
+    /// the entity has infinite mass and never moves
+    pub is_static: bool,
+    /// the level restarts when the main ball touches the entity
+    pub is_deadly: bool,
+    /// the entity is removed after its first hard collision
+    pub is_fragile: bool,
+    /// how hard a fragile entity has to be hit to break, in units per second like
+    /// [`PhysicsParams::strong_collision_speed`], which is used if it isn't set
+    pub break_speed: Option<f64>,
+    /// lasers burn holes through the entity
+    pub is_carvable: bool,
+    /// the entity is removed once it has existed for this long
+    pub lifetime: Option<Duration>,
+    /// decides the sound of collisions
+    pub material: Material,
+    /// the friction coefficient of the entity's surface
+    pub friction: f64,
+    /// collisions never make the entity spin, though they still push it around
+    pub lock_rotation: bool,
+    /// the color the main ball picks up from the entity
+    pub paint: Option<[f32; 3]>,
 }
 
 impl Default for EntityCfg {
@@ -124,32 +991,173 @@ impl Default for EntityCfg {
             is_static: false,
             is_deadly: false,
             is_fragile: false,
+            break_speed: None,
+            is_carvable: false,
+            lifetime: None,
+            material: Material::default(),
+            friction: shape::DEFAULT_FRICTION,
+            lock_rotation: false,
+            paint: None,
+        }
+    }
+}
+
+/// A binding along with the entity it binds to and its load in the last step, which is only
+/// recorded in debug mode, see [`Binding::enforce`]
+type BoundTo = (Binding, Weak<RefCell<dyn Collidable>>, f64);
+
+/// enforces the bindings of an entity with the shape over one of the `substeps` of the time step,
+/// adding what each of them carries to its load in debug mode. Returns how fast the binding pulling back the
+/// hardest changed the velocity of the lighter of its shapes, in units per second
+fn enforce_bindings(
+    shape: &mut dyn Collidable,
+    bindings: &mut [BoundTo],
+    (time_step, substeps): (Duration, u32),
+    params: &PhysicsParams,
+    is_debugging: bool,
+) -> f64 {
+    let mut fastest: f64 = 0.0;
+    for (binding, target, load) in bindings {
+        if let Some(other) = target.upgrade() {
+            let mut other = other.borrow_mut();
+            let impulse = binding.enforce(shape, &mut *other, time_step, substeps);
+            if is_debugging {
+                *load += impulse;
+            }
+            let masses = [
+                shape.collision_data_mut().mass,
+                other.collision_data_mut().mass,
+            ];
+            fastest = fastest.max(velocity_change(impulse, masses) * params.per_second());
         }
     }
+    fastest
+}
+
+/// how many times the bindings are enforced in the next step: twice as many while they pull back
+/// faster than [`UNSTABLE_BINDING_SPEED`], and back down towards the configured number once they
+/// pull back at less than a quarter of it
+fn next_binding_substeps(substeps: usize, configured: usize, binding_speed: f64) -> usize {
+    let configured = configured.max(1);
+    if binding_speed > UNSTABLE_BINDING_SPEED {
+        (substeps * 2).clamp(configured, MAX_BINDING_SUBSTEPS.max(configured))
+    } else if binding_speed < UNSTABLE_BINDING_SPEED / 4.0 {
+        (substeps / 2).max(configured)
+    } else {
+        substeps.max(configured)
+    }
 }
 
 struct Entity {
-    bindings: Vec<(Binding, Weak<RefCell<dyn Collidable>>)>,
+    id: EntityId,
+    bindings: Vec<BoundTo>,
     unbound: Vec<Unbound>,
     is_erasable: bool,
     is_bindable: bool,
     is_static: bool,
     is_deadly: bool,
     is_fragile: bool,
+    break_speed: Option<f64>,
+    is_carvable: bool,
+    lifetime: Option<Duration>,
+    material: Material,
+    friction: f64,
+    lock_rotation: bool,
+    paint: Option<[f32; 3]>,
+    // simulated time since the entity was added, only tracked if it has a lifetime
+    age: Duration,
+    // shown in diagnostics, the tag of a level entity or where it is in the level file
+    debug_name: Option<String>,
+    // drawn by the player, or cut out of such an entity, rather than a part of the level or
+    // dropped by a spawner
+    is_drawn: bool,
+    bounce: BounceTracker,
     shape: Rc<RefCell<dyn Collidable>>,
 }
 
+/// Watches the kinetic energy of an entity for a bounce which never dies down, which the
+/// contact resolution can feed energy into
+#[derive(Clone, Copy, Debug, Default)]
+struct BounceTracker {
+    last_energy: f64,
+    is_rising: bool,
+    // counted over the current window
+    steps: usize,
+    touching_steps: usize,
+    peaks: usize,
+    peak: f64,
+    // the highest energy of the previous window
+    previous_peak: Option<f64>,
+    // the entity is being damped until its energy rises above this
+    damping_below: Option<f64>,
+    // how much of its velocity the entity keeps while it's damped
+    kept_velocity: f64,
+    // the tilt of the world when the entity was caught bouncing
+    angle: f32,
+}
+
+impl BounceTracker {
+    /// records the energy of the entity after a step in a world tilted by `angle`, returning how
+    /// much of its velocity it should keep if it's bouncing
+    fn update(&mut self, energy: f64, is_touching: bool, angle: f32) -> Option<f64> {
+        if let Some(limit) = self.damping_below {
+            if energy <= limit && is_touching && angle == self.angle {
+                self.kept_velocity *= BOUNCE_DAMPING;
+                return Some(self.kept_velocity);
+            }
+            // hit by something, dropped or tilted, so it's moving for a reason
+            *self = BounceTracker::default();
+        }
+
+        if energy < self.last_energy && self.is_rising {
+            self.peaks += 1;
+        }
+        self.is_rising = energy > self.last_energy;
+        self.last_energy = energy;
+        self.peak = self.peak.max(energy);
+        self.steps += 1;
+        self.touching_steps += usize::from(is_touching);
+        if self.steps < BOUNCE_WINDOW {
+            return None;
+        }
+
+        let is_bouncing = self.peaks >= BOUNCE_MIN_PEAKS
+            && self.touching_steps * 2 >= self.steps
+            && self
+                .previous_peak
+                .is_some_and(|previous| self.peak >= previous * BOUNCE_DECAY);
+        *self = BounceTracker {
+            last_energy: energy,
+            is_rising: self.is_rising,
+            previous_peak: Some(self.peak),
+            damping_below: is_bouncing.then_some(self.peak * 2.0),
+            kept_velocity: BOUNCE_DAMPING,
+            angle,
+            ..Default::default()
+        };
+        is_bouncing.then_some(BOUNCE_DAMPING)
+    }
+}
+
 impl Entity {
-    fn new(shape: Rc<RefCell<dyn Collidable>>, entity_type: EntityCfg) -> Self {
+    fn new(id: EntityId, shape: Rc<RefCell<dyn Collidable>>, entity_type: EntityCfg) -> Self {
         let EntityCfg {
             is_erasable,
             is_bindable,
             is_static,
             is_deadly,
             is_fragile,
+            break_speed,
+            is_carvable,
+            lifetime,
+            material,
+            friction,
+            lock_rotation,
+            paint,
         } = entity_type;
 
         Self {
+            id,
             bindings: vec![],
             unbound: vec![],
             shape,
@@ -158,25 +1166,112 @@ impl Entity {
             is_bindable,
             is_deadly,
             is_fragile,
+            break_speed,
+            is_carvable,
+            lifetime,
+            material,
+            friction,
+            lock_rotation,
+            paint,
+            age: Duration::ZERO,
+            debug_name: None,
+            is_drawn: false,
+            bounce: BounceTracker::default(),
+        }
+    }
+
+    /// how diagnostics refer to the entity, by its debug name if it has one
+    fn describe(&self) -> String {
+        match &self.debug_name {
+            Some(name) => format!("entity '{name}'"),
+            None => format!("entity {}", self.id.0),
+        }
+    }
+
+    /// what's wrong with the physical properties of the entity, if anything. Static entities
+    /// have infinite mass and inertia, but never negative or NaN ones, nothing moves
+    /// infinitely fast and no surface has a negative friction coefficient
+    fn invariant_violation(&self) -> Option<String> {
+        let CollisionData {
+            centroid: Point(x, y),
+            mass,
+            inertia,
+            velocity,
+            angular_velocity,
+            friction,
+            ..
+        } = *self.shape.borrow_mut().collision_data_mut();
+        let problem = if mass.is_nan() || mass < 0.0 {
+            "negative mass"
+        } else if inertia.is_nan() || inertia < 0.0 {
+            "negative inertia"
+        } else if !x.is_finite() || !y.is_finite() {
+            "a centroid which is not a finite point"
+        } else if !velocity.0.is_finite() || !velocity.1.is_finite() {
+            "a velocity which is not finite"
+        } else if !angular_velocity.is_finite() {
+            "an angular velocity which is not finite"
+        } else if friction.is_nan() || friction < 0.0 {
+            "a negative friction coefficient"
+        } else {
+            return None;
+        };
+        Some(format!("{} has {problem}", self.describe()))
+    }
+
+    fn cfg(&self) -> EntityCfg {
+        EntityCfg {
+            is_erasable: self.is_erasable,
+            is_bindable: self.is_bindable,
+            is_static: self.is_static,
+            is_deadly: self.is_deadly,
+            is_fragile: self.is_fragile,
+            break_speed: self.break_speed,
+            is_carvable: self.is_carvable,
+            lifetime: self.lifetime,
+            material: self.material,
+            friction: self.friction,
+            lock_rotation: self.lock_rotation,
+            paint: self.paint,
         }
     }
 
-    fn add_rigid(&mut self, at: Point) {
+    fn is_expired(&self) -> bool {
+        self.lifetime.is_some_and(|lifetime| self.age >= lifetime)
+    }
+
+    fn add_rigid(&mut self, at: Point, compliance: f64) {
+        self.unbound
+            .push(Unbound::new_rigid(&*self.shape.borrow(), at, compliance))
+    }
+
+    fn add_hinge(&mut self, at: Point, compliance: f64) {
         self.unbound
-            .push(Unbound::new_rigid(&*self.shape.borrow(), at))
+            .push(Unbound::new_hinge(&*self.shape.borrow(), at, compliance))
     }
 
-    fn add_hinge(&mut self, at: Point) {
+    fn add_weld(&mut self, at: Point, compliance: f64) {
         self.unbound
-            .push(Unbound::new_hinge(&*self.shape.borrow(), at))
+            .push(Unbound::new_weld(&*self.shape.borrow(), at, compliance))
     }
 
-    fn try_bind(&mut self, target: &Rc<RefCell<dyn Collidable>>) {
+    /// drops bindings whose target entity no longer exists
+    fn prune_bindings(&mut self) {
+        self.bindings
+            .retain(|(_, target, _)| target.strong_count() > 0);
+    }
+
+    /// binds the unbound bindings lying inside `target` to it, unless its entity isn't bindable,
+    /// like the main ball
+    fn try_bind(&mut self, target: &Rc<RefCell<dyn Collidable>>, is_target_bindable: bool) {
+        if !is_target_bindable {
+            return;
+        }
         self.unbound.retain(|unbound| {
             if let Some(binding) =
                 Binding::try_bind(&*self.shape.borrow_mut(), *unbound, &*target.borrow_mut())
             {
-                self.bindings.push((binding, Rc::downgrade(target)));
+                self.bindings.push((binding, Rc::downgrade(target), 0.0));
                 false
             } else {
                 true
@@ -185,8 +1280,20 @@ impl Entity {
     }
 }
 
+/// A spawner of the level along with the entities it dropped
+struct ActiveSpawner {
+    spawner: Spawner,
+    interval: Duration,
+    // simulated time since the spawner last dropped an entity
+    since_spawn: Duration,
+    // the entities it dropped which may still exist, oldest first
+    alive: VecDeque<EntityId>,
+}
+
+/// The simulation of a single level
 pub struct Engine {
-    channel: channel::Sender<DisplayMessage>,
+    // `None` if nothing is displayed
+    channel: Option<channel::Sender<DisplayMessage>>,
     // each entity may contain bidings with pointers to entities
     // ocurring later in the vector
     entities: Vec<Entity>,
@@ -200,32 +1307,207 @@ pub struct Engine {
     circles: Vec<WithColor<Weak<RefCell<Circle>>>>,
     lasers: Vec<Laser>,
     doors: Vec<(Polygon, String)>,
+    // the levels behind the doors previewed so far, by the file they're loaded from
+    door_previews: HashMap<String, CachedPreview>,
+    // sent along with every frame, see `DisplayMessage::deaths`
+    deaths: Arc<Vec<(Point, f64)>>,
+    // sent along with every frame, see `DisplayMessage::notes`
+    notes: Arc<Vec<(Point, String)>>,
+    // the path of the ball since the level started or the ball was last lost
+    run: ghost::Recorder,
+    // an earlier run the player races against
+    ghost: Option<Ghost>,
+    // the paint the main ball is leaving marks with, if it has any left
+    paint: Option<BallPaint>,
+    // the marks left since the last frame was sent, see `DisplayMessage::paint_marks`
+    paint_marks: Vec<PaintMark>,
+    // see `DisplayMessage::clear_paint`
+    clear_paint: bool,
     laser_boxes: Vec<Polygon>,
     main_ball_starting_position: Point,
     flags: Vec<Polygon>,
     last_iteration: Instant,
     main_ball: Weak<RefCell<Circle>>,
-    pub angle: f32,
+    angle: f32,
+    target_angle: f32,
     jumps_count: usize,
+    /// the file of the level which should replace this one, set when the ball enters a door
     pub next_level: Option<String>,
     level_stack: Vec<String>,
+    history: VecDeque<Snapshot>,
+    since_snapshot: Duration,
+    is_rewinding: bool,
+    // records the load on every binding, see `DisplayMessage::binding_loads`
+    is_debugging: bool,
+    launch_mode: bool,
+    lasers_push: bool,
+    lasers_harmless: bool,
+    auto_unstick: bool,
+    // how long the main ball has been wedged, see `Engine::is_stuck`
+    wedged_for: Duration,
+    jump_refill: JumpRefillMode,
+    // mass and inertia of the main ball while it's held in place waiting to be launched
+    held_ball: Option<(f64, f64)>,
+    aim: Option<Vector>,
+    next_id: u64,
+    events: VecDeque<GameEvent>,
+    // the carvable entity each laser is burning into and for how long
+    laser_dwells: Vec<Option<(EntityId, Duration)>>,
+    contacts: HashMap<EntityId, Vec<Contact>>,
+    // the pairs of entities touching, to report contacts only when they begin and end
+    contact_pairs: contact_pairs::ContactPairs,
+    // dynamic entities which stop overlapping this area are removed
+    despawn_area: Aabb,
+    // see `DisplayMessage::level_bounds`
+    level_bounds: Aabb,
+    // see `DisplayMessage::camera_bounds`
+    camera_bounds: Aabb,
+    // see `DisplayMessage::level_started`
+    level_started: bool,
+    // see `DisplayMessage::ball_lost`
+    ball_lost: Option<Point>,
+    settle_steps: usize,
+    // consecutive steps during which the kinetic energy was below `SETTLED_ENERGY`
+    still_steps: usize,
+    // kept across reloads, so the colors of drawn shapes repeat on every attempt
+    seed: u64,
+    shape_colors: ShapeColors,
+    // the parameters the engine was built with, before the level and tuning changed them
+    base_params: PhysicsParams,
+    params: PhysicsParams,
+    // how many times pairs of shapes were collided during the last step
+    contact_solves: usize,
+    // see `Engine::binding_substeps`
+    binding_substeps: usize,
+    // the hardest hit the main ball took since the last frame was sent, in level coordinates
+    ball_impact: Option<Impact>,
+    palette: Palette,
+    // the images the level entities are drawn with, see `DisplayMessage::textures`
+    textures: Vec<String>,
+    background: Option<BackgroundSpec>,
+    selected: Option<EntityId>,
+    spawners: Vec<ActiveSpawner>,
+    // seeded like the colors, so the spawners drop entities in the same places on every attempt
+    spawn_rng: StdRng,
+}
+
+/// Creates an [`Engine`], which by default runs headless, without displaying anything
+pub struct EngineBuilder {
+    level: Level,
+    channel: Option<channel::Sender<DisplayMessage>>,
+    settle_steps: usize,
+    seed: u64,
+    shape_colors: ShapeColors,
+    params: PhysicsParams,
+}
+
+impl EngineBuilder {
+    /// an engine simulating the given level, starting from the state it's described in
+    pub fn new(level: Level) -> Self {
+        EngineBuilder {
+            level,
+            channel: None,
+            settle_steps: SETTLE_STEPS,
+            seed: rand::thread_rng().gen(),
+            shape_colors: ShapeColors::default(),
+            params: PhysicsParams::default(),
+        }
+    }
+
+    /// how thoroughly contacts are resolved, see [`PhysicsParams`]. Panics if
+    /// [`PhysicsParams::max_entities`] is 0
+    pub fn params(mut self, params: PhysicsParams) -> Self {
+        params.assert_usable();
+        self.params = params;
+        self
+    }
+
+    /// decides the colors of the shapes drawn by the player, which are random otherwise
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// whether the shapes drawn by the player get colors from a palette or any random ones,
+    /// the default palette if not set
+    pub fn shape_colors(mut self, colors: ShapeColors) -> Self {
+        self.shape_colors = colors;
+        self
+    }
+
+    /// how many consecutive steps everything has to be at rest for before
+    /// [`GameEvent::Settled`] is emitted
+    pub fn settle_after(mut self, steps: usize) -> Self {
+        self.settle_steps = steps;
+        self
+    }
+
+    /// sends a frame to be displayed through the channel after each step, but only if the
+    /// previous one has already been taken
+    pub fn display(mut self, channel: channel::Sender<DisplayMessage>) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// loads the level into a new engine
+    pub fn build(self) -> Engine {
+        Engine::from_builder(self)
+    }
 }
 
 impl Engine {
-    pub fn new(
-        channel: channel::Sender<DisplayMessage>,
-        Level {
+    /// an engine displaying the level through `channel`, see [`EngineBuilder`] for a headless one
+    pub fn new(channel: channel::Sender<DisplayMessage>, level: Level) -> Self {
+        EngineBuilder::new(level).display(channel).build()
+    }
+
+    fn from_builder(
+        EngineBuilder {
+            level,
+            channel,
+            settle_steps,
+            seed,
+            shape_colors,
+            params,
+        }: EngineBuilder,
+    ) -> Self {
+        let despawn_area = level.bounds().expanded(level.despawn_margin);
+        let level_bounds = level.overview_bounds();
+        let camera_bounds = level.camera_bounds.unwrap_or_else(|| level.bounds());
+        // `Level::validate` rejects the pins outside of every entity
+        let pins: Vec<_> = level
+            .pins
+            .iter()
+            .filter_map(|&pin| Some((pin, level.entity_at(pin.at)?)))
+            .collect();
+        let Level {
             initial_ball_position,
+            ball_radius,
             circles,
             polygons,
             lasers,
             doors,
             flags_positions,
-        }: Level,
-    ) -> Self {
+            launch_mode,
+            lasers_push,
+            lasers_harmless,
+            auto_unstick,
+            jump_refill,
+            physics,
+            spawners,
+            bindings,
+            bounds: _,
+            camera_bounds: _,
+            despawn_margin: _,
+            notes,
+            pins: _,
+            background,
+        } = level;
+
         let n_of_circles = circles.len() + 1;
         let n_of_polygons = polygons.len();
         let n_of_laser_boxes = lasers.len();
+        let n_of_lasers = lasers.len();
 
         let doors = doors
             .into_iter()
@@ -252,129 +1534,318 @@ impl Engine {
             last_iteration: Instant::now(),
             main_ball: Weak::new(),
             angle: 0.0,
+            target_angle: 0.0,
             lasers,
             laser_boxes: Vec::with_capacity(n_of_laser_boxes),
             doors,
-            jumps_count: 2,
+            door_previews: HashMap::new(),
+            deaths: Arc::default(),
+            notes: Arc::new(notes),
+            run: ghost::Recorder::default(),
+            ghost: None,
+            paint: None,
+            paint_marks: vec![],
+            // the display may still show the marks of the previous level
+            clear_paint: true,
+            jumps_count: MAX_JUMPS,
             next_level: None,
             level_stack: vec!["level5.ron".to_string()],
+            history: VecDeque::with_capacity(MAX_SNAPSHOTS),
+            since_snapshot: Duration::ZERO,
+            is_rewinding: false,
+            is_debugging: false,
+            launch_mode,
+            lasers_push,
+            lasers_harmless,
+            auto_unstick,
+            wedged_for: Duration::ZERO,
+            jump_refill,
+            held_ball: None,
+            aim: None,
+            next_id: 0,
+            events: VecDeque::new(),
+            laser_dwells: vec![None; n_of_lasers],
+            contacts: HashMap::new(),
+            contact_pairs: contact_pairs::ContactPairs::default(),
+            despawn_area,
+            level_bounds,
+            camera_bounds,
+            level_started: true,
+            ball_lost: None,
+            settle_steps,
+            still_steps: 0,
+            seed,
+            shape_colors,
+            base_params: params,
+            params: params.overridden_by(&physics),
+            contact_solves: 0,
+            binding_substeps: params.binding_substeps.max(1),
+            ball_impact: None,
+            palette: Palette::default(),
+            textures: vec![],
+            background,
+            selected: None,
+            // `Level::validate` rejects the spawners which would never or constantly spawn
+            spawners: spawners
+                .into_iter()
+                .filter(|spawner| spawner.region.len() >= 3)
+                .filter_map(|spawner| {
+                    let interval = Duration::try_from_secs_f64(spawner.interval_seconds).ok()?;
+                    (!interval.is_zero()).then(|| ActiveSpawner {
+                        spawner,
+                        interval,
+                        since_spawn: Duration::ZERO,
+                        alive: VecDeque::new(),
+                    })
+                })
+                .collect(),
+            spawn_rng: StdRng::seed_from_u64(seed),
         };
 
-        let main_ball_weak = engine.add_entity(
-            Circle::new(initial_ball_position, 0.07),
-            EntityCfg {
-                is_bindable: false,
-                is_erasable: false,
-                is_static: false,
-                is_deadly: false,
-                is_fragile: false,
-            },
-        );
+        let main_ball_weak = engine
+            .add_entity(
+                Circle::new(initial_ball_position, ball_radius),
+                EntityCfg {
+                    is_bindable: false,
+                    is_erasable: false,
+                    is_static: false,
+                    is_deadly: false,
+                    is_fragile: false,
+                    break_speed: None,
+                    is_carvable: false,
+                    lifetime: None,
+                    material: Material::Rubber,
+                    friction: shape::DEFAULT_FRICTION,
+                    lock_rotation: false,
+                    paint: None,
+                },
+            )
+            .expect("the main ball always fits");
 
         engine.main_ball = main_ball_weak.clone();
+        engine.entities[0].debug_name = Some("ball".to_string());
 
-        engine.circles.push(main_ball_weak.into());
+        let main_ball_id = engine.main_ball_id();
+        engine.circles.push(WithColor::seeded(
+            main_ball_weak,
+            seed,
+            main_ball_id,
+            &engine.shape_colors,
+        ));
 
-        for entity in polygons {
-            let weak = engine.add_entity(
-                Polygon::new(entity.shape),
-                EntityCfg {
-                    is_bindable: entity.is_bindable,
-                    is_static: entity.is_static,
-                    is_erasable: false,
-                    is_deadly: entity.is_deadly,
-                    is_fragile: entity.is_fragile,
-                },
-            );
+        // where the level entities ended up in `entities`, if they weren't left out
+        let mut polygon_indices = vec![None; polygons.len()];
+        let mut circle_indices = vec![None; circles.len()];
+        // past `PhysicsParams::max_entities`, which may be lower than the default limit
+        // `Level::validate` checks levels against
+        let mut left_out = 0;
+
+        for (i, entity) in polygons.into_iter().enumerate() {
+            // `Level::warnings` reports these
+            if entity.is_collapsed() {
+                continue;
+            }
+            let debug_name = entity.tag.clone().unwrap_or_else(|| format!("polygon {i}"));
+            let cfg = EntityCfg {
+                is_bindable: entity.is_bindable,
+                is_static: entity.is_static,
+                is_erasable: entity.is_erasable,
+                is_deadly: entity.is_deadly,
+                is_fragile: entity.is_fragile,
+                break_speed: entity.break_speed,
+                is_carvable: entity.is_carvable,
+                lifetime: entity.lifetime,
+                material: entity.material(),
+                friction: entity.friction.unwrap_or(shape::DEFAULT_FRICTION),
+                lock_rotation: entity.lock_rotation,
+                paint: entity.paint,
+            };
+            let texture = engine.texture_index(entity.texture);
+            let Ok(weak) = engine.add_entity(Polygon::new(entity.shape), cfg) else {
+                left_out += 1;
+                continue;
+            };
+            polygon_indices[i] = Some(engine.entities.len() - 1);
+            engine.entities.last_mut().unwrap().debug_name = Some(debug_name);
             engine.polygons.push(WithColor {
-                color: if !entity.is_static {
-                    [1.0, 0.85, 0.22]
-                } else if entity.is_deadly {
-                    [1.0, 0.0, 0.0]
-                } else if entity.is_fragile {
-                    [0.7, 0.7, 0.7]
-                } else {
-                    [1.0, 0.85, 0.42]
-                },
+                color: entity_color(&cfg, &engine.palette),
                 shape: weak,
+                texture,
             })
         }
 
-        for entity in circles {
+        for (i, entity) in circles.into_iter().enumerate() {
             let geometry::Circle { center, radius } = entity.shape;
-            let weak = engine.add_entity(
-                Circle::new(center, radius),
-                EntityCfg {
-                    is_bindable: entity.is_bindable,
-                    is_static: entity.is_static,
-                    is_erasable: false,
-                    is_deadly: entity.is_deadly,
-                    is_fragile: entity.is_fragile,
-                },
-            );
+            let debug_name = entity.tag.clone().unwrap_or_else(|| format!("circle {i}"));
+            let cfg = EntityCfg {
+                is_bindable: entity.is_bindable,
+                is_static: entity.is_static,
+                is_erasable: entity.is_erasable,
+                is_deadly: entity.is_deadly,
+                is_fragile: entity.is_fragile,
+                break_speed: entity.break_speed,
+                is_carvable: entity.is_carvable,
+                lifetime: entity.lifetime,
+                material: entity.material(),
+                friction: entity.friction.unwrap_or(shape::DEFAULT_FRICTION),
+                lock_rotation: entity.lock_rotation,
+                paint: entity.paint,
+            };
+            let texture = engine.texture_index(entity.texture);
+            let Ok(weak) = engine.add_entity(Circle::new(center, radius), cfg) else {
+                left_out += 1;
+                continue;
+            };
+            circle_indices[i] = Some(engine.entities.len() - 1);
+            engine.entities.last_mut().unwrap().debug_name = Some(debug_name);
             engine.circles.push(WithColor {
-                color: if !entity.is_static {
-                    [1.0, 0.85, 0.22]
-                } else if entity.is_deadly {
-                    [1.0, 0.0, 0.0]
-                } else if entity.is_fragile {
-                    [0.7, 0.7, 0.7]
-                } else {
-                    [1.0, 0.85, 0.42]
-                },
+                color: entity_color(&cfg, &engine.palette),
                 shape: weak,
+                texture,
             });
         }
 
+        if left_out > 0 {
+            eprintln!(
+                "warning: {left_out} entities of the level are left out, as the engine keeps at \
+                 most {}",
+                engine.params.max_entities
+            );
+        }
+
+        let entity_index = |entity| match entity {
+            EntityRef::Polygon(i) => polygon_indices.get(i).copied().flatten(),
+            EntityRef::Circle(i) => circle_indices.get(i).copied().flatten(),
+        };
+        for LevelBinding {
+            kind,
+            entity_a,
+            entity_b,
+            at,
+        } in bindings
+        {
+            if let (Some(a), Some(b)) = (entity_index(entity_a), entity_index(entity_b)) {
+                engine.bind(a, b, kind, at);
+            }
+        }
+        for (LevelPin { kind, at }, entity) in pins {
+            if let Some(i) = entity_index(entity) {
+                match kind {
+                    BindingKind::Hinge => engine.entities[i].add_hinge(at, 0.0),
+                    BindingKind::Rigid => engine.entities[i].add_rigid(at, 0.0),
+                    BindingKind::Weld => engine.entities[i].add_weld(at, 0.0),
+                }
+            }
+        }
+
+        if engine.launch_mode {
+            engine.hold_ball();
+        }
+
         engine
     }
 
+    /// advances the simulation by the time which has passed since the previous iteration
     pub fn run_iteration(&mut self) {
         let time_step = self.last_iteration.elapsed();
-        let mut is_reset_level = false;
-        let mut is_reset_jumps = false;
         self.last_iteration = Instant::now();
+        self.step(time_step);
+    }
+
+    /// advances the simulation by `time_step`, independently of the wall clock
+    pub fn step(&mut self, time_step: Duration) {
+        if self.is_rewinding {
+            self.since_snapshot += time_step;
+            if self.since_snapshot >= SNAPSHOT_INTERVAL {
+                self.since_snapshot = Duration::ZERO;
+                self.rewind();
+            }
+            if self.is_display_ready() {
+                self.prune_and_send_shapes(vec![]);
+            }
+            return;
+        }
+
+        self.turn_towards_target_angle(time_step);
+        self.canonicalize_order();
+
+        // why the ball is lost in this step, if it is
+        let mut lost_to = None;
+        let mut is_reset_jumps = false;
+        let jump_refill = self.jump_refill;
 
-        // move all shapes, removing ones out of bounds
+        // move all shapes, removing dynamic ones out of bounds and ones past their lifetime
         // don't remove the first one though, as it's the main ball
         let mut is_main_ball = true;
+        let mut removed = vec![];
         self.entities.retain_mut(|entity| {
             let mut shape = entity.shape.borrow_mut();
 
             if !entity.is_static {
-                shape.update_position(time_step, -self.angle as f64);
+                shape.update_position(time_step, -self.angle as f64, &self.params);
+            }
+            if entity.lifetime.is_some() {
+                entity.age += time_step;
             }
 
-            let retain = shape.collision_data_mut().centroid.1 > -5.0 || is_main_ball;
+            let reason = if is_main_ball {
+                None
+            } else if !entity.is_static && !shape.aabb().intersects(&self.despawn_area) {
+                Some(RemovalReason::OutOfBounds)
+            } else if entity.is_expired() {
+                Some(RemovalReason::Expired)
+            } else {
+                None
+            };
             is_main_ball = false;
-            retain
+
+            if let Some(reason) = reason {
+                removed.push((entity.id, reason));
+            }
+            reason.is_none()
         });
+        for (id, reason) in removed {
+            self.emit(GameEvent::EntityRemoved { id, reason });
+        }
+        self.run_spawners(time_step);
 
-        for door in &self.doors {
-            if compute::collision(&door.0, &*self.main_ball.upgrade().unwrap().borrow()).is_some() {
-                self.next_level = Some(door.1.clone());
-                break;
+        let entered = self.doors.iter().find(|door| {
+            compute::collision(&door.0, &*self.main_ball.upgrade().unwrap().borrow()).is_some()
+        });
+        if let Some((_, next_level)) = entered {
+            let next_level = Some(next_level.clone());
+            if self.next_level.is_none() {
+                self.emit(GameEvent::LevelFinished {
+                    time: self.run.elapsed(),
+                });
             }
+            self.next_level = next_level;
         }
 
         //  generate laser polygons
         let mut laser_polygons: Vec<Polygon> = Vec::with_capacity(self.lasers.len());
+        let mut carvable_hits = Vec::with_capacity(self.lasers.len());
         for laser in self.lasers.iter() {
+            let mut carvable_hit = None;
             let start_point = laser.point;
-            let delta = laser.direction * 0.1;
+            let direction = laser.direction().unit();
+            let delta = direction * LASER_STEP;
             let mut end_point = start_point + delta;
             loop {
                 let main_ball_rc = self.main_ball.upgrade().unwrap();
-                if main_ball_rc.borrow().includes(end_point) {
-                    is_reset_level = true;
+                if !self.lasers_harmless && main_ball_rc.borrow().includes(end_point) {
+                    lost_to = Some(DeathCause::Laser);
                     break;
                 }
                 let result = self
                     .entities
                     .iter()
-                    .any(|entity| entity.shape.borrow().includes(end_point));
-                if result {
-                    let offset = laser.direction.perpendicular().unit() * 0.02;
+                    .find(|entity| entity.shape.borrow().includes(end_point));
+                if let Some(entity) = result {
+                    if entity.is_carvable {
+                        carvable_hit = Some((entity.id, end_point));
+                    }
+                    let offset = direction.perpendicular() * 0.02;
                     let start_point_second = start_point + offset;
                     let end_point_second = end_point + offset;
                     laser_polygons.push(Polygon::new(vec![
@@ -387,6 +1858,11 @@ impl Engine {
                 }
                 end_point += delta;
             }
+            carvable_hits.push(carvable_hit);
+        }
+        self.burn_carvable(time_step, carvable_hits);
+        if self.lasers_push {
+            self.push_with_lasers(time_step);
         }
 
         // generate laser boxes
@@ -410,21 +1886,31 @@ impl Engine {
             let data = ball.collision_data_mut();
 
             if data.centroid.0.abs() > 5.0 || data.centroid.1 < -5.0 {
-                is_reset_level = true;
+                lost_to = Some(DeathCause::OutOfBounds);
             }
         }
 
-        // iterate over all pairs of shapes
+        // iterate over all pairs of shapes, in the order described by `canonicalize_order`
         {
             let mut i = 0;
             let mut to_remove = vec![];
+            let mut contacts: HashMap<_, Vec<_>> = HashMap::new();
+            let mut contact_pairs = mem::take(&mut self.contact_pairs);
+            let mut collisions = vec![];
+            let params = self.params;
+            let mut contact_solves = 0;
+            // indices of the pairs still worth colliding again in the later iterations
+            let mut unfrozen = vec![];
+            let mut ball_impact = self.ball_impact;
+            let is_debugging = self.is_debugging;
+            let binding_steps = (time_step, self.binding_substeps as u32);
+            let mut binding_speed: f64 = 0.0;
 
             while let [this, rest @ ..] = &mut self.entities[i..] {
-                let mut shape = this.shape.borrow_mut();
-                if shape.collision_data_mut().inertia < 0.0 || shape.collision_data_mut().mass < 0.0
-                {
-                    println!("Fuck {i}");
+                if let Some(violation) = this.invariant_violation() {
+                    eprintln!("{violation}");
                 }
+                let mut shape = this.shape.borrow_mut();
                 // collide them if they are not bound
                 rest.iter_mut().enumerate().for_each(|(j, other)| {
                     if this.is_static && other.is_static {
@@ -444,21 +1930,95 @@ impl Engine {
                     // });
 
                     // if !is_boud_to_other {
-                    let collision = shape.collide(&mut *other.shape.borrow_mut(), time_step);
-                    if let CollisionType::Strong = collision {
-                        if this.is_fragile {
+                    // resolving one collision usually pushes the shapes fully apart, so a body
+                    // resting on several others would only ever be reported touching one of them
+                    // without the margin
+                    let contact = compute::collision(
+                        &compute::Inflated(&*shape, CONTACT_MARGIN),
+                        &*other.shape.borrow(),
+                    );
+                    let touch = shape.collide(&mut *other.shape.borrow_mut(), time_step, &params);
+                    let masses = [
+                        shape.collision_data_mut().mass,
+                        other.shape.borrow_mut().collision_data_mut().mass,
+                    ];
+                    let collision =
+                        CollisionType::classify(touch, masses, params.strong_velocity_change());
+                    contact_solves += 1;
+                    let is_touching = contact.is_some() || collision.touch().is_some();
+                    if let Some(contact) = contact {
+                        if !is_resting(
+                            shape.collision_data_mut(),
+                            other.shape.borrow_mut().collision_data_mut(),
+                            contact.created_from.0,
+                            contact.point.unit(),
+                            &params,
+                        ) {
+                            unfrozen.push((i, i + j + 1));
+                        }
+                        let normal = contact.point.unit();
+                        let depth = contact.point.norm() - CONTACT_MARGIN;
+                        let point = contact.created_from.0 - normal * CONTACT_MARGIN;
+                        let impulse = collision.touch().map_or(0.0, |touch| touch.impulse);
+                        contacts.entry(this.id).or_default().push(Contact {
+                            other: other.id,
+                            point,
+                            normal,
+                            depth,
+                            impulse,
+                        });
+                        contacts.entry(other.id).or_default().push(Contact {
+                            other: this.id,
+                            point: point - normal * depth,
+                            normal: -normal,
+                            depth,
+                            impulse,
+                        });
+                    }
+                    if is_touching {
+                        let change = contact_pairs.touch(
+                            [this.id, other.id],
+                            [this.material, other.material],
+                            collision.touch().map_or(0.0, |touch| touch.impulse),
+                            matches!(collision, CollisionType::Strong(_)),
+                        );
+                        // landing on anything gives the ball its jumps back, or just touching
+                        // it if they always refill
+                        let is_refilled = change.began || jump_refill == JumpRefillMode::Always;
+                        if is_refilled && i == 0 && !other.is_deadly {
+                            is_reset_jumps = true;
+                        }
+                        if let Some(hit) = change.hit {
+                            collisions.push(GameEvent::Collision(hit));
+                        }
+                    }
+                    // fragile entities break when hit harder than they can take
+                    if let Some(touch) = touch {
+                        let speed = velocity_change(touch.impulse, masses) * params.per_second();
+                        let breaks = |entity: &Entity| {
+                            entity.is_fragile
+                                && speed
+                                    > entity.break_speed.unwrap_or(params.strong_collision_speed)
+                        };
+                        if breaks(this) {
                             to_remove.push(i);
                         }
-                        if other.is_fragile {
+                        if breaks(other) {
                             to_remove.push(i + j + 1);
                         }
                     }
+                    if let CollisionType::Strong(touch) = collision {
+                        if i == 0 && ball_impact.is_none_or(|hit| hit.impulse < touch.impulse) {
+                            ball_impact = Some(Impact {
+                                normal: touch.normal,
+                                impulse: touch.impulse,
+                            });
+                        }
+                    }
 
-                    if let (0, CollisionType::Weak | CollisionType::Strong) = (i, collision) {
+                    if let (0, CollisionType::Weak(_) | CollisionType::Strong(_)) = (i, collision) {
                         if other.is_deadly {
-                            is_reset_level = true;
-                        } else {
-                            is_reset_jumps = true;
+                            lost_to = Some(DeathCause::Deadly);
                         }
                     }
                     //     if let CollisionType::Weak | CollisionType::Strong = collision {
@@ -470,45 +2030,224 @@ impl Engine {
                     // }
                 });
 
-                // enforce binding constraints
-                this.bindings.iter().for_each(|(binding, target)| {
-                    if let Some(other) = target.upgrade() {
-                        binding.enforce(&mut *shape, &mut *other.borrow_mut(), time_step)
-                    }
-                });
+                // enforce binding constraints, the first of the substeps
+                if is_debugging {
+                    this.bindings
+                        .iter_mut()
+                        .for_each(|(_, _, load)| *load = 0.0);
+                }
+                binding_speed = binding_speed.max(enforce_bindings(
+                    &mut *shape,
+                    &mut this.bindings,
+                    binding_steps,
+                    &params,
+                    is_debugging,
+                ));
 
                 i += 1;
             }
+
+            for _ in 1..params.solver_iterations {
+                unfrozen.retain(|&(first, second)| {
+                    let mut first = self.entities[first].shape.borrow_mut();
+                    let mut second = self.entities[second].shape.borrow_mut();
+                    contact_solves += 1;
+                    let Some(touch) = first.collide(&mut *second, time_step, &params) else {
+                        return false;
+                    };
+                    !is_resting(
+                        first.collision_data_mut(),
+                        second.collision_data_mut(),
+                        touch.point,
+                        touch.normal,
+                        &params,
+                    )
+                });
+            }
+            // the rest of the substeps, once the shapes have been collided
+            for _ in 1..self.binding_substeps {
+                for entity in &mut self.entities {
+                    binding_speed = binding_speed.max(enforce_bindings(
+                        &mut *entity.shape.borrow_mut(),
+                        &mut entity.bindings,
+                        binding_steps,
+                        &params,
+                        is_debugging,
+                    ));
+                }
+            }
+            self.binding_substeps = next_binding_substeps(
+                self.binding_substeps,
+                params.binding_substeps,
+                binding_speed,
+            );
+            collisions.extend(
+                contact_pairs
+                    .end_step(time_step, params.contact_persist_interval)
+                    .into_iter()
+                    .map(GameEvent::Collision),
+            );
+            self.contact_pairs = contact_pairs;
+            self.contact_solves = contact_solves;
+            self.contacts = contacts;
+            self.ball_impact = ball_impact;
+            self.update_paint(time_step);
+            for collision in collisions {
+                self.emit(collision);
+            }
             to_remove.dedup();
             to_remove.sort();
             for i in to_remove.into_iter().rev() {
-                let _ = &self.entities.remove(i);
+                let id = self.entities.remove(i).id;
+                self.emit(GameEvent::EntityRemoved {
+                    id,
+                    reason: RemovalReason::Broken,
+                });
             }
         }
 
-        if self.channel.is_empty() {
+        // entities may have been removed out of bounds, by expiring, by breaking or carving
+        self.prune_bindings();
+
+        // before the frame is sent, so the ghost is shown where it was at the end of the step
+        let ball = self.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid;
+        self.run.advance(time_step, ball);
+
+        if self.is_display_ready() {
             self.prune_and_send_shapes(laser_polygons);
+            self.sweep_lasers();
         }
 
-        if is_reset_level {
+        if let Some(cause) = lost_to {
+            let at = self.entities[0]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .centroid;
+            // a level entered through a door is left for the one the door was in
             if self.level_stack.len() > 1 {
                 self.level_stack.pop();
                 self.next_level = Some(self.level_stack.last().unwrap().clone());
             } else {
                 self.reset_level();
             }
+            self.ball_lost = Some(at.rotate(self.angle as f64));
+            self.emit(GameEvent::BallLost { at, cause });
         }
 
         if is_reset_jumps {
             self.reset_jumps();
         }
+        self.update_stuck(time_step);
+
+        if self.params.bounce_guard {
+            self.guard_bounces();
+        }
+
+        if self.total_kinetic_energy() < SETTLED_ENERGY {
+            self.still_steps += 1;
+            if self.still_steps == self.settle_steps {
+                self.emit(GameEvent::Settled);
+            }
+        } else {
+            self.still_steps = 0;
+        }
+
+        self.since_snapshot += time_step;
+        if self.since_snapshot >= SNAPSHOT_INTERVAL {
+            self.since_snapshot = Duration::ZERO;
+            if self.history.len() == MAX_SNAPSHOTS {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.snapshot());
+        }
+    }
+
+    /// freezes the simulation and starts stepping back through the recorded history
+    /// until `set_rewinding(false)` is called
+    pub fn set_rewinding(&mut self, is_rewinding: bool) {
+        if self.is_rewinding != is_rewinding {
+            self.since_snapshot = Duration::ZERO;
+        }
+        self.is_rewinding = is_rewinding;
+    }
+
+    /// shows the ghost running through the level along with the ball, starting over whenever
+    /// the ball does
+    pub fn race(&mut self, ghost: Ghost) {
+        self.ghost = Some(ghost);
+    }
+
+    /// the path the ball took since the level started or it was last lost, as a ghost of the
+    /// level with the name played with the current preset
+    pub fn run_ghost(&self, level: &str) -> Ghost {
+        self.run.ghost(level, self.params.preset)
+    }
+
+    /// the earlier deaths in the level to send along with the frames, see
+    /// [`DisplayMessage::deaths`]
+    pub fn show_deaths(&mut self, deaths: Vec<(Point, f64)>) {
+        self.deaths = Arc::new(deaths);
+    }
+
+    /// leaves a note for the level's designers at the point, given as displayed. Nothing
+    /// collides with it
+    pub fn add_note(&mut self, at: Point, text: String) {
+        let at = at.rotate(-self.angle as f64);
+        Arc::make_mut(&mut self.notes).push((at, text));
+    }
+
+    /// turns debug mode on or off. In it the load every binding carries is recorded each step
+    /// and displayed on its marker
+    pub fn toggle_debugging(&mut self) {
+        self.is_debugging = !self.is_debugging;
+        self.entities
+            .iter_mut()
+            .flat_map(|entity| &mut entity.bindings)
+            .for_each(|(_, _, load)| *load = 0.0);
+    }
+
+    /// restores the most recent snapshot, returning `false` if the history is exhausted
+    pub fn rewind(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// true if the previous frame was taken, or if nothing is displayed at all
+    fn is_display_ready(&self) -> bool {
+        match &self.channel {
+            Some(channel) => channel.is_empty(),
+            None => true,
+        }
     }
 
     fn prune_and_send_shapes(&mut self, laser_polygons: Vec<Polygon>) {
+        let Some(channel) = self.channel.clone() else {
+            self.polygons
+                .retain(|polygon| polygon.shape.strong_count() > 0);
+            self.circles
+                .retain(|circle| circle.shape.strong_count() > 0);
+            self.ball_impact = None;
+            self.paint_marks.clear();
+            return;
+        };
+
         let mut rigid_bindings = Vec::new();
         let mut hinges = Vec::new();
         let mut unbound_rigid_bindings = Vec::new();
         let mut unbound_hinges = Vec::new();
+        let mut welds = Vec::new();
+        let mut unbound_welds = Vec::new();
+        let mut binding_loads = Vec::new();
 
         for Entity {
             bindings,
@@ -517,30 +2256,45 @@ impl Engine {
             ..
         } in &self.entities
         {
-            for (binding, _) in bindings {
-                match binding {
-                    Binding::Hinge { first, .. } => hinges.push(first.on(&*shape.borrow())),
+            for (binding, _, load) in bindings {
+                let (markers, at) = match binding {
+                    Binding::Hinge { first, .. } => (&mut hinges, first.on(&*shape.borrow())),
                     Binding::Rigid {
                         first: (p1, p2), ..
                     } => {
                         let shape = shape.borrow();
-                        rigid_bindings.push((p1.on(&*shape) + p2.on(&*shape)) * 0.5)
+                        (&mut rigid_bindings, (p1.on(&*shape) + p2.on(&*shape)) * 0.5)
                     }
+                    Binding::Weld { first, .. } => (&mut welds, first.on(&*shape.borrow())),
+                };
+                markers.push(at);
+                if self.is_debugging {
+                    binding_loads.push((at.rotate(self.angle as f64), *load));
                 }
             }
 
             for binding in unbound {
                 match binding {
-                    Unbound::Hinge(point) => unbound_hinges.push(point.on(&*shape.borrow())),
-                    Unbound::Rigid(point) => {
+                    Unbound::Hinge(point, _) => unbound_hinges.push(point.on(&*shape.borrow())),
+                    Unbound::Rigid(point, _) => {
                         unbound_rigid_bindings.push(point.on(&*shape.borrow()))
                     }
+                    Unbound::Weld(point, _) => unbound_welds.push(point.on(&*shape.borrow())),
                 }
             }
         }
 
-        let mut polygons: Vec<WithColor<geometry::Polygon>> = to_geometry(&mut self.polygons);
-        let mut circles: Vec<WithColor<geometry::Circle>> = to_geometry(&mut self.circles);
+        let selected = self
+            .selected
+            .and_then(|id| self.entities.iter().find(|entity| entity.id == id))
+            .map(|entity| address(Rc::as_ptr(&entity.shape)));
+        let mut polygons: Vec<WithColor<geometry::Polygon>> =
+            to_geometry(&mut self.polygons, selected);
+        let mut circles: Vec<WithColor<geometry::Circle>> =
+            to_geometry(&mut self.circles, selected);
+        if let (Some(paint), Some(ball)) = (self.paint, circles.first_mut()) {
+            ball.color = paint.color;
+        }
 
         let mut lasers: Vec<WithColor<geometry::Polygon>> =
             Vec::with_capacity(laser_polygons.len());
@@ -583,7 +2337,35 @@ impl Engine {
             circle.shape.rotate(self.angle);
         }
 
-        if let Err(TrySendError::Disconnected(_)) = self.channel.try_send(DisplayMessage {
+        let (mut launch_arrow, trajectory) = match self.aim {
+            Some(aim) if !aim.is_close_enough_to(Vector::ZERO) => {
+                let center = self
+                    .main_ball
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .collision_data_mut()
+                    .centroid;
+                (
+                    polygon_to_geometry(arrow(center, aim), [1.0, 0.4, 0.1]),
+                    self.trajectory(center, aim)
+                        .into_iter()
+                        .map(|point| point.rotate(self.angle as f64))
+                        .collect(),
+                )
+            }
+            _ => (vec![], vec![]),
+        };
+
+        for arrow in &mut launch_arrow {
+            arrow.shape.rotate(self.angle);
+        }
+
+        let player = self.player();
+        let door_preview = self.door_preview();
+        self.ball_impact = None;
+
+        if let Err(TrySendError::Disconnected(_)) = channel.try_send(DisplayMessage {
             polygons,
             circles,
             flags: self.flags.iter().cloned().map(Into::into).collect(),
@@ -591,70 +2373,382 @@ impl Engine {
             hinges,
             unbound_rigid_bindings,
             unbound_hinges,
+            welds: welds
+                .into_iter()
+                .map(|point| point.rotate(self.angle as f64))
+                .collect(),
+            unbound_welds: unbound_welds
+                .into_iter()
+                .map(|point| point.rotate(self.angle as f64))
+                .collect(),
+            binding_loads,
+            is_debugging: self.is_debugging,
             lasers,
             laser_boxes,
             doors,
-            level_idx: self.level_stack.last().unwrap().trim_start_matches("level")[..1]
-                .parse()
-                .unwrap(),
+            launch_arrow,
+            trajectory,
+            player,
+            gravity: self.gravity_direction(),
+            selected: self.selected_entity(),
+            textures: self.textures.clone(),
+            background: self.background.clone(),
+            level_bounds: self.level_bounds,
+            camera_bounds: self.camera_bounds,
+            level_started: mem::take(&mut self.level_started),
+            ball_lost: self.ball_lost.take(),
+            door_preview,
+            deaths: self.deaths.clone(),
+            notes: self.notes.clone(),
+            paint_marks: mem::take(&mut self.paint_marks),
+            clear_paint: mem::take(&mut self.clear_paint),
+            stuck: self.is_stuck(),
+            objectives: self.objectives(),
+            ghost: self
+                .ghost
+                .as_ref()
+                .and_then(|ghost| ghost.position_at(self.run.elapsed()))
+                .map(|position| position.rotate(self.angle as f64)),
+            level_idx: self.level_stack.last().map_or(0, |name| level_index(name)),
         }) {
             panic!("failed to send");
         }
-        for laser in &mut self.lasers {
-            if (Vector::angle_to(laser.inital_direction, laser.direction)).abs() >= laser.range && !laser.is_out {
-                laser.is_out = true;
-                laser.change *= -1.;
-            } else {
-                laser.is_out = false;
-            }
-            // println!("{}", Vector::angle_to(laser.inital_direction, laser.direction));
-            laser.direction = laser.direction.rotate(laser.change);
-        }
     }
 
+    fn sweep_lasers(&mut self) {
+        self.lasers.iter_mut().for_each(Laser::sweep);
+    }
+
+    /// replaces the simulated level, remembering the current one to return to if the ball dies
     pub fn reload_level(self, level: Level, name: String) -> Self {
-        let mut engine = Self::new(self.channel, level);
+        let mut engine = EngineBuilder {
+            level,
+            channel: self.channel,
+            settle_steps: self.settle_steps,
+            seed: self.seed,
+            shape_colors: self.shape_colors,
+            params: self.base_params,
+        }
+        .build();
         let mut stack = self.level_stack;
         stack.push(name);
         engine.level_stack = stack;
+        engine.is_debugging = self.is_debugging;
+        engine.door_previews = self.door_previews;
         engine
     }
 
+    /// binds the unbound bindings of every entity which lie inside the new shape to it,
+    /// unless the shape belongs to an entity which isn't bindable
     pub fn try_bind(&mut self, new_shape: &Rc<RefCell<dyn Collidable>>) {
+        let new_address = address(Rc::as_ptr(new_shape));
+        let is_bindable = self
+            .entities
+            .iter()
+            .find(|entity| address(Rc::as_ptr(&entity.shape)) == new_address)
+            .is_none_or(|entity| entity.is_bindable);
         self.entities
             .iter_mut()
-            .for_each(|shape| shape.try_bind(new_shape))
+            .for_each(|shape| shape.try_bind(new_shape, is_bindable))
+    }
+
+    /// the unit vector gravity pulls along in level coordinates. On screen it always points
+    /// down, as the level is displayed rotated the other way
+    pub fn gravity_direction(&self) -> Vector {
+        Point(0.0, -1.0).rotate(-self.angle as f64)
+    }
+
+    /// the flags and then the doors of the level as displayed
+    fn objectives(&self) -> Vec<Objective> {
+        let centers = |polygons: Vec<Polygon>, kind| {
+            polygons.into_iter().map(move |polygon| Objective {
+                at: geometry::Polygon::from(polygon)
+                    .centroid()
+                    .rotate(self.angle as f64),
+                kind,
+            })
+        };
+        centers(self.flags.clone(), ObjectiveKind::Flag)
+            .chain(centers(
+                self.doors.iter().map(|(door, _)| door.clone()).collect(),
+                ObjectiveKind::Door,
+            ))
+            .collect()
+    }
+
+    /// the level behind the door the ball is near, read from its file the first time and then
+    /// only again once the file has changed, which is checked every `DOOR_PREVIEW_RECHECK`
+    fn door_preview(&mut self) -> Option<DoorPreview> {
+        let ball: geometry::Circle = self.main_ball.upgrade()?.borrow().clone().into();
+        let (door, path) = self.doors.iter().find(|(door, _)| {
+            Aabb::around(door.outline()).is_some_and(|bounds| {
+                bounds.distance_to(ball.center) - ball.radius < DOOR_PREVIEW_DISTANCE
+            })
+        })?;
+
+        let is_stale = self
+            .door_previews
+            .get(path)
+            .is_none_or(|cached| cached.checked.elapsed() >= DOOR_PREVIEW_RECHECK);
+        if is_stale {
+            let file = assets::level(path);
+            let modified = fs::metadata(&file)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            match self.door_previews.get_mut(path) {
+                Some(cached) if cached.modified == modified => cached.checked = Instant::now(),
+                _ => {
+                    let level = match Level::load_from_file(&file) {
+                        Ok(level) => Some(Arc::new(LevelPreview::new(&level))),
+                        Err(err) => {
+                            eprintln!("couldn't preview {path}: {err}");
+                            None
+                        }
+                    };
+                    self.door_previews.insert(
+                        path.clone(),
+                        CachedPreview {
+                            modified,
+                            checked: Instant::now(),
+                            level,
+                        },
+                    );
+                }
+            }
+        }
+        let level = self.door_previews[path].level.clone();
+        Some(DoorPreview {
+            door: Aabb::around(
+                door.outline()
+                    .into_iter()
+                    .map(|point| point.rotate(self.angle as f64)),
+            )?,
+            level,
+        })
+    }
+
+    /// the main ball as it is displayed, i.e. rotated along with the rest of the level
+    fn player(&self) -> Player {
+        let ball = self.main_ball.upgrade().unwrap();
+        let mut ball = ball.borrow_mut();
+        let CollisionData {
+            velocity,
+            angular_velocity,
+            ..
+        } = *ball.collision_data_mut();
+        let mut circle: geometry::Circle = ball.clone().into();
+        circle.rotate(self.angle);
+
+        Player {
+            circle,
+            velocity: velocity.rotate(self.angle as f64) * self.params.per_second(),
+            angular_velocity: angular_velocity * self.params.per_second(),
+            impact: self.ball_impact.map(|impact| Impact {
+                normal: impact.normal.rotate(self.angle as f64),
+                ..impact
+            }),
+        }
+    }
+
+    /// Sequential impulses make the result of a step depend on the order in which pairs are
+    /// resolved, so that order is part of the determinism contract: every entity is paired with
+    /// each one after it, lower index first, and the entities are sorted by id, i.e. by when they
+    /// were added. A broad phase finding the pairs in parallel must still apply the impulses
+    /// serially, in this order.
+    ///
+    /// Entities are only ever pushed with a fresh id or removed, so this is normally a linear
+    /// check rather than an actual sort
+    fn canonicalize_order(&mut self) {
+        self.entities.sort_by_key(|entity| entity.id);
+    }
+
+    /// whether `extra` more entities fit in [`PhysicsParams::max_entities`]
+    fn has_room_for(&self, extra: usize) -> bool {
+        self.entities.len() + extra <= self.params.max_entities
     }
 
     fn add_entity<S: Collidable + 'static>(
         &mut self,
         mut shape: S,
         entity_cfg: EntityCfg,
-    ) -> Weak<RefCell<S>> {
+    ) -> Result<Weak<RefCell<S>>, TooManyEntities> {
+        if !self.has_room_for(1) {
+            return Err(TooManyEntities {
+                max_entities: self.params.max_entities,
+            });
+        }
         if entity_cfg.is_static {
             shape.collision_data_mut().mass = f64::INFINITY;
             shape.collision_data_mut().inertia = f64::INFINITY;
         }
+        shape.collision_data_mut().friction = entity_cfg.friction;
+        shape.collision_data_mut().lock_rotation = entity_cfg.lock_rotation;
 
         let shape = Rc::new(RefCell::new(shape));
         let shape_weak = Rc::downgrade(&shape);
         let shape_dyn: Rc<RefCell<dyn Collidable>> = shape;
 
+        let id = self.next_id();
+        self.entities
+            .push(Entity::new(id, shape_dyn.clone(), entity_cfg));
         self.try_bind(&shape_dyn);
-        self.entities.push(Entity::new(shape_dyn, entity_cfg));
-        shape_weak
+        Ok(shape_weak)
+    }
+
+    /// adds a circle drawn by the player
+    pub fn add_circle(&mut self, circle: Circle) -> Result<(), TooManyEntities> {
+        self.spawn_circle(circle, EntityCfg::default())?;
+        self.entities.last_mut().unwrap().is_drawn = true;
+        Ok(())
+    }
+
+    /// adds a polygon drawn by the player, unless it's left with fewer than 3 vertices once the
+    /// repeated ones are merged
+    pub fn add_polygon(&mut self, polygon: Polygon) -> Result<(), TooManyEntities> {
+        if polygon.outline().len() < 3 {
+            return Ok(());
+        }
+        self.spawn_polygon(polygon, EntityCfg::default())?;
+        self.entities.last_mut().unwrap().is_drawn = true;
+        Ok(())
+    }
+
+    /// adds a circle behaving as configured, in a color picked by the seed unless it's static,
+    /// deadly or fragile
+    pub fn spawn_circle(
+        &mut self,
+        circle: Circle,
+        cfg: EntityCfg,
+    ) -> Result<EntityId, TooManyEntities> {
+        let weak_circle = self.add_entity(circle, cfg)?;
+        // `add_entity` pushes the new entity last
+        let id = self.entities.last().unwrap().id;
+        let circle = self.colored(weak_circle, cfg, id);
+        self.circles.push(circle);
+        Ok(id)
+    }
+
+    /// adds a polygon behaving as configured, in a color picked by the seed unless it's static,
+    /// deadly or fragile
+    pub fn spawn_polygon(
+        &mut self,
+        polygon: Polygon,
+        cfg: EntityCfg,
+    ) -> Result<EntityId, TooManyEntities> {
+        let weak_polygon = self.add_entity(polygon, cfg)?;
+        let id = self.entities.last().unwrap().id;
+        let polygon = self.colored(weak_polygon, cfg, id);
+        self.polygons.push(polygon);
+        Ok(id)
+    }
+
+    /// shapes behaving like the ones drawn by the player get random colors, the others show
+    /// how they behave
+    fn colored<S>(&self, shape: S, cfg: EntityCfg, id: EntityId) -> WithColor<S> {
+        if cfg.is_static || cfg.is_deadly || cfg.is_fragile {
+            WithColor {
+                color: entity_color(&cfg, &self.palette),
+                shape,
+                texture: None,
+            }
+        } else {
+            WithColor::seeded(shape, self.seed, id, &self.shape_colors)
+        }
+    }
+
+    /// drops an entity from every spawner whose interval has passed, unless there's no room left
+    /// for it, then removes the oldest entities of the ones which dropped more than they may keep
+    fn run_spawners(&mut self, time_step: Duration) {
+        let mut replaced = vec![];
+        for i in 0..self.spawners.len() {
+            let interval = self.spawners[i].interval;
+            self.spawners[i].since_spawn += time_step;
+            while self.spawners[i].since_spawn >= interval {
+                self.spawners[i].since_spawn -= interval;
+                if let Ok(id) = self.spawn_from(i) {
+                    self.spawners[i].alive.push_back(id);
+                }
+            }
+
+            let entities = &self.entities;
+            let active = &mut self.spawners[i];
+            active
+                .alive
+                .retain(|id| entities.iter().any(|entity| entity.id == *id));
+            while active.alive.len() > active.spawner.max_alive {
+                replaced.extend(active.alive.pop_front());
+            }
+        }
+
+        if replaced.is_empty() {
+            return;
+        }
+        self.entities
+            .retain(|entity| !replaced.contains(&entity.id));
+        self.prune_bindings();
+        for id in replaced {
+            self.emit(GameEvent::EntityRemoved {
+                id,
+                reason: RemovalReason::Replaced,
+            });
+        }
     }
 
-    pub fn add_circle(&mut self, circle: Circle) {
-        let weak_circle = self.add_entity(circle, EntityCfg::default());
-        self.circles.push(weak_circle.into());
+    /// adds an entity at a random point of the region of the spawner at index `i`
+    fn spawn_from(&mut self, i: usize) -> Result<EntityId, TooManyEntities> {
+        let spawner = &self.spawners[i].spawner;
+        let position = random_point_in(&spawner.region, &mut self.spawn_rng);
+        let velocity = spawner.initial_velocity / self.params.per_second();
+        let cfg = EntityCfg {
+            lifetime: spawner.lifetime,
+            ..Default::default()
+        };
+
+        let id = match spawner.shape {
+            SpawnShape::Circle { radius } => {
+                self.spawn_circle(Circle::new(position, radius), cfg)?
+            }
+            SpawnShape::Box { w, h } => {
+                let (x, y) = (w / 2.0, h / 2.0);
+                let vertices = vec![
+                    position + Point(-x, -y),
+                    position + Point(x, -y),
+                    position + Point(x, y),
+                    position + Point(-x, y),
+                ];
+                self.spawn_polygon(Polygon::new(vertices), cfg)?
+            }
+        };
+
+        let entity = self.entities.last_mut().unwrap();
+        entity.debug_name = Some(format!("spawner {i}"));
+        entity.shape.borrow_mut().collision_data_mut().velocity = velocity;
+        Ok(id)
     }
 
-    pub fn add_polygon(&mut self, polygon: Polygon) {
-        let weak_polygon = self.add_entity(polygon, EntityCfg::default());
-        self.polygons.push(weak_polygon.into());
+    /// adds a static polygon which can't be erased, as if it was a part of the level.
+    /// The vertices are given as displayed, i.e. in the tilted coordinates
+    pub fn add_level_shape(&mut self, vertices: Vec<Point>) -> Result<(), TooManyEntities> {
+        let vertices = vertices
+            .into_iter()
+            .map(|vertex| vertex.rotate(-self.angle as f64))
+            .collect();
+        let cfg = EntityCfg {
+            is_erasable: false,
+            is_bindable: false,
+            is_static: true,
+            ..Default::default()
+        };
+        let weak_polygon = self.add_entity(Polygon::new(vertices), cfg)?;
+        self.polygons.push(WithColor {
+            color: entity_color(&cfg, &self.palette),
+            shape: weak_polygon,
+            texture: None,
+        });
+        Ok(())
     }
 
+    /// removes the entity containing `point`, if there is one and it's erasable
     pub fn erase_at(&mut self, point: Point) {
         if let Some(i) = self
             .entities
@@ -662,149 +2756,3905 @@ impl Engine {
             .position(|shape| shape.shape.borrow().includes(point))
         {
             if self.entities[i].is_erasable {
-                self.entities.remove(i);
+                let id = self.entities.remove(i).id;
+                self.prune_bindings();
+                self.emit(GameEvent::EntityRemoved {
+                    id,
+                    reason: RemovalReason::Erased,
+                });
             }
         }
     }
 
-    pub fn add_hinge(&mut self, point: Point) {
-        if let Some(i) = self
+    /// makes the hinge whose marker is nearest to `point`, given as displayed, stiffer by the
+    /// number of steps, or softer if it's negative, returning its new compliance. Does nothing
+    /// if there's no hinge close enough
+    pub fn tune_hinge_at(&mut self, point: Point, steps: f64) -> Option<f64> {
+        let point = point.rotate(-self.angle as f64);
+        let (_, compliance) = self
+            .entities
+            .iter_mut()
+            .flat_map(|entity| {
+                let shape = entity.shape.clone();
+                entity
+                    .bindings
+                    .iter_mut()
+                    .filter(|(binding, _, _)| matches!(binding, Binding::Hinge { .. }))
+                    .map(move |(binding, _, _)| {
+                        let distance = binding.first_anchor(&*shape.borrow()).to(point).norm();
+                        (distance, binding.compliance_mut())
+                    })
+            })
+            .filter(|(distance, _)| *distance <= HINGE_PICK_RADIUS)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))?;
+
+        let tuned = compliance.max(MIN_HINGE_COMPLIANCE) / HINGE_COMPLIANCE_STEP.powf(steps);
+        *compliance = if tuned < MIN_HINGE_COMPLIANCE {
+            0.0
+        } else {
+            tuned.min(MAX_HINGE_COMPLIANCE)
+        };
+        Some(*compliance)
+    }
+
+    /// removes the erasable entity containing `point`, given as displayed, along with every
+    /// erasable entity bound to it directly or through others, so a whole contraption goes at
+    /// once. Entities which can't be erased, like the level, are kept and the search doesn't go
+    /// past them
+    pub fn erase_connected(&mut self, point: Point) {
+        let point = point.rotate(-self.angle as f64);
+        let Some(start) = self
             .entities
             .iter()
-            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
-        {
-            self.entities[i].add_hinge(point);
+            .position(|entity| entity.shape.borrow().includes(point))
+            .filter(|&i| self.entities[i].is_erasable)
+        else {
+            return;
+        };
+
+        // bindings only point to later entities, so the links are followed both ways
+        let index_of: HashMap<_, _> = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (address(Rc::as_ptr(&entity.shape)), i))
+            .collect();
+        let mut linked = vec![vec![]; self.entities.len()];
+        for (i, entity) in self.entities.iter().enumerate() {
+            for (_, target, _) in &entity.bindings {
+                if let Some(&j) = index_of.get(&address(target.as_ptr())) {
+                    linked[i].push(j);
+                    linked[j].push(i);
+                }
+            }
+        }
+
+        let mut is_erased = vec![false; self.entities.len()];
+        is_erased[start] = true;
+        let mut to_visit = vec![start];
+        while let Some(i) = to_visit.pop() {
+            for &j in &linked[i] {
+                if !is_erased[j] && self.entities[j].is_erasable {
+                    is_erased[j] = true;
+                    to_visit.push(j);
+                }
+            }
+        }
+
+        let mut erased = vec![];
+        let mut is_erased = is_erased.into_iter();
+        self.entities.retain(|entity| {
+            let is_erased = is_erased.next().unwrap();
+            if is_erased {
+                erased.push(entity.id);
+            }
+            !is_erased
+        });
+
+        self.prune_bindings();
+        for id in erased {
+            self.emit(GameEvent::EntityRemoved {
+                id,
+                reason: RemovalReason::Erased,
+            });
         }
     }
 
-    pub fn add_rigid(&mut self, point: Point) {
-        if let Some(i) = self
+    /// selects the entity containing `point`, given as displayed, for editing, or clears the
+    /// selection if there is none
+    pub fn select_at(&mut self, point: Point) {
+        let point = point.rotate(-self.angle as f64);
+        self.selected = self
             .entities
             .iter()
-            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
+            .find(|entity| entity.shape.borrow().includes(point))
+            .map(|entity| entity.id);
+    }
+
+    /// clears the selection made with `select_at`
+    pub fn deselect(&mut self) {
+        self.selected = None;
+    }
+
+    /// the entity selected for editing, `None` if there is none or it doesn't exist anymore
+    pub fn selected_entity(&self) -> Option<SelectedEntity> {
+        let id = self.selected?;
+        let entity = self.entities.iter().find(|entity| entity.id == id)?;
+        let position = entity.shape.borrow_mut().collision_data_mut().centroid;
+        Some(SelectedEntity {
+            id,
+            cfg: entity.cfg(),
+            position,
+        })
+    }
+
+    /// changes the selected entity, if there is one. The main ball can only be moved
+    pub fn edit_selected(&mut self, edit: EntityEdit) {
+        let Some(id) = self.selected else {
+            return;
+        };
+        let Some(i) = self.entities.iter().position(|entity| entity.id == id) else {
+            return;
+        };
+        if i == 0 && !matches!(edit, EntityEdit::Nudge(_)) {
+            return;
+        }
+
+        let entity = &mut self.entities[i];
+        match edit {
+            EntityEdit::ToggleStatic => {
+                let is_static = !entity.is_static;
+                self.set_static(id, is_static);
+                return;
+            }
+            EntityEdit::ToggleDeadly => entity.is_deadly = !entity.is_deadly,
+            EntityEdit::ToggleFragile => entity.is_fragile = !entity.is_fragile,
+            EntityEdit::Friction(change) => {
+                entity.friction = (entity.friction + change).max(0.0);
+                entity.shape.borrow_mut().collision_data_mut().friction = entity.friction;
+            }
+            EntityEdit::Nudge(offset) => entity.shape.borrow_mut().translate(offset),
+        }
+        self.recolor(i);
+    }
+
+    /// makes the entity static or dynamic. A static entity stops and gets an infinite mass, a
+    /// dynamic one gets the mass and inertia of its current shape, starting at rest either way.
+    /// Does nothing if the entity doesn't exist or already is as asked
+    pub fn set_static(&mut self, id: EntityId, is_static: bool) {
+        let Some(i) = self.entities.iter().position(|entity| entity.id == id) else {
+            return;
+        };
+        let entity = &mut self.entities[i];
+        if entity.is_static == is_static {
+            return;
+        }
+
+        entity.is_static = is_static;
         {
-            self.entities[i].add_rigid(point);
+            let mut shape = entity.shape.borrow_mut();
+            let (mass, inertia) = if is_static {
+                (f64::INFINITY, f64::INFINITY)
+            } else {
+                shape.dynamic_mass_and_inertia()
+            };
+            let data = shape.collision_data_mut();
+            data.mass = mass;
+            data.inertia = inertia;
+            data.velocity = Vector::ZERO;
+            data.angular_velocity = 0.0;
         }
+        self.recolor(i);
     }
 
-    pub fn jump(&mut self) {
-        if self.jumps_count != 0 {
-            let main_ball_mut = self.main_ball.upgrade().unwrap();
-            main_ball_mut.borrow_mut().collision_data_mut().velocity +=
-                Point(0.0, 1.0).rotate(-self.angle as f64);
-            self.jumps_count -= 1;
+    /// updates the displayed color of the entity at index `i` after its flags changed
+    fn recolor(&mut self, i: usize) {
+        let entity = &self.entities[i];
+        let color = self.colored((), entity.cfg(), entity.id).color;
+        let shape = address(Rc::as_ptr(&entity.shape));
+        for colored in self
+            .polygons
+            .iter_mut()
+            .filter(|polygon| address(polygon.shape.as_ptr()) == shape)
+        {
+            colored.color = color;
+        }
+        for colored in self
+            .circles
+            .iter_mut()
+            .filter(|circle| address(circle.shape.as_ptr()) == shape)
+        {
+            colored.color = color;
         }
     }
 
-    pub fn reset_level(&self) {
-        let mut ball = self.entities[0].shape.borrow_mut();
-        let data = ball.collision_data_mut();
+    /// erases every entity the player has drawn at once, along with the pieces cut out of them,
+    /// leaving the level and what its spawners dropped as they are
+    pub fn clear_drawn(&mut self) {
+        let mut erased = vec![];
+        self.entities.retain(|entity| {
+            let is_drawn = entity.is_drawn;
+            if is_drawn {
+                erased.push(entity.id);
+            }
+            !is_drawn
+        });
 
-        data.centroid = self.main_ball_starting_position;
-        data.angular_velocity = 0.0;
-        data.velocity = Vector::ZERO;
+        self.prune_bindings();
+        for id in erased {
+            self.emit(GameEvent::EntityRemoved {
+                id,
+                reason: RemovalReason::Erased,
+            });
+        }
     }
 
-    pub fn reset_jumps(&mut self) {
-        self.jumps_count = 2;
-    }
-}
-
-// #[cfg(test)]
-// mod test {
-//     use crate::levels;
-
-//     use super::*;
-
-//     fn init_engine() -> Engine {
-//         Engine::new(
-//             channel::bounded(1).0,
-//             Level {
-//                 initial_ball_position: Point(0.0, 0.5),
-//                 polygons: vec![
-//                     levels::Entity {
-//                         is_bindable: false,
-//                         is_static: true,
-//                         shape: vec![
-//                             Point(0.0, 0.0),
-//                             Point(0.5, 0.0),
-//                             Point(0.5, 0.5),
-//                             Point(0.0, 0.5),
-//                         ],
-//                     },
-//                     levels::Entity {
-//                         is_bindable: false,
-//                         is_static: true,
-//                         shape: vec![
-//                             Point(0.0, 1.0),
-//                             Point(0.5, 1.0),
-//                             Point(0.5, 1.5),
-//                             Point(0.0, 1.5),
-//                         ],
-//                     },
-//                 ],
-//                 circles: vec![levels::Entity {
-//                     is_bindable: false,
-//                     is_static: true,
-//                     shape: geometry::Circle {
-//                         center: Point(0.0, 0.9),
-//                         radius: 0.05,
-//                     },
-//                 }],
-//                 flags_positions: vec![Point(-0.9, 0.0)],
-//             },
-//         )
-//     }
-
-//     #[test]
-//     fn test_engine_creation() {
-//         let engine = init_engine();
-
-//         assert!(engine.circles.len() == 2);
-//         assert!(engine.polygons.len() == 2);
-//         assert!(engine.entities.len() == 4);
-//         assert!(
-//             engine.polygons[1]
-//                 .shape
-//                 .upgrade()
-//                 .unwrap()
-//                 .borrow_mut()
-//                 .collision_data_mut()
-//                 .mass
-//                 == f64::INFINITY
-//         );
-//     }
-
-//     #[test]
-//     fn test_auto_bind() {
-//         let mut engine = init_engine();
-
-//         engine.add_polygon(make_shape! {
-//             (-1.0, -1.0),
-//             (-0.9, -1.0),
-//             (-0.9, -0.9),
-//             (-1.0, -0.9),
-//         });
-
-//         engine.add_rigid(Point(-0.91, -0.91));
-
-//         assert!(engine.entities.last().unwrap().unbound.len() == 1);
-
-//         engine.add_polygon(make_shape! {
-//             (-0.92, -0.92),
-//             (-0.85, -0.92),
-//             (-0.85, -0.85),
-//             (-0.92, -0.85),
-//         });
-
-//         let [.., first, second] = &engine.entities[..] else {
-//             panic!("not enough enitites");
-//         };
-
-//         assert!(first.unbound.is_empty());
-//         assert!(std::ptr::eq(
-//             first.bindings[0].1.as_ptr() as *const c_void,
-//             &*second.shape as *const _ as *const c_void
-//         ));
-//     }
-// }
+    /// cuts every erasable dynamic entity fully crossed by the segment between
+    /// `from` and `to` in two, as long as there's room for the extra halves. Circles are
+    /// approximated with polygons before being cut
+    pub fn slice(&mut self, from: Point, to: Point) {
+        if from.is_close_enough_to(to) {
+            return;
+        }
+
+        for i in (0..self.entities.len()).rev() {
+            let entity = &self.entities[i];
+            if !entity.is_erasable || entity.is_static || !self.has_room_for(1) {
+                continue;
+            }
+
+            let outline = entity.shape.borrow().outline();
+            if let Some(halves) = compute::split(&outline, from, to) {
+                self.replace_with_halves(i, halves, -from.to(to).perpendicular().unit());
+            }
+        }
+    }
+
+    /// replaces the `i`-th entity with its two halves moving apart
+    fn replace_with_halves(
+        &mut self,
+        i: usize,
+        (left, right): (Vec<Point>, Vec<Point>),
+        towards_left: Vector,
+    ) {
+        let data = self.entities[i]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .clone();
+        let halves = [(left, 1.0), (right, -1.0)].map(|(vertices, side)| {
+            let mut half = Polygon::new(vertices);
+            let half_data = half.collision_data_mut();
+            let offset = data.centroid.to(half_data.centroid);
+            half_data.velocity = data.velocity - (offset * data.angular_velocity).perpendicular()
+                + towards_left * (side * SLICE_SEPARATION_SPEED);
+            half_data.angular_velocity = data.angular_velocity;
+            half
+        });
+
+        self.replace_with_pieces(i, halves.into(), RemovalReason::Sliced);
+    }
+
+    /// replaces the `i`-th entity with polygons covering parts of it, moving its bindings
+    /// to whichever piece contains the point they are attached at
+    fn replace_with_pieces(&mut self, i: usize, pieces: Vec<Polygon>, reason: RemovalReason) {
+        let original = self.entities.remove(i);
+        let original_address = address(Rc::as_ptr(&original.shape));
+        let cfg = original.cfg();
+        let (color, texture) = self
+            .polygons
+            .iter()
+            .map(|colored| {
+                (
+                    colored.color,
+                    colored.texture,
+                    address(colored.shape.as_ptr()),
+                )
+            })
+            .chain(self.circles.iter().map(|colored| {
+                (
+                    colored.color,
+                    colored.texture,
+                    address(colored.shape.as_ptr()),
+                )
+            }))
+            .find_map(|(color, texture, shape)| {
+                (shape == original_address).then_some((color, texture))
+            })
+            .unwrap_or_else(|| (entity_color(&cfg, &self.palette), None));
+
+        let pieces: Vec<_> = pieces
+            .into_iter()
+            .map(|mut piece| {
+                if cfg.is_static {
+                    piece.collision_data_mut().mass = f64::INFINITY;
+                    piece.collision_data_mut().inertia = f64::INFINITY;
+                }
+                Rc::new(RefCell::new(piece))
+            })
+            .collect();
+
+        let original_shape = original.shape.borrow();
+        let distance = |piece: usize, point: Point| {
+            let centroid = pieces[piece].borrow_mut().collision_data_mut().centroid;
+            centroid.to(point).norm()
+        };
+        let piece_containing = |point: Point| {
+            pieces
+                .iter()
+                .position(|piece| piece.borrow().includes(point))
+                .or_else(|| {
+                    (0..pieces.len()).min_by(|&first, &second| {
+                        distance(first, point).total_cmp(&distance(second, point))
+                    })
+                })
+        };
+
+        let mut new_entities: Vec<_> = pieces
+            .iter()
+            .map(|piece| {
+                let mut entity = Entity::new(self.next_id(), piece.clone(), cfg);
+                entity.age = original.age;
+                entity.debug_name = original.debug_name.clone();
+                entity.is_drawn = original.is_drawn;
+                entity
+            })
+            .collect();
+
+        for (binding, target, load) in &original.bindings {
+            if let Some(piece) = piece_containing(binding.first_anchor(&*original_shape)) {
+                new_entities[piece].bindings.push((
+                    binding.rebase_first(&*original_shape, &*pieces[piece].borrow()),
+                    target.clone(),
+                    *load,
+                ));
+            }
+        }
+
+        for unbound in &original.unbound {
+            if let Some(piece) = piece_containing(unbound.anchor(&*original_shape)) {
+                new_entities[piece]
+                    .unbound
+                    .push(unbound.rebase(&*original_shape, &*pieces[piece].borrow()));
+            }
+        }
+
+        for (binding, target, _) in self
+            .entities
+            .iter_mut()
+            .flat_map(|entity| &mut entity.bindings)
+            .filter(|(_, target, _)| address(target.as_ptr()) == original_address)
+        {
+            if let Some(piece) = piece_containing(binding.second_anchor(&*original_shape)) {
+                *binding = binding.rebase_second(&*original_shape, &*pieces[piece].borrow());
+                *target = Rc::downgrade(&pieces[piece]) as Weak<RefCell<dyn Collidable>>;
+            }
+        }
+
+        for piece in &pieces {
+            self.polygons.push(WithColor {
+                color,
+                shape: Rc::downgrade(piece),
+                texture,
+            });
+        }
+
+        self.entities.splice(i..i, new_entities);
+        // bindings to an entity which crumbled away completely have nowhere to go
+        self.prune_bindings();
+        self.emit(GameEvent::EntityRemoved {
+            id: original.id,
+            reason,
+        });
+    }
+
+    /// advances how long each laser has been burning into a carvable entity,
+    /// carving a hole at the point it hits once it has dwelled there long enough
+    fn burn_carvable(&mut self, time_step: Duration, hits: Vec<Option<(EntityId, Point)>>) {
+        let mut to_carve = vec![];
+        for (dwell, hit) in self.laser_dwells.iter_mut().zip(hits) {
+            *dwell = match (hit, *dwell) {
+                (Some((id, point)), Some((dwelling_on, time))) if id == dwelling_on => {
+                    if time + time_step >= CARVE_DWELL {
+                        to_carve.push((id, point));
+                        None
+                    } else {
+                        Some((id, time + time_step))
+                    }
+                }
+                (Some((id, _)), _) => Some((id, Duration::ZERO)),
+                (None, _) => None,
+            };
+        }
+
+        for (id, point) in to_carve {
+            if let Some(i) = self.entities.iter().position(|entity| entity.id == id) {
+                self.carve(i, point);
+            }
+        }
+    }
+
+    /// accelerates the first entity each laser hits along the beam, spinning it unless the beam
+    /// goes through its centroid. Static entities can't be pushed
+    fn push_with_lasers(&mut self, time_step: Duration) {
+        let time_step = time_step.as_micros() as f64;
+        let hits: Vec<_> = self
+            .lasers
+            .iter()
+            .filter_map(|laser| {
+                let direction = laser.direction().unit();
+                let hit = self.raycast(laser.point, direction, f64::INFINITY)?;
+                Some((hit, direction))
+            })
+            .collect();
+
+        for (RayHit { id, point, .. }, direction) in hits {
+            let Some(entity) = self.entities.iter().find(|entity| entity.id == id) else {
+                continue;
+            };
+            if entity.is_static {
+                continue;
+            }
+
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            let force = LASER_FORCE.min(MAX_LASER_ACCELERATION * data.mass);
+            let impulse = direction * (force * time_step);
+            data.velocity += impulse / data.mass;
+            data.angular_velocity += data.centroid.to(point).cross(impulse) / data.inertia;
+        }
+    }
+
+    /// cuts an approximately circular hole around `center` out of the `i`-th entity, unless
+    /// there's no room for the pieces it would leave
+    fn carve(&mut self, i: usize, center: Point) {
+        let hole: Vec<_> = (0..N_OF_CARVE_HOLE_SIDES)
+            .map(|k| {
+                let angle = consts::TAU * k as f64 / N_OF_CARVE_HOLE_SIDES as f64;
+                center + Point(CARVE_RADIUS, 0.0).rotate(angle)
+            })
+            .collect();
+
+        let outline = self.entities[i].shape.borrow().outline();
+        let pieces = compute::subtract(&outline, &hole);
+        // the carved entity makes room for one of its pieces
+        if pieces.iter().any(|piece| piece.len() > MAX_CARVED_VERTICES)
+            || !self.has_room_for(pieces.len().saturating_sub(1))
+        {
+            return;
+        }
+
+        // a hole touching the outline may leave slivers which collapse to fewer than 3 vertices
+        let pieces = pieces
+            .into_iter()
+            .map(geometry::merge_close_vertices)
+            .filter(|piece| piece.len() >= 3)
+            .map(Polygon::new)
+            .filter_map(|mut piece| {
+                (piece.collision_data_mut().mass >= MIN_CARVED_AREA).then_some(piece)
+            })
+            .collect();
+        self.replace_with_pieces(i, pieces, RemovalReason::Carved);
+    }
+
+    fn next_id(&mut self) -> EntityId {
+        self.next_id += 1;
+        EntityId(self.next_id - 1)
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        if self.events.len() == MAX_PENDING_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// every contact the entity had during the last iteration, one per touching entity
+    pub fn contacts_for(&self, id: EntityId) -> Vec<Contact> {
+        self.contacts.get(&id).cloned().unwrap_or_default()
+    }
+
+    /// the kinetic energy of all dynamic entities together, taking the velocities in units per
+    /// second and the areas as the masses
+    pub fn total_kinetic_energy(&self) -> f64 {
+        self.entities
+            .iter()
+            .filter(|entity| !entity.is_static)
+            .map(|entity| self.kinetic_energy(entity))
+            .sum()
+    }
+
+    /// damps the dynamic entities caught bouncing against others without ever settling
+    fn guard_bounces(&mut self) {
+        for i in 0..self.entities.len() {
+            let entity = &self.entities[i];
+            if entity.is_static {
+                continue;
+            }
+            let energy = self.kinetic_energy(entity);
+            let is_touching = self.contacts.contains_key(&entity.id);
+            let entity = &mut self.entities[i];
+            if let Some(kept) = entity.bounce.update(energy, is_touching, self.angle) {
+                let mut shape = entity.shape.borrow_mut();
+                let data = shape.collision_data_mut();
+                data.velocity = data.velocity * kept;
+                data.angular_velocity *= kept;
+            }
+        }
+    }
+
+    /// the kinetic energy of a dynamic entity, in the units of `total_kinetic_energy`
+    fn kinetic_energy(&self, entity: &Entity) -> f64 {
+        let CollisionData {
+            mass,
+            inertia,
+            velocity,
+            angular_velocity,
+            ..
+        } = *entity.shape.borrow_mut().collision_data_mut();
+        let velocity = velocity * self.params.per_second();
+        let angular_velocity = angular_velocity * self.params.per_second();
+        (mass * velocity.dot(velocity) + inertia * angular_velocity.powi(2)) / 2.0
+    }
+
+    /// what's wrong with the physical properties of each entity, e.g. a NaN velocity,
+    /// empty if nothing is
+    pub fn invariant_violations(&self) -> Vec<String> {
+        self.entities
+            .iter()
+            .filter_map(Entity::invariant_violation)
+            .collect()
+    }
+
+    /// the id of the ball controlled by the player
+    pub fn main_ball_id(&self) -> EntityId {
+        self.entities[0].id
+    }
+
+    /// the ids of all existing entities, sorted, so the main ball comes first
+    pub fn entity_ids(&self) -> Vec<EntityId> {
+        let mut ids: Vec<_> = self.entities.iter().map(|entity| entity.id).collect();
+        ids.sort();
+        ids
+    }
+
+    /// the center of mass of the entity, `None` if it doesn't exist anymore
+    pub fn entity_position(&self, id: EntityId) -> Option<Point> {
+        let entity = self.entities.iter().find(|entity| entity.id == id)?;
+        let centroid = entity.shape.borrow_mut().collision_data_mut().centroid;
+        Some(centroid)
+    }
+
+    /// the physics parameters in effect, with the level's overrides and any tuning applied
+    pub fn params(&self) -> PhysicsParams {
+        self.params
+    }
+
+    /// multiplies one of the physics parameters by `factor`, until another level is loaded
+    pub fn tune(&mut self, param: TunableParam, factor: f64) {
+        self.params.tune(param, factor);
+    }
+
+    /// replaces the physics parameters in effect, until another level is loaded. Panics if
+    /// [`PhysicsParams::max_entities`] is 0
+    pub fn set_params(&mut self, params: PhysicsParams) {
+        params.assert_usable();
+        self.params = params;
+        self.binding_substeps = params.binding_substeps.max(1);
+    }
+
+    /// how many times pairs of shapes were collided during the last step, counting every
+    /// iteration of [`PhysicsParams::solver_iterations`]
+    pub fn contact_solves(&self) -> usize {
+        self.contact_solves
+    }
+
+    /// how many times the bindings are enforced in a step, each over that fraction of it. It
+    /// starts at [`PhysicsParams::binding_substeps`] and is raised, up to 8, while a binding pulls
+    /// the lighter of its shapes back faster than [`UNSTABLE_BINDING_SPEED`]
+    pub fn binding_substeps(&self) -> usize {
+        self.binding_substeps
+    }
+
+    /// how the entity behaves, `None` if it doesn't exist anymore
+    pub fn entity_cfg(&self, id: EntityId) -> Option<EntityCfg> {
+        let entity = self.entities.iter().find(|entity| entity.id == id)?;
+        Some(entity.cfg())
+    }
+
+    /// the first entity, including the main ball, whose outline the ray from `from` going in
+    /// `direction` crosses at most `max_distance` away. Circles are approximated with polygons,
+    /// as everywhere else, and a ray starting inside an entity hits it where it leaves it
+    ///
+    /// ```
+    /// use zpr_game_engine::{
+    ///     geometry::Point,
+    ///     levels::{Entity, Level},
+    ///     physics::EngineBuilder,
+    /// };
+    ///
+    /// let mut level = Level::new(Point(0.0, 0.0));
+    /// level.polygons.push(Entity::new(
+    ///     vec![Point(1.0, -1.0), Point(1.2, -1.0), Point(1.2, 1.0), Point(1.0, 1.0)],
+    ///     true,
+    /// ));
+    /// let engine = EngineBuilder::new(level).build();
+    ///
+    /// let hit = engine.raycast(Point(0.5, 0.0), Point(1.0, 0.0), 10.0).unwrap();
+    /// assert!((hit.distance - 0.5).abs() < 1e-9);
+    /// assert_ne!(hit.id, engine.main_ball_id());
+    ///
+    /// assert!(engine.raycast(Point(0.5, 0.0), Point(1.0, 0.0), 0.4).is_none());
+    /// assert!(engine.raycast(Point(0.5, 0.0), Point(0.0, 1.0), 10.0).is_none());
+    /// ```
+    pub fn raycast(&self, from: Point, direction: Vector, max_distance: f64) -> Option<RayHit> {
+        let direction = direction.unit();
+        self.entities
+            .iter()
+            .filter_map(|entity| {
+                let outline = entity.shape.borrow().outline();
+                let distance = Looped::<_, 2>::from(outline.into_iter())
+                    .filter_map(|[start, end]| geometry::ray_crossing(from, direction, start, end))
+                    .min_by(f64::total_cmp)?;
+                Some(RayHit {
+                    id: entity.id,
+                    point: from + direction * distance,
+                    distance,
+                })
+            })
+            .filter(|hit| hit.distance <= max_distance)
+            .min_by(|first, second| first.distance.total_cmp(&second.distance))
+    }
+
+    /// returns the events which happened since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        mem::take(&mut self.events).into()
+    }
+
+    /// removes the bindings of every entity which point to an already removed entity.
+    /// Should be called whenever entities are removed, as otherwise they would accumulate
+    fn prune_bindings(&mut self) {
+        self.entities.iter_mut().for_each(Entity::prune_bindings);
+    }
+
+    /// where the image is in `textures`, adding it if it isn't there yet
+    fn texture_index(&mut self, texture: Option<String>) -> Option<usize> {
+        let texture = texture?;
+        Some(
+            match self.textures.iter().position(|path| *path == texture) {
+                Some(i) => i,
+                None => {
+                    self.textures.push(texture);
+                    self.textures.len() - 1
+                }
+            },
+        )
+    }
+
+    /// joins the entities at the indices with a binding at `at`, unless it's outside either of
+    /// them. The binding is kept by the entity coming first, as bindings only point to entities
+    /// later in `entities`
+    fn bind(&mut self, first: usize, second: usize, kind: BindingKind, at: Point) {
+        let (first, second) = (first.min(second), first.max(second));
+        let target = self.entities[second].shape.clone();
+        let entity = &mut self.entities[first];
+        let shape = entity.shape.borrow();
+        let unbound = match kind {
+            BindingKind::Hinge => Unbound::new_hinge(&*shape, at, 0.0),
+            BindingKind::Rigid => Unbound::new_rigid(&*shape, at, 0.0),
+            BindingKind::Weld => Unbound::new_weld(&*shape, at, 0.0),
+        };
+        let binding = Binding::try_bind(&*shape, unbound, &*target.borrow());
+        if let Some(binding) = binding {
+            entity.bindings.push((binding, Rc::downgrade(&target), 0.0));
+        }
+    }
+
+    /// pins a hinge to the bindable entity containing `point`, which binds it to the next
+    /// entity drawn over it
+    pub fn add_hinge(&mut self, point: Point) {
+        self.add_compliant_hinge(point, 0.0);
+    }
+
+    /// like `add_hinge`, but the hinge gives way under load as much as the compliance lets it,
+    /// see `Binding`. A springy hinge stays stable where many stiff ones would fight each other
+    pub fn add_compliant_hinge(&mut self, point: Point, compliance: f64) {
+        if let Some(i) = self
+            .entities
+            .iter()
+            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
+        {
+            self.entities[i].add_hinge(point, compliance);
+        }
+    }
+
+    /// like `add_hinge`, but the bound entities can't turn relative to each other either
+    pub fn add_rigid(&mut self, point: Point) {
+        self.add_compliant_rigid(point, 0.0);
+    }
+
+    /// like `add_rigid`, with a compliance as in `add_compliant_hinge`
+    pub fn add_compliant_rigid(&mut self, point: Point, compliance: f64) {
+        if let Some(i) = self
+            .entities
+            .iter()
+            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
+        {
+            self.entities[i].add_rigid(point, compliance);
+        }
+    }
+
+    /// like `add_rigid`, but the relative angle of the bound entities is held exactly rather
+    /// than by a pair of hinges, so a long arm doesn't sag under its own weight
+    pub fn add_weld(&mut self, point: Point) {
+        self.add_compliant_weld(point, 0.0);
+    }
+
+    /// like `add_weld`, with a compliance as in `add_compliant_hinge`
+    pub fn add_compliant_weld(&mut self, point: Point, compliance: f64) {
+        if let Some(i) = self
+            .entities
+            .iter()
+            .position(|shape| shape.shape.borrow().includes(point) && shape.is_bindable)
+        {
+            self.entities[i].add_weld(point, compliance);
+        }
+    }
+
+    /// pushes the main ball upwards, if it has jumps left and isn't waiting to be launched
+    pub fn jump(&mut self) {
+        if !self.launch_mode && self.jumps_count != 0 {
+            let main_ball_mut = self.main_ball.upgrade().unwrap();
+            main_ball_mut.borrow_mut().collision_data_mut().velocity +=
+                Point(0.0, self.params.jump_impulse).rotate(-self.angle as f64);
+            self.jumps_count -= 1;
+        }
+    }
+
+    /// pushes every movable entity with its centroid within `radius` of `center` away from
+    /// it, like an explosion. The impulse is `strength` at the center and falls off linearly
+    /// to nothing at `radius`. It's applied at the centroids, so nothing starts spinning, and
+    /// entities right at the center are pushed against gravity
+    pub fn apply_radial_impulse(&mut self, center: Point, strength: f64, radius: f64) {
+        let fallback = -self.gravity_direction();
+        for entity in self.entities.iter().filter(|entity| !entity.is_static) {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            if data.mass.is_infinite() {
+                continue;
+            }
+            let offset = center.to(data.centroid);
+            let distance = offset.norm();
+            if distance >= radius {
+                continue;
+            }
+            let direction = if distance < geometry::EPSILON {
+                fallback
+            } else {
+                offset.unit()
+            };
+            data.apply_impulse(
+                Vector::ZERO,
+                direction * (strength * (1.0 - distance / radius)),
+            );
+        }
+    }
+
+    /// current tilt of the world, lagging behind the target set by the player
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// tilts the world towards an absolute angle
+    pub fn set_target_angle(&mut self, angle: f32) {
+        self.target_angle = angle.clamp(-MAX_TILT, MAX_TILT);
+    }
+
+    /// tilts the world further by the given difference, e.g. as reported by the phone
+    pub fn tilt_by(&mut self, angle_diff: f32) {
+        self.set_target_angle(self.target_angle + angle_diff);
+    }
+
+    fn turn_towards_target_angle(&mut self, time_step: Duration) {
+        let max_turn = TILT_SPEED * time_step.as_secs_f32();
+        self.angle += (self.target_angle - self.angle).clamp(-max_turn, max_turn);
+    }
+
+    /// starts the level over at the player's request, like `reset_level`, showing the whole of
+    /// it again
+    pub fn restart(&mut self) {
+        self.reset_level();
+        self.level_started = true;
+    }
+
+    /// returns the main ball to its starting position, leaving everything else as it is
+    pub fn reset_level(&mut self) {
+        self.run.restart();
+        self.paint = None;
+        self.paint_marks.clear();
+        self.clear_paint = true;
+        {
+            let mut ball = self.entities[0].shape.borrow_mut();
+            let data = ball.collision_data_mut();
+
+            data.centroid = self.main_ball_starting_position;
+            data.angular_velocity = 0.0;
+            data.velocity = Vector::ZERO;
+        }
+        self.wedged_for = Duration::ZERO;
+
+        if self.launch_mode {
+            self.hold_ball();
+        }
+    }
+
+    /// picks up paint from the painted entities the main ball touches, and while it has some
+    /// leaves marks of it where it touches anything else
+    fn update_paint(&mut self, time_step: Duration) {
+        if let Some(paint) = &mut self.paint {
+            paint.remaining = paint.remaining.saturating_sub(time_step);
+            if paint.remaining.is_zero() {
+                self.paint = None;
+            }
+        }
+        let Some(contacts) = self.contacts.get(&self.entities[0].id) else {
+            return;
+        };
+        for contact in contacts {
+            let Some(other) = self
+                .entities
+                .iter()
+                .find(|entity| entity.id == contact.other)
+            else {
+                continue;
+            };
+            if let Some(color) = other.paint {
+                self.paint = Some(BallPaint {
+                    color,
+                    remaining: PAINT_TIME,
+                    last_mark: None,
+                });
+                continue;
+            }
+            let Some(paint) = &mut self.paint else {
+                continue;
+            };
+            if paint
+                .last_mark
+                .is_some_and(|last| last.to(contact.point).norm() < PAINT_MARK_SPACING)
+            {
+                continue;
+            }
+            paint.last_mark = Some(contact.point);
+            self.paint_marks.push(PaintMark {
+                at: contact.point,
+                color: paint.color,
+                strength: paint.remaining.as_secs_f32() / PAINT_TIME.as_secs_f32(),
+            });
+        }
+        // the display hasn't taken them for a while, so it would drop the oldest ones anyway
+        if self.paint_marks.len() > MAX_PAINT_MARKS {
+            self.paint_marks
+                .drain(..self.paint_marks.len() - MAX_PAINT_MARKS);
+        }
+    }
+
+    /// freezes the main ball in place by temporarily giving it infinite mass
+    fn hold_ball(&mut self) {
+        if self.held_ball.is_some() {
+            return;
+        }
+
+        let ball = &mut self.entities[0];
+        let mut shape = ball.shape.borrow_mut();
+        let data = shape.collision_data_mut();
+        self.held_ball = Some((data.mass, data.inertia));
+        data.mass = f64::INFINITY;
+        data.inertia = f64::INFINITY;
+        data.velocity = Vector::ZERO;
+        data.angular_velocity = 0.0;
+        drop(shape);
+        ball.is_static = true;
+    }
+
+    /// starts dragging out a launch vector, if the ball is waiting to be launched
+    pub fn start_aiming(&mut self) {
+        if self.held_ball.is_some() {
+            self.aim = Some(Vector::ZERO);
+        }
+    }
+
+    /// aims slingshot-style, the ball will be launched away from `point`.
+    /// The point is in screen space, that is before the world is tilted
+    pub fn aim_at(&mut self, point: Point) {
+        if self.aim.is_none() {
+            return;
+        }
+
+        let center = self.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid;
+        let launch = point.rotate(-self.angle as f64).to(center);
+        self.aim = Some(if launch.norm() > MAX_LAUNCH_DRAG {
+            launch.unit() * MAX_LAUNCH_DRAG
+        } else {
+            launch
+        });
+    }
+
+    /// releases the held ball with the velocity of the current aim
+    pub fn launch(&mut self) {
+        let Some(aim) = self.aim.take() else {
+            return;
+        };
+        let Some((mass, inertia)) = self.held_ball.take() else {
+            return;
+        };
+
+        let ball = &mut self.entities[0];
+        let mut shape = ball.shape.borrow_mut();
+        let data = shape.collision_data_mut();
+        data.mass = mass;
+        data.inertia = inertia;
+        data.velocity = aim * LAUNCH_VELOCITY_COEFFICIENT;
+        drop(shape);
+        ball.is_static = false;
+    }
+
+    /// positions of a ghost ball launched from `center` with the given aim,
+    /// integrated the same way as `Collidable::update_position` but ignoring collisions
+    fn trajectory(&self, center: Point, aim: Vector) -> Vec<Point> {
+        let time_step = TRAJECTORY_STEP.as_micros() as f64;
+        let gravity =
+            Point(0.0, self.params.gravity_coefficient * time_step).rotate(-self.angle as f64);
+        let mut velocity = aim * LAUNCH_VELOCITY_COEFFICIENT;
+        let mut position = center;
+
+        (0..N_OF_TRAJECTORY_DOTS)
+            .map(|_| {
+                position += velocity * self.params.movement_coefficient * time_step;
+                velocity += gravity;
+                position
+            })
+            .collect()
+    }
+
+    /// gives the main ball back its jumps, as when it lands on anything
+    pub fn reset_jumps(&mut self) {
+        self.jumps_count = MAX_JUMPS;
+    }
+
+    /// whether the main ball has been wedged for [`STUCK_TIME`]: barely moving, out of jumps and
+    /// pressed from opposing sides, so the only way out is to start over or erase what holds it
+    pub fn is_stuck(&self) -> bool {
+        self.wedged_for >= STUCK_TIME
+    }
+
+    /// the contacts of the main ball in the last step, with normals pointing towards what it
+    /// touches
+    fn ball_contacts(&self) -> &[Contact] {
+        self.contacts
+            .get(&self.entities[0].id)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// times how long the main ball has been wedged, starting over as soon as it moves freely.
+    /// In a level with `auto_unstick` a ball wedged for [`STUCK_TIME`] is nudged along the
+    /// direction it's least blocked in instead of being reported stuck
+    fn update_stuck(&mut self, time_step: Duration) {
+        let speed = self.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .velocity
+            .norm()
+            * self.params.per_second();
+        let contacts = self.ball_contacts();
+        let is_pressed_from_both_sides = contacts.iter().any(|a| {
+            contacts
+                .iter()
+                .any(|b| a.normal.dot(b.normal) < OPPOSING_CONTACTS_COS)
+        });
+        if speed >= WEDGED_SPEED || self.jumps_count != 0 || !is_pressed_from_both_sides {
+            self.wedged_for = Duration::ZERO;
+            return;
+        }
+        self.wedged_for += time_step;
+        if self.auto_unstick && self.is_stuck() {
+            let direction = self.least_blocked_direction();
+            self.entities[0]
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .velocity += direction * UNSTICK_IMPULSE;
+            self.wedged_for = Duration::ZERO;
+        }
+    }
+
+    /// the direction the main ball could move in with the least of what it touches in the way,
+    /// the one most against gravity of equally free ones
+    fn least_blocked_direction(&self) -> Vector {
+        let up = -self.gravity_direction();
+        let blocked = |direction: Vector| {
+            let in_the_way = self
+                .ball_contacts()
+                .iter()
+                .map(|contact| direction.dot(contact.normal))
+                .fold(0.0, f64::max);
+            // going up only decides between directions which are equally free
+            in_the_way - 0.01 * direction.dot(up)
+        };
+        (0..N_OF_UNSTICK_DIRECTIONS)
+            .map(|i| up.rotate(i as f64 * consts::TAU / N_OF_UNSTICK_DIRECTIONS as f64))
+            .min_by(|a, b| blocked(*a).total_cmp(&blocked(*b)))
+            .unwrap_or(up)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::c_void;
+
+    use crate::levels;
+
+    use super::{shape::Touch, *};
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    fn init_engine() -> (Engine, channel::Receiver<DisplayMessage>) {
+        let (tx, rx) = channel::bounded(1);
+        let engine = Engine::new(
+            tx,
+            Level {
+                initial_ball_position: Point(0.0, 0.5),
+                ball_radius: 0.07,
+                polygons: vec![
+                    levels::Entity {
+                        is_bindable: false,
+                        is_static: true,
+                        is_deadly: false,
+                        is_fragile: false,
+                        break_speed: None,
+                        is_carvable: false,
+                        lifetime: None,
+                        material: None,
+                        friction: None,
+                        lock_rotation: false,
+                        tag: None,
+                        texture: None,
+                        paint: None,
+                        is_erasable: false,
+                        shape: vec![
+                            Point(0.0, 0.0),
+                            Point(0.5, 0.0),
+                            Point(0.5, 0.5),
+                            Point(0.0, 0.5),
+                        ],
+                    },
+                    levels::Entity {
+                        is_bindable: false,
+                        is_static: true,
+                        is_deadly: false,
+                        is_fragile: false,
+                        break_speed: None,
+                        is_carvable: false,
+                        lifetime: None,
+                        material: None,
+                        friction: None,
+                        lock_rotation: false,
+                        tag: None,
+                        texture: None,
+                        paint: None,
+                        is_erasable: false,
+                        shape: vec![
+                            Point(0.0, 1.0),
+                            Point(0.5, 1.0),
+                            Point(0.5, 1.5),
+                            Point(0.0, 1.5),
+                        ],
+                    },
+                ],
+                circles: vec![levels::Entity {
+                    is_bindable: false,
+                    is_static: true,
+                    is_deadly: false,
+                    is_fragile: false,
+                    break_speed: None,
+                    is_carvable: false,
+                    lifetime: None,
+                    material: None,
+                    friction: None,
+                    lock_rotation: false,
+                    tag: None,
+                    texture: None,
+                    paint: None,
+                    is_erasable: false,
+                    shape: geometry::Circle {
+                        center: Point(0.0, 0.9),
+                        radius: 0.05,
+                    },
+                }],
+                lasers: vec![],
+                doors: vec![],
+                flags_positions: vec![Point(-0.9, 0.0)],
+                bounds: None,
+                camera_bounds: None,
+                despawn_margin: 1.0,
+                notes: vec![],
+                launch_mode: false,
+                lasers_push: false,
+                lasers_harmless: false,
+                auto_unstick: false,
+                jump_refill: JumpRefillMode::GroundOnly,
+                physics: PhysicsOverrides::default(),
+                spawners: vec![],
+                bindings: vec![],
+                pins: vec![],
+                background: None,
+            },
+        );
+        (engine, rx)
+    }
+
+    fn main_ball_position(engine: &Engine) -> Point {
+        engine.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid
+    }
+
+    fn total_drawn_area(engine: &Engine) -> f64 {
+        engine
+            .entities
+            .iter()
+            .filter(|entity| entity.is_erasable)
+            .map(|entity| entity.shape.borrow_mut().collision_data_mut().mass)
+            .sum()
+    }
+
+    #[test]
+    fn test_engine_creation() {
+        let (engine, _rx) = init_engine();
+
+        assert!(engine.circles.len() == 2);
+        assert!(engine.polygons.len() == 2);
+        assert!(engine.entities.len() == 4);
+        assert!(
+            engine.polygons[1]
+                .shape
+                .upgrade()
+                .unwrap()
+                .borrow_mut()
+                .collision_data_mut()
+                .mass
+                == f64::INFINITY
+        );
+    }
+
+    #[test]
+    fn test_auto_bind() {
+        let (mut engine, _rx) = init_engine();
+
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.9, -1.0),
+                (-0.9, -0.9),
+                (-1.0, -0.9),
+            })
+            .unwrap();
+
+        engine.add_rigid(Point(-0.91, -0.91));
+
+        assert!(engine.entities.last().unwrap().unbound.len() == 1);
+
+        engine
+            .add_polygon(make_shape! {
+                (-0.92, -0.92),
+                (-0.85, -0.92),
+                (-0.85, -0.85),
+                (-0.92, -0.85),
+            })
+            .unwrap();
+
+        let [.., first, second] = &engine.entities[..] else {
+            panic!("not enough enitites");
+        };
+
+        assert!(first.unbound.is_empty());
+        assert!(std::ptr::eq(
+            first.bindings[0].1.as_ptr() as *const c_void,
+            &*second.shape as *const _ as *const c_void
+        ));
+    }
+
+    #[test]
+    fn test_rewind_replays_identically() {
+        let (mut engine, _rx) = init_engine();
+        engine
+            .add_polygon(make_shape! {
+                (-0.4, 0.6),
+                (-0.2, 0.6),
+                (-0.2, 0.8),
+                (-0.4, 0.8),
+            })
+            .unwrap();
+
+        // the snapshot is taken at the end of the tenth step
+        for _ in 0..10 {
+            engine.step(STEP);
+        }
+        let n_of_entities = engine.entities.len();
+
+        let play = |engine: &mut Engine| {
+            (0..9)
+                .map(|i| {
+                    if i == 3 {
+                        engine.jump();
+                    }
+                    if i == 5 {
+                        engine
+                            .add_circle(Circle::new(Point(-0.8, 0.8), 0.05))
+                            .unwrap();
+                    }
+                    engine.step(STEP);
+                    main_ball_position(engine)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let original = play(&mut engine);
+        assert!(engine.rewind());
+        assert_eq!(engine.entities.len(), n_of_entities);
+        let replayed = play(&mut engine);
+
+        assert_eq!(original, replayed);
+    }
+
+    #[test]
+    fn test_erase_prunes_bindings() {
+        let (mut engine, _rx) = init_engine();
+
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.9, -1.0),
+                (-0.9, -0.9),
+                (-1.0, -0.9),
+            })
+            .unwrap();
+        engine.add_hinge(Point(-0.91, -0.91));
+        engine
+            .add_polygon(make_shape! {
+                (-0.92, -0.92),
+                (-0.8, -0.92),
+                (-0.8, -0.8),
+                (-0.92, -0.8),
+            })
+            .unwrap();
+
+        let first = engine.entities.len() - 2;
+        assert_eq!(engine.entities[first].bindings.len(), 1);
+
+        engine.erase_at(Point(-0.85, -0.85));
+
+        assert_eq!(engine.entities.len(), first + 1);
+        assert!(engine.entities[first].bindings.is_empty());
+    }
+
+    #[test]
+    fn test_erase_connected_removes_hinged_chain() {
+        let (mut engine, _rx) = init_engine();
+        let square = |x: f64| {
+            make_shape! {
+                (x, -1.0),
+                (x + 0.1, -1.0),
+                (x + 0.1, -0.9),
+                (x, -0.9),
+            }
+        };
+
+        let before = engine.entities.len();
+        engine.add_polygon(square(-1.0)).unwrap();
+        engine.add_hinge(Point(-0.91, -0.95));
+        engine.add_polygon(square(-0.92)).unwrap();
+        engine.add_hinge(Point(-0.83, -0.95));
+        engine.add_polygon(square(-0.84)).unwrap();
+        // a shape of its own, which isn't bound to the chain
+        engine.add_polygon(square(-0.5)).unwrap();
+        assert_eq!(engine.entities.len(), before + 4);
+        let _ = engine.take_events();
+
+        engine.erase_connected(Point(-0.95, -0.95));
+
+        assert_eq!(engine.entities.len(), before + 1);
+        assert!(engine.entities[before]
+            .shape
+            .borrow()
+            .includes(Point(-0.45, -0.95)));
+        let erased = engine
+            .take_events()
+            .into_iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    GameEvent::EntityRemoved {
+                        reason: RemovalReason::Erased,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(erased, 3);
+    }
+
+    #[test]
+    fn test_stuck_ball_is_detected() {
+        // a floor and a ceiling closer together than the ball is wide, with the ball squeezed
+        // between them
+        let mut level = Level::new(Point(0.0, 0.07));
+        let wall = |y: f64| {
+            levels::Entity::new(
+                vec![
+                    Point(-1.0, y),
+                    Point(1.0, y),
+                    Point(1.0, y + 0.1),
+                    Point(-1.0, y + 0.1),
+                ],
+                true,
+            )
+        };
+        level.polygons.push(wall(-0.1));
+        level.polygons.push(wall(0.135));
+        let mut engine = EngineBuilder::new(level.clone()).build();
+        let ceiling = engine.entity_ids()[2];
+
+        // the ball isn't stuck while it still has jumps, which don't get it out either
+        for _ in 0..300 {
+            engine.step(STEP);
+        }
+        assert!(!engine.is_stuck());
+        engine.jump();
+        engine.step(STEP);
+        engine.jump();
+        for _ in 0..STUCK_TIME.as_millis() / STEP.as_millis() + 10 {
+            engine.step(STEP);
+        }
+        assert!(engine.is_stuck());
+
+        engine.entities.retain(|entity| entity.id != ceiling);
+        for _ in 0..10 {
+            engine.step(STEP);
+        }
+        assert!(!engine.is_stuck());
+        assert_eq!(engine.wedged_for, Duration::ZERO);
+
+        // the level can have the ball nudged out instead
+        level.auto_unstick = true;
+        let mut engine = EngineBuilder::new(level).build();
+        for i in 0..400 {
+            if i == 10 || i == 11 {
+                engine.jump();
+            }
+            engine.step(STEP);
+            assert!(!engine.is_stuck());
+        }
+        let Point(x, _) = main_ball_position(&engine);
+        assert!(
+            x.abs() > 0.01,
+            "the ball wasn't nudged sideways, it's at {x}"
+        );
+    }
+
+    #[test]
+    fn test_invariant_violations_name_the_entity() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        let mut plank = levels::Entity::new(
+            vec![
+                Point(-0.5, 0.0),
+                Point(0.5, 0.0),
+                Point(0.5, 0.05),
+                Point(-0.5, 0.05),
+            ],
+            false,
+        );
+        plank.tag = Some("seesaw_plank".to_string());
+        level.polygons.push(plank);
+        level.polygons.push(levels::Entity::new(
+            vec![Point(0.6, 0.0), Point(0.8, 0.0), Point(0.7, 0.1)],
+            false,
+        ));
+        let engine = EngineBuilder::new(level).build();
+        assert!(engine
+            .entities
+            .iter()
+            .all(|entity| entity.invariant_violation().is_none()));
+
+        for entity in &engine.entities[1..] {
+            entity.shape.borrow_mut().collision_data_mut().mass = -1.0;
+        }
+        assert_eq!(
+            engine.entities[1].invariant_violation().unwrap(),
+            "entity 'seesaw_plank' has negative mass"
+        );
+        assert_eq!(
+            engine.entities[2].invariant_violation().unwrap(),
+            "entity 'polygon 1' has negative mass"
+        );
+    }
+
+    #[test]
+    fn test_pins_dont_bind_to_the_main_ball() {
+        let (mut engine, _rx) = init_engine();
+
+        // overlapping the ball, which starts at (0.0, 0.5)
+        engine
+            .add_polygon(make_shape! {
+                (-0.1, 0.45),
+                (0.1, 0.45),
+                (0.1, 0.6),
+                (-0.1, 0.6),
+            })
+            .unwrap();
+        engine.add_hinge(Point(0.02, 0.5));
+        let drawn = engine.entities.len() - 1;
+
+        let ball = engine.entities[0].shape.clone();
+        engine.try_bind(&ball);
+        engine
+            .spawn_circle(
+                Circle::new(Point(0.02, 0.5), 0.03),
+                EntityCfg {
+                    is_bindable: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert!(engine.entities[drawn].bindings.is_empty());
+        assert_eq!(engine.entities[drawn].unbound.len(), 1);
+    }
+
+    #[test]
+    fn test_level_entities_can_be_erasable() {
+        let level: Level = ron::from_str(
+            "(
+                initial_ball_position: (0.0, 0.5),
+                circles: [],
+                polygons: [
+                    (shape: [(0.6, 0.0), (0.9, 0.0), (0.9, 0.3), (0.6, 0.3)], is_static: true, is_bindable: false, is_erasable: true),
+                    (shape: [(-0.9, 0.0), (-0.6, 0.0), (-0.6, 0.3), (-0.9, 0.3)], is_static: true, is_bindable: false),
+                ],
+                flags_positions: [],
+            )",
+        )
+        .unwrap();
+        let (tx, _rx) = channel::bounded(1);
+        let mut engine = Engine::new(tx, level);
+
+        engine.erase_at(Point(0.75, 0.15));
+        engine.erase_at(Point(-0.75, 0.15));
+
+        assert_eq!(engine.entities.len(), 2);
+        assert!(!engine.entities[1].is_erasable);
+    }
+
+    #[test]
+    fn test_clear_drawn_keeps_the_level() {
+        let (mut engine, _rx) = init_engine();
+        let n_of_level_entities = engine.entities.len();
+
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.9, -1.0),
+                (-0.9, -0.9),
+                (-1.0, -0.9),
+            })
+            .unwrap();
+        engine.add_hinge(Point(-0.91, -0.91));
+        engine
+            .add_polygon(make_shape! {
+                (-0.92, -0.92),
+                (-0.8, -0.92),
+                (-0.8, -0.8),
+                (-0.92, -0.8),
+            })
+            .unwrap();
+        engine
+            .add_circle(Circle::new(Point(0.8, 0.8), 0.05))
+            .unwrap();
+        engine
+            .add_level_shape(vec![
+                Point(0.6, -0.6),
+                Point(0.9, -0.6),
+                Point(0.9, -0.5),
+                Point(0.6, -0.5),
+            ])
+            .unwrap();
+        let drawn: Vec<_> = engine.entities[n_of_level_entities..][..3]
+            .iter()
+            .map(|entity| entity.id)
+            .collect();
+        engine.take_events();
+
+        engine.clear_drawn();
+
+        assert_eq!(engine.entities.len(), n_of_level_entities + 1);
+        assert!(engine.entities[n_of_level_entities].is_static);
+        assert_eq!(
+            engine.take_events(),
+            drawn
+                .into_iter()
+                .map(|id| GameEvent::EntityRemoved {
+                    id,
+                    reason: RemovalReason::Erased,
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_clear_drawn_keeps_what_spawners_dropped() {
+        let mut level = Level::new(Point(5.0, 5.0));
+        level.physics.gravity_coefficient = Some(0.0);
+        level.spawners.push(Spawner {
+            region: vec![
+                Point(-1.0, -1.0),
+                Point(1.0, -1.0),
+                Point(1.0, 1.0),
+                Point(-1.0, 1.0),
+            ],
+            shape: SpawnShape::Circle { radius: 0.02 },
+            interval_seconds: 0.05,
+            max_alive: 3,
+            initial_velocity: Point(0.0, 0.0),
+            lifetime: None,
+        });
+        let mut engine = EngineBuilder::new(level).seed(7).build();
+        engine
+            .add_polygon(make_shape! {
+                (-0.2, -0.2),
+                (0.2, -0.2),
+                (0.2, 0.2),
+                (-0.2, 0.2),
+            })
+            .unwrap();
+        // slice the drawn square in two, both halves still count as drawn
+        engine.slice(Point(0.0, -0.5), Point(0.0, 0.5));
+        for _ in 0..20 {
+            engine.step(Duration::from_millis(10));
+        }
+        let n_drawn = engine
+            .entities
+            .iter()
+            .filter(|entity| entity.is_drawn)
+            .count();
+        assert_eq!(n_drawn, 2);
+        let n_kept = engine.entities.len() - n_drawn;
+
+        engine.clear_drawn();
+
+        assert_eq!(engine.entities.len(), n_kept);
+        assert_eq!(engine.entity_ids().len() - 1, 3);
+    }
+
+    #[test]
+    fn test_spawned_entities_can_be_queried() {
+        let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5))).build();
+        let cfg = EntityCfg {
+            is_static: true,
+            is_carvable: true,
+            material: Material::Stone,
+            ..Default::default()
+        };
+        let wall = engine
+            .spawn_polygon(
+                make_shape! {
+                    (0.5, 0.0),
+                    (0.6, 0.0),
+                    (0.6, 1.0),
+                    (0.5, 1.0),
+                },
+                cfg,
+            )
+            .unwrap();
+
+        assert_eq!(engine.entity_ids(), vec![engine.main_ball_id(), wall]);
+        assert_eq!(engine.entity_cfg(wall), Some(cfg));
+        assert!(engine
+            .entity_position(wall)
+            .unwrap()
+            .is_close_enough_to(Point(0.55, 0.5)));
+
+        let hit = engine
+            .raycast(Point(0.0, 0.5), Point(2.0, 0.0), 1.0)
+            .unwrap();
+        assert_eq!(hit.id, engine.main_ball_id());
+        assert!(hit.distance > 0.06 && hit.distance < 0.07 + geometry::EPSILON);
+
+        let hit = engine
+            .raycast(Point(0.2, 0.5), Point(2.0, 0.0), 1.0)
+            .unwrap();
+        assert_eq!(hit.id, wall);
+        assert!(hit.point.is_close_enough_to(Point(0.5, 0.5)));
+
+        engine.step(Duration::from_millis(10));
+        engine.erase_at(Point(0.55, 0.5));
+        assert_eq!(engine.entity_position(wall), None);
+        assert_eq!(engine.entity_cfg(wall), None);
+    }
+
+    /// an engine with a box resting on a wide static slope going up to the right, both with a
+    /// friction coefficient of 0.5
+    fn box_on_slope(degrees: f64) -> (Engine, EntityId) {
+        let angle = degrees.to_radians();
+        let rotated = |polygon: Vec<Point>| -> Vec<Point> {
+            polygon
+                .into_iter()
+                .map(|point| point.rotate(angle))
+                .collect()
+        };
+
+        // the ball rests on a ledge of its own, away from the slope
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(2.5, -0.1),
+                Point(3.5, -0.1),
+                Point(3.5, 0.0),
+                Point(2.5, 0.0),
+            ],
+            true,
+        ));
+        let mut slope = levels::Entity::new(
+            rotated(vec![
+                Point(-2.0, -0.2),
+                Point(2.0, -0.2),
+                Point(2.0, 0.0),
+                Point(-2.0, 0.0),
+            ]),
+            true,
+        );
+        slope.friction = Some(0.5);
+        level.polygons.push(slope);
+
+        let mut engine = EngineBuilder::new(level).build();
+        let cfg = EntityCfg {
+            friction: 0.5,
+            ..Default::default()
+        };
+        let block = engine
+            .spawn_polygon(
+                Polygon::new(rotated(vec![
+                    Point(-0.05, 0.0),
+                    Point(0.05, 0.0),
+                    Point(0.05, 0.1),
+                    Point(-0.05, 0.1),
+                ])),
+                cfg,
+            )
+            .unwrap();
+        (engine, block)
+    }
+
+    #[test]
+    fn test_friction_holds_box_on_gentle_slope() {
+        let (mut engine, block) = box_on_slope(10.0);
+        let start = engine.entity_position(block).unwrap();
+        for _ in 0..500 {
+            engine.step(STEP);
+        }
+        let moved = start.to(engine.entity_position(block).unwrap()).norm();
+        assert!(moved < 1e-3, "the box crept by {moved}");
+    }
+
+    /// how far apart the ends of a hinge holding a box under a static bar are after a second,
+    /// with the bindings enforced at least `substeps` times per step
+    fn hinge_stretch(compliance: f64, substeps: usize) -> f64 {
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(2.5, -0.1),
+                Point(3.5, -0.1),
+                Point(3.5, 0.0),
+                Point(2.5, 0.0),
+            ],
+            true,
+        ));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-0.1, 0.5),
+                Point(0.1, 0.5),
+                Point(0.1, 0.55),
+                Point(-0.1, 0.55),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level)
+            .params(PhysicsParams {
+                binding_substeps: substeps,
+                ..PhysicsParams::default()
+            })
+            .build();
+        engine.add_compliant_hinge(Point(0.0, 0.5), compliance);
+        engine
+            .add_polygon(make_shape! {
+                (-0.05, 0.2),
+                (0.05, 0.2),
+                (0.05, 0.5),
+                (-0.05, 0.5),
+            })
+            .unwrap();
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+
+        let bar = &engine.entities[2];
+        let (binding, hanging, _) = &bar.bindings[0];
+        let first = binding.first_anchor(&*bar.shape.borrow());
+        let second = binding.second_anchor(&*hanging.upgrade().unwrap().borrow());
+        first.to(second).norm()
+    }
+
+    /// a heavy arm sticking out of a static bar on a short rigid pin, with the bindings enforced
+    /// `substeps` times per step
+    fn pinned_arm(substeps: usize) -> (Engine, EntityId) {
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(2.5, -0.1),
+                Point(3.5, -0.1),
+                Point(3.5, 0.0),
+                Point(2.5, 0.0),
+            ],
+            true,
+        ));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-0.1, 0.5),
+                Point(0.0, 0.5),
+                Point(0.0, 0.6),
+                Point(-0.1, 0.6),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level)
+            .params(PhysicsParams {
+                binding_substeps: substeps,
+                ..PhysicsParams::default()
+            })
+            .build();
+        engine.add_rigid(Point(-0.02, 0.55));
+        engine
+            .add_polygon(make_shape! {
+                (-0.03, 0.5),
+                (0.5, 0.5),
+                (0.5, 0.6),
+                (-0.03, 0.6),
+            })
+            .unwrap();
+        let arm = engine.entity_ids()[3];
+        (engine, arm)
+    }
+
+    /// how far on average the pinned arm strays from where it was pinned over a second
+    fn pin_error(substeps: usize) -> f64 {
+        let (mut engine, arm) = pinned_arm(substeps);
+        let pinned_at = engine.entity_position(arm).unwrap();
+        let total: f64 = (0..100)
+            .map(|_| {
+                engine.step(STEP);
+                assert_eq!(engine.binding_substeps(), substeps);
+                pinned_at.to(engine.entity_position(arm).unwrap()).norm()
+            })
+            .sum();
+        total / 100.0
+    }
+
+    #[test]
+    fn test_binding_substeps_shrink_pin_error() {
+        let errors = [1, 2, 4].map(pin_error);
+        assert!(errors[0] > 1e-3, "the arm only strayed by {}", errors[0]);
+        // each doubling of the substeps at least about halves the error
+        for pair in errors.windows(2) {
+            assert!(pair[1] < 0.6 * pair[0], "the errors went {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_yanked_binding_raises_substeps() {
+        let (mut engine, arm) = pinned_arm(1);
+        engine.step(STEP);
+        assert_eq!(engine.binding_substeps(), 1);
+
+        // pushes the arm up against its pin
+        let centroid = engine.entity_position(arm).unwrap();
+        engine.apply_radial_impulse(centroid, 0.05, 0.5);
+        engine.step(STEP);
+        assert!(engine.binding_substeps() > 1);
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+        assert_eq!(engine.binding_substeps(), 1);
+    }
+
+    #[test]
+    fn test_wheel_tunes_nearest_hinge() {
+        let (mut engine, _rx) = init_engine();
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.9, -1.0),
+                (-0.9, -0.9),
+                (-1.0, -0.9),
+            })
+            .unwrap();
+        engine.add_hinge(Point(-0.91, -0.91));
+        engine
+            .add_polygon(make_shape! {
+                (-0.92, -0.92),
+                (-0.8, -0.92),
+                (-0.8, -0.8),
+                (-0.92, -0.8),
+            })
+            .unwrap();
+
+        assert_eq!(engine.tune_hinge_at(Point(-0.5, -0.5), -1.0), None);
+        let softer = engine.tune_hinge_at(Point(-0.9, -0.9), -1.0).unwrap();
+        assert_eq!(softer, MIN_HINGE_COMPLIANCE * HINGE_COMPLIANCE_STEP);
+        assert_eq!(
+            engine.tune_hinge_at(Point(-0.91, -0.91), -100.0),
+            Some(MAX_HINGE_COMPLIANCE)
+        );
+        // stiffened far enough it's as stiff as a hinge gets
+        assert_eq!(engine.tune_hinge_at(Point(-0.91, -0.91), 100.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_compliant_hinge_gives_way() {
+        let stiff = hinge_stretch(0.0, 1);
+        let compliant = hinge_stretch(0.01, 1);
+        assert!(stiff < 2e-4, "the stiff hinge stretched by {stiff}");
+        assert!(
+            compliant > 5.0 * stiff,
+            "the compliant hinge only stretched by {compliant}"
+        );
+    }
+
+    #[test]
+    fn test_compliant_hinge_sags_the_same_with_substeps() {
+        let once = hinge_stretch(0.01, 1);
+        let substepped = hinge_stretch(0.01, 4);
+        assert!(
+            (substepped / once - 1.0).abs() < 0.25,
+            "the hinge stretched by {once} with 1 substep and {substepped} with 4"
+        );
+    }
+
+    /// the load on a hinge holding a box of the width under a static bar, once it has settled
+    fn hinge_load(width: f64, is_debugging: bool) -> f64 {
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(2.5, -0.1),
+                Point(3.5, -0.1),
+                Point(3.5, 0.0),
+                Point(2.5, 0.0),
+            ],
+            true,
+        ));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-0.1, 0.5),
+                Point(0.1, 0.5),
+                Point(0.1, 0.55),
+                Point(-0.1, 0.55),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        if is_debugging {
+            engine.toggle_debugging();
+        }
+        engine.add_hinge(Point(0.0, 0.5));
+        let half = width / 2.0;
+        engine
+            .add_polygon(Polygon::new(vec![
+                Point(-half, 0.2),
+                Point(half, 0.2),
+                Point(half, 0.5),
+                Point(-half, 0.5),
+            ]))
+            .unwrap();
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+
+        engine.entities[2].bindings[0].2
+    }
+
+    #[test]
+    fn test_heavier_weight_loads_hinge_more() {
+        let light = hinge_load(0.1, true);
+        let heavy = hinge_load(0.3, true);
+        assert!(light > 0.0, "the hinge carries no load");
+        assert!(
+            heavy > 2.0 * light,
+            "three times the weight only took {heavy} instead of {light}"
+        );
+        assert_eq!(hinge_load(0.3, false), 0.0);
+    }
+
+    /// how far the angle between an arm and the box it's bound to at one end drifts from the
+    /// one they were bound at, while the box rests on a support and the arm sticks out past it
+    fn arm_sag(bind: fn(&mut Engine, Point)) -> f64 {
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(2.5, -0.1),
+                Point(3.5, -0.1),
+                Point(3.5, 0.0),
+                Point(2.5, 0.0),
+            ],
+            true,
+        ));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-0.3, -0.1),
+                Point(0.1, -0.1),
+                Point(0.1, 0.0),
+                Point(-0.3, 0.0),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        engine
+            .add_polygon(make_shape! {
+                (-0.2, 0.0),
+                (0.05, 0.0),
+                (0.05, 0.1),
+                (-0.2, 0.1),
+            })
+            .unwrap();
+        bind(&mut engine, Point(0.03, 0.05));
+        engine
+            .add_polygon(make_shape! {
+                (0.0, 0.03),
+                (0.4, 0.03),
+                (0.4, 0.07),
+                (0.0, 0.07),
+            })
+            .unwrap();
+
+        let relative_angle = |engine: &Engine| {
+            let [.., base, arm] = &engine.entities[..] else {
+                panic!("the boxes weren't added");
+            };
+            let side = |entity: &Entity| {
+                let outline = entity.shape.borrow().outline();
+                outline[0].to(outline[1])
+            };
+            side(base).angle_to(side(arm))
+        };
+        let bound_at = relative_angle(&engine);
+        (0..200)
+            .map(|_| {
+                engine.step(STEP);
+                (relative_angle(&engine) - bound_at).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_weld_holds_relative_angle() {
+        let welded = arm_sag(Engine::add_weld);
+        let rigid = arm_sag(Engine::add_rigid);
+        assert!(welded < 0.01, "the welded arm turned by {welded}");
+        assert!(rigid > 0.01, "the rigidly bound arm only turned by {rigid}");
+    }
+
+    #[test]
+    fn test_level_hinge_swings_pendulum() {
+        let level = Level::parse(
+            "(
+                initial_ball_position: (3.0, 0.07),
+                circles: [],
+                polygons: [
+                    (shape: [(2.5, -0.1), (3.5, -0.1), (3.5, 0.0), (2.5, 0.0)], is_static: true, is_bindable: false),
+                    (shape: [(-0.05, 0.95), (0.05, 0.95), (0.05, 1.05), (-0.05, 1.05)], is_static: true, is_bindable: false),
+                    (shape: [(0.0, 0.98), (0.6, 0.98), (0.6, 1.02), (0.0, 1.02)], is_static: false, is_bindable: true),
+                ],
+                flags_positions: [],
+                bindings: [(kind: Hinge, entity_a: Polygon(1), entity_b: Polygon(2), at: (0.02, 1.0))],
+                pins: [(kind: Rigid, at: (0.5, 1.0))],
+            )",
+        )
+        .unwrap();
+        let mut engine = EngineBuilder::new(level).build();
+        let pendulum = engine.entity_ids()[3];
+        assert_eq!(engine.entities[2].bindings.len(), 1);
+        assert_eq!(engine.entities[3].unbound.len(), 1);
+
+        let pivot = Point(0.02, 1.0);
+        let length = pivot.to(engine.entity_position(pendulum).unwrap()).norm();
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+        let position = engine.entity_position(pendulum).unwrap();
+        assert!(position.1 < 0.85, "the pendulum only fell to {position:?}");
+        assert!((pivot.to(position).norm() - length).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_box_slides_down_steep_slope() {
+        let (mut engine, block) = box_on_slope(45.0);
+        let start = engine.entity_position(block).unwrap();
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+        let Point(x, y) = engine.entity_position(block).unwrap();
+        assert!(x < start.0 - 0.05 && y < start.1 - 0.05);
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_colors() {
+        let colors = |seed| {
+            let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5)))
+                .seed(seed)
+                .build();
+            engine
+                .add_polygon(make_shape! {
+                    (-0.8, -0.8),
+                    (-0.4, -0.8),
+                    (-0.4, -0.5),
+                    (-0.8, -0.5),
+                })
+                .unwrap();
+            engine
+                .add_circle(Circle::new(Point(0.6, -0.2), 0.1))
+                .unwrap();
+            engine.slice(Point(-0.65, -1.0), Point(-0.55, 0.0));
+            // drops the sliced polygon from the shapes to display
+            engine.step(STEP);
+
+            engine
+                .polygons
+                .iter()
+                .map(|polygon| polygon.color)
+                .chain(engine.circles.iter().map(|circle| circle.color))
+                .collect::<Vec<_>>()
+        };
+
+        let first = colors(7);
+        assert_eq!(first, colors(7));
+        assert_ne!(first, colors(8));
+        // both halves of the sliced polygon keep its color
+        assert_eq!(first[0], first[1]);
+    }
+
+    #[test]
+    fn test_palette_only_gives_its_colors() {
+        let palette = vec![[1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5)))
+            .shape_colors(ShapeColors::Palette(palette.clone()))
+            .build();
+        for i in 0..20 {
+            let x = -0.9 + 0.09 * i as f64;
+            engine
+                .add_polygon(make_shape! {
+                    (x, -0.8),
+                    (x + 0.05, -0.8),
+                    (x + 0.05, -0.75),
+                    (x, -0.75),
+                })
+                .unwrap();
+        }
+        let colors: Vec<_> = engine
+            .polygons
+            .iter()
+            .map(|polygon| polygon.color)
+            .collect();
+        assert_eq!(colors.len(), 20);
+        assert!(colors.iter().all(|color| palette.contains(color)));
+        // both colors are used
+        assert!(palette.iter().all(|color| colors.contains(color)));
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let empty = ShapeColors::Palette(vec![]);
+        assert!(DEFAULT_SHAPE_PALETTE.contains(&empty.pick(&mut rng)));
+    }
+
+    #[test]
+    fn test_random_colors_stand_out_from_background() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let dark = (0..1000)
+            .map(|_| [rng.gen(), rng.gen(), rng.gen()])
+            .filter(|&color| luminance(color) < MIN_SHAPE_LUMINANCE)
+            .count();
+        // uniformly random colors would often be too dark
+        assert!(dark > 50, "only {dark} of the colors were too dark");
+
+        let colors: Vec<_> = (0..1000)
+            .map(|_| ShapeColors::Random.pick(&mut rng))
+            .collect();
+        assert!(colors
+            .iter()
+            .all(|&color| luminance(color) >= MIN_SHAPE_LUMINANCE));
+        assert!(DEFAULT_SHAPE_PALETTE
+            .iter()
+            .all(|&color| luminance(color) >= MIN_SHAPE_LUMINANCE));
+    }
+
+    #[test]
+    fn test_slice_conserves_area() {
+        let (mut engine, _rx) = init_engine();
+        engine
+            .add_polygon(make_shape! {
+                (-0.8, -0.8),
+                (-0.4, -0.8),
+                (-0.4, -0.5),
+                (-0.8, -0.5),
+            })
+            .unwrap();
+        engine
+            .add_circle(Circle::new(Point(-0.6, -0.2), 0.1))
+            .unwrap();
+        let area = total_drawn_area(&engine);
+        let n_of_entities = engine.entities.len();
+
+        engine.slice(Point(-0.65, -1.0), Point(-0.55, 0.0));
+
+        assert_eq!(engine.entities.len(), n_of_entities + 2);
+        assert!((total_drawn_area(&engine) - area).abs() < area * 0.02);
+    }
+
+    #[test]
+    fn test_slice_refuses_level_geometry() {
+        let (mut engine, _rx) = init_engine();
+        let n_of_entities = engine.entities.len();
+
+        // crosses both static level polygons and the main ball
+        engine.slice(Point(0.25, -0.5), Point(0.25, 2.0));
+        engine.slice(Point(-0.5, 0.5), Point(0.5, 0.5));
+
+        assert_eq!(engine.entities.len(), n_of_entities);
+    }
+
+    #[test]
+    fn test_slice_moves_bindings_to_halves() {
+        let (mut engine, _rx) = init_engine();
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.6, -1.0),
+                (-0.6, -0.8),
+                (-1.0, -0.8),
+            })
+            .unwrap();
+        engine.add_hinge(Point(-0.65, -0.9));
+        engine
+            .add_polygon(make_shape! {
+                (-0.7, -0.95),
+                (-0.5, -0.95),
+                (-0.5, -0.85),
+                (-0.7, -0.85),
+            })
+            .unwrap();
+
+        engine.slice(Point(-0.8, -1.1), Point(-0.8, -0.7));
+
+        let [.., left, right, _] = &engine.entities[..] else {
+            panic!("not enough entities");
+        };
+        assert!(left.bindings.is_empty());
+        assert_eq!(right.bindings.len(), 1);
+        let (binding, _, _) = right.bindings[0];
+        assert!(binding
+            .first_anchor(&*right.shape.borrow())
+            .is_close_enough_to(Point(-0.65, -0.9)));
+    }
+
+    #[test]
+    fn test_launch_mode_holds_ball_until_launched() {
+        let (mut engine, _rx) = init_engine();
+        engine.launch_mode = true;
+        engine.reset_level();
+        let start = main_ball_position(&engine);
+
+        engine.jump();
+        for _ in 0..20 {
+            engine.step(STEP);
+        }
+        assert!(main_ball_position(&engine).is_close_enough_to(start));
+
+        // dragging to the left of the ball launches it to the right
+        engine.start_aiming();
+        engine.aim_at(start - Point(0.3, 0.0));
+        engine.launch();
+        engine.step(STEP);
+
+        let moved = start.to(main_ball_position(&engine));
+        assert!(moved.0 > 0.0);
+        assert!(!engine.entities[0].is_static);
+    }
+
+    #[test]
+    fn test_tilt_accumulates_and_is_clamped() {
+        let (mut engine, _rx) = init_engine();
+        let settle = |engine: &mut Engine| {
+            for _ in 0..100 {
+                engine.step(STEP);
+            }
+        };
+
+        engine.tilt_by(0.3);
+        engine.tilt_by(0.2);
+        // the world turns gradually rather than jumping to the target
+        engine.step(STEP);
+        assert!(engine.angle() > 0.0 && engine.angle() < 0.5);
+        settle(&mut engine);
+        assert!((engine.angle() - 0.5).abs() < 1e-6);
+
+        engine.set_target_angle(-0.2);
+        settle(&mut engine);
+        assert!((engine.angle() + 0.2).abs() < 1e-6);
+
+        engine.tilt_by(-10.0);
+        settle(&mut engine);
+        assert_eq!(engine.angle(), -MAX_TILT);
+
+        engine.set_target_angle(10.0);
+        settle(&mut engine);
+        assert_eq!(engine.angle(), MAX_TILT);
+    }
+
+    #[test]
+    fn test_toggling_static_freezes_a_falling_box() {
+        let mut level = Level::new(Point(-5.0, 0.0));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(0.0, 1.0),
+                Point(0.2, 1.0),
+                Point(0.2, 1.2),
+                Point(0.0, 1.2),
+            ],
+            false,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        let falling_box = engine.entity_ids()[1];
+        for _ in 0..20 {
+            engine.step(STEP);
+        }
+        let frozen_at = engine.entity_position(falling_box).unwrap();
+        assert!(frozen_at.1 < 1.1);
+
+        engine.select_at(frozen_at);
+        assert_eq!(
+            engine.selected_entity().map(|selected| selected.id),
+            Some(falling_box)
+        );
+        engine.edit_selected(EntityEdit::ToggleStatic);
+        for _ in 0..50 {
+            engine.step(STEP);
+        }
+        assert_eq!(engine.entity_position(falling_box), Some(frozen_at));
+        assert!(engine.selected_entity().unwrap().cfg.is_static);
+
+        // it gets its mass back and falls again
+        engine.edit_selected(EntityEdit::ToggleStatic);
+        for _ in 0..20 {
+            engine.step(STEP);
+        }
+        assert!(engine.entity_position(falling_box).unwrap().1 < frozen_at.1);
+    }
+
+    #[test]
+    fn test_notes_are_kept_in_level_coordinates() {
+        let mut level = Level::new(Point(-5.0, 0.0));
+        level.notes = vec![(Point(0.3, 0.3), "jump here".into())];
+        let mut engine = EngineBuilder::new(level).build();
+
+        engine.angle = consts::FRAC_PI_2 as f32;
+        engine.add_note(Point(0.0, 0.5), "this gap is too wide".into());
+        let [(first, _), (added, text)] = &engine.notes[..] else {
+            panic!("expected two notes, got {:?}", engine.notes);
+        };
+        assert_eq!(*first, Point(0.3, 0.3));
+        assert!(added.is_close_enough_to(Point(0.5, 0.0)));
+        assert_eq!(text, "this gap is too wide");
+    }
+
+    #[test]
+    fn test_editing_friction_of_selected_entity() {
+        let mut level = Level::new(Point(-5.0, 0.0));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(0.0, 0.0),
+                Point(0.2, 0.0),
+                Point(0.2, 0.2),
+                Point(0.0, 0.2),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        let floor = engine.entity_ids()[1];
+        engine.select_at(Point(0.1, 0.1));
+
+        engine.edit_selected(EntityEdit::Friction(0.25));
+        let friction = |engine: &Engine| {
+            let entity = engine.entities.iter().find(|e| e.id == floor).unwrap();
+            let collided_with = entity.shape.borrow_mut().collision_data_mut().friction;
+            (entity.cfg().friction, collided_with)
+        };
+        let raised = shape::DEFAULT_FRICTION + 0.25;
+        assert_eq!(friction(&engine), (raised, raised));
+
+        // it never goes negative
+        engine.edit_selected(EntityEdit::Friction(-10.0));
+        assert_eq!(friction(&engine), (0.0, 0.0));
+    }
+
+    /// the mass, inertia and displayed color of the polygon added last
+    fn last_polygon(engine: &Engine) -> (f64, f64, [f32; 3]) {
+        let entity = engine.entities.last().unwrap();
+        let (mass, inertia) = {
+            let mut shape = entity.shape.borrow_mut();
+            let data = shape.collision_data_mut();
+            (data.mass, data.inertia)
+        };
+        let color = engine.polygons.last().unwrap().color;
+        (mass, inertia, color)
+    }
+
+    #[test]
+    fn test_set_static_stops_moving_entity() {
+        let mut level = Level::new(Point(-5.0, 0.0));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(0.0, 1.0),
+                Point(0.2, 1.0),
+                Point(0.2, 1.2),
+                Point(0.0, 1.2),
+            ],
+            false,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        let id = engine.entity_ids()[1];
+        for _ in 0..20 {
+            engine.step(STEP);
+        }
+        let (_, _, dynamic_color) = last_polygon(&engine);
+
+        engine.set_static(id, true);
+        let stopped_at = engine.entity_position(id).unwrap();
+        for _ in 0..20 {
+            engine.step(STEP);
+        }
+        let (mass, inertia, color) = last_polygon(&engine);
+        assert_eq!((mass, inertia), (f64::INFINITY, f64::INFINITY));
+        assert_eq!(engine.entity_position(id), Some(stopped_at));
+        assert!(engine.entity_cfg(id).unwrap().is_static);
+        assert_ne!(color, dynamic_color);
+    }
+
+    #[test]
+    fn test_set_dynamic_uses_current_shape() {
+        let mut level = Level::new(Point(-5.0, 0.0));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(0.0, 1.0),
+                Point(0.4, 1.0),
+                Point(0.4, 1.1),
+                Point(0.0, 1.1),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        let id = engine.entity_ids()[1];
+        let (_, _, static_color) = last_polygon(&engine);
+        {
+            let mut shape = engine.entities.last().unwrap().shape.borrow_mut();
+            shape.translate(Point(1.0, 0.5));
+            shape.rotate(0.7);
+        }
+
+        engine.set_static(id, false);
+        let (mass, inertia, color) = last_polygon(&engine);
+        let outline = engine.entities.last().unwrap().shape.borrow().outline();
+        let (expected_mass, expected_inertia) = Polygon::new(outline).dynamic_mass_and_inertia();
+        assert!((mass - expected_mass).abs() < 1e-9);
+        assert!((mass - 0.04).abs() < 1e-9);
+        assert!((inertia - expected_inertia).abs() < 1e-9);
+        assert!(!engine.entity_cfg(id).unwrap().is_static);
+        assert_ne!(color, static_color);
+
+        // now it falls
+        let start = engine.entity_position(id).unwrap();
+        for _ in 0..10 {
+            engine.step(STEP);
+        }
+        assert!(engine.entity_position(id).unwrap().1 < start.1);
+    }
+
+    /// how far a floating box has turned and moved after a ball hits it near its top edge
+    fn box_hit_off_center(lock_rotation: bool) -> (f64, f64) {
+        // the static floor only keeps the box inside the level bounds
+        let mut level = Level::new(Point(0.8, -0.4));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.6),
+                Point(1.0, -0.6),
+                Point(1.0, -0.5),
+                Point(-1.0, -0.5),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level)
+            .params(PhysicsParams {
+                gravity_coefficient: 0.0,
+                ..Default::default()
+            })
+            .build();
+        let block = engine
+            .spawn_polygon(
+                Polygon::new(vec![
+                    Point(-0.05, -0.05),
+                    Point(0.05, -0.05),
+                    Point(0.05, 0.05),
+                    Point(-0.05, 0.05),
+                ]),
+                EntityCfg {
+                    lock_rotation,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        engine
+            .spawn_circle(Circle::new(Point(-0.3, 0.04), 0.05), EntityCfg::default())
+            .unwrap();
+        engine
+            .entities
+            .last()
+            .unwrap()
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .velocity = Point(2.0, 0.0);
+
+        let edge_angle = |engine: &Engine| {
+            let entity = engine.entities.iter().find(|entity| entity.id == block);
+            let outline = entity.unwrap().shape.borrow().outline();
+            let edge = outline[0].to(outline[1]);
+            edge.1.atan2(edge.0)
+        };
+        let start = edge_angle(&engine);
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+        let turned = (edge_angle(&engine) - start).abs();
+        (turned, engine.entity_position(block).unwrap().0)
+    }
+
+    #[test]
+    fn test_rotation_locked_box_slides_without_spinning() {
+        let (free_turn, free_shift) = box_hit_off_center(false);
+        assert!(free_turn > 0.1, "the free box only turned by {free_turn}");
+        assert!(free_shift > 0.01);
+
+        let (locked_turn, locked_shift) = box_hit_off_center(true);
+        assert!(locked_turn < 1e-9, "the locked box turned by {locked_turn}");
+        assert!(locked_shift > 0.01);
+    }
+
+    #[test]
+    fn test_objectives_in_level_order() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.flags_positions = vec![Point(-0.95, 0.45), Point(0.25, 0.45)];
+        level.doors.push((
+            vec![
+                Point(-0.5, 0.4),
+                Point(-0.4, 0.4),
+                Point(-0.4, 0.6),
+                Point(-0.5, 0.6),
+            ],
+            "next.ron".into(),
+        ));
+        let engine = EngineBuilder::new(level).build();
+
+        let objectives = engine.objectives();
+        assert_eq!(
+            objectives
+                .iter()
+                .map(|objective| objective.kind)
+                .collect::<Vec<_>>(),
+            [
+                ObjectiveKind::Flag,
+                ObjectiveKind::Flag,
+                ObjectiveKind::Door
+            ]
+        );
+        assert!(objectives[0].at.is_close_enough_to(Point(-0.9, 0.5)));
+        assert!(objectives[1].at.is_close_enough_to(Point(0.3, 0.5)));
+        assert!(objectives[2].at.is_close_enough_to(Point(-0.45, 0.5)));
+    }
+
+    #[test]
+    fn test_door_preview_is_cached_until_file_changes() {
+        let path = std::env::temp_dir().join(format!("whisky-door-{}.ron", std::process::id()));
+        let target = "(initial_ball_position: (0.0, 0.5), circles: [], flags_positions: [],
+            polygons: [(shape: [(-1.0, -0.1), (1.0, -0.1), (1.0, 0.0), (-1.0, 0.0)],
+            is_static: true, is_bindable: false)])";
+        fs::write(&path, target).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let door = |x: f64| {
+            (
+                vec![
+                    Point(x, 0.4),
+                    Point(x + 0.1, 0.4),
+                    Point(x + 0.1, 0.6),
+                    Point(x, 0.6),
+                ],
+                path.to_string_lossy().into_owned(),
+            )
+        };
+
+        let mut far = Level::new(Point(0.0, 0.5));
+        far.doors.push(door(0.6));
+        assert!(EngineBuilder::new(far).build().door_preview().is_none());
+
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.doors.push(door(0.1));
+        let mut engine = EngineBuilder::new(level).build();
+        let preview = engine.door_preview().unwrap();
+        assert!(preview.door.distance_to(Point(0.0, 0.5)) < DOOR_PREVIEW_DISTANCE);
+        let loaded = preview.level.unwrap();
+        assert_eq!(loaded.polygons.len(), 1);
+
+        // the file isn't read again while it stays the same
+        fs::write(&path, "not a level").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(engine.door_preview().unwrap().level, Some(loaded.clone()));
+
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        // nor is the change noticed until it's time to check the file again
+        assert_eq!(engine.door_preview().unwrap().level, Some(loaded));
+        let cached = engine.door_previews.values_mut().next().unwrap();
+        cached.checked -= DOOR_PREVIEW_RECHECK;
+        assert_eq!(engine.door_preview().unwrap().level, None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_radial_impulse_pushes_nearer_entities_harder() {
+        let mut engine = EngineBuilder::new(Level::new(Point(0.8, 0.8)))
+            .params(PhysicsParams {
+                gravity_coefficient: 0.0,
+                ..Default::default()
+            })
+            .build();
+        let center = Point(0.0, 0.0);
+        let offsets = [Point(0.1, 0.0), Point(0.0, -0.2), Point(-0.3, 0.0)];
+        let near = offsets.map(|offset| {
+            engine
+                .spawn_circle(Circle::new(center + offset, 0.02), EntityCfg::default())
+                .unwrap()
+        });
+        let inside = engine
+            .spawn_circle(Circle::new(center, 0.02), EntityCfg::default())
+            .unwrap();
+        let outside = engine
+            .spawn_circle(Circle::new(Point(0.0, 0.5), 0.02), EntityCfg::default())
+            .unwrap();
+        let wall = engine
+            .spawn_circle(
+                Circle::new(Point(0.0, 0.1), 0.02),
+                EntityCfg {
+                    is_static: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        engine.apply_radial_impulse(center, 0.01, 0.4);
+
+        let velocity = |id: EntityId| {
+            let entity = engine.entities.iter().find(|entity| entity.id == id);
+            let velocity = entity
+                .unwrap()
+                .shape
+                .borrow_mut()
+                .collision_data_mut()
+                .velocity;
+            velocity
+        };
+        let speeds = near.map(|id| velocity(id).norm());
+        for (id, offset) in near.into_iter().zip(offsets) {
+            assert!(velocity(id).unit().is_close_enough_to(offset.unit()));
+        }
+        assert!(
+            speeds.windows(2).all(|pair| pair[1] < pair[0]),
+            "{speeds:?}"
+        );
+        // the impulse falls off linearly with the distance
+        assert!((speeds[0] / speeds[2] - 3.0).abs() < 1e-9);
+        assert!(velocity(inside)
+            .unit()
+            .is_close_enough_to(-engine.gravity_direction()));
+        assert_eq!(velocity(outside), Vector::ZERO);
+        assert_eq!(velocity(wall), Vector::ZERO);
+        assert_eq!(velocity(engine.main_ball_id()), Vector::ZERO);
+    }
+
+    #[test]
+    fn test_level_index_of_any_level_name() {
+        assert_eq!(level_index("level5.ron"), 5);
+        assert_eq!(level_index("levels/level12.ron"), 12);
+        assert_eq!(level_index("default.ron"), 0);
+        assert_eq!(level_index("/home/player/level.ron"), 0);
+
+        let (engine, rx) = init_engine();
+        let mut engine = engine.reload_level(
+            Level::new(Point(0.0, 0.5)),
+            "daily/2026-10-16.ron".to_string(),
+        );
+        engine.step(Duration::from_millis(10));
+        assert_eq!(rx.try_recv().unwrap().level_idx, 0);
+    }
+
+    #[test]
+    fn test_spawners_keep_at_most_max_alive() {
+        let mut level = Level::new(Point(5.0, 5.0));
+        // nothing falls, so the spawned entities only disappear when they are replaced
+        level.physics.gravity_coefficient = Some(0.0);
+        level.spawners.push(Spawner {
+            region: vec![
+                Point(-1.0, -1.0),
+                Point(1.0, -1.0),
+                Point(1.0, 1.0),
+                Point(-1.0, 1.0),
+            ],
+            shape: SpawnShape::Circle { radius: 0.02 },
+            interval_seconds: 0.05,
+            max_alive: 5,
+            initial_velocity: Point(0.0, 0.0),
+            lifetime: None,
+        });
+        let mut engine = EngineBuilder::new(level).seed(7).build();
+
+        let spawned: Vec<_> = (0..100)
+            .map(|_| {
+                engine.step(Duration::from_millis(10));
+                engine.entity_ids().len() - 1
+            })
+            .collect();
+        assert!(spawned.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(spawned.iter().max(), Some(&5));
+        assert_eq!(spawned.last(), Some(&5));
+
+        let replaced = engine
+            .take_events()
+            .into_iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    GameEvent::EntityRemoved {
+                        reason: RemovalReason::Replaced,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert!(replaced > 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_entities")]
+    fn test_no_room_for_main_ball_is_rejected() {
+        EngineBuilder::new(Level::new(Point(0.0, 0.5))).params(PhysicsParams {
+            max_entities: 0,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_drawing_is_refused_past_max_entities() {
+        let mut level = Level::new(Point(0.5, 0.9));
+        level.physics.gravity_coefficient = Some(0.0);
+        // keeps the drawn entities within the bounds of the level
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -1.0),
+                Point(1.0, -1.0),
+                Point(1.0, -0.9),
+                Point(-1.0, -0.9),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level)
+            .params(PhysicsParams {
+                max_entities: 5,
+                ..Default::default()
+            })
+            .build();
+
+        let square =
+            |x: f64| make_shape! { (x, 0.0), (x + 0.05, 0.0), (x + 0.05, 0.05), (x, 0.05) };
+        let drawn: Vec<_> = (0..5)
+            .map(|i| {
+                let x = i as f64 * 0.1;
+                let drawn = if i % 2 == 0 {
+                    engine.add_polygon(square(x))
+                } else {
+                    engine.add_circle(Circle::new(Point(x, 0.5), 0.02))
+                };
+                engine.step(Duration::from_millis(10));
+                drawn
+            })
+            .collect();
+        let refused = Err(TooManyEntities { max_entities: 5 });
+        assert_eq!(drawn, [Ok(()), Ok(()), Ok(()), refused, refused]);
+
+        let ids = engine.entity_ids();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(
+            engine.add_level_shape(vec![Point(-1.0, -1.0), Point(1.0, -1.0), Point(1.0, -0.9)]),
+            refused
+        );
+        // crosses both squares, neither of which can be cut in two
+        engine.slice(Point(-1.0, 0.025), Point(1.0, 0.025));
+        for _ in 0..50 {
+            engine.step(Duration::from_millis(10));
+        }
+        assert_eq!(engine.entity_ids(), ids);
+
+        // erasing makes room for one more
+        engine.erase_at(Point(0.025, 0.025));
+        engine.slice(Point(-1.0, 0.025), Point(1.0, 0.025));
+        assert_eq!(engine.entity_ids().len(), 5);
+        assert_eq!(
+            engine.add_circle(Circle::new(Point(0.5, 0.5), 0.02)),
+            refused
+        );
+    }
+
+    #[test]
+    fn test_spawners_skip_past_max_entities() {
+        let mut level = Level::new(Point(0.9, 0.9));
+        level.physics.gravity_coefficient = Some(0.0);
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -1.0),
+                Point(1.0, -1.0),
+                Point(1.0, -0.9),
+                Point(-1.0, -0.9),
+            ],
+            true,
+        ));
+        level.spawners.push(Spawner {
+            region: vec![
+                Point(-0.8, -0.5),
+                Point(0.8, -0.5),
+                Point(0.8, 0.5),
+                Point(-0.8, 0.5),
+            ],
+            shape: SpawnShape::Circle { radius: 0.02 },
+            interval_seconds: 0.05,
+            max_alive: 20,
+            initial_velocity: Point(0.0, 0.0),
+            lifetime: None,
+        });
+        let mut engine = EngineBuilder::new(level)
+            .seed(7)
+            .params(PhysicsParams {
+                max_entities: 6,
+                ..Default::default()
+            })
+            .build();
+
+        for _ in 0..100 {
+            engine.step(Duration::from_millis(10));
+            assert!(engine.entity_ids().len() <= 6);
+        }
+        assert_eq!(engine.entity_ids().len(), 6);
+        assert!(!engine
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::EntityRemoved { .. })));
+    }
+
+    #[test]
+    fn test_entity_expires_after_lifetime() {
+        let (mut engine, _rx) = init_engine();
+
+        engine
+            .add_polygon(make_shape! {
+                (-1.0, -1.0),
+                (-0.9, -1.0),
+                (-0.9, -0.9),
+                (-1.0, -0.9),
+            })
+            .unwrap();
+        engine.add_hinge(Point(-0.91, -0.91));
+        engine
+            .add_entity(
+                make_shape! {
+                    (-0.92, -0.92),
+                    (-0.8, -0.92),
+                    (-0.8, -0.8),
+                    (-0.92, -0.8),
+                },
+                EntityCfg {
+                    is_static: true,
+                    lifetime: Some(Duration::from_secs(1)),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let n_of_entities = engine.entities.len();
+        let expiring = engine.entities[n_of_entities - 1].id;
+        assert_eq!(engine.entities[n_of_entities - 2].bindings.len(), 1);
+
+        for _ in 0..99 {
+            engine.step(STEP);
+        }
+        assert_eq!(engine.entities.len(), n_of_entities);
+        assert!(engine.take_events().is_empty());
+
+        engine.step(STEP);
+        engine.step(STEP);
+        assert_eq!(engine.entities.len(), n_of_entities - 1);
+        assert!(engine.entities[n_of_entities - 2].bindings.is_empty());
+        assert_eq!(
+            engine.take_events(),
+            vec![GameEvent::EntityRemoved {
+                id: expiring,
+                reason: RemovalReason::Expired,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lasers_dont_drift() {
+        let (mut engine, _rx) = init_engine();
+        let laser = |angular_speed| Laser {
+            point: Point(0.0, 2.0),
+            initial_direction: Point(0.6, 0.8),
+            current_angle: 0.0,
+            angular_speed,
+            range: 0.5,
+        };
+        engine.lasers = vec![laser(0.0), laser(0.01)];
+
+        for _ in 0..10000 {
+            engine.sweep_lasers();
+        }
+
+        assert_eq!(engine.lasers[0].direction(), Point(0.6, 0.8));
+        let sweeping = &engine.lasers[1];
+        assert!((sweeping.direction().norm() - 1.0).abs() < 1e-12);
+        assert!(sweeping.current_angle.abs() <= sweeping.range);
+    }
+
+    #[test]
+    fn test_laser_carves_through_wall() {
+        let (mut engine, _rx) = init_engine();
+        engine.lasers.push(Laser {
+            point: Point(-0.88, 0.25),
+            initial_direction: Point(1.0, 0.0),
+            current_angle: 0.0,
+            angular_speed: 0.0,
+            range: 0.0,
+        });
+        engine.laser_dwells.push(None);
+        engine
+            .add_entity(
+                make_shape! {
+                    (-0.5, 0.1),
+                    (-0.45, 0.1),
+                    (-0.45, 0.4),
+                    (-0.5, 0.4),
+                },
+                EntityCfg {
+                    is_static: true,
+                    is_erasable: false,
+                    is_carvable: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let behind_the_wall = Point(-0.475, 0.25);
+        let is_blocked = |engine: &Engine| {
+            engine
+                .entities
+                .iter()
+                .any(|entity| entity.shape.borrow().includes(behind_the_wall))
+        };
+        assert!(is_blocked(&engine));
+
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+
+        assert!(!is_blocked(&engine));
+        // the parts of the wall above and below the hole remain
+        let n_of_pieces = engine
+            .entities
+            .iter()
+            .filter(|entity| entity.is_carvable)
+            .count();
+        assert!(n_of_pieces >= 2);
+    }
+
+    /// overlapping dynamic circles, so resolving them in a different order would move them
+    /// differently
+    fn add_pile_of_circles(engine: &mut Engine) {
+        for (x, y) in [(-0.8, -0.3), (-0.76, -0.31), (-0.78, -0.27), (-0.82, -0.28)] {
+            engine
+                .add_entity(Circle::new(Point(x, y), 0.03), EntityCfg::default())
+                .unwrap();
+        }
+    }
+
+    fn positions_by_id(engine: &Engine) -> Vec<(EntityId, Point)> {
+        let mut positions: Vec<_> = engine
+            .entities
+            .iter()
+            .map(|entity| {
+                let centroid = entity.shape.borrow_mut().collision_data_mut().centroid;
+                (entity.id, centroid)
+            })
+            .collect();
+        positions.sort_by_key(|(id, _)| *id);
+        positions
+    }
+
+    #[test]
+    fn test_resolution_order_matters() {
+        let pile = |order: [usize; 2]| {
+            let mut circles = [
+                Circle::new(Point(0.0, 0.0), 0.1),
+                Circle::new(Point(0.15, 0.0), 0.1),
+                Circle::new(Point(0.0, 0.12), 0.1),
+            ];
+            let [first, rest @ ..] = &mut circles;
+            for j in order {
+                first.collide(&mut rest[j], STEP, &PhysicsParams::default());
+            }
+            circles.map(|mut circle| circle.collision_data_mut().centroid)
+        };
+
+        assert_ne!(pile([0, 1]), pile([1, 0]));
+    }
+
+    #[test]
+    fn test_storage_order_does_not_affect_step() {
+        let (mut ordered, _ordered_rx) = init_engine();
+        let (mut shuffled, _shuffled_rx) = init_engine();
+        add_pile_of_circles(&mut ordered);
+        add_pile_of_circles(&mut shuffled);
+        shuffled.entities[1..].reverse();
+
+        for _ in 0..20 {
+            ordered.step(STEP);
+            shuffled.step(STEP);
+        }
+
+        assert_eq!(positions_by_id(&ordered), positions_by_id(&shuffled));
+    }
+
+    #[test]
+    fn test_player_motion_is_reported_per_second() {
+        let (mut engine, _rx) = init_engine();
+        {
+            let ball = engine.main_ball.upgrade().unwrap();
+            let mut ball = ball.borrow_mut();
+            let data = ball.collision_data_mut();
+            data.velocity = Point(0.5, 0.0) / (MOVEMENT_COEFFICIENT * 1_000_000.0);
+            data.angular_velocity = -3.0 / (MOVEMENT_COEFFICIENT * 1_000_000.0);
+        }
+        engine.angle = consts::FRAC_PI_2 as f32;
+
+        let player = engine.player();
+        assert!((player.speed() - 0.5).abs() < 1e-9);
+        assert!(player.velocity.is_close_enough_to(Point(0.0, 0.5)));
+        assert!((player.angular_velocity + 3.0).abs() < 1e-9);
+        assert!(player.circle.center.is_close_enough_to(Point(-0.5, 0.0)));
+        assert_eq!(player.circle.radius, 0.07);
+    }
+
+    #[test]
+    fn test_settled_once_dropped_ball_comes_to_rest() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(1.0, -0.1),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).settle_after(20).build();
+
+        let mut settled_at = vec![];
+        for i in 0..500 {
+            engine.step(STEP);
+            if engine.take_events().contains(&GameEvent::Settled) {
+                settled_at.push(i);
+            }
+        }
+
+        // the ball has to fall and stop bouncing first
+        assert_eq!(settled_at.len(), 1);
+        assert!(settled_at[0] > 20);
+        assert!(engine.player().speed() < 0.01);
+        let Point(_, height) = engine.entity_position(engine.main_ball_id()).unwrap();
+        assert!(height < 0.1);
+    }
+
+    #[test]
+    fn test_falling_out_of_level_loses_ball() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.flags_positions.push(Point(1.0, 1.0));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+
+        let mut lost = vec![];
+        for _ in 0..1000 {
+            engine.step(STEP);
+            lost.extend(engine.take_events().into_iter().filter_map(|event| {
+                let GameEvent::BallLost { at, cause } = event else {
+                    return None;
+                };
+                assert_eq!(cause, DeathCause::OutOfBounds);
+                Some(at)
+            }));
+            if !lost.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(lost.len(), 1);
+        assert!(lost[0].1 < -5.0);
+        assert!(main_ball_position(&engine).1 > 0.0);
+
+        let frame = rx.try_recv().unwrap();
+        assert_eq!(
+            frame.level_bounds,
+            Aabb {
+                min: Point(0.0, 0.5),
+                max: Point(1.1, 1.1),
+            }
+        );
+    }
+
+    #[test]
+    fn test_frames_tell_camera_about_start_and_lost_ball() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.flags_positions.push(Point(1.0, 1.0));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+
+        let mut frames = vec![];
+        for _ in 0..1000 {
+            engine.step(STEP);
+            frames.extend(
+                rx.try_recv()
+                    .ok()
+                    .map(|frame| (frame.level_started, frame.ball_lost)),
+            );
+            if frames.last().is_some_and(|(_, lost)| lost.is_some()) {
+                break;
+            }
+        }
+        assert_eq!(frames[0], (true, None));
+        let (started, lost) = frames.pop().unwrap();
+        assert!(!started && lost.unwrap().1 < -5.0);
+        assert!(frames[1..].iter().all(|&frame| frame == (false, None)));
+
+        engine.restart();
+        engine.step(STEP);
+        assert_eq!(rx.try_recv().map(|frame| frame.level_started), Ok(true));
+    }
+
+    #[test]
+    fn test_ball_lost_in_level_entered_through_door() {
+        let (engine, _rx) = init_engine();
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.flags_positions.push(Point(1.0, 1.0));
+        let mut engine = engine.reload_level(level, "level2.ron".to_string());
+
+        let mut lost = vec![];
+        for _ in 0..1000 {
+            engine.step(STEP);
+            lost.extend(
+                engine
+                    .take_events()
+                    .into_iter()
+                    .filter(|event| matches!(event, GameEvent::BallLost { .. })),
+            );
+            if !lost.is_empty() {
+                break;
+            }
+        }
+        assert!(matches!(
+            lost[..],
+            [GameEvent::BallLost {
+                cause: DeathCause::OutOfBounds,
+                ..
+            }]
+        ));
+        assert_eq!(engine.next_level.as_deref(), Some("level5.ron"));
+    }
+
+    #[test]
+    fn test_ghost_follows_run_time() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(1.0, -0.1),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+        // moves right by 1 every second
+        engine.race(Ghost {
+            level: "level1".to_string(),
+            preset: "classic".to_string(),
+            time_ms: 2000,
+            interval_ms: 50,
+            samples: (0..=40).map(|i| (i as f32 * 0.05, 0.5)).collect(),
+        });
+        let ghost_after = |engine: &mut Engine, steps: usize| {
+            let mut frame = None;
+            for _ in 0..steps {
+                engine.step(STEP);
+                frame = rx.try_recv().ok();
+            }
+            frame.unwrap().ghost.unwrap().0
+        };
+
+        assert!((ghost_after(&mut engine, 30) - 0.3).abs() < 1e-6);
+        // the snapshot of the present is restored first, then the one before it
+        engine.set_rewinding(true);
+        assert!((ghost_after(&mut engine, 20) - 0.2).abs() < 1e-6);
+        engine.set_rewinding(false);
+        engine.reset_level();
+        assert!((ghost_after(&mut engine, 1) - 0.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_painted_ball_leaves_marks() {
+        const BLUE: [f32; 3] = [0.1, 0.2, 0.9];
+        let floor = |from: f64, to: f64| {
+            levels::Entity::new(
+                vec![
+                    Point(from, -0.1),
+                    Point(to, -0.1),
+                    Point(to, 0.0),
+                    Point(from, 0.0),
+                ],
+                true,
+            )
+        };
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.polygons.push(levels::Entity {
+            paint: Some(BLUE),
+            ..floor(-1.0, 0.0)
+        });
+        level.polygons.push(floor(0.0, 1.0));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+
+        let mut marks = vec![];
+        let mut ball_color = None;
+        for i in 0..200 {
+            // lands across both floors, then rolls off the painted one
+            if i == 100 {
+                engine.angle = -0.3;
+                engine.target_angle = -0.3;
+            }
+            engine.step(STEP);
+            if let Ok(frame) = rx.try_recv() {
+                marks.extend(frame.paint_marks);
+                ball_color = Some(frame.circles[0].color);
+            }
+        }
+        assert_eq!(ball_color, Some(BLUE));
+        assert!(marks.len() > 2);
+        for pair in marks.windows(2) {
+            assert_eq!(pair[1].color, BLUE);
+            assert!(pair[0].at.to(pair[1].at).norm() >= PAINT_MARK_SPACING);
+            assert!(0.0 < pair[1].strength && pair[1].strength <= 1.0);
+        }
+
+        engine.reset_level();
+        engine.step(STEP);
+        let frame = rx.try_recv().unwrap();
+        assert!(frame.clear_paint);
+        assert!(frame.paint_marks.is_empty());
+        assert_ne!(frame.circles[0].color, BLUE);
+    }
+
+    #[test]
+    fn test_rolling_ball_reports_one_contact() {
+        let mut level = Level::new(Point(-0.8, 0.2));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(3.0, -0.1),
+                Point(3.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        let ball = engine.main_ball_id();
+        let floor = engine.entities[1].id;
+
+        let mut events = vec![];
+        for i in 0..300 {
+            // lands, then rolls along the floor for 2 seconds before jumping off it
+            if i == 50 {
+                engine.angle = -0.3;
+                engine.target_angle = -0.3;
+            }
+            if i == 250 {
+                engine.jump();
+            }
+            engine.step(STEP);
+            events.extend(engine.take_events());
+        }
+        assert!(main_ball_position(&engine).0 > -0.6);
+        let phases: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Collision(collision) if collision.entities == [ball, floor] => {
+                    Some(collision.phase)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(phases, [ContactPhase::Began, ContactPhase::Ended]);
+    }
+
+    #[test]
+    fn test_displayed_centroids_turn_with_world() {
+        let mut level = Level::new(Point(0.0, 0.5));
+        let square = vec![
+            Point(0.4, -0.2),
+            Point(0.6, -0.2),
+            Point(0.6, 0.0),
+            Point(0.4, 0.0),
+        ];
+        level.polygons.push(levels::Entity::new(square, true));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+        engine.angle = 0.3;
+        engine.target_angle = 0.3;
+        engine.step(STEP);
+
+        let frame = rx.try_recv().unwrap();
+        let displayed = frame.polygons[0].shape.centroid();
+        assert!(displayed.to(Point(0.5, -0.1).rotate(0.3)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_collision_classification_threshold() {
+        let touch = |impulse| Touch {
+            point: Point(0.0, 0.0),
+            normal: Point(0.0, -1.0),
+            depth: 0.001,
+            impulse,
+        };
+        let is_strong = |impulse, masses| {
+            matches!(
+                CollisionType::classify(Some(touch(impulse)), masses, 1.0),
+                CollisionType::Strong(_)
+            )
+        };
+        let on_floor = [0.01, f64::INFINITY];
+        assert!(!is_strong(0.0099, on_floor));
+        assert!(is_strong(0.0101, on_floor));
+        // the same impulse barely moves something heavier
+        assert!(!is_strong(0.0101, [0.02, 0.03]));
+        assert!(matches!(
+            CollisionType::classify(None, on_floor, 1.0),
+            CollisionType::None
+        ));
+    }
+
+    /// whether a fragile floor breaks when the ball falls on it from the height
+    fn fragile_floor_breaks(
+        height: f64,
+        break_speed: Option<f64>,
+        strong_speed: Option<f64>,
+    ) -> bool {
+        let mut level = Level::new(Point(0.0, 0.07 + height));
+        level.physics.strong_collision_speed = strong_speed;
+        level.polygons.push(levels::Entity {
+            is_fragile: true,
+            break_speed,
+            ..levels::Entity::new(
+                vec![
+                    Point(-1.0, -0.1),
+                    Point(1.0, -0.1),
+                    Point(1.0, 0.0),
+                    Point(-1.0, 0.0),
+                ],
+                true,
+            )
+        });
+        let mut engine = EngineBuilder::new(level).build();
+        for _ in 0..100 {
+            engine.step(STEP);
+        }
+        engine.entities.len() == 1
+    }
+
+    #[test]
+    fn test_fragile_floor_breaks_on_hard_landing() {
+        assert!(!fragile_floor_breaks(0.001, None, None));
+        assert!(fragile_floor_breaks(0.3, None, None));
+        assert!(!fragile_floor_breaks(0.3, Some(10.0), None));
+        assert!(!fragile_floor_breaks(0.3, None, Some(10.0)));
+        assert!(fragile_floor_breaks(0.001, None, Some(0.0001)));
+    }
+
+    #[test]
+    fn test_physics_presets() {
+        let classic = PhysicsParams::default();
+        assert_eq!(PhysicsParams::preset("classic").unwrap().preset, "classic");
+        for name in PRESETS {
+            assert_eq!(PhysicsParams::preset(name).unwrap().preset, name);
+        }
+        let floaty = PhysicsParams::preset("floaty").unwrap();
+        assert!(floaty.gravity_coefficient.abs() < classic.gravity_coefficient.abs());
+        assert!(floaty.linear_damping > 0.0);
+        let snappy = PhysicsParams::preset("snappy").unwrap();
+        assert!(snappy.gravity_coefficient.abs() > classic.gravity_coefficient.abs());
+        assert_eq!(PhysicsParams::preset("bouncy"), None);
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(params_from_args(args(&["level1.ron"])), Ok(classic));
+        assert_eq!(
+            params_from_args(args(&["level1.ron", "--physics=snappy"])),
+            Ok(snappy)
+        );
+        assert_eq!(
+            params_from_args(args(&["--physics=bouncy"])),
+            Err("bouncy".to_string())
+        );
+
+        // the level wins, along with the single parameters it sets on top of its preset
+        let overrides = PhysicsOverrides {
+            preset: Some("floaty".to_string()),
+            movement_coefficient: Some(0.000001),
+            ..Default::default()
+        };
+        let params = PhysicsParams {
+            solver_iterations: 4,
+            ..snappy
+        }
+        .overridden_by(&overrides);
+        assert_eq!(params.preset, "floaty");
+        assert_eq!(params.gravity_coefficient, floaty.gravity_coefficient);
+        assert_eq!(params.movement_coefficient, 0.000001);
+        assert_eq!(params.solver_iterations, 4);
+    }
+
+    #[test]
+    fn test_floaty_ball_falls_slower() {
+        let fallen = |preset| {
+            let mut engine = EngineBuilder::new(Level::new(Point(0.0, 0.5)))
+                .params(PhysicsParams::preset(preset).unwrap())
+                .build();
+            for _ in 0..30 {
+                engine.step(STEP);
+            }
+            0.5 - main_ball_position(&engine).1
+        };
+        assert!(fallen("floaty") < fallen("classic"));
+        assert!(fallen("classic") < fallen("snappy"));
+    }
+
+    #[test]
+    fn test_deep_overlap_is_undone_gently() {
+        let mut level = Level::new(Point(-0.8, 0.07));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.2),
+                Point(1.0, -0.2),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        // drawn halfway into the floor
+        engine
+            .add_polygon(make_shape! {
+                (0.5, -0.02),
+                (0.54, -0.02),
+                (0.54, 0.02),
+                (0.5, 0.02),
+            })
+            .unwrap();
+        let drawn = engine.entities.last().unwrap();
+        let (id, radius) = (drawn.id, drawn.shape.borrow().bounding_radius());
+        let centroid = |engine: &Engine| {
+            let entity = engine.entities.iter().find(|entity| entity.id == id);
+            let mut shape = entity.unwrap().shape.borrow_mut();
+            shape.collision_data_mut().centroid
+        };
+
+        let (mut peak_speed, mut peak_shift): (f64, f64) = (0.0, 0.0);
+        let mut previous = centroid(&engine);
+        for _ in 0..100 {
+            engine.step(STEP);
+            let speed = velocity_of(&engine, id).norm() * engine.params.per_second();
+            peak_speed = peak_speed.max(speed);
+            peak_shift = peak_shift.max(previous.to(centroid(&engine)).norm());
+            previous = centroid(&engine);
+        }
+        assert!(
+            peak_speed < 0.05,
+            "popped out at {peak_speed} units per second"
+        );
+        assert!(peak_shift <= 1.01 * MAX_OVERLAP_CORRECTION * radius);
+        // and it ends up resting on the floor
+        assert!((previous.1 - 0.02).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_hard_landing_reaches_display() {
+        let mut level = Level::new(Point(0.0, 1.0));
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(1.0, -0.1),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let (tx, rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(level).display(tx).build();
+        engine.angle = 0.3;
+        engine.target_angle = 0.3;
+
+        let mut impacts = vec![];
+        for _ in 0..300 {
+            engine.step(STEP);
+            if let Ok(frame) = rx.try_recv() {
+                impacts.extend(frame.player.impact);
+            }
+        }
+        let Impact { normal, impulse } = impacts[0];
+        assert!(impulse > 0.0);
+        // the floor is below the ball in the level, which is displayed turned
+        assert!(normal.is_close_enough_to(Point(0.0, -1.0).rotate(0.3)));
+        // resting on the floor afterwards doesn't count as hitting it
+        assert!(impacts.len() < 10);
+    }
+
+    #[test]
+    fn test_entity_flung_upwards_is_despawned() {
+        let (mut engine, _rx) = init_engine();
+        let circle = engine
+            .add_entity(Circle::new(Point(0.2, 2.2), 0.05), EntityCfg::default())
+            .unwrap();
+        let id = engine.entities.last().unwrap().id;
+        let upwards = Point(0.0, 2.0) / (MOVEMENT_COEFFICIENT * 1_000_000.0);
+        let circle = circle.upgrade().unwrap();
+        circle.borrow_mut().collision_data_mut().velocity = upwards;
+
+        for _ in 0..50 {
+            engine.step(STEP);
+        }
+
+        assert!(engine.entities.iter().all(|entity| entity.id != id));
+        assert!(engine.take_events().contains(&GameEvent::EntityRemoved {
+            id,
+            reason: RemovalReason::OutOfBounds,
+        }));
+    }
+
+    /// a box hit in the middle by a laser shining to the right
+    fn laser_engine(is_static: bool, lasers_push: bool) -> (Engine, EntityId) {
+        let mut level = Level::new(Point(-2.0, 2.0));
+        level.lasers_push = lasers_push;
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(0.5, -0.05),
+                Point(0.6, -0.05),
+                Point(0.6, 0.05),
+                Point(0.5, 0.05),
+            ],
+            is_static,
+        ));
+        level.lasers.push(Laser {
+            point: Point(0.0, 0.0),
+            initial_direction: Point(1.0, 0.0),
+            current_angle: 0.0,
+            angular_speed: 0.0,
+            range: 1.0,
+        });
+
+        let engine = EngineBuilder::new(level).build();
+        let target = engine.entity_ids()[1];
+        (engine, target)
+    }
+
+    fn velocity_of(engine: &Engine, id: EntityId) -> Vector {
+        let entity = engine.entities.iter().find(|entity| entity.id == id);
+        let mut shape = entity.unwrap().shape.borrow_mut();
+        shape.collision_data_mut().velocity
+    }
+
+    /// an engine with the ball high above a floor, and a jump which tells whether it did anything
+    fn jumping_engine(jump_refill: JumpRefillMode) -> (Engine, impl Fn(&mut Engine) -> bool) {
+        let mut level = Level::new(Point(0.0, 1.0));
+        level.jump_refill = jump_refill;
+        level.polygons.push(levels::Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(1.0, -0.1),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        let mut engine = EngineBuilder::new(level).build();
+        engine.step(STEP);
+
+        let jump = |engine: &mut Engine| {
+            let ball = engine.main_ball_id();
+            let before = velocity_of(engine, ball);
+            engine.jump();
+            velocity_of(engine, ball) != before
+        };
+        (engine, jump)
+    }
+
+    #[test]
+    fn test_ground_only_jumps_refill_on_landing() {
+        let (mut engine, jump) = jumping_engine(JumpRefillMode::GroundOnly);
+
+        assert!(jump(&mut engine));
+        assert!(jump(&mut engine));
+        assert!(!jump(&mut engine));
+
+        for _ in 0..500 {
+            engine.step(STEP);
+        }
+        assert!(jump(&mut engine));
+    }
+
+    #[test]
+    fn test_always_jumps_run_out_in_the_air() {
+        let (mut engine, jump) = jumping_engine(JumpRefillMode::Always);
+
+        for _ in 0..MAX_JUMPS {
+            assert!(jump(&mut engine));
+            engine.step(STEP);
+        }
+        assert!(!jump(&mut engine));
+    }
+
+    #[test]
+    fn test_always_jumps_refill_while_touching() {
+        let resting = |jump_refill| {
+            let (mut engine, jump) = jumping_engine(jump_refill);
+            // too weak to lift the ball off the floor
+            engine.params.jump_impulse = 1e-6;
+            for _ in 0..500 {
+                engine.step(STEP);
+            }
+            for _ in 0..MAX_JUMPS {
+                engine.jump();
+            }
+            engine.step(STEP);
+            jump(&mut engine)
+        };
+        assert!(resting(JumpRefillMode::Always));
+        assert!(!resting(JumpRefillMode::GroundOnly));
+    }
+
+    #[test]
+    fn test_pushing_laser_accelerates_light_box() {
+        let (mut pushed, box_id) = laser_engine(false, true);
+        let (mut unpushed, _) = laser_engine(false, false);
+        for _ in 0..10 {
+            pushed.step(STEP);
+            unpushed.step(STEP);
+        }
+
+        assert!(velocity_of(&pushed, box_id).0 > 0.0);
+        assert_eq!(velocity_of(&unpushed, box_id).0, 0.0);
+    }
+
+    #[test]
+    fn test_pushing_laser_doesnt_move_static_wall() {
+        let (mut engine, wall) = laser_engine(true, true);
+        let position = engine.entity_position(wall).unwrap();
+        for _ in 0..10 {
+            engine.step(STEP);
+        }
+
+        assert_eq!(velocity_of(&engine, wall), Vector::ZERO);
+        assert_eq!(engine.entity_position(wall), Some(position));
+    }
+
+    #[test]
+    fn test_entity_color_precedence() {
+        let palette = Palette::default();
+        let color = |is_static, is_deadly, is_fragile| {
+            let cfg = EntityCfg {
+                is_static,
+                is_deadly,
+                is_fragile,
+                ..Default::default()
+            };
+            entity_color(&cfg, &palette)
+        };
+
+        assert_eq!(color(true, true, true), palette.deadly);
+        assert_eq!(color(true, true, false), palette.deadly);
+        assert_eq!(color(false, true, true), palette.deadly);
+        assert_eq!(color(true, false, true), palette.fragile);
+        assert_eq!(color(false, false, true), palette.fragile);
+        assert_eq!(color(false, false, false), palette.dynamic);
+        assert_eq!(color(true, false, false), palette.static_);
+    }
+
+    #[test]
+    fn test_doubled_gravity_doubles_fall_distance() {
+        let fall_distance = |engine: &mut Engine| {
+            let ball = engine.main_ball_id();
+            let Point(_, start) = engine.entity_position(ball).unwrap();
+            for _ in 0..20 {
+                engine.step(STEP);
+            }
+            start - engine.entity_position(ball).unwrap().1
+        };
+
+        let mut normal = EngineBuilder::new(Level::new(Point(0.0, 0.5))).build();
+        let mut doubled = EngineBuilder::new(Level::new(Point(0.0, 0.5)))
+            .params(PhysicsParams {
+                gravity_coefficient: 2.0 * GRAVITY_COEFFICIENT,
+                ..Default::default()
+            })
+            .build();
+        let mut tuned = EngineBuilder::new(Level::new(Point(0.0, 0.5))).build();
+        tuned.tune(TunableParam::Gravity, 2.0);
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.physics.gravity_coefficient = Some(2.0 * GRAVITY_COEFFICIENT);
+        let mut overridden = EngineBuilder::new(level).build();
+
+        let normal = fall_distance(&mut normal);
+        assert!(normal > 0.0);
+        for engine in [&mut doubled, &mut tuned, &mut overridden] {
+            assert!((fall_distance(engine) / normal - 2.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_freezing_contacts_saves_solver_iterations() {
+        let settle_pile = |contact_freeze_velocity| {
+            let mut level = Level::new(Point(-0.5, 0.1));
+            level.polygons.push(levels::Entity::new(
+                vec![
+                    Point(-1.0, -0.1),
+                    Point(1.0, -0.1),
+                    Point(1.0, 0.0),
+                    Point(-1.0, 0.0),
+                ],
+                true,
+            ));
+            let mut engine = EngineBuilder::new(level)
+                .params(PhysicsParams {
+                    solver_iterations: 4,
+                    contact_freeze_velocity,
+                    ..Default::default()
+                })
+                .build();
+            let pile: Vec<_> = [(0.0, 0.05), (0.1, 0.05), (0.05, 0.14), (0.2, 0.05)]
+                .into_iter()
+                .map(|(x, y)| {
+                    engine
+                        .spawn_circle(Circle::new(Point(x, y), 0.05), EntityCfg::default())
+                        .unwrap()
+                })
+                .collect();
+
+            let mut solves = 0;
+            for i in 0..300 {
+                engine.step(STEP);
+                if i >= 200 {
+                    solves += engine.contact_solves();
+                }
+            }
+            let heights: Vec<_> = pile
+                .into_iter()
+                .map(|id| engine.entity_position(id).unwrap().1)
+                .collect();
+            (solves, heights)
+        };
+
+        let (thorough, thorough_heights) = settle_pile(0.0);
+        let (frozen, frozen_heights) = settle_pile(0.01);
+        assert!(frozen < thorough);
+        // the pile still rests on the floor the same way
+        for (thorough, frozen) in thorough_heights.into_iter().zip(frozen_heights) {
+            assert!((thorough - frozen).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_static_entities_outside_bounds_stay() {
+        let (mut engine, _rx) = init_engine();
+        engine
+            .add_entity(
+                make_shape! {
+                    (3.0, -10.0),
+                    (3.1, -10.0),
+                    (3.1, 10.0),
+                    (3.0, 10.0),
+                },
+                EntityCfg {
+                    is_static: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let id = engine.entities.last().unwrap().id;
+
+        for _ in 0..50 {
+            engine.step(STEP);
+        }
+
+        assert!(engine.entities.iter().any(|entity| entity.id == id));
+    }
+
+    #[test]
+    fn test_contacts_with_two_floor_tiles() {
+        let (mut engine, _rx) = init_engine();
+        let mut add_tile = |from: f64, to: f64| {
+            engine
+                .add_entity(
+                    make_shape! {
+                        (from, -0.6),
+                        (to, -0.6),
+                        (to, -0.5),
+                        (from, -0.5),
+                    },
+                    EntityCfg {
+                        is_static: true,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            engine.entities.last().unwrap().id
+        };
+        let tiles = [add_tile(-0.9, -0.6), add_tile(-0.6, -0.3)];
+        engine.entities[0]
+            .shape
+            .borrow_mut()
+            .collision_data_mut()
+            .centroid = Point(-0.6, -0.43);
+
+        for _ in 0..5 {
+            engine.step(STEP);
+        }
+
+        let contacts = engine.contacts_for(engine.main_ball_id());
+        let mut touched: Vec<_> = contacts.iter().map(|contact| contact.other).collect();
+        touched.sort();
+        assert_eq!(touched, tiles);
+        for contact in &contacts {
+            assert!(contact.normal.1 < -0.9);
+            assert!(contact.depth > -CONTACT_MARGIN);
+        }
+
+        let tile_contacts = engine.contacts_for(tiles[0]);
+        assert_eq!(tile_contacts.len(), 1);
+        assert_eq!(tile_contacts[0].other, engine.main_ball_id());
+        assert!(tile_contacts[0].normal.1 > 0.9);
+    }
+
+    /// a circle dropped between two slopes meeting at the origin, with the main ball on a
+    /// ledge of its own, along with the highest energy of the circle over its last second
+    fn circle_in_valley(bounce_guard: bool) -> (Engine, EntityId, f64) {
+        let mut level = Level::new(Point(3.0, 0.07));
+        level.polygons = vec![
+            levels::Entity::new(
+                vec![
+                    Point(2.5, -0.1),
+                    Point(3.5, -0.1),
+                    Point(3.5, 0.0),
+                    Point(2.5, 0.0),
+                ],
+                true,
+            ),
+            levels::Entity::new(
+                vec![
+                    Point(-0.5, 0.5),
+                    Point(0.0, 0.0),
+                    Point(0.0, -0.1),
+                    Point(-0.5, 0.4),
+                ],
+                true,
+            ),
+            levels::Entity::new(
+                vec![
+                    Point(0.0, 0.0),
+                    Point(0.5, 0.5),
+                    Point(0.5, 0.4),
+                    Point(0.0, -0.1),
+                ],
+                true,
+            ),
+        ];
+        let mut engine = EngineBuilder::new(level)
+            .params(PhysicsParams {
+                bounce_guard,
+                ..Default::default()
+            })
+            .build();
+        let circle = engine
+            .spawn_circle(Circle::new(Point(0.0, 0.3), 0.05), EntityCfg::default())
+            .unwrap();
+
+        let mut peak: f64 = 0.0;
+        for i in 0..2000 {
+            engine.step(STEP);
+            if i >= 1900 {
+                let entity = engine.entities.iter().find(|entity| entity.id == circle);
+                peak = peak.max(engine.kinetic_energy(entity.unwrap()));
+            }
+        }
+        (engine, circle, peak)
+    }
+
+    #[test]
+    fn test_bounce_guard_settles_jittering_circle() {
+        // the contacts with both slopes keep pushing the circle back and forth
+        let (_, _, unguarded) = circle_in_valley(false);
+        assert!(
+            unguarded > 1e-9,
+            "the circle settled by itself: {unguarded}"
+        );
+
+        let (mut engine, circle, guarded) = circle_in_valley(true);
+        assert!(guarded < 1e-20, "the circle still jitters: {guarded}");
+        let start = engine.entity_position(circle).unwrap();
+        assert!(start.norm() < 0.1);
+
+        // tilting the world lets it roll away again
+        engine.set_target_angle(1.2);
+        for _ in 0..200 {
+            engine.step(STEP);
+        }
+        assert!(start.to(engine.entity_position(circle).unwrap()).norm() > 0.1);
+    }
+}
@@ -1,8 +1,11 @@
-use std::time::Duration;
+use std::{f64::consts::PI, time::Duration};
 
 use crate::geometry::{Point, Vector};
 
-use super::{compute::simplex::Vertex, shape::Collidable};
+use super::{
+    compute::simplex::Vertex,
+    shape::{Collidable, CollisionData, Response, DEFAULT_RESTITUTION},
+};
 
 /// Refers to a point on a shape. The shape may be translated or rotated
 /// without invalidating this reference, since the reference refers to
@@ -17,17 +20,59 @@ impl PointOnShape {
     pub fn on(self, shape: &(impl Collidable + ?Sized)) -> Point {
         shape.resolve_point_reference(self)
     }
+
+    /// moves the reference from one shape to another,
+    /// keeping it pointed at the same point on the plane
+    pub fn rebase(
+        self,
+        from: &(impl Collidable + ?Sized),
+        to: &(impl Collidable + ?Sized),
+    ) -> Self {
+        to.create_point_reference(self.on(from))
+    }
+}
+
+/// the direction the shape faces, which turns along with it
+fn orientation(shape: &(impl Collidable + ?Sized)) -> Vector {
+    let centroid = PointOnShape {
+        angle_offset: 0.0,
+        length_scale: 0.0,
+    };
+    let facing = PointOnShape {
+        angle_offset: 0.0,
+        length_scale: 1.0,
+    };
+    centroid.on(shape).to(facing.on(shape))
 }
 
+/// the angle the second shape is turned by relative to the first one
+fn relative_angle(shape1: &(impl Collidable + ?Sized), shape2: &(impl Collidable + ?Sized)) -> f64 {
+    orientation(shape1).angle_to(orientation(shape2))
+}
+
+/// Keeps a point of one shape at a point of another. The compliance of a binding is how far it
+/// stretches per unit of force pulling it apart, the inverse of its stiffness, so zero keeps the
+/// points together as firmly as possible and larger values make the binding springy
 #[derive(Clone, Copy)]
 pub enum Binding {
     Hinge {
         first: PointOnShape,
         second: PointOnShape,
+        compliance: f64,
     },
     Rigid {
         first: (PointOnShape, PointOnShape),
         second: (PointOnShape, PointOnShape),
+        compliance: f64,
+    },
+    /// keeps the shapes at the same point and turned by the same angle relative to each other
+    /// as when they were bound, so they move as one without the sag of `Rigid`
+    Weld {
+        first: PointOnShape,
+        second: PointOnShape,
+        /// the angle the second shape was turned by relative to the first one
+        angle: f64,
+        compliance: f64,
     },
 }
 
@@ -40,7 +85,7 @@ impl Binding {
         shape2: &(impl Collidable + ?Sized),
     ) -> Option<Self> {
         match unbound {
-            Unbound::Hinge(first) => {
+            Unbound::Hinge(first, compliance) => {
                 let point = shape1.resolve_point_reference(first);
                 if !shape2.includes(point) {
                     return None;
@@ -48,9 +93,13 @@ impl Binding {
 
                 let second = shape2.create_point_reference(point);
 
-                Some(Self::Hinge { first, second })
+                Some(Self::Hinge {
+                    first,
+                    second,
+                    compliance,
+                })
             }
-            Unbound::Rigid(first) => {
+            Unbound::Rigid(first, compliance) => {
                 let point = shape1.resolve_point_reference(first);
                 if !shape2.includes(point) {
                     return None;
@@ -64,63 +113,314 @@ impl Binding {
                 Some(Self::Rigid {
                     first: (first_left, first_right),
                     second: (second_left, second_right),
+                    compliance,
+                })
+            }
+            Unbound::Weld(first, compliance) => {
+                let point = shape1.resolve_point_reference(first);
+                if !shape2.includes(point) {
+                    return None;
+                }
+
+                Some(Self::Weld {
+                    first,
+                    second: shape2.create_point_reference(point),
+                    angle: relative_angle(shape1, shape2),
+                    compliance,
                 })
             }
         }
     }
 
-    /// enforces the spacial constraints of this binding
-    pub fn enforce(
+    /// the point at which the binding is attached to the first shape
+    pub fn first_anchor(&self, shape: &(impl Collidable + ?Sized)) -> Point {
+        match self {
+            Self::Hinge { first, .. } | Self::Weld { first, .. } => first.on(shape),
+            Self::Rigid {
+                first: (left, right),
+                ..
+            } => (left.on(shape) + right.on(shape)) * 0.5,
+        }
+    }
+
+    /// how far the binding stretches per unit of force, see [`Binding`]
+    pub fn compliance_mut(&mut self) -> &mut f64 {
+        match self {
+            Self::Hinge { compliance, .. }
+            | Self::Rigid { compliance, .. }
+            | Self::Weld { compliance, .. } => compliance,
+        }
+    }
+
+    /// the point at which the binding is attached to the second shape
+    pub fn second_anchor(&self, shape: &(impl Collidable + ?Sized)) -> Point {
+        match self {
+            Self::Hinge { second, .. } | Self::Weld { second, .. } => second.on(shape),
+            Self::Rigid {
+                second: (left, right),
+                ..
+            } => (left.on(shape) + right.on(shape)) * 0.5,
+        }
+    }
+
+    /// reattaches the first end of the binding to another shape
+    pub fn rebase_first(
         self,
+        from: &(impl Collidable + ?Sized),
+        to: &(impl Collidable + ?Sized),
+    ) -> Self {
+        match self {
+            Self::Hinge {
+                first,
+                second,
+                compliance,
+            } => Self::Hinge {
+                first: first.rebase(from, to),
+                second,
+                compliance,
+            },
+            Self::Rigid {
+                first: (left, right),
+                second,
+                compliance,
+            } => Self::Rigid {
+                first: (left.rebase(from, to), right.rebase(from, to)),
+                second,
+                compliance,
+            },
+            Self::Weld {
+                first,
+                second,
+                angle,
+                compliance,
+            } => Self::Weld {
+                first: first.rebase(from, to),
+                second,
+                angle: angle - relative_angle(from, to),
+                compliance,
+            },
+        }
+    }
+
+    /// reattaches the second end of the binding to another shape
+    pub fn rebase_second(
+        self,
+        from: &(impl Collidable + ?Sized),
+        to: &(impl Collidable + ?Sized),
+    ) -> Self {
+        match self {
+            Self::Hinge {
+                first,
+                second,
+                compliance,
+            } => Self::Hinge {
+                first,
+                second: second.rebase(from, to),
+                compliance,
+            },
+            Self::Rigid {
+                first,
+                second: (left, right),
+                compliance,
+            } => Self::Rigid {
+                first,
+                second: (left.rebase(from, to), right.rebase(from, to)),
+                compliance,
+            },
+            Self::Weld {
+                first,
+                second,
+                angle,
+                compliance,
+            } => Self::Weld {
+                first,
+                second: second.rebase(from, to),
+                angle: angle + relative_angle(from, to),
+                compliance,
+            },
+        }
+    }
+
+    /// enforces the spacial constraints of this binding over one of the `substeps` the time step
+    /// is split in, returning the magnitude of the impulse which pulled the bound points back
+    /// together, the load the binding carries in it. The angular impulse keeping a weld's angle
+    /// isn't part of it. A compliant binding works out its stiffness over the substep with its
+    /// share of the compliance, so it gives way about as much however many substeps there are
+    pub fn enforce(
+        mut self,
         shape1: &mut dyn Collidable,
         shape2: &mut dyn Collidable,
         time_step: Duration,
-    ) {
+        substeps: u32,
+    ) -> f64 {
+        let substep = time_step / substeps;
+        *self.compliance_mut() /= f64::from(substeps);
         match self {
-            Self::Hinge { first, second } => {
-                Self::enforce_hinge((shape1, first), (shape2, second), time_step)
+            Self::Hinge {
+                first,
+                second,
+                compliance,
+            } => Self::enforce_hinge((shape1, first), (shape2, second), compliance, substep),
+            Self::Rigid {
+                first,
+                second,
+                compliance,
+            } => {
+                Self::enforce_hinge((shape1, first.0), (shape2, second.0), compliance, substep)
+                    + Self::enforce_hinge(
+                        (shape1, first.1),
+                        (shape2, second.1),
+                        compliance,
+                        substep,
+                    )
             }
-            Self::Rigid { first, second } => {
-                Self::enforce_hinge((shape1, first.0), (shape2, second.0), time_step);
-                Self::enforce_hinge((shape1, first.1), (shape2, second.1), time_step);
+            Self::Weld {
+                first,
+                second,
+                angle,
+                compliance,
+            } => {
+                Self::enforce_angle(shape1, shape2, angle, compliance, substep);
+                Self::enforce_hinge((shape1, first), (shape2, second), compliance, substep)
             }
         }
     }
 
+    /// turns the shapes back to being turned by `angle` relative to each other, the lighter one
+    /// more than the heavier one, and stops them from spinning relative to each other. Only
+    /// the stiffness given by the compliance of the correction is applied in the (sub)step
+    fn enforce_angle(
+        shape1: &mut dyn Collidable,
+        shape2: &mut dyn Collidable,
+        angle: f64,
+        compliance: f64,
+        time_step: Duration,
+    ) {
+        let error = (relative_angle(shape1, shape2) - angle + PI).rem_euclid(2.0 * PI) - PI;
+        let first_inverse = shape1.collision_data_mut().inertia.recip();
+        let second_inverse = shape2.collision_data_mut().inertia.recip();
+        let inverse_inertia = first_inverse + second_inverse;
+        if inverse_inertia == 0.0 {
+            return;
+        }
+        let stiffness = if compliance <= 0.0 {
+            1.0
+        } else {
+            inverse_inertia / (inverse_inertia + compliance / time_step.as_secs_f64().powi(2))
+        };
+
+        let correction = error * stiffness / inverse_inertia;
+        shape1.rotate(correction * first_inverse);
+        shape2.rotate(-correction * second_inverse);
+
+        let first = shape1.collision_data_mut();
+        let second = shape2.collision_data_mut();
+        let impulse =
+            (second.angular_velocity - first.angular_velocity) * stiffness / inverse_inertia;
+        first.angular_velocity += impulse * first_inverse;
+        second.angular_velocity -= impulse * second_inverse;
+    }
+
     fn enforce_hinge(
         first: (&mut dyn Collidable, PointOnShape),
         second: (&mut dyn Collidable, PointOnShape),
-        time_step: Duration,
-    ) {
+        compliance: f64,
+        substep: Duration,
+    ) -> f64 {
         let point1 = first.1.on(first.0);
         let point2 = second.1.on(second.0);
         let translation = point2.to(point1);
         if !translation.is_close_enough_to(Vector::ZERO) {
+            let stiffness = Self::stiffness(
+                first.0.collision_data_mut(),
+                point1,
+                second.0.collision_data_mut(),
+                point2,
+                translation.unit(),
+                compliance,
+                substep,
+            );
             first.0.resolve_collision_with(
                 second.0,
                 Vertex {
                     point: translation,
                     created_from: (point1, point2),
                 },
-                time_step,
-            );
+                &[(point1, point2)],
+                Response {
+                    stiffness,
+                    restitution: DEFAULT_RESTITUTION,
+                    friction_scale: 1.0,
+                    max_correction: f64::INFINITY,
+                },
+                substep,
+            )
+        } else {
+            0.0
         }
     }
+
+    /// the fraction of the correction a binding with the compliance applies in a step, as in
+    /// XPBD: the generalized inverse mass of the bound points divided by itself plus the
+    /// compliance scaled by the squared time step. Zero compliance applies all of it
+    fn stiffness(
+        first: &CollisionData,
+        point1: Point,
+        second: &CollisionData,
+        point2: Point,
+        normal: Vector,
+        compliance: f64,
+        time_step: Duration,
+    ) -> f64 {
+        if compliance <= 0.0 {
+            return 1.0;
+        }
+        let inverse_mass = |data: &CollisionData, point: Point| {
+            data.mass.recip() + data.centroid.to(point).cross(normal).powi(2) / data.inertia
+        };
+        let inverse_mass = inverse_mass(first, point1) + inverse_mass(second, point2);
+        inverse_mass / (inverse_mass + compliance / time_step.as_secs_f64().powi(2))
+    }
 }
 
+/// A binding pinned to a shape, waiting for another one to be drawn over it.
+/// Along with the point it carries the compliance the binding will have
 #[derive(Clone, Copy)]
 pub enum Unbound {
-    Hinge(PointOnShape),
-    Rigid(PointOnShape),
+    Hinge(PointOnShape, f64),
+    Rigid(PointOnShape, f64),
+    Weld(PointOnShape, f64),
 }
 
 impl Unbound {
-    pub fn new_hinge(shape: &(impl Collidable + ?Sized), at: Point) -> Self {
-        Self::Hinge(shape.create_point_reference(at))
+    pub fn new_hinge(shape: &(impl Collidable + ?Sized), at: Point, compliance: f64) -> Self {
+        Self::Hinge(shape.create_point_reference(at), compliance)
+    }
+
+    pub fn new_rigid(shape: &(impl Collidable + ?Sized), at: Point, compliance: f64) -> Self {
+        Self::Rigid(shape.create_point_reference(at), compliance)
     }
 
-    pub fn new_rigid(shape: &(impl Collidable + ?Sized), at: Point) -> Self {
-        Self::Rigid(shape.create_point_reference(at))
+    pub fn new_weld(shape: &(impl Collidable + ?Sized), at: Point, compliance: f64) -> Self {
+        Self::Weld(shape.create_point_reference(at), compliance)
+    }
+
+    pub fn anchor(&self, shape: &(impl Collidable + ?Sized)) -> Point {
+        match self {
+            Self::Hinge(point, _) | Self::Rigid(point, _) | Self::Weld(point, _) => point.on(shape),
+        }
+    }
+
+    pub fn rebase(
+        self,
+        from: &(impl Collidable + ?Sized),
+        to: &(impl Collidable + ?Sized),
+    ) -> Self {
+        match self {
+            Self::Hinge(point, compliance) => Self::Hinge(point.rebase(from, to), compliance),
+            Self::Rigid(point, compliance) => Self::Rigid(point.rebase(from, to), compliance),
+            Self::Weld(point, compliance) => Self::Weld(point.rebase(from, to), compliance),
+        }
     }
 }
 
@@ -138,7 +438,7 @@ mod test {
             (0.0, 1.0),
         };
 
-        let unbound = Unbound::new_hinge(&shape, Point(0.9, 0.9));
+        let unbound = Unbound::new_hinge(&shape, Point(0.9, 0.9), 0.0);
 
         assert!(Binding::try_bind(
             &shape,
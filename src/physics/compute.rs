@@ -1,3 +1,5 @@
+//! the geometry behind collisions and cutting shapes apart
+
 use std::{
     f64::consts::PI,
     panic::{self, RefUnwindSafe},
@@ -19,16 +21,37 @@ pub fn collision(
     // this is here bacause in some very rare cases there appear NaNs in the calculations.
     // The algorithms cannot work with NaNs and panics when attempting to compare them.
     // Since one of the last fixes these panics were not observed, but they might just be
-    // very difficult to cause
-    panic::catch_unwind(|| {
-        let difference = minkowski::Difference(first, second);
-        let initial_point = Point(0.0, 1.0);
-        let simplex = algorithm::gjk::eclosing_simplex(initial_point, difference)?;
-
-        Some(algorithm::epa::closest_point_of(simplex, difference))
-    })
-    .ok()
-    .flatten()
+    // very difficult to cause. `test::fuzz_collision` looks for them
+    panic::catch_unwind(|| unguarded_collision(first, second))
+        .ok()
+        .flatten()
+}
+
+/// `collision` without catching the panics
+fn unguarded_collision(
+    first: &(impl Bounded + ?Sized),
+    second: &(impl Bounded + ?Sized),
+) -> Option<simplex::Vertex> {
+    let difference = minkowski::Difference(first, second);
+    let initial_point = Point(0.0, 1.0);
+    let simplex = algorithm::gjk::eclosing_simplex(initial_point, difference)?;
+
+    Some(algorithm::epa::closest_point_of(simplex, difference))
+}
+
+/// The shape grown by a margin in every direction, with rounded corners.
+/// Colliding it lets shapes which are merely touching be told apart from distant ones.
+/// The margin is ignored by `includes`
+pub struct Inflated<'s, S: ?Sized>(pub &'s S, pub f64);
+
+impl<S: Bounded + ?Sized> Bounded for Inflated<'_, S> {
+    fn support_vector(&self, direction: Vector) -> Point {
+        self.0.support_vector(direction) + direction.unit() * self.1
+    }
+
+    fn includes(&self, point: Point) -> bool {
+        self.0.includes(point)
+    }
 }
 
 /// computes the impulse resulting from a collision between
@@ -63,6 +86,86 @@ pub fn centroid(vertices: &[Point]) -> Point {
     combined_points / (3.0 * doubled_area)
 }
 
+/// splits a convex polygon along the line going through `from` and `to` into
+/// the part on the left and the part on the right of the line.
+///
+/// Returns `None` unless the segment between the two points fully crosses the
+/// polygon, i.e. both of the points where the line enters and exits the polygon
+/// lie on the segment
+pub fn split(vertices: &[Point], from: Point, to: Point) -> Option<(Vec<Point>, Vec<Point>)> {
+    let cut = from.to(to);
+    let mut left = Vec::with_capacity(vertices.len() + 2);
+    let mut right = Vec::with_capacity(vertices.len() + 2);
+    let mut crossings = 0;
+
+    for [first, second] in windows::Looped::from(vertices.iter().copied()) {
+        let first_side = cut.cross(from.to(first));
+        let second_side = cut.cross(from.to(second));
+
+        if first_side >= 0.0 {
+            left.push(first);
+        }
+        if first_side <= 0.0 {
+            right.push(first);
+        }
+
+        if first_side * second_side < 0.0 {
+            let crossing = first + first.to(second) * (first_side / (first_side - second_side));
+            if !(0.0..=1.0).contains(&(from.to(crossing).dot(cut) / cut.dot(cut))) {
+                return None;
+            }
+
+            left.push(crossing);
+            right.push(crossing);
+            crossings += 1;
+        }
+    }
+
+    (crossings == 2 && left.len() >= 3 && right.len() >= 3).then_some((left, right))
+}
+
+/// the part of a convex polygon lying on the left of the line going through `from` and `to`
+pub fn clip(vertices: &[Point], from: Point, to: Point) -> Vec<Point> {
+    let cut = from.to(to);
+    let mut clipped = Vec::with_capacity(vertices.len() + 1);
+
+    for [first, second] in windows::Looped::from(vertices.iter().copied()) {
+        let first_side = cut.cross(from.to(first));
+        let second_side = cut.cross(from.to(second));
+
+        if first_side >= 0.0 {
+            clipped.push(first);
+        }
+        if first_side * second_side < 0.0 {
+            clipped.push(first + first.to(second) * (first_side / (first_side - second_side)));
+        }
+    }
+
+    clipped
+}
+
+/// decomposes the difference of two convex polygons into convex pieces.
+/// The `hole` has to be given counterclockwise. Every piece lies outside of
+/// one edge of the hole and inside all the previous ones, so they don't overlap
+pub fn subtract(vertices: &[Point], hole: &[Point]) -> Vec<Vec<Point>> {
+    let mut remaining = vertices.to_vec();
+    let mut pieces = vec![];
+
+    for [first, second] in windows::Looped::from(hole.iter().copied()) {
+        if remaining.len() < 3 {
+            break;
+        }
+
+        let outside = clip(&remaining, second, first);
+        if outside.len() >= 3 {
+            pieces.push(outside);
+        }
+        remaining = clip(&remaining, first, second);
+    }
+
+    pieces
+}
+
 /// wraps an at most `N` vertex hull around the provided collection of vertices
 /// I would love to put the `directions` array in a constant, but unfortunately
 /// Rust does not support generic const/statics. The static rvalue promotion hack
@@ -108,3 +211,215 @@ pub fn hull<const N: usize>(mut points: impl Iterator<Item = Point>) -> Polygon
         },
     ))
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        f64::consts::TAU,
+        fmt,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        geometry,
+        physics::shape::{Circle, Collidable},
+    };
+
+    const FUZZ_SEED: u64 = 2500;
+    const FUZZ_PAIRS: usize = 5000;
+    /// shrinking takes a while, and the first few failures are usually enough to go on
+    const MAX_REPORTED: usize = 5;
+
+    /// A shape given by the numbers it's made of, so a failing one can be printed and shrunk
+    #[derive(Clone)]
+    enum FuzzShape {
+        Polygon(Vec<Point>),
+        Circle(Point, f64),
+    }
+
+    impl FuzzShape {
+        /// a convex polygon with up to 8 vertices or a circle, anywhere from a hundredth to
+        /// a few units across and at times very thin, lying around `near`
+        fn random(rng: &mut StdRng, near: Point) -> Self {
+            let size = 10f64.powf(rng.gen_range(-2.0..0.5));
+            let center = near + Point(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * size;
+            if rng.gen_bool(0.25) {
+                return FuzzShape::Circle(center, size);
+            }
+
+            // points of an ellipse at increasing angles always make a convex polygon
+            let thinness = if rng.gen_bool(0.2) {
+                10f64.powf(rng.gen_range(-4.0..-1.0))
+            } else {
+                rng.gen_range(0.2..1.0)
+            };
+            let rotation = rng.gen_range(0.0..TAU);
+            let mut angles = (0..rng.gen_range(3..=8))
+                .map(|_| rng.gen_range(0.0..TAU))
+                .collect::<Vec<_>>();
+            angles.sort_by(f64::total_cmp);
+            FuzzShape::Polygon(
+                angles
+                    .into_iter()
+                    .map(|angle| {
+                        let Point(x, y) = Point(angle.cos(), angle.sin());
+                        center + Point(x, y * thinness).rotate(rotation) * size
+                    })
+                    .collect(),
+            )
+        }
+
+        fn build(&self) -> Box<dyn Collidable> {
+            match self {
+                FuzzShape::Polygon(vertices) => Box::new(Polygon::new(vertices.clone())),
+                FuzzShape::Circle(center, radius) => Box::new(Circle::new(*center, *radius)),
+            }
+        }
+
+        /// whether the engine would create the shape, polygons need 3 distinct vertices
+        fn is_valid(&self) -> bool {
+            match self {
+                FuzzShape::Polygon(vertices) => {
+                    geometry::merge_close_vertices(vertices.clone()).len() >= 3
+                }
+                FuzzShape::Circle(_, radius) => *radius > 0.0,
+            }
+        }
+
+        /// slightly simpler versions of the shape, with a vertex less or coordinates rounded
+        fn simplified(&self) -> Vec<FuzzShape> {
+            let round = |value: f64, digits: i32| {
+                let scale = 10f64.powi(digits);
+                (value * scale).round() / scale
+            };
+            let candidates = match self {
+                FuzzShape::Polygon(vertices) => {
+                    let fewer = (0..vertices.len()).filter(|_| vertices.len() > 3).map(|i| {
+                        let mut vertices = vertices.clone();
+                        vertices.remove(i);
+                        vertices
+                    });
+                    let rounded = (0..6).map(|digits| {
+                        vertices
+                            .iter()
+                            .map(|&Point(x, y)| Point(round(x, digits), round(y, digits)))
+                            .collect::<Vec<_>>()
+                    });
+                    fewer
+                        .chain(rounded)
+                        .map(FuzzShape::Polygon)
+                        .collect::<Vec<_>>()
+                }
+                &FuzzShape::Circle(Point(x, y), radius) => (0..6)
+                    .map(|digits| {
+                        FuzzShape::Circle(
+                            Point(round(x, digits), round(y, digits)),
+                            round(radius, digits),
+                        )
+                    })
+                    .collect(),
+            };
+            // the coordinates are printed exactly, so unlike `==` this works for NaNs too
+            candidates
+                .into_iter()
+                .filter(|candidate| format!("{candidate:?}") != format!("{self:?}"))
+                .collect()
+        }
+    }
+
+    impl fmt::Debug for FuzzShape {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                FuzzShape::Polygon(vertices) => {
+                    let vertices = vertices
+                        .iter()
+                        .map(|Point(x, y)| format!("({x:?}, {y:?})"))
+                        .collect::<Vec<_>>();
+                    write!(f, "polygon [{}]", vertices.join(", "))
+                }
+                FuzzShape::Circle(Point(x, y), radius) => {
+                    write!(f, "circle at ({x:?}, {y:?}) with radius {radius:?}")
+                }
+            }
+        }
+    }
+
+    /// what goes wrong when colliding the shapes, if anything
+    fn collision_problem(first: &FuzzShape, second: &FuzzShape) -> Option<String> {
+        let (first, second) = (first.build(), second.build());
+        let collision =
+            panic::catch_unwind(AssertUnwindSafe(|| unguarded_collision(&*first, &*second)));
+        let is_finite = |Point(x, y): Point| x.is_finite() && y.is_finite();
+        match collision {
+            Err(_) => Some("panicked".to_string()),
+            Ok(Some(vertex))
+                if ![vertex.point, vertex.created_from.0, vertex.created_from.1]
+                    .into_iter()
+                    .all(is_finite) =>
+            {
+                Some(format!("returned {vertex:?}"))
+            }
+            Ok(_) => None,
+        }
+    }
+
+    /// the simplest pair of shapes which still has the problem, as found by simplifying one of
+    /// the shapes at a time for as long as the problem remains
+    fn shrink(mut pair: (FuzzShape, FuzzShape)) -> (FuzzShape, FuzzShape) {
+        loop {
+            let (first, second) = &pair;
+            let simpler = first
+                .simplified()
+                .into_iter()
+                .map(|first| (first, second.clone()))
+                .chain(
+                    second
+                        .simplified()
+                        .into_iter()
+                        .map(|second| (first.clone(), second)),
+                )
+                .filter(|(first, second)| first.is_valid() && second.is_valid())
+                .find(|(first, second)| collision_problem(first, second).is_some());
+            match simpler {
+                Some(simpler) => pair = simpler,
+                None => return pair,
+            }
+        }
+    }
+
+    /// Collides random pairs of shapes without catching panics, which `collision` would hide.
+    /// A failure is shrunk and reported with the coordinates of the shapes, so it can be
+    /// turned into a test of its own
+    #[test]
+    fn fuzz_collision() {
+        let mut rng = StdRng::seed_from_u64(FUZZ_SEED);
+        let failures = (0..FUZZ_PAIRS)
+            .map(|_| {
+                // anywhere a level reaches, where the coordinates are larger than the shapes
+                let near = Point(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0));
+                let first = FuzzShape::random(&mut rng, near);
+                (first, FuzzShape::random(&mut rng, near))
+            })
+            .filter(|(first, second)| collision_problem(first, second).is_some())
+            .collect::<Vec<_>>();
+
+        let reports = failures
+            .iter()
+            .take(MAX_REPORTED)
+            .map(|pair| {
+                let (first, second) = shrink(pair.clone());
+                let problem = collision_problem(&first, &second).unwrap();
+                format!("colliding {first:?} with {second:?} {problem}")
+            })
+            .collect::<Vec<_>>();
+        assert!(
+            failures.is_empty(),
+            "{} of {FUZZ_PAIRS} pairs failed, the first ones shrunk:\n{}",
+            failures.len(),
+            reports.join("\n")
+        );
+    }
+}
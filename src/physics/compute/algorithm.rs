@@ -1,3 +1,7 @@
+//! the two halves of collision detection, GJK telling whether shapes overlap
+//! and EPA finding how far apart they have to be pushed
+
+/// finding whether two shapes collide
 pub mod gjk {
     use std::collections::BinaryHeap;
 
@@ -60,6 +64,7 @@ pub mod gjk {
     }
 }
 
+/// finding the minimum translation vector of two colliding shapes
 pub mod epa {
     use std::collections::BinaryHeap;
 
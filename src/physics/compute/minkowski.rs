@@ -1,8 +1,12 @@
+//! the minkowski difference of two shapes, which contains the origin iff they overlap
+
 use crate::{
     geometry::Vector,
     physics::{compute::simplex, shape::Bounded},
 };
 
+/// The shape consisting of every point of the first shape minus every point of the second one,
+/// which is only ever sampled and not actually constructed
 pub struct Difference<'s, S1: ?Sized, S2: ?Sized>(pub &'s S1, pub &'s S2);
 
 // why the Copy and Clone derive macros place Copy bounds on S1 and S2 here is beyond me
@@ -15,6 +19,8 @@ impl<'s, S1: ?Sized, S2: ?Sized> Clone for Difference<'s, S1, S2> {
 }
 
 impl<'s, S1: Bounded + ?Sized, S2: Bounded + ?Sized> Difference<'s, S1, S2> {
+    /// the point of the difference furthest in the given direction, along with the points of
+    /// the shapes it was created from
     pub fn support_vector(&self, direction: Vector) -> simplex::Vertex {
         let first = self.0.support_vector(direction);
         let second = self.1.support_vector(-direction);
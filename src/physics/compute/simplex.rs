@@ -4,31 +4,46 @@ use std::cmp::Ordering;
 
 use crate::geometry::Point;
 
+/// A point of the minkowski difference
 #[derive(Debug, Clone, Copy)]
 pub struct Vertex {
+    /// the point itself, the vector from the second shape to the first one
     pub point: Point,
+    /// the points of the first and the second shape the point is the difference of
     pub created_from: (Point, Point),
 }
 
+/// A simplex which doesn't enclose anything yet
 #[derive(Debug)]
 pub enum Partial {
+    /// a single vertex
     Point(Vertex),
+    /// a segment between two vertices
     Line(Vertex, Vertex),
 }
 
+/// A simplex enclosing the origin, degenerate if the origin lies on its boundary
 pub enum Simplex {
+    /// the vertex is the origin
     Point(Vertex),
+    /// the origin lies on the segment between the vertices
     Line(Vertex, Vertex),
+    /// the origin lies inside the triangle
     Triangle(Vertex, Vertex, Vertex),
 }
 
+/// The outcome of extending a partial simplex by a vertex
 pub enum ClosureResult {
+    /// the origin might still be enclosed by sampling further in this direction
     NextDirection(Point),
+    /// the sampled shape can't contain the origin
     ExcludesOrigin,
+    /// the origin is enclosed by the simplex
     IncludesOrigin(Simplex),
 }
 
 impl Partial {
+    /// adds the vertex to the simplex, dropping ones which can't help enclose the origin
     pub fn try_to_enclose(&mut self, new: Vertex) -> ClosureResult {
         if new.point.is_close_enough_to(Point::ZERO) {
             return ClosureResult::IncludesOrigin(Simplex::Point(new));
@@ -101,18 +116,25 @@ impl Partial {
     }
 }
 
+/// An edge of the polytope expanded by EPA, ordered so the closest one to the origin is the
+/// greatest
 #[derive(Debug)]
 pub struct Edge {
+    /// from the origin to the closest point of the edge
     pub distance_to_origin: f64,
+    /// points from the origin towards the closest point of the edge
     pub towards_segment: Point,
+    /// the ends of the edge
     pub segment: (Vertex, Vertex),
 }
 
 impl Edge {
+    /// panics if the distance to the origin can't be computed
     pub fn new(first: Vertex, second: Vertex) -> Self {
         Self::try_new(first, second).unwrap()
     }
 
+    /// `None` if the distance to the origin can't be computed, e.g. the vertices are too close
     pub fn try_new(first: Vertex, second: Vertex) -> Option<Self> {
         if first.point.to(second.point).dot(-first.point) <= 0.0 {
             return Some(Self::redundant(first, second));
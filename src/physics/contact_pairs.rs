@@ -0,0 +1,192 @@
+//! which pairs of entities are touching, so a contact is reported once when it begins and once
+//! when it ends rather than in every step it lasts, e.g. while a ball rolls along a floor. Only
+//! contacts in which the entities hit each other hard are reported, the ones of entities
+//! resting on each other aren't worth a sound
+
+use std::{collections::HashMap, time::Duration};
+
+use super::{CollisionEvent, ContactPhase, EntityId};
+use crate::audio::Material;
+
+/// how long two entities have to stop touching for their contact to end, so a ball skipping
+/// over the bumps of a floor it's rolling on doesn't begin a new contact with every one
+const END_DELAY: Duration = Duration::from_millis(50);
+
+/// A contact which began and hasn't ended yet
+#[derive(Clone, Copy, Debug)]
+struct PairContact {
+    materials: [Material; 2],
+    /// the strongest impulse the entities were pushed apart with since the contact began
+    max_impulse: f64,
+    /// the entities hit each other hard at some point of the contact, so it's reported
+    is_strong: bool,
+    /// the entities touched in the current step
+    is_touching: bool,
+    untouched_for: Duration,
+    /// since the first hard hit of the contact or since it was last reported as persisting
+    since_report: Duration,
+}
+
+/// What touching in a step changed for a pair of entities
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TouchChange {
+    /// the entities didn't touch before
+    pub began: bool,
+    /// the event of the contact beginning to be reported, if it's the first hard hit of it
+    pub hit: Option<CollisionEvent>,
+}
+
+/// The contacts between entities, keyed by their ids with the one added earlier first
+#[derive(Clone, Debug, Default)]
+pub struct ContactPairs {
+    pairs: HashMap<[EntityId; 2], PairContact>,
+}
+
+impl ContactPairs {
+    /// records that the entities touch in the current step, having been pushed apart with the
+    /// impulse, hard if `is_strong`
+    pub fn touch(
+        &mut self,
+        entities: [EntityId; 2],
+        materials: [Material; 2],
+        impulse: f64,
+        is_strong: bool,
+    ) -> TouchChange {
+        let mut change = TouchChange::default();
+        let contact = self.pairs.entry(entities).or_insert_with(|| {
+            change.began = true;
+            PairContact {
+                materials,
+                max_impulse: 0.0,
+                is_strong: false,
+                is_touching: true,
+                untouched_for: Duration::ZERO,
+                since_report: Duration::ZERO,
+            }
+        });
+        contact.max_impulse = contact.max_impulse.max(impulse);
+        contact.is_touching = true;
+        contact.untouched_for = Duration::ZERO;
+        if is_strong && !contact.is_strong {
+            contact.is_strong = true;
+            change.hit = Some(CollisionEvent {
+                entities,
+                materials,
+                impulse,
+                phase: ContactPhase::Began,
+            });
+        }
+        change
+    }
+
+    /// ends the step, returning the reported contacts which ended along with the ones which
+    /// are reported as persisting every `persist_interval`, if it's set
+    pub fn end_step(
+        &mut self,
+        time_step: Duration,
+        persist_interval: Option<Duration>,
+    ) -> Vec<CollisionEvent> {
+        let mut events = vec![];
+        self.pairs.retain(|&entities, contact| {
+            let event = |phase| CollisionEvent {
+                entities,
+                materials: contact.materials,
+                impulse: contact.max_impulse,
+                phase,
+            };
+            if !contact.is_touching {
+                contact.untouched_for += time_step;
+                if contact.untouched_for > END_DELAY {
+                    if contact.is_strong {
+                        events.push(event(ContactPhase::Ended));
+                    }
+                    return false;
+                }
+            }
+            contact.is_touching = false;
+            if !contact.is_strong {
+                return true;
+            }
+            contact.since_report += time_step;
+            if persist_interval.is_some_and(|interval| contact.since_report >= interval) {
+                events.push(event(ContactPhase::Persisted));
+                contact.since_report = Duration::ZERO;
+            }
+            true
+        });
+        // the order of a hash map would change from run to run
+        events.sort_by_key(|event| event.entities);
+        events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+    const PAIR: [EntityId; 2] = [EntityId(0), EntityId(1)];
+
+    fn touch(pairs: &mut ContactPairs, impulse: f64, is_strong: bool) -> TouchChange {
+        pairs.touch(PAIR, [Material::Rubber, Material::Wood], impulse, is_strong)
+    }
+
+    #[test]
+    fn test_contact_begins_and_ends_once() {
+        let mut pairs = ContactPairs::default();
+        let first = touch(&mut pairs, 0.0, false);
+        assert!(first.began);
+        assert_eq!(first.hit, None);
+        assert!(pairs.end_step(STEP, None).is_empty());
+
+        // a short gap doesn't end the contact, and only its first hard hit is reported
+        for impulse in [0.02, 0.05, 0.01, 0.06] {
+            let change = touch(&mut pairs, impulse, impulse > 0.04);
+            assert!(!change.began);
+            assert_eq!(change.hit.is_some(), impulse == 0.05);
+            assert!(pairs.end_step(STEP, None).is_empty());
+            assert!(pairs.end_step(STEP, None).is_empty());
+        }
+
+        let mut ended = vec![];
+        for _ in 0..10 {
+            ended.extend(pairs.end_step(STEP, None));
+        }
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].phase, ContactPhase::Ended);
+        assert_eq!(ended[0].impulse, 0.06);
+        assert!(touch(&mut pairs, 0.0, false).began);
+    }
+
+    #[test]
+    fn test_only_hard_contacts_are_reported() {
+        let mut pairs = ContactPairs::default();
+        let mut events = vec![];
+        for _ in 0..100 {
+            touch(&mut pairs, 0.01, false);
+            events.extend(pairs.end_step(STEP, Some(Duration::from_millis(250))));
+        }
+        for _ in 0..10 {
+            events.extend(pairs.end_step(STEP, None));
+        }
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_long_contact_persists() {
+        let mut pairs = ContactPairs::default();
+        let hit = touch(&mut pairs, 0.1, true).hit.unwrap();
+        assert_eq!(hit.phase, ContactPhase::Began);
+        assert_eq!(hit.impulse, 0.1);
+        let mut persisted = 0;
+        for _ in 0..100 {
+            touch(&mut pairs, 0.01, false);
+            persisted += pairs
+                .end_step(STEP, Some(Duration::from_millis(250)))
+                .iter()
+                .filter(|event| event.phase == ContactPhase::Persisted)
+                .count();
+        }
+        assert_eq!(persisted, 4);
+    }
+}
@@ -1,13 +1,14 @@
+//! the shapes simulated by the engine, which unlike the ones in `geometry` are moving
+//! and carry their mass
+
 use std::{panic::RefUnwindSafe, time::Duration};
 
 use crate::{
-    geometry::{Point, Vector},
+    geometry::{Aabb, Point, Vector},
     physics::compute,
 };
 
-use super::{
-    binding::PointOnShape, compute::simplex::Vertex, GRAVITY_COEFFICIENT, MOVEMENT_COEFFICIENT,
-};
+use super::{binding::PointOnShape, compute::simplex::Vertex, PhysicsParams};
 
 mod circle;
 mod polygon;
@@ -15,103 +16,242 @@ mod polygon;
 pub use circle::Circle;
 pub use polygon::Polygon;
 
+/// The outcome of colliding two shapes
 pub enum CollisionType {
+    /// the shapes weren't overlapping
     None,
-    Weak,
-    Strong,
+    /// the shapes were overlapping, but were pushed apart gently, e.g. one is resting on the other
+    Weak(Touch),
+    /// the shapes hit each other hard
+    Strong(Touch),
+}
+
+/// how much the impulse changes the velocity of the lighter of the shapes with the masses, in
+/// the units velocities are stored in
+pub fn velocity_change(impulse: f64, [first_mass, second_mass]: [f64; 2]) -> f64 {
+    impulse / first_mass.min(second_mass)
+}
+
+impl CollisionType {
+    /// tells a hard hit from a gentle touch between shapes with the masses: the collision is
+    /// strong if it changed the velocity of the lighter shape by more than `threshold`, in the
+    /// units velocities are stored in, see [`velocity_change`]. Normalizing by the mass makes
+    /// a small ball and a big box hitting the floor at the same speed equally hard
+    pub fn classify(touch: Option<Touch>, masses: [f64; 2], threshold: f64) -> Self {
+        match touch {
+            None => CollisionType::None,
+            Some(touch) if velocity_change(touch.impulse, masses) > threshold => {
+                CollisionType::Strong(touch)
+            }
+            Some(touch) => CollisionType::Weak(touch),
+        }
+    }
+
+    /// `None` if the shapes weren't overlapping
+    pub fn touch(&self) -> Option<Touch> {
+        match self {
+            Self::None => None,
+            Self::Weak(touch) | Self::Strong(touch) => Some(*touch),
+        }
+    }
+}
+
+/// Describes a resolved collision from the point of view of the first shape
+#[derive(Clone, Copy, Debug)]
+pub struct Touch {
+    /// the deepest point of the first shape inside the second one
+    pub point: Point,
+    /// unit vector pointing from the first shape towards the second one
+    pub normal: Vector,
+    /// how far the shapes were overlapping
+    pub depth: f64,
+    /// the impulse which pushed the shapes apart
+    pub impulse: f64,
 }
 
+/// How firmly the overlap of two shapes is resolved, see
+/// [`Collidable::resolve_collision_with`]
+#[derive(Clone, Copy, Debug)]
+pub struct Response {
+    /// the fraction of the approach stopped and of the overlap undone, below 1 for soft
+    /// contacts like compliant bindings
+    pub stiffness: f64,
+    /// how much of their approach shapes hitting each other hard bounce back with
+    pub restitution: f64,
+    /// scales the friction of both surfaces
+    pub friction_scale: f64,
+    /// how far the shapes are separated at most
+    pub max_correction: f64,
+}
+
+/// the friction coefficient of shapes whose entity doesn't set its own
+pub const DEFAULT_FRICTION: f64 = 0.5;
+/// the default `PhysicsParams::restitution`, which bindings always use
+pub const DEFAULT_RESTITUTION: f64 = 0.2;
+/// how deep shapes are left overlapping when they are pushed apart, so resting ones keep
+/// touching between steps rather than falling back onto each other every other step
+const PENETRATION_SLOP: f64 = 1e-4;
+/// shapes hitting each other slower than this don't bounce, so resting ones stay in contact
+/// rather than hopping a little every step. In the units of `CollisionData::velocity`, it's
+/// about as fast as a fall from a thousandth of a unit
+const MIN_BOUNCE_VELOCITY: f64 = 0.1;
+/// how many times the impulses are corrected at every contact point of a collision
+const CONTACT_ITERATIONS: usize = 4;
+
+/// A convex shape, which is all GJK and EPA need to know about it
 pub trait Bounded {
+    /// the point of the shape furthest in the given direction
     fn support_vector(&self, direction: Vector) -> Point;
+    /// true if the point lies inside the shape
     fn includes(&self, point: Point) -> bool;
+
+    /// the radius of the circle around the center of `aabb` containing the shape
+    fn bounding_radius(&self) -> f64 {
+        let Aabb { min, max } = self.aabb();
+        min.to(max).norm() / 2.0
+    }
+
+    /// the smallest axis-aligned box containing the shape
+    fn aabb(&self) -> Aabb {
+        Aabb {
+            min: Point(
+                self.support_vector(Point(-1.0, 0.0)).0,
+                self.support_vector(Point(0.0, -1.0)).1,
+            ),
+            max: Point(
+                self.support_vector(Point(1.0, 0.0)).0,
+                self.support_vector(Point(0.0, 1.0)).1,
+            ),
+        }
+    }
 }
 
+/// A shape which can move and collide with others
 pub trait Collidable: Bounded + RefUnwindSafe {
+    /// rotates the shape counterclockwise around its centroid
     fn rotate(&mut self, angle: f64);
+    /// moves the shape without turning it
     fn translate(&mut self, translation: Vector);
+    /// the mass, position and motion of the shape
     fn collision_data_mut(&mut self) -> &mut CollisionData;
+    /// the mass and moment of inertia the shape has when it isn't static
+    fn dynamic_mass_and_inertia(&self) -> (f64, f64);
 
+    /// applies the impulse pushing the overlapping shapes apart at the given points of each
+    /// one and separates them, returning the impulse. Shapes overlapping deeper than they can
+    /// be separated in one step don't bounce
     fn resolve_collision_with(
         &mut self,
         other: &mut dyn Collidable,
         collision: Vertex,
+        points: &[(Point, Point)],
+        response: Response,
         time_step: Duration,
-    ) -> bool {
-        const RESTITUTION: f64 = 0.2;
+    ) -> f64 {
+        let Response {
+            stiffness,
+            restitution,
+            friction_scale,
+            max_correction,
+        } = response;
+        let normal = collision.point.unit();
+        let is_deep = collision.point.norm() > max_correction;
 
         let first = self.collision_data_mut();
         let second = other.collision_data_mut();
+        let friction = (first.friction * second.friction).sqrt() * friction_scale;
+        let tangent = -normal.perpendicular();
+        // the impulses act on a rotation-locked shape through no lever arm at all, as if its
+        // inertia was infinite. Actually making it infinite would make the shape look static
+        let (first_locked, second_locked) = (first.lock_rotation, second.lock_rotation);
+        let lever = |is_locked: bool, offset: Vector| if is_locked { Vector::ZERO } else { offset };
 
-        let first_offset = first.centroid.to(collision.created_from.0);
-        let second_offset = second.centroid.to(collision.created_from.1);
-        let normal = collision.point.unit();
-        let first_velocity =
-            first.velocity - (first_offset * first.angular_velocity).perpendicular();
-        let second_velocity =
-            second.velocity - (second_offset * second.angular_velocity).perpendicular();
-        let relative_velocity = second_velocity - first_velocity;
-
-        let impulse = compute::impulse(
-            first.clone(),
-            second.clone(),
-            first_offset,
-            second_offset,
-            normal,
-            relative_velocity,
-            RESTITUTION + 1.0,
-        );
-
-        if impulse > 0.0 {
-            let friction_normal = -normal.perpendicular();
-
-            let static_friction_impulse = compute::impulse(
-                first.clone(),
-                second.clone(),
-                first_offset,
-                second_offset,
-                friction_normal,
-                relative_velocity,
-                1.0,
-            );
-
-            let friction_impulse = if static_friction_impulse > impulse * 1e-4 {
-                compute::impulse(
-                    first.clone(),
-                    second.clone(),
+        // sequential impulses: each contact point in turn gets the impulse correcting its
+        // velocity, which disturbs the others a little, so it's repeated a few times. The
+        // impulses each point received in total are what's clamped, the normal one to never
+        // pull the shapes together, the friction one to the Coulomb cone of the normal one
+        let mut contacts = points
+            .iter()
+            .map(|&(first_point, second_point)| {
+                let first_offset = first.centroid.to(first_point);
+                let second_offset = second.centroid.to(second_point);
+                let approach_velocity = (second.velocity_at(second_offset)
+                    - first.velocity_at(first_offset))
+                .dot(normal);
+                let bounce_velocity = if approach_velocity < -MIN_BOUNCE_VELOCITY && !is_deep {
+                    -restitution * approach_velocity
+                } else {
+                    0.0
+                };
+                Contact {
                     first_offset,
                     second_offset,
-                    friction_normal,
-                    relative_velocity,
-                    (50.0 * collision.point.norm()).min(1.0),
-                )
-            } else {
-                // the static fricion started causing problems
-                // in the later stages of tuning
-                0.0
-                // static_friction_impulse
-            };
-
-            first.velocity -= normal * (impulse / first.mass);
-            first.angular_velocity -= impulse * first_offset.cross(normal) / first.inertia;
-
-            second.velocity += normal * (impulse / second.mass);
-            second.angular_velocity += impulse * second_offset.cross(normal) / second.inertia;
-
-            first.velocity -= friction_normal * (friction_impulse / first.mass);
-            first.angular_velocity -=
-                friction_impulse * first_offset.cross(friction_normal) / first.inertia;
-
-            second.velocity += friction_normal * (friction_impulse / second.mass);
-            second.angular_velocity +=
-                friction_impulse * second_offset.cross(friction_normal) / second.inertia;
+                    // a soft contact lets the shapes keep approaching, only a bit slower
+                    bounce_velocity: bounce_velocity * stiffness
+                        + approach_velocity.min(0.0) * (1.0 - stiffness),
+                    normal_impulse: 0.0,
+                    friction_impulse: 0.0,
+                }
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..CONTACT_ITERATIONS {
+            for contact in &mut contacts {
+                let Contact {
+                    first_offset,
+                    second_offset,
+                    ..
+                } = *contact;
+                let relative_velocity =
+                    second.velocity_at(second_offset) - first.velocity_at(first_offset);
+                let (first_lever, second_lever) = (
+                    lever(first_locked, first_offset),
+                    lever(second_locked, second_offset),
+                );
+                let normal_impulse = (contact.normal_impulse
+                    + compute::impulse(
+                        first.clone(),
+                        second.clone(),
+                        first_lever,
+                        second_lever,
+                        normal,
+                        relative_velocity - normal * contact.bounce_velocity,
+                        1.0,
+                    ))
+                .max(0.0);
+                let change = normal * (normal_impulse - contact.normal_impulse);
+                contact.normal_impulse = normal_impulse;
+                first.apply_impulse(first_lever, -change);
+                second.apply_impulse(second_lever, change);
+
+                let relative_velocity =
+                    second.velocity_at(second_offset) - first.velocity_at(first_offset);
+                let max_friction_impulse = friction * contact.normal_impulse;
+                let friction_impulse = (contact.friction_impulse
+                    + compute::impulse(
+                        first.clone(),
+                        second.clone(),
+                        first_lever,
+                        second_lever,
+                        tangent,
+                        relative_velocity,
+                        1.0,
+                    ))
+                .clamp(-max_friction_impulse, max_friction_impulse);
+                let change = tangent * (friction_impulse - contact.friction_impulse);
+                contact.friction_impulse = friction_impulse;
+                first.apply_impulse(first_lever, -change);
+                second.apply_impulse(second_lever, change);
+            }
         }
+        let impulse = contacts.iter().map(|contact| contact.normal_impulse).sum();
 
         if first.mass.is_finite() || second.mass.is_finite() {
             let translation = normal
-                * collision
-                    .point
-                    .norm()
-                    .min(1e-6 * time_step.as_micros() as f64);
+                * (collision.point.norm() - PENETRATION_SLOP)
+                    .max(0.0)
+                    .min(1e-6 * time_step.as_micros() as f64)
+                    .min(max_correction)
+                * stiffness;
             let i1 = first.mass.recip();
             let i2 = second.mass.recip();
             let i_sum = i1 + i2;
@@ -119,50 +259,156 @@ pub trait Collidable: Bounded + RefUnwindSafe {
             self.translate(-translation * (i1 / i_sum));
             other.translate(translation * (i2 / i_sum));
         }
-        impulse > 0.02
+        impulse
     }
 
-    fn collide(&mut self, other: &mut dyn Collidable, time_step: Duration) -> CollisionType {
-        let Some(collision) = compute::collision(self, other) else {
-            return CollisionType::None;
-        };
+    /// resolves the collision between the shapes, if they are overlapping, see
+    /// [`CollisionType::classify`] for how hard it was
+    fn collide(
+        &mut self,
+        other: &mut dyn Collidable,
+        time_step: Duration,
+        params: &PhysicsParams,
+    ) -> Option<Touch> {
+        let collision = compute::collision(self, other)?;
 
         if collision.point.is_close_enough_to(Vector::ZERO) {
-            return CollisionType::None;
+            return None;
         }
 
-        if self.resolve_collision_with(other, collision, time_step) {
-            CollisionType::Strong
-        } else {
-            CollisionType::Weak
-        }
+        let normal = collision.point.unit();
+        let points = contact_points(
+            self.side_facing(normal),
+            other.side_facing(-normal),
+            collision,
+        );
+        let max_correction =
+            params.max_overlap_correction * self.bounding_radius().min(other.bounding_radius());
+        let response = Response {
+            stiffness: 1.0,
+            restitution: params.restitution,
+            friction_scale: params.friction_scale,
+            max_correction,
+        };
+        let impulse = self.resolve_collision_with(other, collision, &points, response, time_step);
+        Some(Touch {
+            point: collision.created_from.0,
+            normal: collision.point.unit(),
+            depth: collision.point.norm(),
+            impulse,
+        })
     }
 
+    /// where a point attached to the shape currently is
     fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point;
+    /// attaches the point to the shape, so it follows it as it moves
     fn create_point_reference(&self, point: Point) -> PointOnShape;
 
-    fn update_position(&mut self, time_step: Duration, angle: f64) {
+    /// vertices of the shape, curved shapes are approximated with a polygon
+    fn outline(&self) -> Vec<Point>;
+
+    /// the flat side of the shape facing almost exactly in the direction, if there is one
+    fn side_facing(&self, _direction: Vector) -> Option<[Point; 2]> {
+        None
+    }
+
+    /// moves the shape along with its velocities and accelerates it by gravity, which points
+    /// down after being rotated by `angle`
+    fn update_position(&mut self, time_step: Duration, angle: f64, params: &PhysicsParams) {
         let time_step = time_step.as_micros() as f64;
 
         let velocity = self.collision_data_mut().velocity;
         let angular_velocity = self.collision_data_mut().angular_velocity;
 
-        self.collision_data_mut().velocity +=
-            Point(0.0, GRAVITY_COEFFICIENT * time_step).rotate(angle);
-        self.rotate(angular_velocity * MOVEMENT_COEFFICIENT * time_step);
-        self.translate(velocity * MOVEMENT_COEFFICIENT * time_step);
+        let data = self.collision_data_mut();
+        data.velocity = (data.velocity
+            + Point(0.0, params.gravity_coefficient * time_step).rotate(angle))
+            * (1.0 - params.linear_damping).powf(time_step / 1_000_000.0);
+        self.rotate(angular_velocity * params.movement_coefficient * time_step);
+        self.translate(velocity * params.movement_coefficient * time_step);
+    }
+}
+
+/// the points of each shape where the impulses resolving a collision are applied. Shapes lying
+/// on each other along a flat side touch at both ends of where the sides overlap, otherwise only
+/// at the deepest point the collision was found at. Pushing at a single corner would tip a box
+/// resting on the ground over onto its other corner every step, and make it walk down slopes
+fn contact_points(
+    first_side: Option<[Point; 2]>,
+    second_side: Option<[Point; 2]>,
+    collision: Vertex,
+) -> Vec<(Point, Point)> {
+    let (Some(first_side), Some(second_side)) = (first_side, second_side) else {
+        return vec![collision.created_from];
+    };
+    let tangent = collision.point.perpendicular().unit();
+    let extent = |side: [Point; 2]| {
+        let [from, to] = side.map(|point| tangent.dot(point));
+        (from.min(to), from.max(to))
+    };
+    let (first_min, first_max) = extent(first_side);
+    let (second_min, second_max) = extent(second_side);
+    let (overlap_min, overlap_max) = (first_min.max(second_min), first_max.min(second_max));
+    if overlap_min > overlap_max {
+        return vec![collision.created_from];
     }
+
+    let (first_point, second_point) = collision.created_from;
+    [overlap_min, overlap_max]
+        .into_iter()
+        .map(|along| {
+            let shift = tangent * (along - tangent.dot(first_point));
+            (first_point + shift, second_point + shift)
+        })
+        .collect()
 }
 
+/// A shape which can be turned into the one it's displayed as
 pub trait Shape: Collidable + Clone + Into<Self::Underlying> {
+    /// the corresponding shape from `geometry`
     type Underlying;
 }
 
+/// The physical properties of a shape. Static shapes have infinite mass and inertia.
+/// The velocities are in units per microsecond, scaled by `PhysicsParams::movement_coefficient`
 #[derive(Clone, Debug)]
 pub struct CollisionData {
+    /// the center of mass
     pub centroid: Point,
+    /// equal to the area, every shape has the same density
     pub mass: f64,
+    /// the moment of inertia around the centroid
     pub inertia: f64,
+    /// the velocity of the centroid
     pub velocity: Vector,
+    /// positive when spinning counterclockwise
     pub angular_velocity: f64,
+    /// how rough the surface is, where two shapes touch the geometric mean of their
+    /// coefficients is used
+    pub friction: f64,
+    /// collisions push the shape around without ever making it spin
+    pub lock_rotation: bool,
+}
+
+impl CollisionData {
+    /// the velocity of the point at the offset from the centroid, taking the spin into account
+    pub fn velocity_at(&self, offset: Vector) -> Vector {
+        self.velocity - (offset * self.angular_velocity).perpendicular()
+    }
+
+    /// changes the velocities as if the impulse hit the point at the offset from the centroid
+    pub fn apply_impulse(&mut self, offset: Vector, impulse: Vector) {
+        self.velocity += impulse / self.mass;
+        self.angular_velocity += offset.cross(impulse) / self.inertia;
+    }
+}
+
+/// A point where two colliding shapes touch, along with the impulses it received so far
+struct Contact {
+    first_offset: Vector,
+    second_offset: Vector,
+    /// how fast the shapes should move apart at the point once the collision is resolved
+    bounce_velocity: f64,
+    normal_impulse: f64,
+    friction_impulse: f64,
 }
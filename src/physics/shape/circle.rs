@@ -1,14 +1,17 @@
+use std::f64::consts::TAU;
+
 use crate::{
     geometry::{self, Point, Vector},
     physics::binding::PointOnShape,
 };
 
-use super::{Bounded, Collidable, CollisionData, Shape};
+use super::{Bounded, Collidable, CollisionData, Shape, DEFAULT_FRICTION};
 
 impl Shape for Circle {
     type Underlying = geometry::Circle;
 }
 
+/// A circle which keeps track of how far it has turned, so points can be attached to it
 #[derive(Clone)]
 pub struct Circle {
     radius: f64,
@@ -17,20 +20,28 @@ pub struct Circle {
 }
 
 impl Circle {
+    /// a circle at rest
     pub fn new(center: Point, radius: f64) -> Self {
-        let mass = std::f64::consts::PI * radius.powi(2);
+        let (mass, inertia) = Self::mass_and_inertia(radius);
         Self {
             radius,
             angle: 0.0,
             collision_properties: CollisionData {
                 centroid: center,
                 mass,
-                inertia: mass * radius.powi(2) / 2.0,
+                inertia,
                 velocity: Point::ZERO,
                 angular_velocity: 0.0,
+                friction: DEFAULT_FRICTION,
+                lock_rotation: false,
             },
         }
     }
+
+    fn mass_and_inertia(radius: f64) -> (f64, f64) {
+        let mass = std::f64::consts::PI * radius.powi(2);
+        (mass, mass * radius.powi(2) / 2.0)
+    }
 }
 
 impl Bounded for Circle {
@@ -48,6 +59,10 @@ impl Collidable for Circle {
         &mut self.collision_properties
     }
 
+    fn dynamic_mass_and_inertia(&self) -> (f64, f64) {
+        Self::mass_and_inertia(self.radius)
+    }
+
     fn translate(&mut self, translation: Vector) {
         self.collision_properties.centroid += translation;
     }
@@ -69,6 +84,17 @@ impl Collidable for Circle {
             length_scale: to_point.norm() / self.radius,
         }
     }
+
+    fn outline(&self) -> Vec<Point> {
+        const N_OF_VERTICES: usize = 24;
+
+        (0..N_OF_VERTICES)
+            .map(|i| {
+                Point(self.radius, 0.0).rotate(self.angle + TAU * i as f64 / N_OF_VERTICES as f64)
+                    + self.collision_properties.centroid
+            })
+            .collect()
+    }
 }
 
 impl From<Circle> for geometry::Circle {
@@ -3,8 +3,15 @@ use crate::{
     physics::{binding::PointOnShape, compute},
 };
 
-use super::{Bounded, Collidable, CollisionData};
+use super::{Bounded, Collidable, CollisionData, DEFAULT_FRICTION};
 
+/// keeps the mass and inertia of slivers positive, so impulses never divide by zero
+const MIN_MASS: f64 = 1e-9;
+const MIN_INERTIA: f64 = 1e-12;
+/// a side faces a direction if its outward normal is less than about a degree away from it
+const FACING_COSINE: f64 = 0.9998;
+
+/// A convex polygon
 #[derive(Clone)]
 pub struct Polygon {
     vertices: Vec<Point>,
@@ -13,7 +20,10 @@ pub struct Polygon {
 }
 
 impl Polygon {
+    /// a polygon at rest, the vertices have to be in counterclockwise order.
+    /// Repeated vertices are merged, so fewer than given may be left
     pub fn new(vertices: Vec<Point>) -> Self {
+        let vertices = geometry::merge_close_vertices(vertices);
         let centroid = compute::centroid(&vertices);
         let (inertia, mass) = Self::intertia_and_mass(centroid, &vertices);
 
@@ -25,6 +35,8 @@ impl Polygon {
                 velocity: Vector::ZERO,
                 angular_velocity: 0.0,
                 centroid,
+                friction: DEFAULT_FRICTION,
+                lock_rotation: false,
             },
             angle: 0.0,
         }
@@ -55,7 +67,10 @@ impl Polygon {
         )
         .reduce(|(inertia_sum, mass_sum), (inertia, mass)| (inertia_sum + inertia, mass_sum + mass))
         .unwrap();
-        ((inertia_sum / 12.0).abs(), (mass_sum / 2.0).abs())
+        (
+            (inertia_sum / 12.0).abs().max(MIN_INERTIA),
+            (mass_sum / 2.0).abs().max(MIN_MASS),
+        )
     }
 }
 
@@ -101,6 +116,12 @@ impl Collidable for Polygon {
         &mut self.collision_properties
     }
 
+    fn dynamic_mass_and_inertia(&self) -> (f64, f64) {
+        let (inertia, mass) =
+            Self::intertia_and_mass(self.collision_properties.centroid, &self.vertices);
+        (mass, inertia)
+    }
+
     fn resolve_point_reference(&self, point_ref: PointOnShape) -> Point {
         (self
             .collision_properties
@@ -119,13 +140,24 @@ impl Collidable for Polygon {
             length_scale: to_point.norm() / to_first_vertex.norm(),
         }
     }
+
+    fn outline(&self) -> Vec<Point> {
+        self.vertices.clone()
+    }
+
+    fn side_facing(&self, direction: Vector) -> Option<[Point; 2]> {
+        let direction = direction.unit();
+        // the vertices go counterclockwise, so the outward normals point to the right of the sides
+        windows::Looped::from(self.vertices.iter().copied()).find(|[first, second]| {
+            first.to(*second).perpendicular().unit().dot(direction) > FACING_COSINE
+        })
+    }
 }
 
 impl From<Polygon> for geometry::Polygon {
     fn from(shape: Polygon) -> Self {
         Self {
             vertices: shape.vertices,
-            centroid: shape.collision_properties.centroid,
         }
     }
 }
@@ -146,4 +178,25 @@ mod test {
         assert!(polygon.includes(Point(0.2, 0.4)));
         assert!(!polygon.includes(Point(0.2, 0.6)));
     }
+
+    #[test]
+    fn test_doubled_vertices_are_merged() {
+        let mut polygon = Polygon::new(vec![
+            Point(0.0, 0.0),
+            Point(0.0, 0.0),
+            Point(1.0, 0.0),
+            Point(1.0, 1.0),
+            Point(1.0, 1.0 + geometry::EPSILON / 2.0),
+            Point(0.0, 1.0),
+            Point(0.0, 0.0),
+        ]);
+
+        assert_eq!(polygon.outline().len(), 4);
+        assert!((polygon.collision_data_mut().mass - 1.0).abs() < geometry::EPSILON);
+
+        // a sliver with no area still gets some mass
+        let mut sliver = Polygon::new(vec![Point(0.0, 0.0), Point(1.0, 0.0), Point(2.0, 0.0)]);
+        assert!(sliver.collision_data_mut().mass > 0.0);
+        assert!(sliver.collision_data_mut().inertia > 0.0);
+    }
 }
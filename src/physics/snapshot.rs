@@ -0,0 +1,179 @@
+//! compact copies of the engine state used to rewind the simulation
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+
+use super::{
+    address,
+    binding::{Binding, Unbound},
+    shape::{Circle, Collidable, Polygon},
+    Engine, Entity, EntityCfg, EntityId, WithColor,
+};
+use crate::geometry::Laser;
+
+/// The shapes are stored by value and the bindings refer to their targets
+/// by index rather than through pointers, so a snapshot owns no `Rc`s and
+/// is independent of the entities it was taken from
+pub struct Snapshot {
+    entities: Vec<EntitySnapshot>,
+    lasers: Vec<Laser>,
+    angle: f32,
+    jumps_count: usize,
+    held_ball: Option<(f64, f64)>,
+    /// how far into the run the ball was
+    run_time: Duration,
+}
+
+enum ShapeSnapshot {
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+struct EntitySnapshot {
+    id: EntityId,
+    age: Duration,
+    shape: ShapeSnapshot,
+    color: [f32; 3],
+    texture: Option<usize>,
+    bindings: Vec<(Binding, usize)>,
+    unbound: Vec<Unbound>,
+    cfg: EntityCfg,
+    debug_name: Option<String>,
+    is_drawn: bool,
+}
+
+impl Engine {
+    pub(super) fn snapshot(&self) -> Snapshot {
+        let indices: HashMap<_, _> = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (address(Rc::as_ptr(&entity.shape)), i))
+            .collect();
+
+        let mut shapes: HashMap<_, _> = self
+            .polygons
+            .iter()
+            .filter_map(
+                |WithColor {
+                     color,
+                     shape,
+                     texture,
+                 }| {
+                    let polygon = shape.upgrade()?;
+                    let polygon = ShapeSnapshot::Polygon(polygon.borrow().clone());
+                    Some((address(shape.as_ptr()), (polygon, *color, *texture)))
+                },
+            )
+            .chain(self.circles.iter().filter_map(
+                |WithColor {
+                     color,
+                     shape,
+                     texture,
+                 }| {
+                    let circle = shape.upgrade()?;
+                    let circle = ShapeSnapshot::Circle(circle.borrow().clone());
+                    Some((address(shape.as_ptr()), (circle, *color, *texture)))
+                },
+            ))
+            .collect();
+
+        let entities = self
+            .entities
+            .iter()
+            .filter_map(|entity| {
+                let (shape, color, texture) = shapes.remove(&address(Rc::as_ptr(&entity.shape)))?;
+                Some(EntitySnapshot {
+                    id: entity.id,
+                    age: entity.age,
+                    shape,
+                    color,
+                    texture,
+                    bindings: entity
+                        .bindings
+                        .iter()
+                        .filter_map(|(binding, target, _)| {
+                            Some((*binding, *indices.get(&address(target.as_ptr()))?))
+                        })
+                        .collect(),
+                    unbound: entity.unbound.clone(),
+                    cfg: entity.cfg(),
+                    debug_name: entity.debug_name.clone(),
+                    is_drawn: entity.is_drawn,
+                })
+            })
+            .collect();
+
+        Snapshot {
+            entities,
+            lasers: self.lasers.clone(),
+            angle: self.angle,
+            jumps_count: self.jumps_count,
+            held_ball: self.held_ball,
+            run_time: self.run.elapsed(),
+        }
+    }
+
+    /// replaces all entities with the ones stored in the snapshot.
+    /// Shapes drawn after the snapshot was taken cease to exist
+    pub(super) fn restore(&mut self, snapshot: Snapshot) {
+        self.entities.clear();
+        self.polygons.clear();
+        self.circles.clear();
+
+        let shapes: Vec<Rc<RefCell<dyn Collidable>>> = snapshot
+            .entities
+            .iter()
+            .map(|entity| -> Rc<RefCell<dyn Collidable>> {
+                match &entity.shape {
+                    ShapeSnapshot::Polygon(polygon) => {
+                        let polygon = Rc::new(RefCell::new(polygon.clone()));
+                        self.polygons.push(WithColor {
+                            color: entity.color,
+                            shape: Rc::downgrade(&polygon),
+                            texture: entity.texture,
+                        });
+                        polygon
+                    }
+                    ShapeSnapshot::Circle(circle) => {
+                        let circle = Rc::new(RefCell::new(circle.clone()));
+                        self.circles.push(WithColor {
+                            color: entity.color,
+                            shape: Rc::downgrade(&circle),
+                            texture: entity.texture,
+                        });
+                        circle
+                    }
+                }
+            })
+            .collect();
+
+        // the main ball is always the first entity and the first circle
+        if let Some(main_ball) = self.circles.first() {
+            self.main_ball = main_ball.shape.clone();
+        }
+
+        for (entity, shape) in snapshot.entities.into_iter().zip(&shapes) {
+            let mut restored = Entity::new(entity.id, shape.clone(), entity.cfg);
+            restored.age = entity.age;
+            restored.unbound = entity.unbound;
+            restored.debug_name = entity.debug_name;
+            restored.is_drawn = entity.is_drawn;
+            restored.bindings = entity
+                .bindings
+                .into_iter()
+                .map(|(binding, target)| (binding, Rc::downgrade(&shapes[target]), 0.0))
+                .collect();
+            self.entities.push(restored);
+        }
+
+        self.lasers = snapshot.lasers;
+        self.angle = snapshot.angle;
+        self.target_angle = snapshot.angle;
+        self.jumps_count = snapshot.jumps_count;
+        self.run.rewind_to(snapshot.run_time);
+        self.held_ball = snapshot.held_ball;
+        if self.held_ball.is_none() {
+            self.aim = None;
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! a quick check that the game works on the machine it was built for, run with `--self-test`.
+//! It plays a small level without a window, drawing, binding and erasing shapes along the way
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel;
+
+use crate::{
+    geometry::{self, Point},
+    levels::Level,
+    physics::{shape::Polygon, DisplayMessage, EngineBuilder, WithColor},
+};
+
+/// a floor, a wall on the right for the laser to shine at and a laser sweeping across the level
+const LEVEL: &str = "(
+    initial_ball_position: (0.0, 0.5),
+    circles: [],
+    polygons: [
+        (shape: [(-1.0, -0.1), (1.0, -0.1), (1.0, 0.0), (-1.0, 0.0)], is_static: true, is_bindable: false),
+        (shape: [(0.9, 0.0), (1.0, 0.0), (1.0, 1.0), (0.9, 1.0)], is_static: true, is_bindable: false),
+    ],
+    flags_positions: [],
+    lasers: [(point: (-0.9, 0.3), initial_direction: (1.0, 0.0), angular_speed: 0.01, range: 0.3)],
+    lasers_harmless: true,
+)";
+const ITERATIONS: usize = 600;
+const STEP: Duration = Duration::from_millis(10);
+/// the self-test fails if it takes longer than this, as the game would then run too slowly
+const TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// The outcome of [`run`]
+pub struct Report {
+    /// how many frames were sent to be displayed
+    pub frames: usize,
+    /// how many entities were left at the end
+    pub entities: usize,
+    /// how long the self-test took, which fails it past [`TIME_LIMIT`]
+    pub elapsed: Duration,
+    /// everything which went wrong, empty if the self-test passed
+    pub failures: Vec<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "self-test {}: {ITERATIONS} iterations, {} frames and {} entities in {:.0?}",
+            if self.is_ok() { "passed" } else { "failed" },
+            self.frames,
+            self.entities,
+            self.elapsed,
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+/// plays the embedded level for a few seconds of game time, checking the state of the engine
+/// after every step and every frame it sends
+pub fn run() -> Report {
+    let start = Instant::now();
+    let mut failures = vec![];
+    let level = match Level::parse(LEVEL) {
+        Ok(level) => level,
+        Err(err) => {
+            return Report {
+                frames: 0,
+                entities: 0,
+                elapsed: start.elapsed(),
+                failures: vec![format!("the embedded level doesn't load: {err}")],
+            }
+        }
+    };
+    let level_entities = level.polygons.len() + level.circles.len() + 1;
+
+    let (tx, rx) = channel::bounded(1);
+    let mut engine = EngineBuilder::new(level).seed(0).display(tx).build();
+    let mut frames = 0;
+    let mut first_beams = None;
+    let mut last_frame = None;
+
+    for i in 0..ITERATIONS {
+        let drawn = match i {
+            0 => engine.add_polygon(square(Point(0.3, 0.0), Point(0.4, 0.1))),
+            50 => {
+                engine.add_hinge(Point(0.35, 0.05));
+                engine.add_polygon(square(Point(0.32, 0.02), Point(0.5, 0.12)))
+            }
+            200 => {
+                engine.jump();
+                Ok(())
+            }
+            300 => {
+                let drawn = engine.add_polygon(square(Point(-0.6, 0.2), Point(-0.5, 0.3)));
+                engine.erase_at(Point(-0.55, 0.25));
+                drawn
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = drawn {
+            failures.push(format!("at step {i}: {err}"));
+        }
+        engine.step(STEP);
+
+        for violation in engine.invariant_violations() {
+            failures.push(format!("after step {i}: {violation}"));
+        }
+        if let Ok(frame) = rx.try_recv() {
+            if let Some(problem) = frame_problem(&frame) {
+                failures.push(format!("frame {frames}: {problem}"));
+            }
+            first_beams.get_or_insert_with(|| beams(&frame));
+            frames += 1;
+            last_frame = Some(frame);
+        }
+        if failures.len() > 10 {
+            break;
+        }
+    }
+
+    let entities = engine.entity_ids().len();
+    if entities != level_entities + 2 {
+        failures.push(format!(
+            "expected {} entities, found {entities}",
+            level_entities + 2
+        ));
+    }
+    match last_frame {
+        None => failures.push("no frame was sent".into()),
+        Some(frame) => {
+            if first_beams == Some(beams(&frame)) {
+                failures.push("the laser didn't sweep".into());
+            }
+            if frame.hinges.is_empty() {
+                failures.push("the drawn shapes weren't bound with a hinge".into());
+            }
+        }
+    }
+
+    let elapsed = start.elapsed();
+    if elapsed > TIME_LIMIT {
+        failures.push(format!("took {elapsed:.0?}, longer than {TIME_LIMIT:?}"));
+    }
+
+    Report {
+        frames,
+        entities,
+        elapsed,
+        failures,
+    }
+}
+
+fn square(min: Point, max: Point) -> Polygon {
+    Polygon::new(vec![min, Point(max.0, min.1), max, Point(min.0, max.1)])
+}
+
+fn beams(frame: &DisplayMessage) -> Vec<Point> {
+    frame
+        .lasers
+        .iter()
+        .flat_map(|beam| beam.shape.vertices.clone())
+        .collect()
+}
+
+/// what's wrong with a frame sent to be displayed, if anything
+fn frame_problem(frame: &DisplayMessage) -> Option<String> {
+    let is_finite = |Point(x, y): &Point| x.is_finite() && y.is_finite();
+    let are_polygons_sane = frame
+        .polygons
+        .iter()
+        .chain(&frame.lasers)
+        .chain(&frame.laser_boxes)
+        .chain(&frame.doors)
+        .all(|WithColor { shape, .. }| {
+            shape.vertices.len() >= 3 && shape.vertices.iter().all(is_finite)
+        });
+    let are_circles_sane = frame
+        .circles
+        .iter()
+        .map(|circle| circle.shape)
+        .chain([frame.player.circle])
+        .all(|geometry::Circle { center, radius }| is_finite(&center) && radius > 0.0);
+
+    if frame.circles.is_empty() {
+        Some("the main ball is missing".into())
+    } else if !are_polygons_sane {
+        Some("a polygon is degenerate or not finite".into())
+    } else if !are_circles_sane {
+        Some("a circle is not finite or has no radius".into())
+    } else if (frame.gravity.norm() - 1.0).abs() > geometry::EPSILON {
+        Some("the gravity isn't a unit vector".into())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let report = run();
+        assert!(report.is_ok(), "{report}");
+        assert!(report.frames > 0);
+    }
+}
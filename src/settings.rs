@@ -0,0 +1,60 @@
+//! the player's preferences, kept in `settings.ron` in the directory the game is started from.
+//! Everything left out of the file keeps its default, so it only has to list what's changed,
+//! e.g. `(shape_colors: Random)` or `(shape_colors: Palette([(1.0, 0.5, 0.0), (0.2, 0.6, 1.0)]))`
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persist::{self, Persist},
+    physics::ShapeColors,
+};
+
+/// the file the settings are read from
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+/// What the player can choose outside of the game
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// the colors of the shapes drawn by the player
+    pub shape_colors: ShapeColors,
+}
+
+impl Persist for Settings {
+    const VERSION: u32 = 1;
+}
+
+impl Settings {
+    /// the settings in [`SETTINGS_FILE`], the default ones if there's no such file. A file which
+    /// can't be read is put aside, see [`persist::load_or_quarantine`]
+    pub fn load() -> Self {
+        persist::load_or_quarantine(Path::new(SETTINGS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_only_listed_settings_change() {
+        assert_eq!(
+            persist::read::<Settings>("()").unwrap(),
+            Settings::default()
+        );
+        assert_eq!(
+            persist::read::<Settings>("(shape_colors: Random)").unwrap(),
+            Settings {
+                shape_colors: ShapeColors::Random
+            }
+        );
+        assert_eq!(
+            persist::read::<Settings>("(shape_colors: Palette([(1.0, 0.5, 0.0)]))").unwrap(),
+            Settings {
+                shape_colors: ShapeColors::Palette(vec![[1.0, 0.5, 0.0]])
+            }
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! draws the game's geometry into an image without the GPU, for thumbnails, tests comparing
+//! frames against reference images and looking through simulations run without a window.
+//! Shapes are filled with the colors they're sent to the GPU with, with no textures or effects
+
+use std::{
+    fs, io,
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossbeam::channel;
+
+use crate::{
+    geometry::{Aabb, Circle, Point},
+    levels::{Level, FLAG_SIZE},
+    physics::{DisplayMessage, EngineBuilder},
+};
+
+/// what the frame is cleared with, as in the window when the level has no background
+const BACKGROUND: [f32; 3] = [1.0, 1.0, 1.0];
+/// the colors of a level drawn on its own, without an engine deciding them
+const STATIC_COLOR: [f32; 3] = [0.25, 0.25, 0.25];
+const DOOR_COLOR: [f32; 3] = [0.55, 0.35, 0.2];
+const FLAG_COLOR: [f32; 3] = [0.15, 0.65, 0.25];
+const BALL_COLOR: [f32; 3] = [0.85, 0.2, 0.15];
+/// how much of the image is left empty around a level drawn with [`View::fitting`], as a
+/// fraction of its size
+const FIT_MARGIN: f64 = 0.05;
+/// the size of the frames saved by [`record`], in pixels
+pub const HEADLESS_SIZE: u32 = 256;
+/// how much game time passes in a step of [`record`]
+const HEADLESS_STEP: Duration = Duration::from_millis(10);
+/// how many steps `--headless` simulates, ten seconds of game time
+pub const HEADLESS_STEPS: usize = 1000;
+pub(crate) const RENDER_EVERY_ARG: &str = "--render-every";
+
+/// Reasons for which an image couldn't be saved or loaded
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("couldn't read or write the image: {0}")]
+    Io(#[from] io::Error),
+    #[error("couldn't save the image: {0}")]
+    Encode(#[from] png::EncodingError),
+    #[error("couldn't read the image: {0}")]
+    Decode(#[from] png::DecodingError),
+    #[error("the image isn't 8-bit RGBA")]
+    Format,
+}
+
+/// The part of the game's coordinates drawn into an image. Like the window, the image spans
+/// from -1 to 1 in both axes at a zoom of 1, with the y axis pointing up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    /// the point drawn in the middle of the image
+    pub center: Point,
+    /// how much bigger than at a zoom of 1 everything is drawn
+    pub zoom: f64,
+    /// the size of the image, in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl View {
+    /// the coordinates the window shows, stretched over an image of the size like over a
+    /// window of it
+    pub fn window(width: u32, height: u32) -> Self {
+        View {
+            center: Point::ZERO,
+            zoom: 1.0,
+            width,
+            height,
+        }
+    }
+
+    /// shows all of the box with a margin around it
+    pub fn fitting(bounds: Aabb, width: u32, height: u32) -> Self {
+        let size = bounds.min.to(bounds.max);
+        let extent = size.0.max(size.1).max(f64::EPSILON);
+        View {
+            center: (bounds.min + bounds.max) * 0.5,
+            zoom: 2.0 / (extent * (1.0 + 2.0 * FIT_MARGIN)),
+            width,
+            height,
+        }
+    }
+
+    /// where the point is in the image, in pixels from its top left corner
+    pub(crate) fn position_of(&self, point: Point) -> Point {
+        let Point(x, y) = (point - self.center) * self.zoom;
+        Point(
+            (x + 1.0) / 2.0 * self.width as f64,
+            (1.0 - y) / 2.0 * self.height as f64,
+        )
+    }
+
+    /// the point drawn at the position in the image, the inverse of `position_of`
+    fn point_at(&self, Point(column, row): Point) -> Point {
+        let x = column / self.width as f64 * 2.0 - 1.0;
+        let y = 1.0 - row / self.height as f64 * 2.0;
+        self.center + Point(x, y) * (1.0 / self.zoom)
+    }
+}
+
+/// An RGBA image, row by row from the top
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// the color as it's written into an image, the same as the GPU writes into the window
+fn to_rgba(color: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    [r, g, b, 255]
+}
+
+impl Image {
+    /// an image filled with the color
+    pub fn new(width: u32, height: u32, color: [f32; 3]) -> Self {
+        Image {
+            width,
+            height,
+            pixels: to_rgba(color).repeat((width * height) as usize),
+        }
+    }
+
+    fn set(&mut self, column: u32, row: u32, color: [u8; 4]) {
+        let i = 4 * (row * self.width + column) as usize;
+        self.pixels[i..i + 4].copy_from_slice(&color);
+    }
+
+    /// fills the pixels whose middles are inside the polygon, which may be concave
+    pub fn fill_polygon(&mut self, view: &View, vertices: &[Point], color: [f32; 3]) {
+        let color = to_rgba(color);
+        let vertices: Vec<_> = vertices.iter().map(|&v| view.position_of(v)).collect();
+        for row in 0..self.height {
+            let y = row as f64 + 0.5;
+            // where the edges cross the middle of the row, counting an edge which ends on it
+            // only once
+            let mut crossings: Vec<_> = vertices
+                .iter()
+                .zip(vertices.iter().cycle().skip(1))
+                .filter(|(from, to)| (from.1 <= y) != (to.1 <= y))
+                .map(|(from, to)| from.0 + (y - from.1) / (to.1 - from.1) * (to.0 - from.0))
+                .collect();
+            crossings.sort_by(f64::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                // the columns whose middles are in the span
+                let first = (span[0] - 0.5).ceil().max(0.0) as u32;
+                let last = (span[1] - 0.5).ceil().min(self.width as f64);
+                for column in first..last.max(0.0) as u32 {
+                    self.set(column, row, color);
+                }
+            }
+        }
+    }
+
+    /// fills the pixels whose middles are inside the circle
+    pub fn fill_circle(&mut self, view: &View, Circle { center, radius }: Circle, color: [f32; 3]) {
+        let color = to_rgba(color);
+        let top_left = view.position_of(center + Point(-radius, radius));
+        let bottom_right = view.position_of(center + Point(radius, -radius));
+        let columns = top_left.0.floor().max(0.0) as u32
+            ..(bottom_right.0.ceil().max(0.0) as u32).min(self.width);
+        let rows = top_left.1.floor().max(0.0) as u32
+            ..(bottom_right.1.ceil().max(0.0) as u32).min(self.height);
+        for row in rows {
+            for column in columns.clone() {
+                let point = view.point_at(Point(column as f64 + 0.5, row as f64 + 0.5));
+                if center.to(point).norm() <= radius {
+                    self.set(column, row, color);
+                }
+            }
+        }
+    }
+
+    /// the biggest difference between a channel of a pixel in this image and the same one in
+    /// the other, `None` if they aren't the same size
+    pub fn max_difference(&self, other: &Image) -> Option<u8> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        Some(
+            self.pixels
+                .iter()
+                .zip(&other.pixels)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0),
+        )
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), RenderError> {
+        let mut encoder = png::Encoder::new(
+            io::BufWriter::new(fs::File::create(path)?),
+            self.width,
+            self.height,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+
+    pub fn load_png(path: &Path) -> Result<Self, RenderError> {
+        let mut reader = png::Decoder::new(fs::File::open(path)?).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels)?;
+        if (info.color_type, info.bit_depth) != (png::ColorType::Rgba, png::BitDepth::Eight) {
+            return Err(RenderError::Format);
+        }
+        pixels.truncate(info.buffer_size());
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+/// draws the entities, lasers and doors of a frame in the order the GPU does, leaving out the
+/// indicators drawn over them
+pub fn render(message: &DisplayMessage, view: &View) -> Image {
+    let mut image = Image::new(view.width, view.height, BACKGROUND);
+    let polygons = message
+        .polygons
+        .iter()
+        .chain(&message.lasers)
+        .chain(&message.laser_boxes)
+        .chain(&message.doors)
+        .chain(&message.launch_arrow);
+    for polygon in polygons {
+        image.fill_polygon(view, &polygon.shape.vertices, polygon.color);
+    }
+    for circle in &message.circles {
+        image.fill_circle(view, circle.shape, circle.color);
+    }
+    image
+}
+
+/// draws the static entities, doors and flags of a level and where the ball starts, leaving
+/// out the dynamic entities as they don't stay where the level puts them
+pub fn render_level(level: &Level, view: &View) -> Image {
+    let mut image = Image::new(view.width, view.height, BACKGROUND);
+    for polygon in level.polygons.iter().filter(|polygon| polygon.is_static) {
+        image.fill_polygon(view, &polygon.shape, STATIC_COLOR);
+    }
+    for (door, _) in &level.doors {
+        image.fill_polygon(view, door, DOOR_COLOR);
+    }
+    for circle in level.circles.iter().filter(|circle| circle.is_static) {
+        image.fill_circle(view, circle.shape, STATIC_COLOR);
+    }
+    for &flag in &level.flags_positions {
+        let flag = [
+            flag,
+            flag + Point(FLAG_SIZE, 0.0),
+            flag + Point(FLAG_SIZE, FLAG_SIZE),
+            flag + Point(0.0, FLAG_SIZE),
+        ];
+        image.fill_polygon(view, &flag, FLAG_COLOR);
+    }
+    image.fill_circle(
+        view,
+        Circle {
+            center: level.initial_ball_position,
+            radius: level.ball_radius,
+        },
+        BALL_COLOR,
+    );
+    image
+}
+
+/// simulates the level for `steps` steps without a window, saving every `every`-th frame to
+/// `frame_<step>.png` in the directory, and returns where they were saved
+pub fn record(
+    level: Level,
+    steps: usize,
+    every: usize,
+    directory: &Path,
+) -> Result<Vec<PathBuf>, RenderError> {
+    fs::create_dir_all(directory)?;
+    let (frames_tx, frames_rx) = channel::bounded(1);
+    let mut engine = EngineBuilder::new(level).display(frames_tx).build();
+    let view = View::window(HEADLESS_SIZE, HEADLESS_SIZE);
+    let mut saved = vec![];
+    for step in 0..steps {
+        engine.step(HEADLESS_STEP);
+        // taken every step so the engine sends the next one
+        let Ok(frame) = frames_rx.try_recv() else {
+            continue;
+        };
+        if step % every.max(1) == 0 {
+            let path = directory.join(format!("frame_{step:05}.png"));
+            render(&frame, &view).save_png(&path)?;
+            saved.push(path);
+        }
+    }
+    Ok(saved)
+}
+
+/// reads `--render-every <steps> <directory>`, `None` if it's missing or isn't followed by both
+pub fn render_every_from_args(
+    args: impl IntoIterator<Item = String>,
+) -> Option<(Result<usize, ParseIntError>, PathBuf)> {
+    let mut args = args
+        .into_iter()
+        .skip_while(|arg| arg != RENDER_EVERY_ARG)
+        .skip(1);
+    let every = args.next()?;
+    let directory = args.next()?;
+    Some((every.parse(), PathBuf::from(directory)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::levels::Entity;
+
+    /// compared with the first frame of `golden_level`
+    const GOLDEN_FRAME: &str = "assets/golden/first_frame.png";
+    /// how much a channel of a pixel may differ from the reference, so small changes in
+    /// rounding don't fail the test
+    const GOLDEN_TOLERANCE: u8 = 2;
+
+    fn square(min: Point, size: f64) -> Vec<Point> {
+        vec![
+            min,
+            min + Point(size, 0.0),
+            min + Point(size, size),
+            min + Point(0.0, size),
+        ]
+    }
+
+    fn pixel(image: &Image, column: u32, row: u32) -> [u8; 4] {
+        let i = 4 * (row * image.width + column) as usize;
+        image.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_shapes_cover_their_pixels() {
+        let view = View::window(10, 10);
+        let mut image = Image::new(10, 10, BACKGROUND);
+        // the top left quarter, with the y axis pointing up
+        image.fill_polygon(&view, &square(Point(-1.0, 0.0), 1.0), [1.0, 0.0, 0.0]);
+        image.fill_circle(
+            &view,
+            Circle {
+                center: Point(0.5, -0.5),
+                radius: 0.3,
+            },
+            [0.0, 0.0, 1.0],
+        );
+
+        let red = [255, 0, 0, 255];
+        let white = [255; 4];
+        let blue = [0, 0, 255, 255];
+        let reds = (0..10)
+            .flat_map(|row| (0..10).map(move |column| (column, row)))
+            .filter(|&(column, row)| pixel(&image, column, row) == red)
+            .count();
+        assert_eq!(reds, 25);
+        assert_eq!(pixel(&image, 0, 0), red);
+        assert_eq!(pixel(&image, 4, 4), red);
+        assert_eq!(pixel(&image, 5, 4), white);
+        assert_eq!(pixel(&image, 4, 5), white);
+        assert_eq!(pixel(&image, 7, 7), blue);
+        assert_eq!(pixel(&image, 9, 9), white);
+    }
+
+    #[test]
+    fn test_concave_polygon() {
+        let view = View::window(20, 20);
+        let mut image = Image::new(20, 20, BACKGROUND);
+        // a U, open at the top
+        let outline = [
+            (-1.0, -1.0),
+            (1.0, -1.0),
+            (1.0, 1.0),
+            (0.5, 1.0),
+            (0.5, -0.5),
+            (-0.5, -0.5),
+            (-0.5, 1.0),
+            (-1.0, 1.0),
+        ]
+        .map(|(x, y)| Point(x, y));
+        image.fill_polygon(&view, &outline, [0.0, 0.0, 0.0]);
+        assert_eq!(pixel(&image, 10, 2), [255; 4]);
+        assert_eq!(pixel(&image, 2, 2), [0, 0, 0, 255]);
+        assert_eq!(pixel(&image, 10, 18), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_view_fitting_shows_the_whole_box() {
+        let bounds = Aabb {
+            min: Point(2.0, 1.0),
+            max: Point(6.0, 3.0),
+        };
+        let view = View::fitting(bounds, 100, 100);
+        for corner in [bounds.min, bounds.max] {
+            let Point(column, row) = view.position_of(corner);
+            assert!((0.0..=100.0).contains(&column) && (0.0..=100.0).contains(&row));
+            assert!(view.point_at(view.position_of(corner)).to(corner).norm() < 1e-9);
+        }
+    }
+
+    fn golden_level() -> Level {
+        let mut level = Level::new(Point(0.0, 0.5));
+        level.polygons.push(Entity::new(
+            vec![
+                Point(-1.0, -0.1),
+                Point(1.0, -0.1),
+                Point(1.0, 0.0),
+                Point(-1.0, 0.0),
+            ],
+            true,
+        ));
+        level
+            .polygons
+            .push(Entity::new(square(Point(-0.6, 0.0), 0.2), false));
+        level.circles.push(Entity::new(
+            Circle {
+                center: Point(0.5, 0.3),
+                radius: 0.1,
+            },
+            false,
+        ));
+        level
+    }
+
+    /// set `UPDATE_GOLDEN` to save the frame as the new reference after changing how the
+    /// level or the frames are drawn on purpose
+    #[test]
+    fn test_first_frame_matches_golden_image() {
+        let (frames_tx, frames_rx) = channel::bounded(1);
+        let mut engine = EngineBuilder::new(golden_level())
+            .seed(0)
+            .display(frames_tx)
+            .build();
+        engine.step(HEADLESS_STEP);
+        let frame = render(&frames_rx.try_recv().unwrap(), &View::window(64, 64));
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_FRAME);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            frame.save_png(&path).unwrap();
+        }
+        let golden = Image::load_png(&path).unwrap();
+        let difference = frame.max_difference(&golden);
+        assert!(
+            difference.is_some_and(|difference| difference <= GOLDEN_TOLERANCE),
+            "the frame differs from {GOLDEN_FRAME} by {difference:?}"
+        );
+    }
+
+    #[test]
+    fn test_render_every_from_args() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let (every, directory) = render_every_from_args(args(&[
+            "level.ron",
+            "--headless",
+            "--render-every",
+            "5",
+            "frames/",
+        ]))
+        .unwrap();
+        assert_eq!((every, directory), (Ok(5), PathBuf::from("frames/")));
+        assert!(render_every_from_args(args(&["level.ron", "--render-every", "5"])).is_none());
+        assert!(matches!(
+            render_every_from_args(args(&["--render-every", "often", "frames"])),
+            Some((Err(_), _))
+        ));
+    }
+
+    #[test]
+    fn test_record_saves_every_nth_frame() {
+        let directory =
+            std::env::temp_dir().join(format!("whisky-soft-render-{}", std::process::id()));
+        let saved = record(golden_level(), 10, 4, &directory).unwrap();
+        assert_eq!(
+            saved,
+            [0, 4, 8].map(|step| directory.join(format!("frame_{step:05}.png")))
+        );
+        let image = Image::load_png(&saved[0]).unwrap();
+        assert_eq!((image.width, image.height), (HEADLESS_SIZE, HEADLESS_SIZE));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}